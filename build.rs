@@ -0,0 +1,35 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+/// Directory containing the core package files
+const CORE_PACKAGE_DIR: &str = "src/pkg/core";
+
+fn main() {
+	println!("cargo::rerun-if-changed={CORE_PACKAGE_DIR}");
+
+	// Generate a table of the hashes of all of the core packages so that their embedded contents can be verified
+	let mut entries: Vec<_> = std::fs::read_dir(CORE_PACKAGE_DIR)
+		.unwrap()
+		.map(|entry| entry.unwrap().file_name().into_string().unwrap())
+		.filter_map(|name| {
+			let id = name
+				.strip_suffix(".pkg.txt")
+				.or_else(|| name.strip_suffix(".json"))?;
+			Some((id.to_string(), name))
+		})
+		.collect();
+	entries.sort();
+
+	let mut out = String::from("const CORE_PACKAGE_HASHES: &[(&str, &str)] = &[\n");
+	for (id, name) in entries {
+		let contents = std::fs::read(format!("{CORE_PACKAGE_DIR}/{name}")).unwrap();
+		let hash = hex::encode(Sha256::digest(contents));
+		writeln!(out, "\t(\"{id}\", \"{hash}\"),").unwrap();
+	}
+	out.push_str("];\n");
+
+	let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+	std::fs::write(out_dir.join("core_package_hashes.rs"), out).unwrap();
+}
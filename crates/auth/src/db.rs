@@ -4,6 +4,9 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{bail, ensure, Context};
 use mcvm_shared::util::utc_timestamp;
+use oauth2::basic::{BasicClient, BasicErrorResponse, BasicErrorResponseType};
+use oauth2::reqwest::async_http_client;
+use oauth2::{RefreshToken, RequestTokenError, TokenResponse};
 use rsa::traits::PublicKeyParts;
 use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
 use serde::{Deserialize, Serialize};
@@ -54,6 +57,17 @@ impl AuthDatabase {
 		Ok(())
 	}
 
+	/// Checks that a directory can be used to store the database, creating it if it does not exist
+	/// and making sure that files can be written to it
+	pub fn validate_dir(path: &Path) -> anyhow::Result<()> {
+		std::fs::create_dir_all(path).context("Failed to create database directory")?;
+		let test_path = path.join(".write_test");
+		std::fs::write(&test_path, []).context("Database directory is not writable")?;
+		std::fs::remove_file(test_path).context("Failed to remove write test file")?;
+
+		Ok(())
+	}
+
 	/// Get the path to the main database file
 	fn get_db_path(dir: &Path) -> PathBuf {
 		dir.join("db.json")
@@ -117,6 +131,84 @@ impl AuthDatabase {
 			None
 		}
 	}
+
+	/// Removes the users whose refresh tokens have been rejected by Microsoft, returning their IDs.
+	/// Expired users are logged out instead, keeping their passkeys like `logout_user`. Users are never
+	/// removed because of network errors, and users with passkeys are never removed, as their tokens can't be read
+	pub async fn prune_invalid(&mut self, client: &BasicClient) -> anyhow::Result<Vec<String>> {
+		let now = utc_timestamp().context("Failed to get the current time")?;
+		let mut removed = Vec::new();
+		for (user_id, user) in &mut self.contents.users {
+			if now >= user.expires {
+				user.sensitive = SensitiveUserInfoSerialized::None;
+				continue;
+			}
+
+			match check_refresh_token(user, client).await {
+				TokenValidity::Invalid => removed.push(user_id.clone()),
+				TokenValidity::Valid(Some(new_token)) => {
+					// The old refresh token may have been revoked by refreshing, so replace it
+					if let SensitiveUserInfoSerialized::Raw(sensitive) = &mut user.sensitive {
+						sensitive.refresh_token = Some(new_token.secret().clone());
+						user.expires = calculate_expiration_date();
+					}
+				}
+				TokenValidity::Valid(None) | TokenValidity::Unknown => {}
+			}
+		}
+
+		for user_id in &removed {
+			self.contents.users.remove(user_id);
+		}
+		self.write().context("Failed to write to database")?;
+
+		removed.sort();
+		Ok(removed)
+	}
+}
+
+/// Whether the refresh token of a user is still usable
+enum TokenValidity {
+	/// The token works, with the new refresh token if one was given when refreshing
+	Valid(Option<RefreshToken>),
+	/// The token was rejected and will never work again
+	Invalid,
+	/// The token could not be checked, either because it is not accessible
+	/// or because of a temporary failure
+	Unknown,
+}
+
+/// Checks whether the refresh token of a user can still be used by refreshing it
+async fn check_refresh_token(user: &DatabaseUser, client: &BasicClient) -> TokenValidity {
+	let SensitiveUserInfoSerialized::Raw(sensitive) = &user.sensitive else {
+		return TokenValidity::Unknown;
+	};
+	let Some(refresh_token) = &sensitive.refresh_token else {
+		return TokenValidity::Unknown;
+	};
+
+	let result = client
+		.exchange_refresh_token(&RefreshToken::new(refresh_token.clone()))
+		.request_async(async_http_client)
+		.await;
+	match result {
+		Ok(token) => TokenValidity::Valid(token.refresh_token().cloned()),
+		Err(e) if is_refresh_error_unrecoverable(&e) => TokenValidity::Invalid,
+		Err(..) => TokenValidity::Unknown,
+	}
+}
+
+/// Checks whether an error from refreshing a token means that the token has been permanently
+/// rejected. Other failures, such as network errors, could go away when trying again
+fn is_refresh_error_unrecoverable<RE: std::error::Error>(
+	error: &RequestTokenError<RE, BasicErrorResponse>,
+) -> bool {
+	match error {
+		RequestTokenError::ServerResponse(response) => {
+			matches!(response.error(), BasicErrorResponseType::InvalidGrant)
+		}
+		_ => false,
+	}
 }
 
 /// Structure for the auth database
@@ -356,3 +448,161 @@ pub fn calculate_expiration_date() -> u64 {
 	let now = utc_timestamp().unwrap_or_default();
 	now + REFRESH_TOKEN_EXPIRATION
 }
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read, Write};
+
+	use oauth2::StandardErrorResponse;
+
+	use super::*;
+
+	#[test]
+	fn test_custom_database_dir() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let db_dir = dir.join("profile").join("auth");
+		AuthDatabase::validate_dir(&db_dir).unwrap();
+		assert!(!db_dir.join(".write_test").exists());
+
+		let sensitive = SensitiveUserInfo {
+			refresh_token: Some("token".into()),
+			xbox_uid: None,
+			keypair: None,
+		};
+		let user =
+			DatabaseUser::new("user".into(), "Steve".into(), "uuid".into(), 0, sensitive).unwrap();
+		let mut db = AuthDatabase::open(&db_dir).unwrap();
+		db.update_user(user, "user").unwrap();
+		assert!(db_dir.join("db.json").exists());
+
+		let db = AuthDatabase::open(&db_dir).unwrap();
+		let user = db
+			.get_user("user")
+			.expect("User should be read from the custom path");
+		assert_eq!(user.username, "Steve");
+		let sensitive = user.get_sensitive_info_no_passkey().unwrap();
+		assert_eq!(sensitive.refresh_token.as_deref(), Some("token"));
+
+		// A directory can't be created inside of a file
+		let file_path = dir.join("file");
+		std::fs::write(&file_path, "").unwrap();
+		assert!(AuthDatabase::validate_dir(&file_path.join("auth")).is_err());
+	}
+
+	#[tokio::test]
+	async fn test_prune_invalid() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut db = AuthDatabase::open(dir.path()).unwrap();
+		let expires = utc_timestamp().unwrap() + 3600;
+		for (user_id, expires) in [("revoked", expires), ("transient", expires), ("expired", 0)] {
+			let sensitive = SensitiveUserInfo {
+				refresh_token: Some(user_id.into()),
+				xbox_uid: None,
+				keypair: None,
+			};
+			let user = DatabaseUser::new(
+				user_id.into(),
+				user_id.into(),
+				"uuid".into(),
+				expires,
+				sensitive,
+			)
+			.unwrap();
+			db.update_user(user, user_id).unwrap();
+		}
+
+		let url = serve_token_endpoint();
+		let client = BasicClient::new(
+			oauth2::ClientId::new("client".into()),
+			None,
+			oauth2::AuthUrl::new(url.clone()).unwrap(),
+			Some(oauth2::TokenUrl::new(url).unwrap()),
+		);
+		let removed = db.prune_invalid(&client).await.unwrap();
+		assert_eq!(removed, vec!["revoked".to_string()]);
+
+		let db = AuthDatabase::open(dir.path()).unwrap();
+		assert!(db.get_user("revoked").is_none());
+		// Temporary failures don't remove the user
+		assert!(db.get_user("transient").unwrap().is_logged_in());
+		// Expired users are logged out, but kept
+		assert!(!db.get_user("expired").unwrap().is_logged_in());
+	}
+
+	/// Starts a local token endpoint that rejects the refresh token 'revoked' with invalid_grant
+	/// and is temporarily unavailable for every other token. Returns the URL of the endpoint.
+	fn serve_token_endpoint() -> String {
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}/token", listener.local_addr().unwrap());
+		std::thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(mut stream) = stream else {
+					return;
+				};
+				let request = read_request(&mut stream);
+				let (status, error) = if request.contains("refresh_token=revoked") {
+					("400 Bad Request", "invalid_grant")
+				} else {
+					("503 Service Unavailable", "temporarily_unavailable")
+				};
+				let body = format!(r#"{{"error":"{error}"}}"#);
+				let response = format!(
+					"HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+					body.len()
+				);
+				let _ = stream.write_all(response.as_bytes());
+			}
+		});
+
+		url
+	}
+
+	/// Reads an HTTP request with its body from a stream
+	fn read_request(stream: &mut std::net::TcpStream) -> String {
+		let mut request = Vec::new();
+		let mut buf = [0; 1024];
+		loop {
+			let text = String::from_utf8_lossy(&request).to_string();
+			if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+				let length = headers
+					.lines()
+					.find_map(|x| {
+						x.to_lowercase()
+							.strip_prefix("content-length:")?
+							.trim()
+							.parse()
+							.ok()
+					})
+					.unwrap_or(0);
+				if body.len() >= length {
+					return text;
+				}
+			}
+			match stream.read(&mut buf) {
+				Ok(0) | Err(..) => return text,
+				Ok(len) => request.extend_from_slice(&buf[..len]),
+			}
+		}
+	}
+
+	#[test]
+	fn test_refresh_error_classification() {
+		type Error = RequestTokenError<std::io::Error, BasicErrorResponse>;
+		let server_error =
+			|kind| Error::ServerResponse(StandardErrorResponse::new(kind, None, None));
+
+		let invalid_grant = server_error(BasicErrorResponseType::InvalidGrant);
+		assert!(is_refresh_error_unrecoverable(&invalid_grant));
+
+		let timeout = Error::Request(std::io::Error::new(
+			std::io::ErrorKind::TimedOut,
+			"Connection timed out",
+		));
+		assert!(!is_refresh_error_unrecoverable(&timeout));
+		let unavailable = server_error(BasicErrorResponseType::Extension(
+			"temporarily_unavailable".into(),
+		));
+		assert!(!is_refresh_error_unrecoverable(&unavailable));
+	}
+}
@@ -1,7 +1,7 @@
 use super::mc_msa::{
 	MinecraftAccessToken, MinecraftAuthenticationResponse, MinecraftAuthorizationFlow,
 };
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
 use mcvm_shared::translate;
 pub use oauth2::basic::{BasicClient, BasicTokenType};
@@ -88,6 +88,7 @@ fn get_device_code_url() -> anyhow::Result<DeviceAuthorizationUrl> {
 
 /// Create the OAuth client that will be used. You will have to supply your own ClientID.
 pub fn create_client(client_id: ClientId) -> anyhow::Result<BasicClient> {
+	validate_client_id(&client_id)?;
 	let client = BasicClient::new(
 		client_id,
 		None,
@@ -99,6 +100,26 @@ pub fn create_client(client_id: ClientId) -> anyhow::Result<BasicClient> {
 	Ok(client)
 }
 
+/// Checks that a Microsoft client ID is well-formed. Client IDs of Azure applications
+/// are UUIDs, such as '402abc71-43fb-45c1-b230-e7fc9d4485fe'
+pub fn validate_client_id(client_id: &ClientId) -> anyhow::Result<()> {
+	let id = client_id.as_str();
+	if id.is_empty() {
+		bail!("The Microsoft client ID is empty. Make sure that one is configured");
+	}
+
+	let groups: Vec<_> = id.split('-').collect();
+	let is_valid = groups.iter().map(|x| x.len()).eq([8, 4, 4, 4, 12])
+		&& groups
+			.iter()
+			.all(|x| x.chars().all(|c| c.is_ascii_hexdigit()));
+	if !is_valid {
+		bail!("The Microsoft client ID '{id}' is not a valid UUID");
+	}
+
+	Ok(())
+}
+
 /// First part of the auth process
 pub async fn generate_login_page(
 	client: &BasicClient,
@@ -252,3 +273,20 @@ pub struct Keypair {
 	#[serde(alias = "publicKey")]
 	pub public_key: String,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_client_id_validation() {
+		let validate = |id: &str| validate_client_id(&ClientId::new(id.into()));
+
+		assert!(validate("402abc71-43fb-45c1-b230-e7fc9d4485fe").is_ok());
+		assert!(validate("").is_err());
+		assert!(validate("my-client-id").is_err());
+		assert!(validate("402abc71-43fb-45c1-b230-e7fc9d4485fz").is_err());
+		// An empty client ID is rejected before making any requests
+		assert!(create_client(ClientId::new(String::new())).is_err());
+	}
+}
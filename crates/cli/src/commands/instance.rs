@@ -9,9 +9,13 @@ use itertools::Itertools;
 use mcvm::config::builder::InstanceBuilder;
 use mcvm::config::modifications::{apply_modifications_and_write, ConfigModification};
 use mcvm::config::Config;
+use mcvm::core::net::game_files::verify::FileStatus;
+use mcvm::core::util::secrets::get_ms_client_id;
 use mcvm::core::util::versions::{MinecraftLatestVersion, MinecraftVersionDeser};
 use mcvm::instance::transfer::load_formats;
+use mcvm::instance::update::packages::InstallSummary;
 use mcvm::instance::update::InstanceUpdateContext;
+use mcvm::instance::verify::VerifyReport;
 use mcvm::instance::Instance;
 use mcvm::io::lock::Lockfile;
 use mcvm::shared::id::InstanceID;
@@ -23,7 +27,6 @@ use reqwest::Client;
 
 use super::CmdData;
 use crate::output::{icons_enabled, HYPHEN_POINT, INSTANCE, LOADER, PACKAGE, VERSION};
-use crate::secrets::get_ms_client_id;
 
 #[derive(Debug, Subcommand)]
 pub enum InstanceSubcommand {
@@ -46,6 +49,10 @@ pub enum InstanceSubcommand {
 		/// if you have authenticated at least once
 		#[arg(short, long)]
 		offline: bool,
+		/// Whether to skip all network operations and only use cached files.
+		/// Implies offline authentication
+		#[arg(long)]
+		no_network: bool,
 		/// The instance to launch, as an instance reference (profile:instance)
 		instance: Option<String>,
 	},
@@ -95,6 +102,14 @@ pub enum InstanceSubcommand {
 		#[arg(short, long)]
 		output: Option<String>,
 	},
+	#[command(about = "Check the files of an instance for corruption")]
+	Verify {
+		/// The instance to check
+		instance: Option<String>,
+		/// Whether to repair the missing and corrupt files that are found
+		#[arg(short, long)]
+		repair: bool,
+	},
 }
 
 pub async fn run(command: InstanceSubcommand, mut data: CmdData<'_>) -> anyhow::Result<()> {
@@ -103,8 +118,9 @@ pub async fn run(command: InstanceSubcommand, mut data: CmdData<'_>) -> anyhow::
 		InstanceSubcommand::Launch {
 			user,
 			offline,
+			no_network,
 			instance,
-		} => launch(instance, user, offline, data).await,
+		} => launch(instance, user, offline, no_network, data).await,
 		InstanceSubcommand::Info { instance } => info(&mut data, &instance).await,
 		InstanceSubcommand::Update {
 			force,
@@ -125,6 +141,9 @@ pub async fn run(command: InstanceSubcommand, mut data: CmdData<'_>) -> anyhow::
 			format,
 			output,
 		} => export(&mut data, instance, format, output).await,
+		InstanceSubcommand::Verify { instance, repair } => {
+			verify(&mut data, instance, repair).await
+		}
 	}
 }
 
@@ -233,6 +252,7 @@ pub async fn launch(
 	instance: Option<String>,
 	user: Option<String>,
 	offline: bool,
+	no_network: bool,
 	mut data: CmdData<'_>,
 ) -> anyhow::Result<()> {
 	data.ensure_config(true).await?;
@@ -259,13 +279,17 @@ pub async fn launch(
 			paths: &data.paths,
 			lock: &mut lock,
 			client: &client,
+			offline: no_network,
 			output: data.output,
 		};
 
-		instance
+		let summary = instance
 			.update(true, false, &mut ctx)
 			.await
 			.context("Failed to perform first update for instance")?;
+		if let Some(summary) = summary {
+			summary.check_failures()?;
+		}
 
 		// Since the update was successful, we can mark the instance as ready
 		lock.update_instance_has_done_first_update(&instance_id);
@@ -283,6 +307,9 @@ pub async fn launch(
 	let launch_settings = LaunchSettings {
 		ms_client_id: get_ms_client_id(),
 		offline_auth: offline,
+		offline: no_network,
+		censor_secrets: config.prefs.censor_secrets,
+		bandwidth_limit: config.prefs.bandwidth_limit,
 	};
 	let instance_handle = instance
 		.launch(
@@ -366,13 +393,18 @@ async fn update(
 			paths: &data.paths,
 			lock: &mut lock,
 			client: &client,
+			offline: false,
 			output: data.output,
 		};
 
-		instance
+		let summary = instance
 			.update(!skip_packages, force, &mut ctx)
 			.await
 			.context("Failed to update instance")?;
+		if let Some(summary) = summary {
+			print_install_summary(&summary);
+			summary.check_failures()?;
+		}
 
 		// Clear the package registry to prevent dependency chains in requests being carried over
 		config.packages.clear();
@@ -386,6 +418,18 @@ async fn update(
 	Ok(())
 }
 
+/// Prints a concise summary of the packages installed during an update
+fn print_install_summary(summary: &InstallSummary) {
+	cprintln!(
+		"<s>Installed <g>{}</> packages, <b>{}</> up to date, <r>{}</> failed (downloaded {} KiB in {:.1}s)",
+		summary.installed.len(),
+		summary.skipped.len(),
+		summary.failed.len(),
+		summary.bytes_downloaded / 1024,
+		summary.time.as_secs_f32()
+	);
+}
+
 async fn add(data: &mut CmdData<'_>) -> anyhow::Result<()> {
 	data.ensure_config(true).await?;
 	let mut config = data.get_raw_config()?;
@@ -549,6 +593,92 @@ async fn export(
 	Ok(())
 }
 
+async fn verify(
+	data: &mut CmdData<'_>,
+	instance: Option<String>,
+	repair: bool,
+) -> anyhow::Result<()> {
+	data.ensure_config(true).await?;
+	let config = data.config.get_mut();
+
+	let instance = pick_instance(instance, config)?;
+	let instance = config
+		.instances
+		.get_mut(&instance)
+		.context("The provided instance does not exist")?;
+
+	let mut lock = Lockfile::open(&data.paths).context("Failed to open lockfile")?;
+	let report = instance
+		.verify(&lock, &data.paths)
+		.context("Failed to verify instance")?;
+
+	if report.is_ok() {
+		cprintln!("<g>All {} files are intact.", report.checked);
+		return Ok(());
+	}
+
+	cprintln!(
+		"<s>Checked {} files, <r>{}</> are bad:",
+		report.checked,
+		report.bad_files.len()
+	);
+	print_bad_files(&report);
+
+	if !repair {
+		cprintln!("<s>Run with <b>--repair</> to fix these files");
+		return Ok(());
+	}
+
+	let client = Client::new();
+	let mut ctx = InstanceUpdateContext {
+		packages: &mut config.packages,
+		users: &config.users,
+		plugins: &config.plugins,
+		prefs: &config.prefs,
+		paths: &data.paths,
+		lock: &mut lock,
+		client: &client,
+		offline: false,
+		output: data.output,
+	};
+	let repair_report = instance
+		.repair(&report, &mut ctx)
+		.await
+		.context("Failed to repair instance")?;
+	if let Some(summary) = &repair_report.install_summary {
+		print_install_summary(summary);
+		summary.check_failures()?;
+	}
+	lock.finish(&data.paths)
+		.context("Failed to finish using lockfile")?;
+
+	let report = repair_report.verify_report;
+	if !report.is_ok() {
+		cprintln!("<s><r>{}</> files are still bad:", report.bad_files.len());
+		print_bad_files(&report);
+		bail!("Failed to repair all of the files");
+	}
+	cprintln!("<g>Repaired instance.");
+
+	Ok(())
+}
+
+/// Print the bad files from verifying an instance
+fn print_bad_files(report: &VerifyReport) {
+	for file in &report.bad_files {
+		let status = match file.status {
+			FileStatus::Missing => "Missing",
+			_ => "Corrupt",
+		};
+		cprintln!(
+			"{}<r>{}</> {}",
+			HYPHEN_POINT,
+			status,
+			file.path.to_string_lossy()
+		);
+	}
+}
+
 /// Pick which instance to use
 pub fn pick_instance(instance: Option<String>, config: &Config) -> anyhow::Result<InstanceID> {
 	if let Some(instance) = instance {
@@ -11,10 +11,13 @@ use color_print::{cformat, cprintln};
 
 use mcvm::plugin::PluginManager;
 use mcvm::config::{Config, ConfigDeser};
+use mcvm::core::util::secrets::get_ms_client_id;
 use mcvm::io::paths::Paths;
+use mcvm::pkg::repo::find_shadowed_packages;
 use mcvm::plugin_crate::hooks::{self, AddTranslations};
 use mcvm::shared::later::Later;
 use mcvm::shared::output::{MCVMOutput, MessageContents, MessageLevel};
+use reqwest::Client;
 
 use self::config::ConfigSubcommand;
 use self::files::FilesSubcommand;
@@ -178,11 +181,18 @@ impl<'a> CmdData<'a> {
 					plugins,
 					show_warnings,
 					&self.paths,
-					crate::secrets::get_ms_client_id(),
+					get_ms_client_id(),
 					self.output,
 				)
 				.context("Failed to load config")?,
 			);
+
+			self.output
+				.set_censor_secrets(self.config.get().prefs.censor_secrets);
+
+			if show_warnings && self.config.get().prefs.warn_shadowed_packages {
+				self.warn_shadowed_packages().await?;
+			}
 		}
 
 		// Update the translation map from plugins
@@ -204,6 +214,33 @@ impl<'a> CmdData<'a> {
 		Ok(())
 	}
 
+	/// Display warnings for packages that are shadowed by other repositories
+	async fn warn_shadowed_packages(&mut self) -> anyhow::Result<()> {
+		let client = Client::new();
+		let repos = &mut self.config.get_mut().packages.repos;
+		let shadowed = find_shadowed_packages(repos, &self.paths, &client, self.output)
+			.await
+			.context("Failed to check for shadowed packages")?;
+
+		for info in shadowed {
+			self.output.display(
+				MessageContents::Warning(format!(
+					"Package '{}' from repository '{}' shadows the same package in repositories {}",
+					info.package,
+					info.winner,
+					info.shadowed
+						.iter()
+						.map(|x| format!("'{x}'"))
+						.collect::<Vec<_>>()
+						.join(", ")
+				)),
+				MessageLevel::Important,
+			);
+		}
+
+		Ok(())
+	}
+
 	/// Get the raw deserialized config
 	pub fn get_raw_config(&self) -> anyhow::Result<ConfigDeser> {
 		let config =
@@ -4,9 +4,13 @@ use std::{collections::HashMap, sync::Arc};
 use super::CmdData;
 use itertools::Itertools;
 use mcvm::parse::lex::Token;
+use mcvm::pkg::eval::custom::PluginContentTypes;
+use mcvm::pkg::repo::validate_repository;
 use mcvm::pkg_crate::metadata::PackageMetadata;
 use mcvm::pkg_crate::properties::PackageProperties;
-use mcvm::pkg_crate::{parse_and_validate, PackageContentType, PkgRequest, PkgRequestSource};
+use mcvm::pkg_crate::{
+	parse_and_validate_custom, PackageContentType, PkgRequest, PkgRequestSource,
+};
 use mcvm::shared::id::{InstanceID, ProfileID};
 use mcvm::shared::util::print::ReplPrinter;
 
@@ -55,6 +59,15 @@ This package does not need to be installed, it just has to be in the index."
 		/// The package to print
 		package: String,
 	},
+	#[command(
+		about = "Print the parsed structure of a script package",
+		long_about = "Print the routines, instructions, and conditions that the parser
+produced for a script package. This is useful for debugging package scripts."
+	)]
+	Debug {
+		/// The package to debug
+		package: String,
+	},
 	#[command(about = "Print information about a specific package")]
 	Info {
 		/// Whether to remove formatting and warnings from the output
@@ -71,9 +84,17 @@ This package does not need to be installed, it just has to be in the index."
 		command: RepoSubcommand,
 	},
 	#[command(about = "List available packages from all repositories")]
-	ListAll {},
+	ListAll {
+		/// Whether to include packages that are deprecated or archived
+		#[arg(short, long)]
+		deprecated: bool,
+	},
 	#[command(about = "Browse packages from the remote repositories")]
-	Browse {},
+	Browse {
+		/// Whether to include packages that are deprecated or archived
+		#[arg(short, long)]
+		deprecated: bool,
+	},
 }
 
 #[derive(Debug, Subcommand)]
@@ -90,6 +111,11 @@ pub enum RepoSubcommand {
 		/// The repository to get info about
 		repo: String,
 	},
+	#[command(about = "Check that every package in a repository is valid")]
+	Validate {
+		/// The repository to validate
+		repo: String,
+	},
 }
 
 pub async fn run(subcommand: PackageSubcommand, data: &mut CmdData<'_>) -> anyhow::Result<()> {
@@ -97,10 +123,11 @@ pub async fn run(subcommand: PackageSubcommand, data: &mut CmdData<'_>) -> anyho
 		PackageSubcommand::List { raw, instance } => list(data, raw, instance).await,
 		PackageSubcommand::Sync { filter } => sync(data, filter).await,
 		PackageSubcommand::Cat { raw, package } => cat(data, &package, raw).await,
+		PackageSubcommand::Debug { package } => debug(data, &package).await,
 		PackageSubcommand::Info { raw, package } => info(data, &package, raw).await,
 		PackageSubcommand::Repository { command } => repo(command, data).await,
-		PackageSubcommand::ListAll {} => list_all(data).await,
-		PackageSubcommand::Browse {} => browse(data).await,
+		PackageSubcommand::ListAll { deprecated } => list_all(data, deprecated).await,
+		PackageSubcommand::Browse { deprecated } => browse(data, deprecated).await,
 	}
 }
 
@@ -210,10 +237,14 @@ async fn sync(data: &mut CmdData<'_>, filter: Vec<String>) -> anyhow::Result<()>
 		packages.push((id, contents, content_type));
 	}
 	let errors = Arc::new(Mutex::new(Vec::new()));
+	let custom = PluginContentTypes {
+		plugins: &config.plugins,
+		paths: &data.paths,
+	};
 	packages
 		.into_par_iter()
 		.for_each(|(id, contents, content_type)| {
-			if let Err(e) = parse_and_validate(&contents, content_type) {
+			if let Err(e) = parse_and_validate_custom(&contents, content_type, &custom) {
 				errors.lock().expect("Poisoned mutex").push(cformat!(
 					"<y>Warning: Package '{}' was invalid:\n{:#?}",
 					id,
@@ -235,7 +266,10 @@ async fn cat(data: &mut CmdData<'_>, id: &str, raw: bool) -> anyhow::Result<()>
 
 	let client = Client::new();
 
-	let req = Arc::new(PkgRequest::parse(id, PkgRequestSource::UserRequire));
+	let req = Arc::new(PkgRequest::parse_validated(
+		id,
+		PkgRequestSource::UserRequire,
+	)?);
 	let contents = config
 		.packages
 		.load(&req, &data.paths, &client, data.output)
@@ -261,6 +295,35 @@ async fn cat(data: &mut CmdData<'_>, id: &str, raw: bool) -> anyhow::Result<()>
 	Ok(())
 }
 
+async fn debug(data: &mut CmdData<'_>, id: &str) -> anyhow::Result<()> {
+	data.ensure_config(true).await?;
+	let config = data.config.get_mut();
+
+	let client = Client::new();
+
+	let req = Arc::new(PkgRequest::parse_validated(
+		id,
+		PkgRequestSource::UserRequire,
+	)?);
+	let content_type = config
+		.packages
+		.content_type(&req, &data.paths, &client, data.output)
+		.await?;
+	if !matches!(content_type, PackageContentType::Script) {
+		bail!("Only script packages can be debugged");
+	}
+	let contents = config
+		.packages
+		.load(&req, &data.paths, &client, data.output)
+		.await?;
+
+	let parsed = mcvm::parse::parse::lex_and_parse(&contents).context("Failed to parse package")?;
+	cprintln!("<s,b>Parsed structure of package <g>{}</g>:</s,b>", req);
+	print!("{}", parsed.dump());
+
+	Ok(())
+}
+
 /// Pretty-print a package script
 fn pretty_print_package_script(contents: &str) -> anyhow::Result<()> {
 	let mut lexed = mcvm::parse::lex::lex(contents)?;
@@ -344,7 +407,10 @@ async fn info(data: &mut CmdData<'_>, id: &str, raw: bool) -> anyhow::Result<()>
 
 	let client = Client::new();
 
-	let req = Arc::new(PkgRequest::parse(id, PkgRequestSource::UserRequire));
+	let req = Arc::new(PkgRequest::parse_validated(
+		id,
+		PkgRequestSource::UserRequire,
+	)?);
 	let metadata = config
 		.packages
 		.get_metadata(&req, &data.paths, &client, data.output)
@@ -449,6 +515,7 @@ async fn repo(subcommand: RepoSubcommand, data: &mut CmdData<'_>) -> anyhow::Res
 	match subcommand {
 		RepoSubcommand::List { raw } => repo_list(data, raw).await,
 		RepoSubcommand::Info { repo } => repo_info(data, repo).await,
+		RepoSubcommand::Validate { repo } => repo_validate(data, repo).await,
 	}
 }
 
@@ -534,14 +601,45 @@ async fn repo_info(data: &mut CmdData<'_>, repo_id: String) -> anyhow::Result<()
 	Ok(())
 }
 
-async fn list_all(data: &mut CmdData<'_>) -> anyhow::Result<()> {
+async fn repo_validate(data: &mut CmdData<'_>, repo_id: String) -> anyhow::Result<()> {
+	data.ensure_config(true).await?;
+	let config = data.config.get_mut();
+
+	let repo = config.packages.repos.iter_mut().find(|x| x.id == repo_id);
+	let Some(repo) = repo else {
+		bail!("Repository {repo_id} does not exist");
+	};
+
+	let client = Client::new();
+	let results = validate_repository(repo, &config.plugins, &data.paths, &client, data.output)
+		.await
+		.context("Failed to validate repository")?;
+
+	let total = results.len();
+	let mut failed = 0;
+	for (package, result) in results {
+		if let Err(e) = result {
+			failed += 1;
+			cprintln!("{}<r>{}</>: {:?}", HYPHEN_POINT, package, e);
+		}
+	}
+
+	if failed > 0 {
+		bail!("{failed} of {total} packages are invalid");
+	}
+	cprintln!("<g>All {} packages are valid.", total);
+
+	Ok(())
+}
+
+async fn list_all(data: &mut CmdData<'_>, deprecated: bool) -> anyhow::Result<()> {
 	data.ensure_config(true).await?;
 	let config = data.config.get_mut();
 
 	let client = Client::new();
 	let mut packages = config
 		.packages
-		.get_all_available_packages(&data.paths, &client, data.output)
+		.get_all_available_packages(deprecated, &data.paths, &client, data.output)
 		.await
 		.context("Failed to get list of available packages")?;
 	packages.sort();
@@ -553,14 +651,14 @@ async fn list_all(data: &mut CmdData<'_>) -> anyhow::Result<()> {
 	Ok(())
 }
 
-async fn browse(data: &mut CmdData<'_>) -> anyhow::Result<()> {
+async fn browse(data: &mut CmdData<'_>, deprecated: bool) -> anyhow::Result<()> {
 	data.ensure_config(true).await?;
 	let config = data.config.get_mut();
 
 	let client = Client::new();
 	let mut packages = config
 		.packages
-		.get_all_available_packages(&data.paths, &client, data.output)
+		.get_all_available_packages(deprecated, &data.paths, &client, data.output)
 		.await
 		.context("Failed to get list of available packages")?;
 	packages.sort();
@@ -95,7 +95,7 @@ async fn status(data: &mut CmdData<'_>) -> anyhow::Result<()> {
 
 	match config.users.get_chosen_user() {
 		Some(user) => {
-			let user_valid = user.is_auth_valid(&data.paths.core);
+			let user_valid = user.is_auth_valid(config.users.get_auth_dir(&data.paths.core));
 			if user_valid {
 				cprint!("<g>Logged in as ");
 			} else {
@@ -130,7 +130,7 @@ async fn passkey(data: &mut CmdData<'_>, user: Option<String>) -> anyhow::Result
 		bail!("Specified user does not exist");
 	};
 
-	user.update_passkey(&data.paths.core, data.output)
+	user.update_passkey(config.users.get_auth_dir(&data.paths.core), data.output)
 		.await
 		.context("Failed to update passkey")?;
 
@@ -157,6 +157,7 @@ async fn auth(data: &mut CmdData<'_>, user: Option<String>) -> anyhow::Result<()
 async fn logout(data: &mut CmdData<'_>, user: Option<String>) -> anyhow::Result<()> {
 	data.ensure_config(true).await?;
 	let config = data.config.get_mut();
+	let auth_dir = config.users.get_auth_dir(&data.paths.core).to_owned();
 	let user = if let Some(user) = user {
 		config.users.get_user_mut(&user)
 	} else {
@@ -166,8 +167,7 @@ async fn logout(data: &mut CmdData<'_>, user: Option<String>) -> anyhow::Result<
 		bail!("Specified user does not exist");
 	};
 
-	user.logout(&data.paths.core)
-		.context("Failed to logout user")?;
+	user.logout(&auth_dir).context("Failed to logout user")?;
 
 	Ok(())
 }
@@ -1,6 +1,5 @@
 mod commands;
 mod output;
-mod secrets;
 
 use std::process::ExitCode;
 
@@ -36,6 +36,7 @@ pub struct TerminalOutput {
 	log_file: File,
 	latest_log_file: File,
 	translation_map: Option<TranslationMap>,
+	censor_secrets: bool,
 }
 
 #[async_trait::async_trait]
@@ -45,6 +46,10 @@ impl MCVMOutput for TerminalOutput {
 		self.display_text_impl(text, level);
 	}
 
+	fn should_censor_secrets(&self) -> bool {
+		self.censor_secrets
+	}
+
 	fn display_message(&mut self, message: Message) {
 		let _ = self.log_message(
 			&Self::format_message_log(message.contents.clone()),
@@ -136,9 +141,15 @@ impl TerminalOutput {
 			log_file: file,
 			latest_log_file: latest_file,
 			translation_map: None,
+			censor_secrets: true,
 		})
 	}
 
+	/// Set whether to censor secrets in displayed and logged messages
+	pub fn set_censor_secrets(&mut self, censor_secrets: bool) {
+		self.censor_secrets = censor_secrets;
+	}
+
 	/// Display text
 	fn display_text_impl(&mut self, text: String, level: MessageLevel) {
 		if !level.at_least(&self.level) {
@@ -283,7 +294,9 @@ fn disp_pkg_request_with_colors(req: PkgRequest) -> String {
 		PkgRequestSource::UserRequire => cformat!("<y>{}", req.id),
 		PkgRequestSource::Bundled(..) => cformat!("<b>{}", req.id),
 		PkgRequestSource::Refused(..) => cformat!("<r>{}", req.id),
-		PkgRequestSource::Dependency(..) | PkgRequestSource::Repository => {
+		PkgRequestSource::Dependency(..)
+		| PkgRequestSource::Recommended(..)
+		| PkgRequestSource::Repository => {
 			cformat!("<c>{}", req.id)
 		}
 	}
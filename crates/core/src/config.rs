@@ -1,4 +1,5 @@
 use mcvm_auth::mc::ClientId;
+use mcvm_shared::util::ArchiveType;
 
 use crate::util::secrets::get_ms_client_id;
 
@@ -20,11 +21,17 @@ pub struct Configuration {
 	pub(crate) force_reinstall: bool,
 	/// Whether to allow offline installs
 	pub(crate) allow_offline: bool,
+	/// Whether to skip all network operations and only use cached files
+	pub(crate) offline: bool,
 	/// Whether to censor user credentials in output messages and logs
 	pub(crate) censor_secrets: bool,
 	/// Whether to use file copies instead of hardlinks. Useful if you
 	/// are on a filesystem that doesn't like hardlinks
 	pub(crate) disable_hardlinks: bool,
+	/// The maximum number of bytes per second for downloads. None or zero means unlimited
+	pub(crate) bandwidth_limit: Option<u64>,
+	/// The type of archive to download Java installations as, for sources that provide multiple types
+	pub(crate) java_archive_type: ArchiveType,
 	/// Launcher branding
 	pub(crate) branding: BrandingProperties,
 }
@@ -42,8 +49,11 @@ impl Configuration {
 			ms_client_id: get_ms_client_id(),
 			force_reinstall: false,
 			allow_offline: false,
+			offline: false,
 			censor_secrets: true,
 			disable_hardlinks: false,
+			bandwidth_limit: None,
+			java_archive_type: ArchiveType::preferred(),
 			branding: BrandingProperties::default(),
 		}
 	}
@@ -86,6 +96,12 @@ impl ConfigBuilder {
 
 	builder_method!(allow_offline, bool, "Set whether to allow offline installs");
 
+	builder_method!(
+		offline,
+		bool,
+		"Set whether to skip all network operations and only use cached files"
+	);
+
 	builder_method!(
 		censor_secrets,
 		bool,
@@ -98,6 +114,18 @@ impl ConfigBuilder {
 		"Set whether to disable the use of hardlinks"
 	);
 
+	builder_method!(
+		bandwidth_limit,
+		Option<u64>,
+		"Set the maximum number of bytes per second for downloads"
+	);
+
+	builder_method!(
+		java_archive_type,
+		ArchiveType,
+		"Set the type of archive to download Java installations as, overriding the default for the platform"
+	);
+
 	builder_method!(branding, BrandingProperties, "Set the branding properties");
 }
 
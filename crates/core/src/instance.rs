@@ -1,18 +1,22 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context};
-use mcvm_shared::output::MCVMOutput;
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
+use mcvm_shared::util::ArchiveType;
 use mcvm_shared::Side;
 
 use crate::config::BrandingProperties;
 use crate::io::files::paths::Paths;
 use crate::io::files::update_hardlink;
+use crate::io::java::args::find_unsupported_args;
 use crate::io::java::classpath::Classpath;
 use crate::io::java::install::{JavaInstallParameters, JavaInstallation};
 use crate::io::persistent::PersistentData;
 use crate::io::update::UpdateManager;
-use crate::launch::{LaunchConfiguration, LaunchParameters};
-use crate::net::game_files::client_meta::ClientMeta;
+use crate::launch::{
+	get_last_launch_command, LaunchCommand, LaunchConfiguration, LaunchParameters, PreparedLaunch,
+};
+use crate::net::game_files::client_meta::{get_required_java_version, ClientMeta};
 use crate::net::game_files::version_manifest::VersionManifestAndList;
 use crate::net::game_files::{game_jar, libraries};
 use crate::user::UserManager;
@@ -30,7 +34,6 @@ pub struct Instance<'params> {
 	java: JavaInstallation,
 	jar_path: PathBuf,
 	classpath: Classpath,
-	main_class: String,
 }
 
 impl<'params> Instance<'params> {
@@ -48,17 +51,17 @@ impl<'params> Instance<'params> {
 		}
 
 		// Install Java
-		let java_vers = &params.client_meta.java_info.major_version;
+		let java_vers = get_required_java_version(params.version, params.client_meta);
 		let java_params = JavaInstallParameters {
 			paths: params.paths,
 			update_manager: params.update_manager,
 			persistent: params.persistent,
 			req_client: params.req_client,
+			archive_type: params.java_archive_type,
 		};
-		let java =
-			JavaInstallation::install(config.launch.java.clone(), *java_vers, java_params, o)
-				.await
-				.context("Failed to install or update Java")?;
+		let java = JavaInstallation::install(config.launch.java.clone(), java_vers, java_params, o)
+			.await
+			.context("Failed to install or update Java")?;
 
 		let is_valid = java
 			.verify()
@@ -67,6 +70,18 @@ impl<'params> Instance<'params> {
 			bail!("Java installation is invalid");
 		}
 
+		// Check that the JVM arguments are supported by the Java version
+		let jvm_args = config.launch.generate_jvm_args();
+		for (arg, support) in find_unsupported_args(&jvm_args, java.get_major_version()) {
+			o.display(
+				MessageContents::Warning(format!(
+					"JVM argument '{arg}' requires {support}, but Java {} is being used",
+					java.get_major_version()
+				)),
+				MessageLevel::Important,
+			);
+		}
+
 		params.persistent.dump(params.paths).await?;
 
 		// Get the game jar
@@ -155,16 +170,6 @@ impl<'params> Instance<'params> {
 		}
 		classpath.add_path(&jar_path)?;
 
-		// Main class
-		let main_class = if let Some(main_class) = &config.main_class {
-			main_class.clone()
-		} else {
-			match config.side.get_side() {
-				Side::Client => params.client_meta.main_class.clone(),
-				Side::Server => DEFAULT_SERVER_MAIN_CLASS.into(),
-			}
-		};
-
 		// Server EULA
 		if let InstanceKind::Server { create_eula, .. } = &config.side {
 			if *create_eula {
@@ -183,7 +188,6 @@ impl<'params> Instance<'params> {
 			java,
 			jar_path,
 			classpath,
-			main_class,
 		})
 	}
 
@@ -201,6 +205,18 @@ impl<'params> Instance<'params> {
 		&mut self,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<InstanceHandle> {
+		let prepared = self.prepare_launch(o).await?;
+		prepared
+			.spawn(o)
+			.context("Failed to spawn instance process")
+	}
+
+	/// Prepare everything needed to launch the instance, such as authenticating the user and
+	/// creating the launch command, without starting the game process
+	pub async fn prepare_launch(
+		&mut self,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<PreparedLaunch> {
 		let params = LaunchParameters {
 			version: self.params.version,
 			version_manifest: self.params.version_manifest,
@@ -208,7 +224,7 @@ impl<'params> Instance<'params> {
 			launch_dir: &self.config.path,
 			java: &self.java,
 			classpath: &self.classpath,
-			main_class: &self.main_class,
+			main_class_override: self.config.main_class.as_deref(),
 			launch_config: &self.config.launch,
 			paths: self.params.paths,
 			req_client: self.params.req_client,
@@ -217,20 +233,21 @@ impl<'params> Instance<'params> {
 			censor_secrets: self.params.censor_secrets,
 			branding: self.params.branding,
 		};
-		let handle = crate::launch::launch(params, o)
+		let prepared = crate::launch::prepare(params, o)
 			.await
-			.map_err(|e| {
-				eprintln!("🔥 Core launch failure details: {:#}", e);
-				e
-			})
 			.context("Failed to run launch routine")?;
-		Ok(handle)
+		Ok(prepared)
 	}
 
 	/// Get the JAR path of the instance
 	pub fn get_jar_path(&self) -> &Path {
 		&self.jar_path
 	}
+
+	/// Get the command that was last used to launch the instance, with secrets censored
+	pub fn get_last_launch_command(&self) -> anyhow::Result<Option<LaunchCommand>> {
+		get_last_launch_command(&self.config.path)
+	}
 }
 
 /// Configuration for an instance
@@ -243,7 +260,8 @@ pub struct InstanceConfiguration {
 	pub launch: LaunchConfiguration,
 	/// JAR path override. If this is set, the default JAR file will not be downloaded
 	pub jar_path: Option<PathBuf>,
-	/// Java main class override
+	/// Java main class override, used instead of the main class from the client meta.
+	/// This is set by modloaders
 	pub main_class: Option<String>,
 	/// Additional libraries to add to the classpath.
 	/// These must be absolute paths to Java libraries already installed on the
@@ -380,5 +398,6 @@ pub(crate) struct InstanceParameters<'a> {
 	pub client_assets_and_libs: &'a mut ClientAssetsAndLibraries,
 	pub censor_secrets: bool,
 	pub disable_hardlinks: bool,
+	pub java_archive_type: ArchiveType,
 	pub branding: &'a BrandingProperties,
 }
@@ -1,5 +1,10 @@
+use std::collections::HashSet;
 use std::fmt::Display;
 
+use anyhow::bail;
+
+use super::JavaMajorVersion;
+
 /// An amount of memory, used for Java memory arguments
 #[derive(Debug, Clone, PartialEq)]
 pub enum MemoryNum {
@@ -34,6 +39,16 @@ impl MemoryNum {
 		}
 	}
 
+	/// Converts into the equivalent amount in megabytes, rounding down
+	pub fn to_megabytes(&self) -> u32 {
+		match self {
+			Self::B(n) => *n / 1024 / 1024,
+			Self::Kb(n) => *n / 1024,
+			Self::Mb(n) => *n,
+			Self::Gb(n) => *n * 1024,
+		}
+	}
+
 	/// Averages two amounts of memory
 	pub fn avg(left: Self, right: Self) -> Self {
 		Self::B((left.to_bytes() + right.to_bytes()) / 2)
@@ -75,6 +90,326 @@ impl MemoryArg {
 	}
 }
 
+/// A named preset of tuned JVM arguments (Usually for garbage collector optimization)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArgsPreset {
+	/// No preset
+	#[default]
+	None,
+	/// Aikar's flags
+	Aikars,
+	/// Krusic's flags
+	Krusic,
+	/// Obydux's flags
+	Obydux,
+	/// A basic set of G1GC flags
+	G1gcDefault,
+}
+
+impl ArgsPreset {
+	/// Parse an ArgsPreset from a string
+	pub fn parse(string: &str) -> Option<Self> {
+		match string {
+			"none" => Some(Self::None),
+			"aikars" => Some(Self::Aikars),
+			"krusic" => Some(Self::Krusic),
+			"obydux" => Some(Self::Obydux),
+			"g1gc_default" => Some(Self::G1gcDefault),
+			_ => None,
+		}
+	}
+
+	/// Generate the JVM arguments for this preset. Some presets
+	/// are tuned differently depending on the maximum heap size
+	pub fn generate_args(&self, max_mem: Option<&MemoryNum>) -> Vec<String> {
+		match self {
+			Self::None => vec![],
+			Self::Aikars => {
+				// Aikar recommends different G1 tuning for heaps of 12GB or more
+				let is_large = max_mem.is_some_and(|x| x.to_megabytes() >= 12 * 1024);
+				let (
+					new_size_percent,
+					max_new_size_percent,
+					heap_region_size,
+					reserve_percent,
+					ihop,
+				) = if is_large {
+					("40", "50", "16M", "15", "20")
+				} else {
+					("30", "40", "8M", "20", "15")
+				};
+
+				vec![
+					"-XX:+UseG1GC".to_string(),
+					"-XX:+ParallelRefProcEnabled".to_string(),
+					"-XX:MaxGCPauseMillis=200".to_string(),
+					"-XX:+UnlockExperimentalVMOptions".to_string(),
+					"-XX:+DisableExplicitGC".to_string(),
+					"-XX:+AlwaysPreTouch".to_string(),
+					format!("-XX:G1NewSizePercent={new_size_percent}"),
+					format!("-XX:G1MaxNewSizePercent={max_new_size_percent}"),
+					format!("-XX:G1HeapRegionSize={heap_region_size}"),
+					format!("-XX:G1ReservePercent={reserve_percent}"),
+					"-XX:G1HeapWastePercent=5".to_string(),
+					"-XX:G1MixedGCCountTarget=4".to_string(),
+					format!("-XX:InitiatingHeapOccupancyPercent={ihop}"),
+					"-XX:G1MixedGCLiveThresholdPercent=90".to_string(),
+					"-XX:G1RSetUpdatingPauseTimePercent=5".to_string(),
+					"-XX:SurvivorRatio=32".to_string(),
+					"-XX:+PerfDisableSharedMem".to_string(),
+					"-XX:MaxTenuringThreshold=1".to_string(),
+					"-Dusing.aikars.flags=https://mcflags.emc.gs".to_string(),
+					"-Daikars.new.flags=true".to_string(),
+				]
+			}
+			Self::Krusic => vec![
+				"-XX:+UnlockExperimentalVMOptions".to_string(),
+				"-XX:+DisableExplicitGC".to_string(),
+				"-XX:-UseParallelGC".to_string(),
+				"-XX:-UseG1GC".to_string(),
+				"-XX:+UseZGC".to_string(),
+			],
+			Self::Obydux => vec![
+				"-XX:+UnlockExperimentalVMOptions".to_string(),
+				"-XX:+UnlockDiagnosticVMOptions".to_string(),
+				"-Dterminal.jline=false".to_string(),
+				"-Dterminal.ansi=true".to_string(),
+				"-Djline.terminal=jline.UnsupportedTerminal".to_string(),
+				"-Dlog4j2.formatMsgNoLookups=true".to_string(),
+				"-XX:+AlwaysActAsServerClassMachine".to_string(),
+				"-XX:+AlwaysPreTouch".to_string(),
+				"-XX:+DisableExplicitGC".to_string(),
+				"-XX:+UseNUMA".to_string(),
+				"-XX:AllocatePrefetchStyle=3".to_string(),
+				"-XX:NmethodSweepActivity=1".to_string(),
+				"-XX:ReservedCodeCacheSize=400M".to_string(),
+				"-XX:NonNMethodCodeHeapSize=12M".to_string(),
+				"-XX:ProfiledCodeHeapSize=194M".to_string(),
+				"-XX:NonProfiledCodeHeapSize=194M".to_string(),
+				"-XX:+PerfDisableSharedMem".to_string(),
+				"-XX:+UseFastUnorderedTimeStamps".to_string(),
+				"-XX:+UseCriticalJavaThreadPriority".to_string(),
+				"-XX:+EagerJVMCI".to_string(),
+				"-Dgraal.TuneInlinerExploration=1".to_string(),
+				"-Dgraal.CompilerConfiguration=enterprise".to_string(),
+				"-XX:+UseG1GC".to_string(),
+				"-XX:+ParallelRefProcEnabled".to_string(),
+				"-XX:MaxGCPauseMillis=200".to_string(),
+				"-XX:+UnlockExperimentalVMOptions".to_string(),
+				"-XX:+UnlockDiagnosticVMOptions".to_string(),
+				"-XX:+DisableExplicitGC".to_string(),
+				"-XX:+AlwaysPreTouch".to_string(),
+				"-XX:G1NewSizePercent=30".to_string(),
+				"-XX:G1MaxNewSizePercent=40".to_string(),
+				"-XX:G1HeapRegionSize=8M".to_string(),
+				"-XX:G1ReservePercent=20".to_string(),
+				"-XX:G1HeapWastePercent=5".to_string(),
+				"-XX:G1MixedGCCountTarget=4".to_string(),
+				"-XX:InitiatingHeapOccupancyPercent=15".to_string(),
+				"-XX:G1MixedGCLiveThresholdPercent=90".to_string(),
+				"-XX:G1RSetUpdatingPauseTimePercent=5".to_string(),
+				"-XX:SurvivorRatio=32".to_string(),
+				"-XX:+PerfDisableSharedMem".to_string(),
+				"-XX:MaxTenuringThreshold=1".to_string(),
+				"-XX:-UseBiasedLocking".to_string(),
+				"-XX:+UseStringDeduplication".to_string(),
+				"-XX:+UseFastUnorderedTimeStamps".to_string(),
+				"-XX:+UseAES".to_string(),
+				"-XX:+UseAESIntrinsics".to_string(),
+				"-XX:+UseFMA".to_string(),
+				"-XX:+UseLoopPredicate".to_string(),
+				"-XX:+RangeCheckElimination".to_string(),
+				"-XX:+EliminateLocks".to_string(),
+				"-XX:+DoEscapeAnalysis".to_string(),
+				"-XX:+UseCodeCacheFlushing".to_string(),
+				"-XX:+SegmentedCodeCache".to_string(),
+				"-XX:+UseFastJNIAccessors".to_string(),
+				"-XX:+OptimizeStringConcat".to_string(),
+				"-XX:+UseCompressedOops".to_string(),
+				"-XX:+UseThreadPriorities".to_string(),
+				"-XX:+OmitStackTraceInFastThrow".to_string(),
+				"-XX:+TrustFinalNonStaticFields".to_string(),
+				"-XX:ThreadPriorityPolicy=1".to_string(),
+				"-XX:+UseInlineCaches".to_string(),
+				"-XX:+RewriteBytecodes".to_string(),
+				"-XX:+RewriteFrequentPairs".to_string(),
+				"-XX:+UseNUMA".to_string(),
+				"-XX:-DontCompileHugeMethods".to_string(),
+				"-XX:+UseFPUForSpilling".to_string(),
+				"-XX:+UseVectorCmov".to_string(),
+				"-XX:+UseXMMForArrayCopy".to_string(),
+				"-XX:+UseTransparentHugePages".to_string(),
+				"-XX:+UseLargePages".to_string(),
+				"-Dfile.encoding=UTF-8".to_string(),
+				"-Xlog:async".to_string(),
+				"--add-modules".to_string(),
+				"jdk.incubator.vector".to_string(),
+			],
+			Self::G1gcDefault => vec![
+				"-XX:+UseG1GC".to_string(),
+				"-XX:+ParallelRefProcEnabled".to_string(),
+				"-XX:MaxGCPauseMillis=200".to_string(),
+				"-XX:+DisableExplicitGC".to_string(),
+			],
+		}
+	}
+
+	/// Checks whether this preset chooses a garbage collector
+	pub fn selects_gc(&self) -> bool {
+		self.generate_args(None)
+			.iter()
+			.any(|x| is_gc_selection_arg(x))
+	}
+
+	/// Makes sure that user-supplied JVM arguments do not conflict with this preset
+	pub fn check_conflicts(&self, jvm_args: &[String]) -> anyhow::Result<()> {
+		if !self.selects_gc() {
+			return Ok(());
+		}
+		if let Some(arg) = jvm_args.iter().find(|x| is_gc_selection_arg(x)) {
+			bail!("JVM argument '{arg}' conflicts with the garbage collector chosen by the arguments preset");
+		}
+
+		Ok(())
+	}
+}
+
+/// Checks if a JVM argument enables a specific garbage collector
+pub fn is_gc_selection_arg(arg: &str) -> bool {
+	arg.strip_prefix("-XX:+Use")
+		.is_some_and(|x| x.ends_with("GC"))
+}
+
+/// JVM arguments that take their value as the next argument
+static ARGS_WITH_SEPARATE_VALUE: &[&str] = &[
+	"-cp",
+	"-classpath",
+	"--class-path",
+	"-p",
+	"--module-path",
+	"--upgrade-module-path",
+	"--add-modules",
+	"--add-opens",
+	"--add-exports",
+	"--add-reads",
+];
+
+/// JVM arguments that can only have one value. These are matched by prefix
+static SINGLE_VALUED_ARGS: &[&str] = &["-Xmx", "-Xms", "-Xmn", "-Xss"];
+
+/// Deduplicates JVM arguments and resolves conflicts between them. Identical arguments
+/// are only kept the first time they appear, and when a single-valued argument such as
+/// `-Xmx` is given more than once, the last one is used. Fails if the arguments select
+/// more than one garbage collector
+pub fn normalize_jvm_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<Vec<String>> {
+	// Keep arguments together with their separate values so that they are handled as one
+	let mut groups = Vec::new();
+	let mut args = args.into_iter();
+	while let Some(arg) = args.next() {
+		let mut group = vec![arg];
+		if ARGS_WITH_SEPARATE_VALUE.contains(&group[0].as_str()) {
+			group.extend(args.next());
+		}
+		groups.push(group);
+	}
+
+	// Go backwards so that the last instance of each single-valued argument is kept
+	let mut seen_single_valued = HashSet::new();
+	let mut out: Vec<Vec<String>> = groups
+		.into_iter()
+		.rev()
+		.filter(|group| {
+			match SINGLE_VALUED_ARGS
+				.iter()
+				.find(|prefix| group[0].starts_with(*prefix))
+			{
+				Some(prefix) => seen_single_valued.insert(*prefix),
+				None => true,
+			}
+		})
+		.collect();
+	out.reverse();
+
+	let mut seen = HashSet::new();
+	out.retain(|group| seen.insert(group.clone()));
+
+	let mut gc_args = out
+		.iter()
+		.map(|group| &group[0])
+		.filter(|arg| is_gc_selection_arg(arg));
+	if let (Some(first), Some(second)) = (gc_args.next(), gc_args.next()) {
+		bail!("JVM arguments '{first}' and '{second}' select different garbage collectors");
+	}
+
+	Ok(out.into_iter().flatten().collect())
+}
+
+/// Range of Java major versions that support a JVM argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JavaSupportRange {
+	/// The first Java version to support the argument
+	pub min: Option<u16>,
+	/// The last Java version to support the argument
+	pub max: Option<u16>,
+}
+
+impl JavaSupportRange {
+	/// Checks if a Java version is within this range
+	pub fn contains(&self, version: &JavaMajorVersion) -> bool {
+		!self.min.is_some_and(|min| version.0 < min) && !self.max.is_some_and(|max| version.0 > max)
+	}
+}
+
+impl Display for JavaSupportRange {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match (self.min, self.max) {
+			(Some(min), Some(max)) => write!(f, "Java {min}-{max}"),
+			(Some(min), None) => write!(f, "Java {min}+"),
+			(None, Some(max)) => write!(f, "Java {max} or older"),
+			(None, None) => write!(f, "any Java version"),
+		}
+	}
+}
+
+/// JVM arguments that are only supported by some Java versions. These are matched
+/// by prefix, and the first match is used
+static ARG_JAVA_SUPPORT: &[(&str, Option<u16>, Option<u16>)] = &[
+	("-XX:+UseZGC", Some(15), None),
+	("-XX:+ZGenerational", Some(21), None),
+	("-XX:+UseShenandoahGC", Some(12), None),
+	("-XX:+UseEpsilonGC", Some(11), None),
+	("-XX:+UseConcMarkSweepGC", None, Some(13)),
+	("-XX:+UseParNewGC", None, Some(9)),
+	("-Xlog:async", Some(17), None),
+	("-Xlog:", Some(9), None),
+	("jdk.incubator.vector", Some(16), None),
+];
+
+/// Gets the range of Java versions that support a JVM argument, if it is restricted to some versions
+pub fn get_arg_java_support(arg: &str) -> Option<JavaSupportRange> {
+	ARG_JAVA_SUPPORT
+		.iter()
+		.find(|(prefix, ..)| arg.starts_with(prefix))
+		.map(|(_, min, max)| JavaSupportRange {
+			min: *min,
+			max: *max,
+		})
+}
+
+/// Finds the JVM arguments that are not supported by a Java version,
+/// along with the versions that do support them
+pub fn find_unsupported_args<'a>(
+	args: &'a [String],
+	java_version: &JavaMajorVersion,
+) -> Vec<(&'a str, JavaSupportRange)> {
+	args.iter()
+		.filter_map(|arg| {
+			let support = get_arg_java_support(arg)?;
+			(!support.contains(java_version)).then_some((arg.as_str(), support))
+		})
+		.collect()
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -100,4 +435,129 @@ mod tests {
 			"-Xms128".to_string()
 		);
 	}
+
+	#[test]
+	fn test_args_preset_expansion() {
+		assert!(ArgsPreset::None.generate_args(None).is_empty());
+		assert_eq!(
+			ArgsPreset::Krusic.generate_args(None),
+			vec![
+				"-XX:+UnlockExperimentalVMOptions".to_string(),
+				"-XX:+DisableExplicitGC".to_string(),
+				"-XX:-UseParallelGC".to_string(),
+				"-XX:-UseG1GC".to_string(),
+				"-XX:+UseZGC".to_string(),
+			]
+		);
+		assert_eq!(
+			ArgsPreset::G1gcDefault.generate_args(None),
+			vec![
+				"-XX:+UseG1GC".to_string(),
+				"-XX:+ParallelRefProcEnabled".to_string(),
+				"-XX:MaxGCPauseMillis=200".to_string(),
+				"-XX:+DisableExplicitGC".to_string(),
+			]
+		);
+		let obydux = ArgsPreset::Obydux.generate_args(None);
+		assert!(obydux.contains(&"-XX:+UseG1GC".to_string()));
+		assert!(obydux.contains(&"-XX:ReservedCodeCacheSize=400M".to_string()));
+		assert_eq!(obydux.last(), Some(&"jdk.incubator.vector".to_string()));
+		let aikars = ArgsPreset::Aikars.generate_args(None);
+		assert_eq!(aikars.len(), 20);
+		assert_eq!(aikars[0], "-XX:+UseG1GC");
+	}
+
+	#[test]
+	fn test_aikars_memory_scaling() {
+		let small = ArgsPreset::Aikars.generate_args(Some(&MemoryNum::Gb(4)));
+		assert!(small.contains(&"-XX:G1NewSizePercent=30".to_string()));
+		assert!(small.contains(&"-XX:G1HeapRegionSize=8M".to_string()));
+		assert!(small.contains(&"-XX:InitiatingHeapOccupancyPercent=15".to_string()));
+		assert_eq!(small, ArgsPreset::Aikars.generate_args(None));
+
+		let large = ArgsPreset::Aikars.generate_args(Some(&MemoryNum::Mb(16384)));
+		assert!(large.contains(&"-XX:G1NewSizePercent=40".to_string()));
+		assert!(large.contains(&"-XX:G1MaxNewSizePercent=50".to_string()));
+		assert!(large.contains(&"-XX:G1HeapRegionSize=16M".to_string()));
+		assert!(large.contains(&"-XX:G1ReservePercent=15".to_string()));
+		assert!(large.contains(&"-XX:InitiatingHeapOccupancyPercent=20".to_string()));
+	}
+
+	#[test]
+	fn test_args_preset_conflicts() {
+		let manual = vec!["-XX:+UseZGC".to_string()];
+		assert!(ArgsPreset::Aikars.check_conflicts(&manual).is_err());
+		assert!(ArgsPreset::None.check_conflicts(&manual).is_ok());
+		assert!(ArgsPreset::Aikars
+			.check_conflicts(&["-Dfoo=bar".to_string()])
+			.is_ok());
+		assert!(!is_gc_selection_arg("-XX:-UseG1GC"));
+		assert!(is_gc_selection_arg("-XX:+UseShenandoahGC"));
+	}
+
+	#[test]
+	fn test_normalize_duplicate_memory_args() {
+		let args = vec![
+			"-Xmx2g".to_string(),
+			"-Dfoo=bar".to_string(),
+			"-Xms1g".to_string(),
+			"-Dfoo=bar".to_string(),
+			"-Xmx4g".to_string(),
+			"--add-modules".to_string(),
+			"jdk.incubator.vector".to_string(),
+			"--add-modules".to_string(),
+			"java.se".to_string(),
+			"-Xms512m".to_string(),
+		];
+		assert_eq!(
+			normalize_jvm_args(args).unwrap(),
+			vec![
+				"-Dfoo=bar".to_string(),
+				"-Xmx4g".to_string(),
+				"--add-modules".to_string(),
+				"jdk.incubator.vector".to_string(),
+				"--add-modules".to_string(),
+				"java.se".to_string(),
+				"-Xms512m".to_string(),
+			]
+		);
+	}
+
+	#[test]
+	fn test_normalize_gc_conflicts() {
+		let args = vec!["-XX:+UseG1GC".to_string(), "-XX:+UseZGC".to_string()];
+		assert!(normalize_jvm_args(args).is_err());
+
+		let mut args = ArgsPreset::Obydux.generate_args(None);
+		args.push("-XX:+UseG1GC".to_string());
+		let normalized = normalize_jvm_args(args).unwrap();
+		assert_eq!(
+			normalized.iter().filter(|x| *x == "-XX:+UseG1GC").count(),
+			1
+		);
+
+		let args = ArgsPreset::Krusic.generate_args(None);
+		assert!(normalize_jvm_args(args).is_ok());
+	}
+
+	#[test]
+	fn test_arg_java_support() {
+		let args = vec!["-XX:+UseZGC".to_string(), "-Dfoo=bar".to_string()];
+		let unsupported = find_unsupported_args(&args, &JavaMajorVersion(8));
+		assert_eq!(
+			unsupported,
+			vec![(
+				"-XX:+UseZGC",
+				JavaSupportRange {
+					min: Some(15),
+					max: None
+				}
+			)]
+		);
+		assert!(find_unsupported_args(&args, &JavaMajorVersion(17)).is_empty());
+
+		let args = vec!["-XX:+UseConcMarkSweepGC".to_string()];
+		assert!(find_unsupported_args(&args, &JavaMajorVersion(8)).is_empty());
+		assert_eq!(find_unsupported_args(&args, &JavaMajorVersion(17)).len(), 1);
+	}
 }
@@ -44,6 +44,17 @@ impl Classpath {
 		self.string.push_str(string);
 	}
 
+	/// Appends a string to the end of the classpath if it is not already an entry in it.
+	/// Returns whether the entry was added
+	pub fn add_unique(&mut self, string: &str) -> bool {
+		if self.string.split(CLASSPATH_SEP).any(|x| x == string) {
+			return false;
+		}
+		self.add(string);
+
+		true
+	}
+
 	/// Converts a path to a string and appends it to the classpath
 	pub fn add_path(&mut self, path: &Path) -> anyhow::Result<()> {
 		self.add(
@@ -105,4 +116,14 @@ mod tests {
 			format!("foo{0}bar{0}baz{0}hello{0}world", CLASSPATH_SEP)
 		);
 	}
+
+	#[test]
+	fn test_classpath_add_unique() {
+		let mut classpath = Classpath::new();
+		assert!(classpath.add_unique("foo"));
+		assert!(classpath.add_unique("bar"));
+		assert!(!classpath.add_unique("foo"));
+		assert!(!classpath.add_unique("bar"));
+		assert_eq!(classpath.get_str(), format!("foo{CLASSPATH_SEP}bar"));
+	}
 }
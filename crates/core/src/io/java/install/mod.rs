@@ -18,7 +18,7 @@ use crate::io::files::{self, paths::Paths};
 use crate::io::persistent::{PersistentData, PersistentDataJavaInstallation};
 use crate::io::update::UpdateManager;
 use crate::net::{self, download};
-use mcvm_shared::util::preferred_archive_extension;
+use mcvm_shared::util::ArchiveType;
 
 use super::JavaMajorVersion;
 
@@ -160,6 +160,7 @@ pub(crate) struct JavaInstallParameters<'a> {
 	pub update_manager: &'a mut UpdateManager,
 	pub persistent: &'a mut PersistentData,
 	pub req_client: &'a reqwest::Client,
+	pub archive_type: ArchiveType,
 }
 
 async fn install_auto(
@@ -191,7 +192,7 @@ async fn install_adoptium(
 	params: &mut JavaInstallParameters<'_>,
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<PathBuf> {
-	if params.update_manager.allow_offline {
+	if params.update_manager.allow_offline() {
 		if let Some(directory) = params
 			.persistent
 			.get_java_path(PersistentDataJavaInstallation::Adoptium, major_version)
@@ -214,7 +215,7 @@ async fn install_zulu(
 	params: &mut JavaInstallParameters<'_>,
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<PathBuf> {
-	if params.update_manager.allow_offline {
+	if params.update_manager.allow_offline() {
 		if let Some(directory) = params
 			.persistent
 			.get_java_path(PersistentDataJavaInstallation::Zulu, major_version)
@@ -237,7 +238,7 @@ async fn install_graalvm(
 	params: &mut JavaInstallParameters<'_>,
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<PathBuf> {
-	if params.update_manager.allow_offline {
+	if params.update_manager.allow_offline() {
 		if let Some(directory) = params
 			.persistent
 			.get_java_path(PersistentDataJavaInstallation::GraalVM, major_version)
@@ -261,6 +262,9 @@ async fn update_adoptium(
 	params: &mut JavaInstallParameters<'_>,
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<PathBuf> {
+	params
+		.update_manager
+		.ensure_online(&format!("Adoptium Java {major_version}"))?;
 	let out_dir = params.paths.java.join("adoptium");
 	files::create_dir(&out_dir)?;
 	let version = net::java::adoptium::get_latest(major_version, params.req_client)
@@ -287,12 +291,13 @@ async fn update_adoptium(
 
 	params.persistent.dump(params.paths).await?;
 
-	let arc_extension = preferred_archive_extension();
+	let bin_url = version.binary.package.link;
+	// Adoptium only provides one type of archive for each platform
+	let archive_type = ArchiveType::from_file_name(&bin_url).unwrap_or_default();
+	let arc_extension = archive_type.extension();
 	let arc_name = format!("adoptium{major_version}{arc_extension}");
 	let arc_path = out_dir.join(arc_name);
 
-	let bin_url = version.binary.package.link;
-
 	o.display(
 		MessageContents::StartProcess(translate!(
 			o,
@@ -310,7 +315,7 @@ async fn update_adoptium(
 		MessageContents::StartProcess(translate!(o, StartExtractingJava)),
 		MessageLevel::Important,
 	);
-	extract_archive_file(&arc_path, &out_dir).context("Failed to extract")?;
+	extract_archive_file(&arc_path, &out_dir, archive_type).context("Failed to extract")?;
 	o.display(
 		MessageContents::StartProcess(translate!(o, StartRemovingJavaArchive)),
 		MessageLevel::Important,
@@ -331,14 +336,21 @@ async fn update_zulu(
 	params: &mut JavaInstallParameters<'_>,
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<PathBuf> {
+	params
+		.update_manager
+		.ensure_online(&format!("Zulu Java {major_version}"))?;
 	let out_dir = params.paths.java.join("zulu");
 	files::create_dir(&out_dir)?;
 
-	let package = net::java::zulu::get_latest(major_version, params.req_client)
-		.await
-		.context("Failed to get the latest Zulu version")?;
+	let package =
+		net::java::zulu::get_latest(major_version, params.archive_type, params.req_client)
+			.await
+			.context("Failed to get the latest Zulu version")?;
 
-	let extracted_dir = out_dir.join(net::java::zulu::extract_dir_name(&package.name));
+	let extracted_dir = out_dir.join(net::java::zulu::extract_dir_name(
+		&package.name,
+		params.archive_type,
+	));
 
 	if !params
 		.persistent
@@ -370,7 +382,7 @@ async fn update_zulu(
 		MessageContents::StartProcess(translate!(o, StartExtractingJava)),
 		MessageLevel::Important,
 	);
-	extract_archive_file(&arc_path, &out_dir).context("Failed to extract")?;
+	extract_archive_file(&arc_path, &out_dir, params.archive_type).context("Failed to extract")?;
 	o.display(
 		MessageContents::StartProcess(translate!(o, StartRemovingJavaArchive)),
 		MessageLevel::Important,
@@ -391,6 +403,9 @@ async fn update_graalvm(
 	params: &mut JavaInstallParameters<'_>,
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<PathBuf> {
+	params
+		.update_manager
+		.ensure_online(&format!("GraalVM Java {major_version}"))?;
 	let out_dir = params.paths.java.join("graalvm");
 	files::create_dir(&out_dir)?;
 
@@ -398,16 +413,17 @@ async fn update_graalvm(
 		MessageContents::StartProcess(translate!(o, DownloadingGraalVM)),
 		MessageLevel::Important,
 	);
-	let archive = net::java::graalvm::get_latest(major_version, params.req_client)
-		.await
-		.context("Failed to download the latest GraalVM version")?;
+	let archive =
+		net::java::graalvm::get_latest(major_version, params.archive_type, params.req_client)
+			.await
+			.context("Failed to download the latest GraalVM version")?;
 
 	// We have to extract now since we need the extracted dir
 	o.display(
 		MessageContents::StartProcess(translate!(o, StartExtractingJava)),
 		MessageLevel::Important,
 	);
-	let dir_name = extract_archive(std::io::Cursor::new(archive), &out_dir)
+	let dir_name = extract_archive(std::io::Cursor::new(archive), &out_dir, params.archive_type)
 		.context("Failed to extract GraalVM archive")?;
 
 	let extracted_dir = out_dir.join(&dir_name);
@@ -438,18 +454,26 @@ async fn update_graalvm(
 }
 
 /// Extracts the archive file
-fn extract_archive_file(arc_path: &Path, out_dir: &Path) -> anyhow::Result<()> {
+fn extract_archive_file(
+	arc_path: &Path,
+	out_dir: &Path,
+	archive_type: ArchiveType,
+) -> anyhow::Result<()> {
 	let file = File::open(arc_path).context("Failed to read archive file")?;
 	let file = BufReader::new(file);
 
-	extract_archive(file, out_dir)?;
+	extract_archive(file, out_dir, archive_type)?;
 
 	Ok(())
 }
 
 /// Extracts the JRE archive (either a tar or a zip) and also returns the internal extraction directory name
-fn extract_archive<R: Read + Seek>(reader: R, out_dir: &Path) -> anyhow::Result<String> {
-	let dir_name = if cfg!(windows) {
+fn extract_archive<R: Read + Seek>(
+	reader: R,
+	out_dir: &Path,
+	archive_type: ArchiveType,
+) -> anyhow::Result<String> {
+	let dir_name = if let ArchiveType::Zip = archive_type {
 		let mut archive = ZipArchive::new(reader).context("Failed to open zip archive")?;
 
 		let dir_name = archive
@@ -1,6 +1,10 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
+use anyhow::bail;
+
+use crate::net::download::BandwidthLimiter;
+
 /// Manager for when we are updating profile files.
 /// It will keep track of files we have already downloaded, manage task requirements, etc
 #[derive(Debug)]
@@ -9,8 +13,12 @@ pub struct UpdateManager {
 	pub(crate) force: bool,
 	/// Whether we will prioritize local files instead of remote ones
 	pub(crate) allow_offline: bool,
+	/// Whether all network operations are skipped and only cached files are used
+	offline: bool,
 	/// File paths that are added when they have been updated by other functions
 	files: HashSet<PathBuf>,
+	/// Limiter for the rate of downloads, shared between all of them
+	bandwidth_limiter: Option<BandwidthLimiter>,
 }
 
 impl UpdateManager {
@@ -19,10 +27,28 @@ impl UpdateManager {
 		Self {
 			force,
 			allow_offline,
+			offline: false,
 			files: HashSet::new(),
+			bandwidth_limiter: None,
 		}
 	}
 
+	/// Set whether to skip all network operations and only use cached files
+	pub fn set_offline(&mut self, offline: bool) {
+		self.offline = offline;
+	}
+
+	/// Set the maximum number of bytes per second for downloads.
+	/// None or zero means that downloads are unlimited
+	pub fn set_bandwidth_limit(&mut self, bytes_per_sec: Option<u64>) {
+		self.bandwidth_limiter = bytes_per_sec.and_then(BandwidthLimiter::new);
+	}
+
+	/// Gets the limiter for the rate of downloads, if there is a limit
+	pub fn get_bandwidth_limiter(&self) -> Option<&BandwidthLimiter> {
+		self.bandwidth_limiter.as_ref()
+	}
+
 	/// Add a single tracked file to the manager
 	pub fn add_file(&mut self, file: PathBuf) {
 		self.files.insert(file);
@@ -50,7 +76,22 @@ impl UpdateManager {
 	/// Gets whether the manager allows being offline and not checking for
 	/// file updates
 	pub fn allow_offline(&self) -> bool {
-		self.allow_offline
+		self.allow_offline || self.offline
+	}
+
+	/// Gets whether the manager is in offline mode, where all network operations
+	/// are skipped and only cached files are used
+	pub fn is_offline(&self) -> bool {
+		self.offline
+	}
+
+	/// Fails with an error if the manager is in offline mode. Should be called
+	/// before any network operation, with a description of what is being downloaded
+	pub fn ensure_online(&self, what: &str) -> anyhow::Result<()> {
+		if self.offline {
+			bail!("Offline mode: {what} is not cached");
+		}
+		Ok(())
 	}
 
 	/// Gets whether the manager forces the reinstallation of files
@@ -8,7 +8,7 @@ use crate::launch::{LaunchParameters, QuickPlayType};
 use crate::io::files::paths::Paths;
 use crate::net::game_files::assets::get_virtual_dir_path;
 use crate::net::game_files::client_meta::args::ArgumentItem;
-use crate::user::UserKind;
+use crate::user::{User, UserKind};
 
 /// Process an argument for the client from the client meta
 pub(crate) fn process_arg(arg: &ArgumentItem, params: &LaunchParameters) -> Vec<String> {
@@ -163,70 +163,75 @@ pub(crate) fn replace_arg_placeholders(arg: &str, params: &LaunchParameters) ->
 	}
 
 	// QuickPlayType
-	out = out.replace(placeholder!("quickPlayPath"), "quickPlay/log.json");
-	let quickplay_singleplayer = if let QuickPlayType::World { world } = &params.launch_config.quick_play {
-		world.as_str()
-	} else {
-		""
-	};
-	let quickplay_multiplayer = if let QuickPlayType::Server { server, port } = &params.launch_config.quick_play {
-		if let Some(port) = port {
-			format!("{server}:{port}")
+	// Resolve against the game directory so that the log isn't put in an overridden working directory
+	out = out.replace(
+		placeholder!("quickPlayPath"),
+		params.launch_dir.join("quickPlay/log.json").to_str()?,
+	);
+	let quickplay_singleplayer =
+		if let QuickPlayType::World { world } = &params.launch_config.quick_play {
+			world.as_str()
 		} else {
-			server.clone()
-		}
-	} else {
-		String::new()
-	};
-	let quickplay_realms = if let QuickPlayType::Realm { realm } = &params.launch_config.quick_play {
+			""
+		};
+	let quickplay_multiplayer =
+		if let QuickPlayType::Server { server, port } = &params.launch_config.quick_play {
+			if let Some(port) = port {
+				format!("{server}:{port}")
+			} else {
+				server.clone()
+			}
+		} else {
+			String::new()
+		};
+	let quickplay_realms = if let QuickPlayType::Realm { realm } = &params.launch_config.quick_play
+	{
 		realm.as_str()
 	} else {
 		""
 	};
-	
-	out = out.replace(placeholder!("quickPlaySingleplayer"), quickplay_singleplayer);
+
+	out = out.replace(
+		placeholder!("quickPlaySingleplayer"),
+		quickplay_singleplayer,
+	);
 	out = out.replace(placeholder!("quickPlayMultiplayer"), &quickplay_multiplayer);
 	out = out.replace(placeholder!("quickPlayRealms"), quickplay_realms);
-	
+
 	// If this argument contains quick play placeholders and all are empty, skip it entirely
 	// This prevents "Only one quick play option can be specified" error in modern Minecraft (1.20+)
 	// Only apply this fix to versions that actually enforce the single quick play restriction
 	let needs_quickplay_fix = {
 		if let (Some(current), Some(min)) = (
 			version_compare::Version::from(params.version),
-			version_compare::Version::from("1.20")
+			version_compare::Version::from("1.20"),
 		) {
-			let result = current >= min;
-			// Debug output to understand what's happening
-			eprintln!("🐛 QUICKPLAY DEBUG: version={}, needs_fix={}", params.version, result);
-			result
+			current >= min
 		} else {
-			// If version parsing fails, assume we need the fix for safety
-			eprintln!("🐛 QUICKPLAY DEBUG: version parsing failed for {}", params.version);
 			false
 		}
 	};
-	
+
 	if needs_quickplay_fix {
 		// Skip placeholder arguments when all quick play values are empty
-		if (arg.contains(placeholder!("quickPlaySingleplayer")) || 
-		    arg.contains(placeholder!("quickPlayMultiplayer")) || 
-		    arg.contains(placeholder!("quickPlayRealms"))) &&
-		   quickplay_singleplayer.is_empty() && 
-		   quickplay_multiplayer.is_empty() && 
-		   quickplay_realms.is_empty() {
-			eprintln!("🐛 QUICKPLAY DEBUG: Skipping empty quick play placeholder argument: {}", arg);
+		if (arg.contains(placeholder!("quickPlaySingleplayer"))
+			|| arg.contains(placeholder!("quickPlayMultiplayer"))
+			|| arg.contains(placeholder!("quickPlayRealms")))
+			&& quickplay_singleplayer.is_empty()
+			&& quickplay_multiplayer.is_empty()
+			&& quickplay_realms.is_empty()
+		{
 			return None;
 		}
-		
+
 		// Also skip final form arguments when they would be empty
-		if (arg.starts_with("--quickPlaySingleplayer") || 
-		    arg.starts_with("--quickPlayMultiplayer") || 
-		    arg.starts_with("--quickPlayRealms")) &&
-		   quickplay_singleplayer.is_empty() && 
-		   quickplay_multiplayer.is_empty() && 
-		   quickplay_realms.is_empty() {
-			eprintln!("🐛 QUICKPLAY DEBUG: Skipping empty quick play final argument: {}", arg);
+		if (arg.starts_with("--quickPlaySingleplayer")
+			|| arg.starts_with("--quickPlayMultiplayer")
+			|| arg.starts_with("--quickPlayRealms"))
+			&& quickplay_singleplayer.is_empty()
+			&& quickplay_multiplayer.is_empty()
+			&& quickplay_realms.is_empty()
+		{
 			return None;
 		}
 	}
@@ -343,8 +348,86 @@ pub fn create_quick_play_args(
 	out
 }
 
+/// Checks whether the game arguments from the client meta use the legacy format of versions
+/// before 1.6, which starts with the username and session as positional arguments
+pub(crate) fn is_legacy_arg_format(args: &str) -> bool {
+	args.split(' ')
+		.next()
+		.is_some_and(|x| !x.is_empty() && !x.starts_with('-'))
+}
+
+/// Create the game arguments for versions before 1.6. The username and session are passed
+/// positionally, and any flags after them are filled in like modern arguments
+pub(crate) fn create_legacy_game_args(args: &str, params: &LaunchParameters) -> Vec<String> {
+	let user = params.users.get_chosen_user();
+	assemble_legacy_args(
+		args,
+		user.and_then(|x| x.get_name())
+			.map(String::as_str)
+			.unwrap_or("UnknownUser"),
+		&get_legacy_session_id(user),
+		|arg| replace_arg_placeholders(arg, params),
+	)
+}
+
+/// Assemble legacy arguments from the username, session, and the flags in the meta arguments
+fn assemble_legacy_args(
+	args: &str,
+	username: &str,
+	session: &str,
+	mut replace: impl FnMut(&str) -> Option<String>,
+) -> Vec<String> {
+	let mut out = vec![username.to_string(), session.to_string()];
+	let flags = args
+		.split(' ')
+		.filter(|x| !x.is_empty())
+		.skip_while(|x| !x.starts_with('-'));
+	out.extend(flags.filter_map(&mut replace));
+
+	out
+}
+
+/// Get the session ID that legacy versions expect. Users without an
+/// access token get the placeholder that the game treats as offline
+fn get_legacy_session_id(user: Option<&User>) -> String {
+	let Some(user) = user else {
+		return "-".into();
+	};
+	match (user.get_access_token(), user.get_uuid()) {
+		(Some(token), Some(uuid)) => format!("token:{}:{uuid}", token.0),
+		_ => "-".into(),
+	}
+}
+
 /// Fill the logging path argument with the correct path
 pub fn fill_logging_path_arg(arg: String, version: &str, paths: &Paths) -> Option<String> {
 	let path = crate::net::game_files::log_config::get_path(version, paths);
 	Some(arg.replace(placeholder!("path"), path.to_str()?))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_legacy_args() {
+		let args = "${auth_player_name} ${auth_session} --gameDir ${game_directory} --assetsDir ${game_assets}";
+		assert!(is_legacy_arg_format(args));
+		assert!(!is_legacy_arg_format(
+			"--username ${auth_player_name} --session ${auth_session}"
+		));
+
+		let user = User::new(UserKind::Demo, "demo".into());
+		let session = get_legacy_session_id(Some(&user));
+		let out = assemble_legacy_args(args, "Steve", &session, |arg| {
+			Some(
+				arg.replace("${game_directory}", "game")
+					.replace("${game_assets}", "assets"),
+			)
+		});
+		assert_eq!(
+			out,
+			vec!["Steve", "-", "--gameDir", "game", "--assetsDir", "assets"]
+		);
+	}
+}
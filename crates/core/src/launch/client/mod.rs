@@ -5,15 +5,18 @@ use anyhow::{anyhow, bail, Context};
 
 use std::collections::HashMap;
 
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
 #[cfg(target_os = "linux")]
 use mcvm_shared::versions::VersionPattern;
-use mcvm_shared::{output::MCVMOutput, skip_none};
+use mcvm_shared::{skip_none, translate};
 
 pub use args::create_quick_play_args;
 
 use crate::net::game_files::client_meta::args::Arguments;
+use crate::net::minecraft::{get_user_profile, set_active_skin, CosmeticState, SkinVariant};
+use crate::user::UserManager;
 
-use super::{process::LaunchProcessProperties, LaunchParameters};
+use super::{process::LaunchProcessProperties, LaunchConfiguration, LaunchParameters};
 
 /// Create launch properties for the client
 pub(crate) async fn get_launch_props(
@@ -32,6 +35,21 @@ pub(crate) async fn get_launch_props(
 		.await
 		.context("Failed to authenticate user")?;
 
+	warn_demo_user(params.users, params.launch_config, o);
+
+	if let Some(variant) = params.launch_config.preferred_skin_variant {
+		if let Err(e) = select_skin_variant(variant, params, o).await {
+			o.display(
+				MessageContents::Warning(translate!(
+					o,
+					SkinChangeFailed,
+					"error" = &format!("{e:#}")
+				)),
+				MessageLevel::Important,
+			);
+		}
+	}
+
 	// Build up arguments
 	let mut jvm_args = Vec::new();
 	let mut game_args = Vec::new();
@@ -72,8 +90,12 @@ pub(crate) async fn get_launch_props(
 			jvm_args.push("-cp".into());
 			jvm_args.push(params.classpath.get_str());
 
-			for arg in args.split(' ') {
-				game_args.push(skip_none!(args::replace_arg_placeholders(arg, params)));
+			if args::is_legacy_arg_format(args) {
+				game_args.extend(args::create_legacy_game_args(args, params));
+			} else {
+				for arg in args.split(' ') {
+					game_args.push(skip_none!(args::replace_arg_placeholders(arg, params)));
+				}
 			}
 		}
 	}
@@ -89,8 +111,63 @@ pub(crate) async fn get_launch_props(
 	Ok(props)
 }
 
+/// Warn that the session is time-limited if the chosen user is a demo user
+fn warn_demo_user(
+	users: &UserManager,
+	launch_config: &LaunchConfiguration,
+	o: &mut impl MCVMOutput,
+) {
+	if launch_config.suppress_demo_warning {
+		return;
+	}
+	if users.get_chosen_user().is_some_and(|x| x.is_demo()) {
+		o.display(
+			MessageContents::Warning(translate!(o, DemoTimeLimitWarning)),
+			MessageLevel::Important,
+		);
+	}
+}
+
+/// Switch the active skin of the chosen user to an owned skin with a variant.
+/// Only Microsoft users have skins
+async fn select_skin_variant(
+	variant: SkinVariant,
+	params: &LaunchParameters<'_>,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<()> {
+	let Some(user) = params.users.get_chosen_user() else {
+		return Ok(());
+	};
+	if !user.is_microsoft() {
+		return Ok(());
+	}
+	let Some(access_token) = user.get_access_token() else {
+		return Ok(());
+	};
+
+	let profile = get_user_profile(&access_token.0, params.req_client)
+		.await
+		.context("Failed to get user profile")?;
+	let Some(skin) = profile.select_skin(variant) else {
+		o.display(
+			MessageContents::Warning(translate!(
+				o,
+				SkinVariantUnavailable,
+				"variant" = variant.to_api_str()
+			)),
+			MessageLevel::Important,
+		);
+		return Ok(());
+	};
+	if skin.cosmetic.state != CosmeticState::Active {
+		set_active_skin(&access_token.0, skin, params.req_client).await?;
+	}
+
+	Ok(())
+}
+
 /// Get additional environment variables for the client
-fn get_additional_environment_variables(
+pub(super) fn get_additional_environment_variables(
 	version: &str,
 	version_list: &[String],
 ) -> HashMap<String, String> {
@@ -115,3 +192,32 @@ fn get_additional_environment_variables(
 
 	env_vars
 }
+
+#[cfg(test)]
+mod tests {
+	use mcvm_auth::mc::ClientId;
+	use mcvm_shared::output::Recording;
+
+	use crate::user::{User, UserKind};
+
+	use super::*;
+
+	#[test]
+	fn test_demo_user_warning() {
+		let mut users = UserManager::new(ClientId::new(String::new()));
+		users.add_user(User::new(UserKind::Demo, "demo".into()));
+		users.choose_user("demo").unwrap();
+
+		let mut o = Recording::default();
+		warn_demo_user(&users, &LaunchConfiguration::new(), &mut o);
+		assert_eq!(o.messages.len(), 1);
+		assert!(o.messages[0].contains("limited time"));
+
+		let config = LaunchConfiguration::builder()
+			.suppress_demo_warning(true)
+			.build();
+		let mut o = Recording::default();
+		warn_demo_user(&users, &config, &mut o);
+		assert!(o.messages.is_empty());
+	}
+}
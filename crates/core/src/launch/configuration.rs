@@ -1,7 +1,11 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
-use crate::io::java::args::MemoryNum;
+use crate::io::java::args::{ArgsPreset, MemoryNum};
 use crate::io::java::install::JavaInstallationKind;
+use crate::net::minecraft::SkinVariant;
+
+use super::LogCaptureConfig;
 
 /// Options for launching an instance
 #[derive(Debug)]
@@ -10,6 +14,8 @@ pub struct LaunchConfiguration {
 	pub java: JavaInstallationKind,
 	/// JVM arguments
 	pub jvm_args: Vec<String>,
+	/// Preset for JVM arguments
+	pub preset: ArgsPreset,
 	/// Game arguments
 	pub game_args: Vec<String>,
 	/// Minimum JVM memory
@@ -24,6 +30,17 @@ pub struct LaunchConfiguration {
 	pub quick_play: QuickPlayType,
 	/// Whether or not to use the Log4J configuration
 	pub use_log4j_config: bool,
+	/// A directory to run the game process in instead of the instance directory.
+	/// Paths in arguments like the game directory still point to the instance directory
+	pub cwd_override: Option<PathBuf>,
+	/// Settings for capturing the output of the game to log files in the instance directory.
+	/// Output is not captured if this is not set
+	pub log_capture: Option<LogCaptureConfig>,
+	/// Whether to suppress the warning that demo users can only play for a limited time
+	pub suppress_demo_warning: bool,
+	/// A skin variant to switch the active skin of the user to before launching.
+	/// One of the skins that the user owns is used
+	pub preferred_skin_variant: Option<SkinVariant>,
 }
 
 impl LaunchConfiguration {
@@ -32,6 +49,7 @@ impl LaunchConfiguration {
 		Self {
 			java: JavaInstallationKind::Auto,
 			jvm_args: Vec::new(),
+			preset: ArgsPreset::None,
 			game_args: Vec::new(),
 			min_mem: None,
 			max_mem: None,
@@ -39,6 +57,10 @@ impl LaunchConfiguration {
 			wrappers: Vec::new(),
 			quick_play: QuickPlayType::None,
 			use_log4j_config: false,
+			cwd_override: None,
+			log_capture: None,
+			suppress_demo_warning: false,
+			preferred_skin_variant: None,
 		}
 	}
 
@@ -84,6 +106,12 @@ impl LaunchConfigBuilder {
 		self
 	}
 
+	/// Set the preset for JVM arguments to use
+	pub fn preset(mut self, preset: ArgsPreset) -> Self {
+		self.config.preset = preset;
+		self
+	}
+
 	/// Set additional game arguments to use
 	pub fn game_args(mut self, game_args: Vec<String>) -> Self {
 		self.config.game_args = game_args;
@@ -125,6 +153,30 @@ impl LaunchConfigBuilder {
 		self.config.use_log4j_config = use_log4j_config;
 		self
 	}
+
+	/// Set a directory to run the game process in instead of the instance directory
+	pub fn cwd_override(mut self, cwd_override: PathBuf) -> Self {
+		self.config.cwd_override = Some(cwd_override);
+		self
+	}
+
+	/// Capture the output of the game to rotating log files in the instance directory
+	pub fn log_capture(mut self, log_capture: LogCaptureConfig) -> Self {
+		self.config.log_capture = Some(log_capture);
+		self
+	}
+
+	/// Set whether to suppress the warning that demo users can only play for a limited time
+	pub fn suppress_demo_warning(mut self, suppress_demo_warning: bool) -> Self {
+		self.config.suppress_demo_warning = suppress_demo_warning;
+		self
+	}
+
+	/// Set a skin variant to switch the active skin of the user to before launching
+	pub fn preferred_skin_variant(mut self, variant: SkinVariant) -> Self {
+		self.config.preferred_skin_variant = Some(variant);
+		self
+	}
 }
 
 impl Default for LaunchConfigBuilder {
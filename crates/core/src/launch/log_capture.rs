@@ -0,0 +1,262 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+
+use anyhow::Context;
+
+/// The name of the directory in the launch directory that captured logs are stored in
+pub const LOG_CAPTURE_DIR: &str = "mcvm_logs";
+/// The base name of the captured log files
+const LOG_FILE_NAME: &str = "output";
+
+/// Settings for capturing the output of the game process to log files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogCaptureConfig {
+	/// The maximum size of a log file in bytes before it is rotated
+	pub max_file_size: u64,
+	/// The maximum number of rotated log files to keep along with the current one
+	pub max_files: u32,
+}
+
+impl Default for LogCaptureConfig {
+	fn default() -> Self {
+		Self {
+			max_file_size: 10 * 1024 * 1024,
+			max_files: 5,
+		}
+	}
+}
+
+/// Handle for the threads capturing the output of a process
+#[derive(Debug)]
+pub struct LogCaptureHandle {
+	writer: JoinHandle<anyhow::Result<()>>,
+}
+
+impl LogCaptureHandle {
+	/// Waits for all of the output of the process to be written. This will block until the process exits
+	pub fn join(self) -> anyhow::Result<()> {
+		self.writer
+			.join()
+			.map_err(|_| anyhow::anyhow!("Log writer thread panicked"))?
+	}
+}
+
+/// Capture of the output of a process that has its log file opened, but hasn't started yet
+pub struct LogCapture {
+	log: RotatingLog,
+}
+
+impl LogCapture {
+	/// Opens the rotating log file in a directory. This should be done before the process
+	/// is spawned so that it isn't left running without its output being captured
+	pub fn open(dir: &Path, config: &LogCaptureConfig) -> anyhow::Result<Self> {
+		let log = RotatingLog::open(&dir.join(LOG_CAPTURE_DIR), config.clone())
+			.context("Failed to open log file")?;
+
+		Ok(Self { log })
+	}
+
+	/// Starts copying the stdout and stderr of a child process to both the output of this
+	/// process and the log file. The output of the child must be piped.
+	/// Lines are written to the file on a separate thread so that a slow disk never blocks the process
+	pub fn start(self, child: &mut Child) -> LogCaptureHandle {
+		let mut log = self.log;
+
+		let (sender, receiver) = channel::<String>();
+		if let Some(stdout) = child.stdout.take() {
+			spawn_reader(stdout, sender.clone(), std::io::stdout());
+		}
+		if let Some(stderr) = child.stderr.take() {
+			spawn_reader(stderr, sender.clone(), std::io::stderr());
+		}
+		// The writer stops once both of the readers have finished and dropped their senders
+		std::mem::drop(sender);
+
+		let writer = std::thread::spawn(move || {
+			for line in receiver {
+				log.write_line(&line)?;
+			}
+			Ok(())
+		});
+
+		LogCaptureHandle { writer }
+	}
+}
+
+/// Spawns a thread that echoes lines from a stream and sends them to the log writer.
+/// If the writer stops, such as when the disk is full, the lines are still echoed
+fn spawn_reader<R: Read + Send + 'static, W: Write + Send + 'static>(
+	stream: R,
+	sender: Sender<String>,
+	mut echo: W,
+) -> JoinHandle<()> {
+	std::thread::spawn(move || {
+		let mut reader = BufReader::new(stream);
+		let mut sender = Some(sender);
+		let mut buf = Vec::new();
+		loop {
+			buf.clear();
+			match reader.read_until(b'\n', &mut buf) {
+				Ok(0) | Err(..) => break,
+				Ok(..) => {}
+			}
+			let _ = echo.write_all(&buf);
+			if let Some(log_sender) = &sender {
+				let line = String::from_utf8_lossy(&buf);
+				let line = line.trim_end_matches(['\n', '\r']).to_string();
+				if log_sender.send(line).is_err() {
+					sender = None;
+				}
+			}
+		}
+	})
+}
+
+/// A log file that is moved to a numbered file once it gets too large
+struct RotatingLog {
+	dir: PathBuf,
+	config: LogCaptureConfig,
+	file: File,
+	size: u64,
+}
+
+impl RotatingLog {
+	/// Opens the log in a directory. The previous log is rotated so that every launch starts a new file
+	fn open(dir: &Path, config: LogCaptureConfig) -> anyhow::Result<Self> {
+		std::fs::create_dir_all(dir).context("Failed to create log directory")?;
+		let path = get_log_path(dir, 0);
+		if path.metadata().is_ok_and(|x| x.len() > 0) {
+			rotate(dir, config.max_files)?;
+		}
+
+		Ok(Self {
+			dir: dir.to_path_buf(),
+			file: File::create(path).context("Failed to create log file")?,
+			size: 0,
+			config,
+		})
+	}
+
+	/// Writes a line to the log, rotating it first if the line would make it too large
+	fn write_line(&mut self, line: &str) -> anyhow::Result<()> {
+		let len = line.len() as u64 + 1;
+		if self.size > 0 && self.size + len > self.config.max_file_size {
+			rotate(&self.dir, self.config.max_files)?;
+			self.file =
+				File::create(get_log_path(&self.dir, 0)).context("Failed to create log file")?;
+			self.size = 0;
+		}
+		writeln!(self.file, "{line}").context("Failed to write to log file")?;
+		self.size += len;
+
+		Ok(())
+	}
+}
+
+/// Shifts the numbered log files up by one, removing the oldest
+fn rotate(dir: &Path, max_files: u32) -> anyhow::Result<()> {
+	if max_files == 0 {
+		return Ok(());
+	}
+	for i in (0..max_files).rev() {
+		let path = get_log_path(dir, i);
+		if path.exists() {
+			std::fs::rename(&path, get_log_path(dir, i + 1))
+				.context("Failed to rotate log file")?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Gets the path to a log file. Index 0 is the current log file
+fn get_log_path(dir: &Path, index: u32) -> PathBuf {
+	if index == 0 {
+		dir.join(format!("{LOG_FILE_NAME}.log"))
+	} else {
+		dir.join(format!("{LOG_FILE_NAME}.{index}.log"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_log_rotation() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let config = LogCaptureConfig {
+			max_file_size: 16,
+			max_files: 1,
+		};
+
+		let mut log = RotatingLog::open(dir, config).unwrap();
+		for i in 1..=5 {
+			log.write_line(&format!("line {i}")).unwrap();
+		}
+		std::mem::drop(log);
+
+		let read = |index| std::fs::read_to_string(get_log_path(dir, index)).unwrap();
+		assert_eq!(read(0), "line 5\n");
+		assert_eq!(read(1), "line 3\nline 4\n");
+		// Only one rotated file is kept
+		assert!(!get_log_path(dir, 2).exists());
+	}
+
+	#[test]
+	fn test_echo_after_writer_stops() {
+		/// Writer that can be read from after being moved to the reader thread
+		#[derive(Clone, Default)]
+		struct SharedBuf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+		impl Write for SharedBuf {
+			fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+				self.0.lock().unwrap().write(buf)
+			}
+
+			fn flush(&mut self) -> std::io::Result<()> {
+				Ok(())
+			}
+		}
+
+		let (sender, receiver) = channel();
+		// The writer has already failed
+		std::mem::drop(receiver);
+		let echo = SharedBuf::default();
+		let stream = std::io::Cursor::new(b"first\nsecond\n".to_vec());
+		spawn_reader(stream, sender, echo.clone()).join().unwrap();
+
+		assert_eq!(echo.0.lock().unwrap().as_slice(), b"first\nsecond\n");
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn test_capture_output() {
+		use std::process::{Command, Stdio};
+
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+
+		let mut child = Command::new("sh")
+			.args(["-c", "echo hello; echo world >&2"])
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.unwrap();
+		let capture = LogCapture::open(dir, &LogCaptureConfig::default()).unwrap();
+		let handle = capture.start(&mut child);
+		child.wait().unwrap();
+		handle.join().unwrap();
+
+		let contents =
+			std::fs::read_to_string(get_log_path(&dir.join(LOG_CAPTURE_DIR), 0)).unwrap();
+		let mut lines: Vec<_> = contents.lines().collect();
+		lines.sort();
+		assert_eq!(lines, vec!["hello", "world"]);
+	}
+}
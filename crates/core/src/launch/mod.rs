@@ -2,11 +2,14 @@
 mod client;
 /// Configuration for launch settings
 mod configuration;
+/// Capturing the output of the game process to log files
+mod log_capture;
 /// Actual launching of the game process
 mod process;
 /// Server-specific launch functionality
 mod server;
 
+use std::collections::HashMap;
 use std::path::Path;
 
 use anyhow::Context;
@@ -14,9 +17,9 @@ use mcvm_shared::output::MCVMOutput;
 use mcvm_shared::Side;
 
 use self::client::create_quick_play_args;
-use self::process::{launch_game_process, LaunchGameProcessParameters};
+use self::process::{prepare_game_process, LaunchGameProcessParameters};
 use crate::config::BrandingProperties;
-use crate::instance::InstanceKind;
+use crate::instance::{InstanceKind, DEFAULT_SERVER_MAIN_CLASS};
 use crate::io::files::paths::Paths;
 use crate::io::java::args::MemoryArg;
 use crate::io::java::classpath::Classpath;
@@ -30,13 +33,16 @@ pub use self::configuration::{
 	LaunchConfigBuilder, LaunchConfiguration, QuickPlayType, WrapperCommand,
 };
 
+pub use self::log_capture::{LogCapture, LogCaptureConfig, LogCaptureHandle, LOG_CAPTURE_DIR};
 pub use self::process::launch_process;
+pub use self::process::{get_last_launch_command, LaunchCommand, PreparedLaunch};
 pub use self::process::{LaunchProcessParameters, LaunchProcessProperties};
 
-pub(crate) async fn launch(
+/// Prepare the launch of an instance without spawning the process
+pub(crate) async fn prepare(
 	mut params: LaunchParameters<'_>,
 	o: &mut impl MCVMOutput,
-) -> anyhow::Result<InstanceHandle> {
+) -> anyhow::Result<PreparedLaunch> {
 	let command = params.java.get_jvm_path();
 	// Get side-specific launch properties
 	let props = match params.side.get_side() {
@@ -49,25 +55,18 @@ pub(crate) async fn launch(
 		.users
 		.get_chosen_user()
 		.and_then(|x| x.get_access_token());
-	
-	// Debug logging for authentication
-	if let Some(chosen_user) = params.users.get_chosen_user() {
-		eprintln!("🔍 Chosen user: {}", chosen_user.get_id());
-		if let Some(token) = chosen_user.get_access_token() {
-			let token_str = format!("{:?}", token);
-			eprintln!("✅ User has access token (length: {})", token_str.len());
-			eprintln!("🔍 Token preview: {}...", if token_str.len() > 50 { &token_str[..50] } else { &token_str });
-		} else {
-			eprintln!("❌ User has NO access token!");
-		}
-	} else {
-		eprintln!("❌ No chosen user found!");
-	}
+
+	let main_class = get_main_class(
+		params.main_class_override,
+		params.side.get_side(),
+		params.client_meta,
+	);
 
 	let proc_params = LaunchGameProcessParameters {
 		command: command.as_os_str(),
 		cwd: params.launch_dir,
-		main_class: Some(params.main_class),
+		main_class: Some(&main_class),
+		classpath: params.classpath,
 		props,
 		launch_config: params.launch_config,
 		version: params.version,
@@ -77,10 +76,20 @@ pub(crate) async fn launch(
 		censor_secrets: params.censor_secrets,
 	};
 
-	let child = launch_game_process(proc_params, o).context("Failed to launch game process")?;
+	prepare_game_process(proc_params, o).context("Failed to prepare game process")
+}
 
-	let handle = InstanceHandle::new(child);
-	Ok(handle)
+/// Get the environment variables that are added to the game process on top of the
+/// configured ones, which are needed by some game versions
+pub fn get_additional_env_vars(
+	side: Side,
+	version: &str,
+	version_list: &[String],
+) -> HashMap<String, String> {
+	match side {
+		Side::Client => self::client::get_additional_environment_variables(version, version_list),
+		Side::Server => HashMap::new(),
+	}
 }
 
 /// Container struct for parameters for launching an instance
@@ -91,7 +100,7 @@ pub(crate) struct LaunchParameters<'a> {
 	pub launch_dir: &'a Path,
 	pub java: &'a JavaInstallation,
 	pub classpath: &'a Classpath,
-	pub main_class: &'a str,
+	pub main_class_override: Option<&'a str>,
 	pub launch_config: &'a LaunchConfiguration,
 	pub paths: &'a Paths,
 	pub req_client: &'a reqwest::Client,
@@ -101,10 +110,28 @@ pub(crate) struct LaunchParameters<'a> {
 	pub branding: &'a BrandingProperties,
 }
 
+/// Get the main class to launch the game with. Modloaders replace the main class
+/// from the client meta using the override
+pub(crate) fn get_main_class(
+	main_class_override: Option<&str>,
+	side: Side,
+	client_meta: &ClientMeta,
+) -> String {
+	if let Some(main_class) = main_class_override {
+		return main_class.to_string();
+	}
+
+	match side {
+		Side::Client => client_meta.main_class.clone(),
+		Side::Server => DEFAULT_SERVER_MAIN_CLASS.into(),
+	}
+}
+
 impl LaunchConfiguration {
 	/// Create the args for the JVM when launching the game
 	pub fn generate_jvm_args(&self) -> Vec<String> {
-		let mut out = self.jvm_args.clone();
+		let mut out = self.preset.generate_args(self.max_mem.as_ref());
+		out.extend(self.jvm_args.clone());
 
 		if let Some(n) = &self.min_mem {
 			out.push(MemoryArg::Min.to_string(n));
@@ -160,17 +187,29 @@ impl LaunchConfiguration {
 pub struct InstanceHandle {
 	/// The child process for the launched instance
 	process: std::process::Child,
+	/// The capture of the process output, if it is enabled
+	log_capture: Option<LogCaptureHandle>,
 }
 
 impl InstanceHandle {
 	/// Construct a new InstanceHandle
-	fn new(process: std::process::Child) -> Self {
-		Self { process }
+	fn new(process: std::process::Child, log_capture: Option<LogCaptureHandle>) -> Self {
+		Self {
+			process,
+			log_capture,
+		}
 	}
 
-	/// Waits for the process to complete
+	/// Waits for the process to complete and for all of its captured output to be written
 	pub fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
-		self.process.wait()
+		let status = self.process.wait()?;
+		if let Some(log_capture) = self.log_capture.take() {
+			log_capture
+				.join()
+				.map_err(|e| std::io::Error::other(format!("{e:?}")))?;
+		}
+
+		Ok(status)
 	}
 
 	/// Kills the process early
@@ -178,9 +217,38 @@ impl InstanceHandle {
 		self.process.kill()
 	}
 
+	/// Gets the PID of the game process
+	pub fn get_pid(&self) -> u32 {
+		self.process.id()
+	}
+
 	/// Gets the internal child process for the game, consuming the
-	/// InstanceHandle
+	/// InstanceHandle. Any output capture keeps running in the background
 	pub fn get_process(self) -> std::process::Child {
 		self.process
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::net::game_files::client_meta::create_test_meta_json;
+
+	use super::*;
+
+	#[test]
+	fn test_main_class_override() {
+		let meta: ClientMeta =
+			serde_json::from_value(create_test_meta_json(serde_json::json!([]))).unwrap();
+
+		assert_eq!(
+			get_main_class(None, Side::Client, &meta),
+			"net.minecraft.client.main.Main"
+		);
+		assert_eq!(
+			get_main_class(None, Side::Server, &meta),
+			DEFAULT_SERVER_MAIN_CLASS
+		);
+		let fabric = "net.fabricmc.loader.impl.launch.knot.KnotClient";
+		assert_eq!(get_main_class(Some(fabric), Side::Client, &meta), fabric);
+	}
+}
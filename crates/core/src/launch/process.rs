@@ -1,24 +1,28 @@
-use std::collections::HashMap;
-use std::ffi::OsStr;
-use std::path::Path;
-use std::process::{Child, Command};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use mcvm_auth::mc::AccessToken;
-use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel, CENSOR_STR};
 use mcvm_shared::translate;
+use serde::{Deserialize, Serialize};
 
 use crate::instance::InstanceKind;
+use crate::io::java::args::normalize_jvm_args;
+use crate::io::java::classpath::Classpath;
+use crate::io::{json_from_file, json_to_file_pretty};
 use crate::util::versions::VersionName;
 use crate::WrapperCommand;
 
-use super::LaunchConfiguration;
+use super::{InstanceHandle, LaunchConfiguration, LogCapture, LogCaptureConfig};
 
-/// Launch the game process
-pub(crate) fn launch_game_process(
+/// Prepare the game process without spawning it
+pub(crate) fn prepare_game_process(
 	mut params: LaunchGameProcessParameters<'_>,
 	o: &mut impl MCVMOutput,
-) -> anyhow::Result<std::process::Child> {
+) -> anyhow::Result<PreparedLaunch> {
 	// Modify the parameters based on game-specific properties
 
 	// Prepend generated game args to the beginning
@@ -40,21 +44,93 @@ pub(crate) fn launch_game_process(
 		launch_config: params.launch_config,
 	};
 
-	o.display(
-		MessageContents::Success(translate!(o, Launch)),
-		MessageLevel::Important,
-	);
-
-	// Get the command and output it
-	let mut cmd = get_process_launch_command(proc_params)
+	let main_class = params.main_class.unwrap_or_default().to_string();
+	let cmd = get_process_launch_command(proc_params)
 		.context("Failed to create process launch command")?;
 
-	output_launch_command(&cmd, params.user_access_token, params.censor_secrets, o)?;
+	Ok(PreparedLaunch {
+		program: PathBuf::from(cmd.get_program()),
+		args: cmd.get_args().map(OsString::from).collect(),
+		env: cmd
+			.get_envs()
+			.filter_map(|(env, val)| Some((env.to_os_string(), val?.to_os_string())))
+			.collect(),
+		cwd: cmd.get_current_dir().unwrap_or(params.cwd).to_path_buf(),
+		main_class,
+		classpath: params.classpath.clone(),
+		launch_dir: params.cwd.to_path_buf(),
+		access_token: params.user_access_token.cloned(),
+		censor_secrets: params.censor_secrets,
+		log_capture: params.launch_config.log_capture.clone(),
+	})
+}
+
+/// A launch of the game that has been fully prepared, but not started yet
+#[derive(Debug, Clone)]
+pub struct PreparedLaunch {
+	/// The program to run. This is the path to the JVM unless wrapper commands are used
+	pub program: PathBuf,
+	/// The arguments to the program, including the JVM arguments, main class, and game arguments
+	pub args: Vec<OsString>,
+	/// Environment variables set for the process on top of the ones it inherits
+	pub env: HashMap<OsString, OsString>,
+	/// The directory the process will be run in
+	pub cwd: PathBuf,
+	/// The Java main class of the game
+	pub main_class: String,
+	/// The classpath of the game
+	pub classpath: Classpath,
+	/// The launch directory of the instance, where the launch command is stored
+	launch_dir: PathBuf,
+	/// The access token of the user, used to censor it from output
+	access_token: Option<AccessToken>,
+	/// Whether to censor secrets in output
+	censor_secrets: bool,
+	/// Settings for capturing the output of the process
+	log_capture: Option<LogCaptureConfig>,
+}
+
+impl PreparedLaunch {
+	/// Create the command that will be spawned for this launch
+	pub fn to_command(&self) -> Command {
+		let mut cmd = Command::new(&self.program);
+		cmd.args(&self.args);
+		cmd.envs(&self.env);
+		cmd.current_dir(&self.cwd);
+		cmd
+	}
 
-	// Spawn
-	let child = cmd.spawn().context("Failed to spawn child process")?;
+	/// Start the game process and get its handle
+	pub fn spawn(&self, o: &mut impl MCVMOutput) -> anyhow::Result<InstanceHandle> {
+		o.display(
+			MessageContents::Success(translate!(o, Launch)),
+			MessageLevel::Important,
+		);
 
-	Ok(child)
+		let mut cmd = self.to_command();
+		let access_token = self.access_token.as_ref();
+		output_launch_command(&cmd, access_token, self.censor_secrets, o)?;
+
+		// The stored command is always censored, as it is meant to be shared when debugging
+		LaunchCommand::new(&cmd, access_token)
+			.write(&self.launch_dir)
+			.context("Failed to store launch command")?;
+
+		let log_capture = if let Some(log_capture) = &self.log_capture {
+			cmd.stdout(Stdio::piped());
+			cmd.stderr(Stdio::piped());
+			let capture = LogCapture::open(&self.launch_dir, log_capture)
+				.context("Failed to start capturing game output")?;
+			Some(capture)
+		} else {
+			None
+		};
+
+		let mut child = cmd.spawn().context("Failed to spawn child process")?;
+		let log_capture = log_capture.map(|x| x.start(&mut child));
+
+		Ok(InstanceHandle::new(child, log_capture))
+	}
 }
 
 /// Launch a generic process with the core's config system
@@ -71,13 +147,27 @@ pub fn get_process_launch_command(params: LaunchProcessParameters<'_>) -> anyhow
 	let mut cmd = create_wrapped_command(params.command, &params.launch_config.wrappers);
 
 	// Fill out the command properties
-	cmd.current_dir(params.cwd);
+	let cwd = match &params.launch_config.cwd_override {
+		Some(cwd_override) => {
+			if !cwd_override.is_dir() {
+				bail!(
+					"Working directory override {} does not exist",
+					cwd_override.display()
+				);
+			}
+			cwd_override
+		}
+		None => params.cwd,
+	};
+	cmd.current_dir(cwd);
 	cmd.envs(params.launch_config.env.clone());
 	cmd.envs(params.props.additional_env_vars);
 
 	// Add the arguments
-	cmd.args(params.launch_config.generate_jvm_args());
-	cmd.args(params.props.jvm_args);
+	let mut jvm_args = params.launch_config.generate_jvm_args();
+	jvm_args.extend(params.props.jvm_args);
+	let jvm_args = normalize_jvm_args(jvm_args).context("Invalid JVM arguments")?;
+	cmd.args(jvm_args);
 	if let Some(main_class) = params.main_class {
 		cmd.arg(main_class);
 	}
@@ -111,12 +201,8 @@ fn output_launch_command(
 		MessageLevel::Debug,
 	);
 
-	const CENSOR_STR: &str = "***";
 	for arg in command.get_args() {
-		let mut arg = arg.to_string_lossy().to_string();
-		if let Some(access_token) = &access_token {
-			arg = arg.replace(&access_token.0, CENSOR_STR);
-		}
+		let arg = censor_access_token(arg, access_token);
 		o.display(
 			MessageContents::ListItem(Box::new(MessageContents::Simple(arg))),
 			MessageLevel::Debug,
@@ -131,7 +217,11 @@ fn output_launch_command(
 	for (env, val) in command.get_envs() {
 		let Some(val) = val else { continue };
 		let env = env.to_string_lossy().to_string();
-		let val = val.to_string_lossy().to_string();
+		let val = if censor_secrets && is_secret_env_var(&env) {
+			CENSOR_STR.to_string()
+		} else {
+			censor_access_token(val, access_token)
+		};
 
 		o.display(
 			MessageContents::ListItem(Box::new(MessageContents::Property(
@@ -155,6 +245,129 @@ fn output_launch_command(
 	Ok(())
 }
 
+/// Converts a part of a command to a string, replacing the access token with asterisks if one is given
+fn censor_access_token(value: &OsStr, access_token: Option<&AccessToken>) -> String {
+	let value = value.to_string_lossy().to_string();
+	match access_token {
+		Some(access_token) if !access_token.0.is_empty() => {
+			value.replace(&access_token.0, CENSOR_STR)
+		}
+		_ => value,
+	}
+}
+
+/// Parts of environment variable names that mark their values as secret
+const SECRET_ENV_VAR_PARTS: &[&str] = &[
+	"TOKEN",
+	"SECRET",
+	"PASSWORD",
+	"PASSWD",
+	"API_KEY",
+	"CREDENTIAL",
+];
+
+/// Checks if an environment variable probably contains a secret based on its name
+fn is_secret_env_var(name: &str) -> bool {
+	let name = name.to_uppercase();
+	SECRET_ENV_VAR_PARTS.iter().any(|part| name.contains(part))
+}
+
+/// The name of the file in the launch directory that the last launch command is stored in as JSON
+pub const LAST_LAUNCH_JSON_FILE: &str = "last_launch.json";
+/// The name of the file in the launch directory that the last launch command is stored in as text
+pub const LAST_LAUNCH_TEXT_FILE: &str = "last_launch.txt";
+
+/// A launch command stored for debugging, with secrets censored
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LaunchCommand {
+	/// The program that was run, usually the path to the JVM
+	pub program: String,
+	/// The arguments to the program, including the classpath
+	pub args: Vec<String>,
+	/// Environment variables that were set for the process
+	pub env: BTreeMap<String, String>,
+	/// The directory the process was run in
+	pub dir: Option<PathBuf>,
+}
+
+impl LaunchCommand {
+	/// Create a LaunchCommand from a command, censoring the access token if one is given
+	/// and the values of environment variables that look like secrets
+	pub fn new(command: &Command, access_token: Option<&AccessToken>) -> Self {
+		let censor = |value: &OsStr| censor_access_token(value, access_token);
+
+		Self {
+			program: censor(command.get_program()),
+			args: command.get_args().map(censor).collect(),
+			env: command
+				.get_envs()
+				.filter_map(|(env, val)| {
+					let env = env.to_string_lossy().to_string();
+					let val = if is_secret_env_var(&env) {
+						CENSOR_STR.to_string()
+					} else {
+						censor(val?)
+					};
+					Some((env, val))
+				})
+				.collect(),
+			dir: command.get_current_dir().map(Path::to_path_buf),
+		}
+	}
+
+	/// Write this command to the JSON and text files in a directory
+	pub fn write(&self, dir: &Path) -> anyhow::Result<()> {
+		json_to_file_pretty(dir.join(LAST_LAUNCH_JSON_FILE), self)
+			.context("Failed to write launch command JSON")?;
+		std::fs::write(dir.join(LAST_LAUNCH_TEXT_FILE), self.to_shell_string())
+			.context("Failed to write launch command text")?;
+
+		Ok(())
+	}
+
+	/// Format this command so that it can be pasted into a shell
+	pub fn to_shell_string(&self) -> String {
+		let mut out = String::new();
+		if let Some(dir) = &self.dir {
+			out.push_str(&format!("cd {}\n", quote_shell_arg(&dir.to_string_lossy())));
+		}
+		for (env, val) in &self.env {
+			out.push_str(&format!("{env}={} ", quote_shell_arg(val)));
+		}
+		out.push_str(&quote_shell_arg(&self.program));
+		for arg in &self.args {
+			out.push(' ');
+			out.push_str(&quote_shell_arg(arg));
+		}
+		out.push('\n');
+
+		out
+	}
+}
+
+/// Get the last command that was used to launch from a launch directory, if there is one
+pub fn get_last_launch_command(dir: &Path) -> anyhow::Result<Option<LaunchCommand>> {
+	let path = dir.join(LAST_LAUNCH_JSON_FILE);
+	if !path.exists() {
+		return Ok(None);
+	}
+
+	json_from_file(path).context("Failed to read last launch command")
+}
+
+/// Quote an argument for a shell if it contains any special characters
+fn quote_shell_arg(arg: &str) -> String {
+	let is_plain = !arg.is_empty()
+		&& arg
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c));
+	if is_plain {
+		arg.to_string()
+	} else {
+		format!("'{}'", arg.replace('\'', "'\\''"))
+	}
+}
+
 /// Creates a command wrapped in multiple other wrappers
 fn create_wrapped_command(command: &OsStr, wrappers: &[WrapperCommand]) -> Command {
 	let mut cmd = Command::new(command);
@@ -181,6 +394,8 @@ pub(crate) struct LaunchGameProcessParameters<'a> {
 	pub cwd: &'a Path,
 	/// The Java main class to run
 	pub main_class: Option<&'a str>,
+	/// The classpath of the game
+	pub classpath: &'a Classpath,
 	pub props: LaunchProcessProperties,
 	pub launch_config: &'a LaunchConfiguration,
 	pub version: &'a VersionName,
@@ -218,6 +433,8 @@ pub struct LaunchProcessProperties {
 
 #[cfg(test)]
 mod tests {
+	use mcvm_shared::output::{NoOp, Recording};
+
 	use super::*;
 
 	#[test]
@@ -241,4 +458,169 @@ mod tests {
 		assert_eq!(args.next(), Some(OsStr::new("hello")));
 		assert_eq!(args.next(), Some(OsStr::new("run")));
 	}
+
+	#[test]
+	fn test_prepare_game_process() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+
+		let mut classpath = Classpath::new();
+		classpath.add_path(&dir.join("server.jar")).unwrap();
+		let launch_config = LaunchConfiguration::new();
+		let version = VersionName::from("1.20.1");
+		let side = InstanceKind::Server {
+			create_eula: false,
+			show_gui: false,
+		};
+		let params = LaunchGameProcessParameters {
+			command: OsStr::new("java"),
+			cwd: dir,
+			main_class: Some("net.minecraft.server.Main"),
+			classpath: &classpath,
+			props: LaunchProcessProperties {
+				jvm_args: vec!["-cp".into(), classpath.get_str()],
+				game_args: vec!["nogui".into()],
+				..Default::default()
+			},
+			launch_config: &launch_config,
+			version: &version,
+			version_list: &[],
+			side: &side,
+			user_access_token: None,
+			censor_secrets: true,
+		};
+
+		let prepared = prepare_game_process(params, &mut NoOp).unwrap();
+		assert_eq!(prepared.program, PathBuf::from("java"));
+		assert_eq!(prepared.main_class, "net.minecraft.server.Main");
+		assert!(!prepared.classpath.get_paths().is_empty());
+		assert_eq!(prepared.cwd, dir);
+		assert_eq!(
+			prepared.args,
+			vec![
+				OsString::from("-cp"),
+				OsString::from(classpath.get_str()),
+				OsString::from("net.minecraft.server.Main"),
+				OsString::from("nogui"),
+			]
+		);
+		// Nothing is stored until the process is spawned
+		assert!(get_last_launch_command(dir).unwrap().is_none());
+
+		// Servers can be moved to a different working directory
+		let cwd = dir.join("fast_disk");
+		std::fs::create_dir_all(&cwd).unwrap();
+		let launch_config = LaunchConfiguration::builder()
+			.cwd_override(cwd.clone())
+			.build();
+		let params = LaunchGameProcessParameters {
+			command: OsStr::new("java"),
+			cwd: dir,
+			main_class: Some("net.minecraft.server.Main"),
+			classpath: &classpath,
+			props: LaunchProcessProperties::default(),
+			launch_config: &launch_config,
+			version: &version,
+			version_list: &[],
+			side: &side,
+			user_access_token: None,
+			censor_secrets: true,
+		};
+		let prepared = prepare_game_process(params, &mut NoOp).unwrap();
+		assert_eq!(prepared.cwd, cwd);
+		assert_eq!(prepared.launch_dir, dir);
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn test_cwd_override() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let launch_dir = dir.join("instance");
+		let cwd = dir.join("fast_disk");
+		std::fs::create_dir_all(&launch_dir).unwrap();
+		std::fs::create_dir_all(&cwd).unwrap();
+
+		let launch_config = LaunchConfiguration::builder()
+			.cwd_override(cwd.clone())
+			.build();
+		let params = LaunchProcessParameters {
+			command: OsStr::new("sh"),
+			cwd: &launch_dir,
+			main_class: None,
+			props: LaunchProcessProperties {
+				jvm_args: vec!["-c".into(), "pwd".into()],
+				..Default::default()
+			},
+			launch_config: &launch_config,
+		};
+		let output = get_process_launch_command(params)
+			.unwrap()
+			.output()
+			.unwrap();
+		let process_cwd = PathBuf::from(String::from_utf8(output.stdout).unwrap().trim());
+		assert_eq!(
+			process_cwd.canonicalize().unwrap(),
+			cwd.canonicalize().unwrap()
+		);
+
+		let launch_config = LaunchConfiguration::builder()
+			.cwd_override(dir.join("missing"))
+			.build();
+		let params = LaunchProcessParameters {
+			command: OsStr::new("sh"),
+			cwd: &launch_dir,
+			main_class: None,
+			props: LaunchProcessProperties::default(),
+			launch_config: &launch_config,
+		};
+		assert!(get_process_launch_command(params).is_err());
+	}
+
+	#[test]
+	fn test_last_launch_command() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+
+		let token = AccessToken("secret-token".into());
+		let mut cmd = Command::new("java");
+		cmd.current_dir(dir);
+		cmd.env("FOO", "bar baz");
+		cmd.env("GITHUB_TOKEN", "secret-env");
+		cmd.args(["-Xmx2g", "--accessToken", "secret-token"]);
+		LaunchCommand::new(&cmd, Some(&token)).write(dir).unwrap();
+
+		let command = get_last_launch_command(dir).unwrap().unwrap();
+		assert_eq!(command.program, "java");
+		assert_eq!(command.args, vec!["-Xmx2g", "--accessToken", "***"]);
+		assert_eq!(command.env.get("FOO").map(String::as_str), Some("bar baz"));
+		assert_eq!(
+			command.env.get("GITHUB_TOKEN").map(String::as_str),
+			Some("***")
+		);
+		let text = std::fs::read_to_string(dir.join(LAST_LAUNCH_TEXT_FILE)).unwrap();
+		assert!(text.contains("FOO='bar baz' GITHUB_TOKEN='***' java -Xmx2g --accessToken '***'"));
+		for file in [LAST_LAUNCH_JSON_FILE, LAST_LAUNCH_TEXT_FILE] {
+			let contents = std::fs::read_to_string(dir.join(file)).unwrap();
+			assert!(!contents.contains("secret-token"));
+			assert!(!contents.contains("secret-env"));
+		}
+	}
+
+	#[test]
+	fn test_output_launch_command_censoring() {
+		let mut cmd = Command::new("java");
+		cmd.env("API_KEY", "secret-env");
+		cmd.arg("-Xmx2g");
+
+		// An empty token should not be replaced everywhere
+		let mut o = Recording::default();
+		output_launch_command(&cmd, Some(&AccessToken(String::new())), true, &mut o).unwrap();
+		assert!(o.messages.iter().any(|x| x.contains("-Xmx2g")));
+		assert!(!o.messages.iter().any(|x| x.contains("secret-env")));
+
+		let mut o = Recording::default();
+		output_launch_command(&cmd, None, false, &mut o).unwrap();
+		assert!(o.messages.iter().any(|x| x.contains("secret-env")));
+	}
 }
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
+use std::path::Path;
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 
 use crate::InstanceKind;
 
 use super::{process::LaunchProcessProperties, LaunchParameters};
 
+/// Files that the server only reads from its working directory
+const SERVER_CONFIG_FILES: [&str; 2] = ["server.properties", "eula.txt"];
+
 /// Create launch properties for the server
 pub(crate) fn get_launch_props(
 	params: &LaunchParameters,
@@ -22,6 +26,20 @@ pub(crate) fn get_launch_props(
 		game_args.push("nogui".into());
 	}
 
+	// Keep the worlds and config of the server in the instance directory when it is run somewhere else
+	if let Some(cwd_override) = &params.launch_config.cwd_override {
+		game_args.push("--universe".into());
+		game_args.push(
+			params
+				.launch_dir
+				.to_str()
+				.context("Failed to convert launch directory to a string")?
+				.into(),
+		);
+		copy_config_files(params.launch_dir, cwd_override)
+			.context("Failed to copy server config files to the working directory")?;
+	}
+
 	let props = LaunchProcessProperties {
 		jvm_args,
 		game_args,
@@ -29,3 +47,45 @@ pub(crate) fn get_launch_props(
 	};
 	Ok(props)
 }
+
+/// Copy the config files of the server from the instance directory to the
+/// directory it is run in, as the server has no arguments for their paths
+fn copy_config_files(launch_dir: &Path, cwd: &Path) -> anyhow::Result<()> {
+	for file in SERVER_CONFIG_FILES {
+		let path = launch_dir.join(file);
+		if path.exists() {
+			std::fs::copy(&path, cwd.join(file))
+				.with_context(|| format!("Failed to copy {file}"))?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_copy_config_files() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let launch_dir = dir.join("instance");
+		let cwd = dir.join("fast_disk");
+		std::fs::create_dir_all(&launch_dir).unwrap();
+		std::fs::create_dir_all(&cwd).unwrap();
+		std::fs::write(launch_dir.join("eula.txt"), "eula = true\n").unwrap();
+		std::fs::write(cwd.join("server.properties"), "motd=old").unwrap();
+
+		copy_config_files(&launch_dir, &cwd).unwrap();
+		assert_eq!(
+			std::fs::read_to_string(cwd.join("eula.txt")).unwrap(),
+			"eula = true\n"
+		);
+		// Files that the instance doesn't have are left alone
+		assert_eq!(
+			std::fs::read_to_string(cwd.join("server.properties")).unwrap(),
+			"motd=old"
+		);
+	}
+}
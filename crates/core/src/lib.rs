@@ -31,7 +31,8 @@ use anyhow::Context;
 use io::java::install::{JavaInstallParameters, JavaInstallation, JavaInstallationKind};
 use io::java::JavaMajorVersion;
 use io::{persistent::PersistentData, update::UpdateManager};
-use mcvm_shared::output::{self, MCVMOutput};
+use mcvm_shared::output::{self, MCVMOutput, MessageContents, MessageLevel};
+use mcvm_shared::translate;
 use mcvm_shared::versions::VersionInfo;
 use net::game_files::version_manifest::{make_version_list, VersionEntry, VersionManifestAndList};
 use user::UserManager;
@@ -72,11 +73,14 @@ impl MCVMCore {
 	pub fn with_config_and_paths(config: Configuration, paths: Paths) -> anyhow::Result<Self> {
 		let persistent =
 			PersistentData::open(&paths).context("Failed to open persistent data file")?;
+		let mut update_manager = UpdateManager::new(config.force_reinstall, config.allow_offline);
+		update_manager.set_offline(config.offline);
+		update_manager.set_bandwidth_limit(config.bandwidth_limit);
 		let out = Self {
 			paths,
 			req_client: reqwest::Client::new(),
 			persistent,
-			update_manager: UpdateManager::new(config.force_reinstall, config.allow_offline),
+			update_manager,
 			versions: VersionRegistry::new(),
 			users: UserManager::new(config.ms_client_id.clone()),
 			config,
@@ -168,11 +172,40 @@ impl MCVMCore {
 			users: &mut self.users,
 			censor_secrets: self.config.censor_secrets,
 			disable_hardlinks: self.config.disable_hardlinks,
+			java_archive_type: self.config.java_archive_type,
 			branding: &self.config.branding,
 		};
 		Ok(InstalledVersion { inner, params })
 	}
 
+	/// Uninstall a version of the game, removing its client meta, game JARs, and any libraries
+	/// and assets that are not used by other installed versions. Returns the number of bytes freed
+	pub fn uninstall_version(
+		&mut self,
+		version: &str,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<u64> {
+		o.display(
+			MessageContents::StartProcess(translate!(
+				o,
+				StartUninstallingVersion,
+				"version" = version
+			)),
+			MessageLevel::Important,
+		);
+
+		let freed = net::game_files::uninstall::uninstall(version, &self.paths)
+			.context("Failed to uninstall version")?;
+		self.versions.remove_version(version);
+
+		o.display(
+			MessageContents::Success(translate!(o, FinishUninstallingVersion)),
+			MessageLevel::Important,
+		);
+
+		Ok(freed)
+	}
+
 	/// Get just the VersionInfo for a version, without creating the version.
 	/// This is useful for doing your own installation of things. This will download
 	/// the version manifest if it is not downloaded already
@@ -201,6 +234,7 @@ impl MCVMCore {
 			update_manager: &mut self.update_manager,
 			persistent: &mut self.persistent,
 			req_client: &self.req_client,
+			archive_type: self.config.java_archive_type,
 		};
 		let java = JavaInstallation::install(kind, major_version, java_params, o)
 			.await
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -13,7 +13,7 @@ use tokio::{sync::Semaphore, task::JoinSet};
 use crate::io::files::{self, paths::Paths};
 use crate::io::update::{UpdateManager, UpdateMethodResult};
 use crate::io::{json_from_file, json_to_file};
-use crate::net::download::{self, get_transfer_limit};
+use crate::net::download::{self, get_transfer_limit, BandwidthLimiter};
 use crate::util::versions::VersionName;
 
 use super::client_meta::ClientMeta;
@@ -25,6 +25,26 @@ pub struct AssetIndex {
 	pub objects: HashMap<String, IndexEntry>,
 }
 
+impl AssetIndex {
+	/// Computes the assets in this index that are new or changed compared to the index
+	/// of a previously installed version. Since assets are stored by their hash, objects
+	/// that are shared between the two indexes are skipped, even if they were renamed.
+	/// The output is sorted by asset name.
+	pub fn diff<'a>(&'a self, previous: &AssetIndex) -> Vec<(&'a str, &'a IndexEntry)> {
+		let previous_hashes: HashSet<&str> =
+			previous.objects.values().map(|x| x.hash.as_str()).collect();
+		let mut out: Vec<_> = self
+			.objects
+			.iter()
+			.filter(|(_, asset)| !previous_hashes.contains(asset.hash.as_str()))
+			.map(|(name, asset)| (name.as_str(), asset))
+			.collect();
+		out.sort_by_key(|(name, _)| *name);
+
+		out
+	}
+}
+
 /// A single asset in the index
 #[derive(Deserialize, Serialize)]
 pub struct IndexEntry {
@@ -53,11 +73,8 @@ pub async fn get(
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<UpdateMethodResult> {
 	let mut out = UpdateMethodResult::new();
-	let version_string = version.to_string();
-	let indexes_dir = paths.assets.join("indexes");
-	files::create_dir(&indexes_dir)?;
-
-	let index_path = indexes_dir.join(version_string + ".json");
+	let index_path = get_index_path(paths, version);
+	files::create_leading_dirs(&index_path)?;
 	let index_url = &client_meta.asset_index.url;
 
 	let (objects_dir, virtual_dir) = create_dirs(paths, version, version_list)
@@ -66,6 +83,8 @@ pub async fn get(
 
 	let index = match download_index(index_url, &index_path, manager, client, false).await {
 		Ok(val) => val,
+		// Downloading the index again is not possible without a connection
+		Err(err) if manager.is_offline() => return Err(err),
 		Err(err) => {
 			o.display(
 				MessageContents::Error(translate!(o, AssetIndexFailed)),
@@ -121,6 +140,7 @@ pub async fn get(
 
 	let count = assets_to_download.len();
 	if count > 0 {
+		manager.ensure_online(&format!("{count} assets"))?;
 		o.display(
 			MessageContents::StartProcess(translate!(
 				o,
@@ -138,11 +158,12 @@ pub async fn get(
 	let sem = Arc::new(Semaphore::new(get_transfer_limit()));
 	for asset in assets_to_download {
 		let client = client.clone();
+		let limiter = manager.get_bandwidth_limiter().cloned();
 		let sem = sem.clone();
 		let fut = async move {
 			let _permit = sem.acquire().await;
 
-			try_3!({ download_asset(&asset, &client).await })
+			try_3!({ download_asset(&asset, &client, limiter.as_ref()).await })
 				.context("Failed three times to download asset")?;
 
 			Ok::<String, anyhow::Error>(asset.name)
@@ -192,8 +213,12 @@ pub async fn get(
 }
 
 /// Downloads and loads a single asset
-async fn download_asset(asset: &AssetData, client: &Client) -> anyhow::Result<()> {
-	let response = download::bytes(&asset.url, &client)
+async fn download_asset(
+	asset: &AssetData,
+	client: &Client,
+	limiter: Option<&BandwidthLimiter>,
+) -> anyhow::Result<()> {
+	let response = download::bytes_limited(&asset.url, client, limiter)
 		.await
 		.context("Failed to download asset")?;
 
@@ -239,9 +264,11 @@ async fn download_index(
 	client: &Client,
 	force: bool,
 ) -> anyhow::Result<AssetIndex> {
-	let index = if manager.allow_offline && !force && path.exists() {
+	let use_cached = manager.is_offline() || (manager.allow_offline() && !force);
+	let index = if use_cached && path.exists() {
 		json_from_file(path).context("Failed to read asset index contents from file")?
 	} else {
+		manager.ensure_online("asset index")?;
 		let index = download::json(url, client)
 			.await
 			.context("Failed to download asset index")?;
@@ -272,7 +299,95 @@ async fn create_dirs(
 	Ok((objects_dir, virtual_dir))
 }
 
+/// Get the path to the stored asset index for a version
+pub fn get_index_path(paths: &Paths, version: &str) -> PathBuf {
+	paths.assets.join("indexes").join(format!("{version}.json"))
+}
+
+/// Get the path to where an asset object is stored
+pub fn get_object_path(paths: &Paths, asset: &IndexEntry) -> PathBuf {
+	paths.assets.join("objects").join(asset.get_hash_path())
+}
+
 /// Get the virtual assets directory path
 pub fn get_virtual_dir_path(paths: &Paths) -> PathBuf {
 	paths.assets.join("virtual").join("legacy")
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn create_index(objects: &[(&str, &str)]) -> AssetIndex {
+		AssetIndex {
+			objects: objects
+				.iter()
+				.map(|(name, hash)| {
+					(
+						name.to_string(),
+						IndexEntry {
+							hash: hash.to_string(),
+							size: 1,
+						},
+					)
+				})
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn test_asset_index_diff() {
+		let previous = create_index(&[
+			("minecraft/sounds/a.ogg", "aaaa"),
+			("minecraft/sounds/b.ogg", "bbbb"),
+			("minecraft/lang/en_us.json", "cccc"),
+			("minecraft/icons/icon.png", "dddd"),
+		]);
+		let new = create_index(&[
+			("minecraft/sounds/a.ogg", "aaaa"),
+			("minecraft/sounds/b.ogg", "bbbb"),
+			// Changed
+			("minecraft/lang/en_us.json", "eeee"),
+			// Renamed but with the same contents
+			("minecraft/icons/icon_32.png", "dddd"),
+			// Added
+			("minecraft/sounds/c.ogg", "ffff"),
+		]);
+
+		let diff: Vec<_> = new
+			.diff(&previous)
+			.into_iter()
+			.map(|(name, asset)| (name, asset.hash.as_str()))
+			.collect();
+		assert_eq!(
+			diff,
+			vec![
+				("minecraft/lang/en_us.json", "eeee"),
+				("minecraft/sounds/c.ogg", "ffff"),
+			]
+		);
+
+		assert!(new.diff(&new).is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_offline_missing_index() {
+		let mut manager = UpdateManager::new(false, false);
+		manager.set_offline(true);
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("index.json");
+
+		// The URL can't be resolved, so this would fail differently if a request was made
+		let result = download_index(
+			"https://invalid.invalid/index.json",
+			&path,
+			&manager,
+			&Client::new(),
+			false,
+		)
+		.await;
+		let err = result.err().expect("Index should not be available offline");
+		assert_eq!(err.to_string(), "Offline mode: asset index is not cached");
+		assert!(!path.exists());
+	}
+}
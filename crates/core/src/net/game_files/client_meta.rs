@@ -4,6 +4,7 @@ use anyhow::{bail, Context};
 use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
 use mcvm_shared::translate;
 use mcvm_shared::util::DeserListOrSingle;
+use mcvm_shared::versions::closest_match;
 use reqwest::Client;
 use serde::Deserialize;
 use zip::ZipArchive;
@@ -13,8 +14,9 @@ use crate::io::java::JavaMajorVersion;
 use crate::io::json_from_file;
 use crate::io::update::UpdateManager;
 use crate::net::download::ProgressiveDownload;
+use crate::util::versions::VersionName;
 
-use super::version_manifest::VersionManifest;
+use super::version_manifest::{make_version_list, VersionManifest};
 
 /// The client metadata, which is used for information about
 /// how to set up and launch the client for every version of the game.
@@ -32,9 +34,10 @@ pub struct ClientMeta {
 	pub assets_version: String,
 	/// File downloads
 	pub downloads: Downloads,
-	/// Java information
+	/// Java information. Some custom or very old metas may not include this
 	#[serde(rename = "javaVersion")]
-	pub java_info: JavaInfo,
+	#[serde(default)]
+	pub java_info: Option<JavaInfo>,
 	/// Libraries to download for the client
 	pub libraries: Vec<libraries::Library>,
 	/// Java main class for the client
@@ -49,6 +52,10 @@ pub struct ClientMeta {
 pub struct AssetIndexInfo {
 	/// The URL to the assets index for this version
 	pub url: String,
+	/// The total size in bytes of all of the assets in the index
+	#[serde(rename = "totalSize")]
+	#[serde(default)]
+	pub total_size: Option<u64>,
 }
 
 /// Download information for different files
@@ -65,6 +72,12 @@ pub struct Downloads {
 pub struct DownloadInfo {
 	/// The URL to the file
 	pub url: String,
+	/// The size of the file in bytes
+	#[serde(default)]
+	pub size: Option<u64>,
+	/// The SHA-1 hash of the file
+	#[serde(default)]
+	pub sha1: Option<String>,
 }
 
 /// Information about Java for this version
@@ -75,6 +88,35 @@ pub struct JavaInfo {
 	pub major_version: JavaMajorVersion,
 }
 
+/// Gets the Java major version that a Minecraft version requires, using the
+/// `javaVersion` field of its client meta. If the meta does not specify one,
+/// the version is guessed from the version name, falling back to Java 8
+pub fn get_required_java_version(version: &VersionName, meta: &ClientMeta) -> JavaMajorVersion {
+	if let Some(java_info) = &meta.java_info {
+		return java_info.major_version;
+	}
+
+	// Guess based on the minor version of releases
+	let minor = version
+		.strip_prefix("1.")
+		.and_then(|x| x.split(['.', '-', ' ']).next())
+		.and_then(|x| x.parse::<u16>().ok());
+	let patch = version
+		.strip_prefix("1.")
+		.and_then(|x| x.split(['.', '-', ' ']).nth(1))
+		.and_then(|x| x.parse::<u16>().ok())
+		.unwrap_or(0);
+	let major = match minor {
+		Some(21..) => 21,
+		Some(20) if patch >= 5 => 21,
+		Some(18..=20) => 17,
+		Some(17) => 16,
+		_ => 8,
+	};
+
+	JavaMajorVersion::new(major)
+}
+
 /// Information about logging for this version
 #[derive(Deserialize, Debug, Clone)]
 pub struct LogInfo {
@@ -180,6 +222,12 @@ pub mod libraries {
 		pub path: String,
 		/// URL to download the artifact from
 		pub url: String,
+		/// The size of the artifact in bytes
+		#[serde(default)]
+		pub size: Option<u64>,
+		/// The SHA-1 hash of the artifact
+		#[serde(default)]
+		pub sha1: Option<String>,
 	}
 
 	/// Extraction rules for a library
@@ -331,6 +379,10 @@ pub async fn get(
 		.iter()
 		.find(|x| x.id == version_string);
 	let Some(entry) = entry else {
+		let version_list = make_version_list(version_manifest);
+		if let Some(suggestion) = closest_match(&version_string, &version_list) {
+			bail!("Minecraft version does not exist or was not found in the manifest. Did you mean '{suggestion}'?");
+		}
 		bail!("Minecraft version does not exist or was not found in the manifest");
 	};
 
@@ -339,10 +391,13 @@ pub async fn get(
 	files::create_dir(&version_dir).context("Failed to create versions directory")?;
 	let path = version_dir.join(client_meta_name);
 
-	let meta = if manager.allow_offline && path.exists() {
+	let meta = if manager.allow_offline() && path.exists() {
 		json_from_file(path).context("Failed to read client meta contents from file")?
 	} else {
-		let mut download = ProgressiveDownload::bytes(&entry.url, client).await?;
+		manager.ensure_online("client meta")?;
+		let mut download = ProgressiveDownload::bytes(&entry.url, client)
+			.await?
+			.with_bandwidth_limit(manager.get_bandwidth_limiter().cloned());
 
 		while !download.is_finished() {
 			download.poll_download().await?;
@@ -395,3 +450,80 @@ pub async fn get(
 
 	Ok(meta)
 }
+
+/// Creates the JSON for a minimal client meta with the given libraries, for use in tests.
+/// Other fields can be changed on the returned value before deserializing it
+#[doc(hidden)]
+pub fn create_test_meta_json(libraries: serde_json::Value) -> serde_json::Value {
+	serde_json::json!({
+		"minecraftArguments": "--username ${auth_player_name}",
+		"assetIndex": {"url": "https://example.com/assets.json"},
+		"assets": "1.0",
+		"downloads": {
+			"client": {"url": "https://example.com/client.jar"},
+			"server": {"url": "https://example.com/server.jar"}
+		},
+		"libraries": libraries,
+		"mainClass": "net.minecraft.client.main.Main",
+		"logging": {
+			"client": {
+				"argument": "-Dlog4j.configurationFile=${path}",
+				"file": {"url": "https://example.com/log4j.xml"}
+			}
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn create_test_meta(java_version: Option<u16>) -> ClientMeta {
+		let mut meta = create_test_meta_json(serde_json::json!([]));
+		if let Some(java_version) = java_version {
+			meta["javaVersion"] =
+				serde_json::json!({"component": "java-runtime", "majorVersion": java_version});
+		}
+		serde_json::from_value(meta).unwrap()
+	}
+
+	#[test]
+	fn test_required_java_version() {
+		let meta = create_test_meta(Some(8));
+		assert_eq!(
+			get_required_java_version(&"1.12.2".into(), &meta),
+			JavaMajorVersion(8)
+		);
+
+		let meta = create_test_meta(Some(21));
+		assert_eq!(
+			get_required_java_version(&"1.21".into(), &meta),
+			JavaMajorVersion(21)
+		);
+	}
+
+	#[test]
+	fn test_required_java_version_fallback() {
+		let meta = create_test_meta(None);
+		assert_eq!(
+			get_required_java_version(&"1.7.10".into(), &meta),
+			JavaMajorVersion(8)
+		);
+		assert_eq!(
+			get_required_java_version(&"1.17.1".into(), &meta),
+			JavaMajorVersion(16)
+		);
+		assert_eq!(
+			get_required_java_version(&"1.20.4".into(), &meta),
+			JavaMajorVersion(17)
+		);
+		assert_eq!(
+			get_required_java_version(&"1.20.6".into(), &meta),
+			JavaMajorVersion(21)
+		);
+		assert_eq!(
+			get_required_java_version(&"23w13a".into(), &meta),
+			JavaMajorVersion(8)
+		);
+	}
+}
@@ -0,0 +1,147 @@
+use mcvm_shared::Side;
+
+use crate::io::files::paths::Paths;
+
+use super::assets::{self, AssetIndex};
+use super::client_meta::ClientMeta;
+use super::libraries;
+
+/// A preview of the files that installing a client version will download.
+/// Files that already exist are not counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadPlan {
+	/// The number of files that will be downloaded. This does not include
+	/// assets if `assets_estimated` is true.
+	pub file_count: usize,
+	/// The total number of bytes that will be downloaded
+	pub total_bytes: u64,
+	/// The number of bytes from the game JAR
+	pub game_jar_bytes: u64,
+	/// The number of bytes from libraries
+	pub library_bytes: u64,
+	/// The number of bytes from assets
+	pub asset_bytes: u64,
+	/// Whether the asset size is only an estimate from the client meta,
+	/// because the asset index has not been downloaded yet
+	pub assets_estimated: bool,
+}
+
+impl DownloadPlan {
+	/// Computes the download plan for a client version from its client meta
+	/// and, if it is available, its asset index. Nothing is downloaded.
+	pub fn compute(
+		version: &str,
+		client_meta: &ClientMeta,
+		asset_index: Option<&AssetIndex>,
+		paths: &Paths,
+	) -> Self {
+		let mut out = Self::default();
+
+		let jar_path = crate::io::minecraft::game_jar::get_path(Side::Client, version, None, paths);
+		if !jar_path.exists() {
+			out.file_count += 1;
+			out.game_jar_bytes = client_meta.downloads.client.size.unwrap_or_default();
+		}
+
+		if let Ok(artifacts) = libraries::get_artifacts(client_meta, paths) {
+			for (artifact, path) in artifacts {
+				if path.exists() {
+					continue;
+				}
+				out.file_count += 1;
+				out.library_bytes += artifact.size.unwrap_or_default();
+			}
+		}
+
+		if let Some(asset_index) = asset_index {
+			for asset in asset_index.objects.values() {
+				if assets::get_object_path(paths, asset).exists() {
+					continue;
+				}
+				out.file_count += 1;
+				out.asset_bytes += asset.size as u64;
+			}
+		} else {
+			out.assets_estimated = true;
+			out.asset_bytes = client_meta.asset_index.total_size.unwrap_or_default();
+		}
+
+		out.total_bytes = out.game_jar_bytes + out.library_bytes + out.asset_bytes;
+
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::net::game_files::client_meta::create_test_meta_json;
+
+	use super::*;
+
+	fn create_meta() -> ClientMeta {
+		let mut meta = create_test_meta_json(serde_json::json!([
+			{
+				"name": "foo:cached:1.0",
+				"downloads": {
+					"artifact": {"path": "foo/cached/1.0/cached-1.0.jar", "url": "https://example.com/cached.jar", "size": 30}
+				}
+			},
+			{
+				"name": "foo:new:1.0",
+				"downloads": {
+					"artifact": {"path": "foo/new/1.0/new-1.0.jar", "url": "https://example.com/new.jar", "size": 70}
+				}
+			}
+		]));
+		meta["assetIndex"]["totalSize"] = 1000.into();
+		meta["downloads"]["client"]["size"] = 500.into();
+		meta["downloads"]["server"]["size"] = 400.into();
+		serde_json::from_value(meta).unwrap()
+	}
+
+	const TEST_INDEX: &str = r#"{
+		"objects": {
+			"minecraft/cached.png": {"hash": "aa00000000000000000000000000000000000000", "size": 10},
+			"minecraft/new.png": {"hash": "bb00000000000000000000000000000000000000", "size": 20},
+			"minecraft/new.ogg": {"hash": "cc00000000000000000000000000000000000000", "size": 40}
+		}
+	}"#;
+
+	#[test]
+	fn test_download_plan() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir(data_dir.to_owned()).unwrap();
+
+		let meta = create_meta();
+		let index: AssetIndex = serde_json::from_str(TEST_INDEX).unwrap();
+
+		// Pre-cache one library and one asset
+		let cached_lib = paths
+			.internal
+			.join("libraries/foo/cached/1.0/cached-1.0.jar");
+		std::fs::create_dir_all(cached_lib.parent().unwrap()).unwrap();
+		std::fs::write(&cached_lib, "").unwrap();
+		let cached_asset = assets::get_object_path(&paths, &index.objects["minecraft/cached.png"]);
+		std::fs::create_dir_all(cached_asset.parent().unwrap()).unwrap();
+		std::fs::write(&cached_asset, "").unwrap();
+
+		let plan = DownloadPlan::compute("1.0", &meta, Some(&index), &paths);
+		assert_eq!(
+			plan,
+			DownloadPlan {
+				file_count: 4,
+				total_bytes: 500 + 70 + 60,
+				game_jar_bytes: 500,
+				library_bytes: 70,
+				asset_bytes: 60,
+				assets_estimated: false,
+			}
+		);
+
+		let plan = DownloadPlan::compute("1.0", &meta, None, &paths);
+		assert!(plan.assets_estimated);
+		assert_eq!(plan.asset_bytes, 1000);
+		assert_eq!(plan.file_count, 2);
+	}
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Context};
@@ -17,7 +17,7 @@ use crate::net::download::{self, get_transfer_limit};
 use mcvm_shared::skip_none;
 use mcvm_shared::util;
 
-use super::client_meta::libraries::ExtractionRules;
+use super::client_meta::libraries::{Artifact, ExtractionRules};
 use super::client_meta::{libraries::Library, ClientMeta};
 
 /// Downloads base client libraries.
@@ -39,45 +39,22 @@ pub async fn get(
 		.join(version)
 		.join("natives");
 	files::create_dir(&natives_path)?;
-	let natives_jars_path = paths.internal.join("natives");
-
-	let mut natives = Vec::new();
-
-	let libraries = get_list(client_meta);
+	let natives = get_native_jars(client_meta, paths)?;
 
 	let mut libs_to_download = Vec::new();
-
-	for lib in libraries {
-		if !lib.natives.is_empty() {
-			let key = skip_none!(get_natives_classifier_key(&lib.natives));
-
-			let classifier = lib
-				.downloads
-				.native_classifiers
-				.get(&key)
-				.ok_or(anyhow!("Native lib artifact does not exist"))?;
-
-			let path = natives_jars_path.join(classifier.path.clone());
-
-			natives.push((path.clone(), &lib.name, &lib.extract));
-			if !manager.should_update_file(&path) {
-				continue;
-			}
-			libs_to_download.push((lib.name.clone(), classifier.clone(), path));
+	for lib in get_list(client_meta) {
+		let Some((artifact, path)) = get_library_artifact(lib, paths)? else {
 			continue;
-		}
-		if let Some(artifact) = &lib.downloads.artifact {
-			let path = libraries_path.join(artifact.path.clone());
-			if !manager.should_update_file(&path) {
-				continue;
-			}
-			libs_to_download.push((lib.name.clone(), artifact.clone(), path));
+		};
+		if !manager.should_update_file(&path) {
 			continue;
 		}
+		libs_to_download.push((lib.name.clone(), artifact.clone(), path));
 	}
 
 	let count = libs_to_download.len();
 	if count > 0 {
+		manager.ensure_online(&format!("{count} libraries"))?;
 		o.display(
 			MessageContents::StartProcess(translate!(
 				o,
@@ -95,6 +72,7 @@ pub async fn get(
 	let sem = Arc::new(Semaphore::new(get_transfer_limit()));
 	for (name, library, path) in libs_to_download {
 		let client = client.clone();
+		let limiter = manager.get_bandwidth_limiter().cloned();
 		let sem = sem.clone();
 		let path_clone = path.clone();
 		let fut = async move {
@@ -102,7 +80,7 @@ pub async fn get(
 
 			let _permit = sem.acquire().await;
 
-			let response = download::bytes(library.url, &client)
+			let response = download::bytes_limited(library.url, &client, limiter.as_ref())
 				.await
 				.context("Failed to download library")?;
 			tokio::fs::write(&path_clone, response)
@@ -147,12 +125,13 @@ pub async fn get(
 		);
 	}
 
-	for (path, name, extract) in natives {
+	for (path, lib) in natives {
+		let name = &lib.name;
 		o.display(
 			MessageContents::StartProcess(translate!(o, StartExtractingNative, "lib" = name)),
 			MessageLevel::Debug,
 		);
-		let natives_result = extract_native(&path, &natives_path, extract, manager, o)
+		let natives_result = extract_native(&path, &natives_path, &lib.extract, manager, o)
 			.with_context(|| format!("Failed to extract native library {name}"))?;
 		out.merge(natives_result);
 	}
@@ -168,33 +147,72 @@ pub async fn get(
 
 /// Gets the classpath from Minecraft libraries
 pub fn get_classpath(client_meta: &ClientMeta, paths: &Paths) -> anyhow::Result<Classpath> {
-	let natives_jars_path = paths.internal.join("natives");
-	let libraries_path = paths.internal.join("libraries");
-
 	let mut classpath = Classpath::new();
-	let libraries = get_list(client_meta);
-	for lib in libraries {
-		if !lib.natives.is_empty() {
-			let key = skip_none!(get_natives_classifier_key(&lib.natives));
-
-			let classifier = lib
-				.downloads
-				.native_classifiers
-				.get(&key)
-				.ok_or(anyhow!("Native lib artifact does not exist"))?;
+	for (.., path) in get_artifacts(client_meta, paths)? {
+		classpath.add_path(&path)?;
+	}
+	Ok(classpath)
+}
 
-			let path = natives_jars_path.join(classifier.path.clone());
-			classpath.add_path(&path)?;
+/// Gets the artifacts of all of the libraries allowed on this system,
+/// along with the paths they are stored at
+pub fn get_artifacts<'meta>(
+	client_meta: &'meta ClientMeta,
+	paths: &Paths,
+) -> anyhow::Result<Vec<(&'meta Artifact, PathBuf)>> {
+	let mut out = Vec::new();
+	for lib in get_list(client_meta) {
+		if let Some(artifact) = get_library_artifact(lib, paths)? {
+			out.push(artifact);
+		}
+	}
+	Ok(out)
+}
 
+/// Gets the native JARs that have to be extracted, along with the libraries they come from.
+/// Older versions list the natives of a library in a natives block with a classifier for each OS,
+/// while newer versions have a separate library for each native with a Maven classifier like
+/// `natives-linux`
+fn get_native_jars<'meta>(
+	client_meta: &'meta ClientMeta,
+	paths: &Paths,
+) -> anyhow::Result<Vec<(PathBuf, &'meta Library)>> {
+	let mut out = Vec::new();
+	for lib in get_list(client_meta) {
+		if lib.natives.is_empty() && get_native_classifier(lib).is_none() {
 			continue;
 		}
-		if let Some(artifact) = &lib.downloads.artifact {
-			let path = libraries_path.join(artifact.path.clone());
-			classpath.add_path(&path)?;
-			continue;
+		if let Some((.., path)) = get_library_artifact(lib, paths)? {
+			out.push((path, lib));
 		}
 	}
-	Ok(classpath)
+	Ok(out)
+}
+
+/// Gets the artifact of a library to download for this system, along with the path it is stored at
+fn get_library_artifact<'meta>(
+	lib: &'meta Library,
+	paths: &Paths,
+) -> anyhow::Result<Option<(&'meta Artifact, PathBuf)>> {
+	if !lib.natives.is_empty() {
+		let Some(key) = get_natives_classifier_key(&lib.natives) else {
+			return Ok(None);
+		};
+
+		let classifier = lib
+			.downloads
+			.native_classifiers
+			.get(&key)
+			.ok_or(anyhow!("Native lib artifact does not exist"))?;
+
+		let path = paths.internal.join("natives").join(&classifier.path);
+		return Ok(Some((classifier, path)));
+	}
+
+	Ok(lib.downloads.artifact.as_ref().map(|artifact| {
+		let path = paths.internal.join("libraries").join(&artifact.path);
+		(artifact, path)
+	}))
 }
 
 /// Get the key for the natives classifier
@@ -207,26 +225,61 @@ fn get_natives_classifier_key(classifiers: &HashMap<String, String>) -> Option<S
 	Some(key)
 }
 
-/// Checks the rules of a game library to see if it should be installed
+/// Gets the Maven classifier of a library if it is a native, like `natives-linux`
+/// in `org.lwjgl:lwjgl:3.3.1:natives-linux`
+fn get_native_classifier(lib: &Library) -> Option<&str> {
+	let classifier = lib.name.split(':').nth(3)?;
+	classifier.starts_with("natives-").then_some(classifier)
+}
+
+/// Checks whether a native classifier is for the current architecture. The OS is already
+/// checked by the library rules. Classifiers without an architecture, like `natives-linux`, are allowed
+fn is_native_classifier_for_arch(classifier: &str) -> bool {
+	let Some(arch) = classifier.splitn(3, '-').nth(2) else {
+		return true;
+	};
+	let arch = match arch {
+		"arm64" | "aarch_64" => "aarch64",
+		"arm32" => "arm",
+		other => other,
+	};
+
+	arch == util::ARCH_STRING
+}
+
+/// Checks the rules of a game library to see if it should be installed on this platform.
+/// Rules are applied in order, and the last rule that matches decides whether the library
+/// is allowed. Libraries without any rules are always allowed.
 fn is_allowed(lib: &Library) -> bool {
-	for rule in &lib.rules {
-		let allowed = rule.action.is_allowed();
-		if let Some(os_name) = &rule.os.name {
-			if allowed != (os_name.to_string() == util::OS_STRING) {
-				return false;
-			}
+	if let Some(classifier) = get_native_classifier(lib) {
+		if !is_native_classifier_for_arch(classifier) {
+			return false;
 		}
-		if let Some(os_arch) = &rule.os.arch {
-			if allowed != (os_arch.to_string() == util::ARCH_STRING) {
-				return false;
-			}
+	}
+	if lib.rules.is_empty() {
+		return true;
+	}
+
+	let mut allowed = false;
+	for rule in &lib.rules {
+		let os_matches = match &rule.os.name {
+			Some(os_name) => os_name.to_string() == util::OS_STRING,
+			None => true,
+		};
+		let arch_matches = match &rule.os.arch {
+			Some(os_arch) => os_arch.to_string() == util::ARCH_STRING,
+			None => true,
+		};
+		if os_matches && arch_matches {
+			allowed = rule.action.is_allowed();
 		}
 	}
 
-	true
+	allowed
 }
 
 /// Extract the files of a native library into the natives directory.
+/// Newer native JARs store their files in subdirectories, so the files are flattened
 fn extract_native(
 	path: &Path,
 	natives_dir: &Path,
@@ -250,7 +303,7 @@ fn extract_native(
 		if let Some(extension) = rel_path.extension() {
 			match extension.to_str() {
 				Some("so" | "dylib" | "dll") => {
-					let out_path = natives_dir.join(rel_path);
+					let out_path = natives_dir.join(skip_none!(rel_path.file_name()));
 					if !manager.should_update_file(&out_path) {
 						continue;
 					}
@@ -280,3 +333,195 @@ fn extract_native(
 pub fn get_list(client_meta: &ClientMeta) -> impl Iterator<Item = &Library> {
 	client_meta.libraries.iter().filter(|lib| is_allowed(lib))
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::net::game_files::client_meta::create_test_meta_json;
+
+	use super::*;
+
+	fn parse_library(json: &str) -> Library {
+		serde_json::from_str(json).unwrap()
+	}
+
+	#[test]
+	fn test_library_rules() {
+		let lib = parse_library(r#"{"name": "foo:bar:1.0"}"#);
+		assert!(is_allowed(&lib));
+
+		let lib = parse_library(
+			r#"{
+				"name": "foo:bar:1.0",
+				"rules": [
+					{"action": "allow"},
+					{"action": "disallow", "os": {"name": "osx"}}
+				]
+			}"#,
+		);
+		assert_eq!(is_allowed(&lib), util::OS_STRING != "macos");
+	}
+
+	#[test]
+	#[cfg(target_os = "linux")]
+	fn test_windows_native_skipped_on_linux() {
+		let lib = parse_library(
+			r#"{
+				"name": "org.lwjgl:lwjgl:3.3.1:natives-windows",
+				"downloads": {
+					"artifact": {
+						"path": "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-windows.jar",
+						"url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-windows.jar"
+					}
+				},
+				"rules": [
+					{"action": "allow", "os": {"name": "windows"}}
+				]
+			}"#,
+		);
+		assert!(!is_allowed(&lib));
+
+		// Old-style native classifiers without a Linux entry
+		let lib = parse_library(
+			r#"{
+				"name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+				"natives": {"windows": "natives-windows"},
+				"downloads": {
+					"classifiers": {
+						"natives-windows": {
+							"path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-windows.jar",
+							"url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-windows.jar"
+						}
+					}
+				}
+			}"#,
+		);
+		assert!(is_allowed(&lib));
+		assert_eq!(get_natives_classifier_key(&lib.natives), None);
+	}
+
+	fn parse_meta(libraries: &str) -> ClientMeta {
+		let libraries = serde_json::from_str(libraries).unwrap();
+		serde_json::from_value(create_test_meta_json(libraries)).unwrap()
+	}
+
+	fn get_native_jar_paths(meta: &ClientMeta, paths: &Paths) -> Vec<PathBuf> {
+		get_native_jars(meta, paths)
+			.unwrap()
+			.into_iter()
+			.map(|(path, ..)| path)
+			.collect()
+	}
+
+	#[test]
+	fn test_native_classifier_arch() {
+		assert!(is_native_classifier_for_arch("natives-linux"));
+		assert_eq!(
+			is_native_classifier_for_arch("natives-macos-arm64"),
+			util::ARCH_STRING == "aarch64"
+		);
+		assert_eq!(
+			is_native_classifier_for_arch("natives-windows-x86"),
+			util::ARCH_STRING == "x86"
+		);
+	}
+
+	#[test]
+	#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+	fn test_modern_natives() {
+		let paths = Paths::new_no_create().unwrap();
+		let lib = |classifier: &str, os: &str| {
+			format!(
+				r#"{{
+					"name": "org.lwjgl:lwjgl:3.3.1:{classifier}",
+					"downloads": {{
+						"artifact": {{
+							"path": "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-{classifier}.jar",
+							"url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-{classifier}.jar"
+						}}
+					}},
+					"rules": [{{"action": "allow", "os": {{"name": "{os}"}}}}]
+				}}"#
+			)
+		};
+		let meta = parse_meta(&format!(
+			r#"[
+				{{
+					"name": "org.lwjgl:lwjgl:3.3.1",
+					"downloads": {{
+						"artifact": {{
+							"path": "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar",
+							"url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"
+						}}
+					}}
+				}},
+				{},
+				{},
+				{}
+			]"#,
+			lib("natives-linux", "linux"),
+			lib("natives-linux-arm64", "linux"),
+			lib("natives-windows", "windows"),
+		));
+
+		let libraries = paths.internal.join("libraries/org/lwjgl/lwjgl/3.3.1");
+		assert_eq!(
+			get_native_jar_paths(&meta, &paths),
+			vec![libraries.join("lwjgl-3.3.1-natives-linux.jar")]
+		);
+		// Modern natives are also put on the classpath
+		let artifacts: Vec<_> = get_artifacts(&meta, &paths)
+			.unwrap()
+			.into_iter()
+			.map(|(.., path)| path)
+			.collect();
+		assert_eq!(
+			artifacts,
+			vec![
+				libraries.join("lwjgl-3.3.1.jar"),
+				libraries.join("lwjgl-3.3.1-natives-linux.jar")
+			]
+		);
+	}
+
+	#[test]
+	#[cfg(target_os = "linux")]
+	fn test_legacy_natives() {
+		let paths = Paths::new_no_create().unwrap();
+		let meta = parse_meta(
+			r#"[
+				{
+					"name": "org.lwjgl.lwjgl:lwjgl:2.9.4",
+					"downloads": {
+						"artifact": {
+							"path": "org/lwjgl/lwjgl/lwjgl/2.9.4/lwjgl-2.9.4.jar",
+							"url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/lwjgl/2.9.4/lwjgl-2.9.4.jar"
+						}
+					}
+				},
+				{
+					"name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+					"natives": {"linux": "natives-linux", "windows": "natives-windows"},
+					"downloads": {
+						"classifiers": {
+							"natives-linux": {
+								"path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-linux.jar",
+								"url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-linux.jar"
+							},
+							"natives-windows": {
+								"path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-windows.jar",
+								"url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-windows.jar"
+							}
+						}
+					}
+				}
+			]"#,
+		);
+
+		assert_eq!(
+			get_native_jar_paths(&meta, &paths),
+			vec![paths.internal.join(
+				"natives/org/lwjgl/lwjgl/lwjgl-platform/2.9.4/lwjgl-platform-2.9.4-natives-linux.jar"
+			)]
+		);
+	}
+}
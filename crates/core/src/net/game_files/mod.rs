@@ -2,8 +2,14 @@
 pub mod assets;
 /// Structure for the client metadata file
 pub mod client_meta;
+/// Previewing what a version install will download
+pub mod download_plan;
 /// Downloading game Java libraries
 pub mod libraries;
+/// Removing the files of installed versions
+pub mod uninstall;
+/// Checking the files of installed versions for corruption
+pub mod verify;
 /// Downloading and using the version manifest
 pub mod version_manifest;
 
@@ -40,6 +46,7 @@ pub mod game_jar {
 		if !manager.should_update_file(&path) {
 			return Ok(());
 		}
+		manager.ensure_online(&format!("{side_str} JAR"))?;
 
 		let process = OutputProcess::new(o);
 		let download_message = translate!(process.0, StartDownloadingGameJar, "side" = &side_str);
@@ -53,7 +60,9 @@ pub mod game_jar {
 			Side::Server => &client_meta.downloads.server,
 		};
 
-		let mut download = ProgressiveDownload::file(&download.url, path, client).await?;
+		let mut download = ProgressiveDownload::file(&download.url, path, client)
+			.await?
+			.with_bandwidth_limit(manager.get_bandwidth_limiter().cloned());
 		while !download.is_finished() {
 			download.poll_download().await?;
 			process.0.display(
@@ -99,6 +108,7 @@ pub mod log_config {
 		if !manager.should_update_file(&path) {
 			return Ok(());
 		}
+		manager.ensure_online("logging config")?;
 
 		let url = &client_meta.logging.client.file.url;
 		download::file(url, &path, client).await?;
@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use mcvm_shared::Side;
+
+use crate::io::files::paths::Paths;
+use crate::io::json_from_file;
+
+use super::assets::{self, AssetIndex};
+use super::client_meta::ClientMeta;
+use super::libraries;
+
+/// Removes the stored files of a version, returning the number of bytes that were freed.
+/// Libraries and assets are only removed if no other installed version references them.
+pub fn uninstall(version: &str, paths: &Paths) -> anyhow::Result<u64> {
+	let version_dir = get_version_dir(version, paths);
+	let files = get_version_files(version, paths)?;
+	if !version_dir.exists() && files.iter().all(|x| !x.exists()) {
+		bail!("Version '{version}' is not installed");
+	}
+
+	// Collect the files of every other version so that we don't remove anything shared
+	let mut shared = HashSet::new();
+	for other in get_installed_versions(paths).context("Failed to get installed versions")? {
+		if other == version {
+			continue;
+		}
+		let other_files = get_version_files(&other, paths)
+			.with_context(|| format!("Failed to get the files used by version '{other}'"))?;
+		shared.extend(other_files);
+	}
+
+	let mut freed = 0;
+	for file in files.difference(&shared) {
+		let Ok(metadata) = file.metadata() else {
+			continue;
+		};
+		std::fs::remove_file(file)
+			.with_context(|| format!("Failed to remove file {}", file.display()))?;
+		freed += metadata.len();
+	}
+
+	if version_dir.exists() {
+		freed += get_dir_size(&version_dir);
+		std::fs::remove_dir_all(&version_dir).context("Failed to remove version directory")?;
+	}
+
+	Ok(freed)
+}
+
+/// Gets the names of all of the versions that have a stored client meta
+pub fn get_installed_versions(paths: &Paths) -> anyhow::Result<Vec<String>> {
+	let versions_dir = paths.internal.join("versions");
+	if !versions_dir.exists() {
+		return Ok(Vec::new());
+	}
+
+	let mut out = Vec::new();
+	for entry in versions_dir
+		.read_dir()
+		.context("Failed to read versions directory")?
+	{
+		let entry = entry?;
+		let Ok(name) = entry.file_name().into_string() else {
+			continue;
+		};
+		if get_client_meta_path(&name, paths).exists() {
+			out.push(name);
+		}
+	}
+	out.sort();
+
+	Ok(out)
+}
+
+/// Gets the files outside of the version directory that a stored version uses
+fn get_version_files(version: &str, paths: &Paths) -> anyhow::Result<HashSet<PathBuf>> {
+	let mut out = HashSet::new();
+	out.insert(crate::io::minecraft::game_jar::get_path(
+		Side::Client,
+		version,
+		None,
+		paths,
+	));
+	out.insert(crate::io::minecraft::game_jar::get_path(
+		Side::Server,
+		version,
+		None,
+		paths,
+	));
+
+	let meta_path = get_client_meta_path(version, paths);
+	if meta_path.exists() {
+		let client_meta: ClientMeta =
+			json_from_file(meta_path).context("Failed to read client meta")?;
+		for (_, path) in libraries::get_artifacts(&client_meta, paths)? {
+			out.insert(path);
+		}
+	}
+
+	let index_path = assets::get_index_path(paths, version);
+	if index_path.exists() {
+		let index: AssetIndex =
+			json_from_file(&index_path).context("Failed to read asset index")?;
+		for asset in index.objects.values() {
+			out.insert(assets::get_object_path(paths, asset));
+		}
+		out.insert(index_path);
+	}
+
+	Ok(out)
+}
+
+/// Get the directory where the client meta and natives for a version are stored
+fn get_version_dir(version: &str, paths: &Paths) -> PathBuf {
+	paths.internal.join("versions").join(version)
+}
+
+/// Get the path to the stored client meta for a version
+fn get_client_meta_path(version: &str, paths: &Paths) -> PathBuf {
+	get_version_dir(version, paths).join(format!("{version}.json"))
+}
+
+/// Gets the total size of the files in a directory
+fn get_dir_size(dir: &Path) -> u64 {
+	let Ok(entries) = dir.read_dir() else {
+		return 0;
+	};
+	let mut out = 0;
+	for entry in entries.flatten() {
+		let Ok(metadata) = entry.metadata() else {
+			continue;
+		};
+		if metadata.is_dir() {
+			out += get_dir_size(&entry.path());
+		} else {
+			out += metadata.len();
+		}
+	}
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::net::game_files::client_meta::create_test_meta_json;
+
+	use super::*;
+
+	fn create_meta(libraries: &[&str]) -> String {
+		let libraries: Vec<_> = libraries
+			.iter()
+			.map(|name| {
+				serde_json::json!({
+					"name": format!("foo:{name}:1.0"),
+					"downloads": {
+						"artifact": {
+							"path": format!("foo/{name}.jar"),
+							"url": format!("https://example.com/{name}.jar")
+						}
+					}
+				})
+			})
+			.collect();
+		create_test_meta_json(libraries.into()).to_string()
+	}
+
+	fn write_file(path: &Path, contents: &str) {
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, contents).unwrap();
+	}
+
+	#[test]
+	fn test_uninstall_shared_library() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir(data_dir.to_owned()).unwrap();
+
+		write_file(
+			&get_client_meta_path("1.0", &paths),
+			&create_meta(&["shared", "old"]),
+		);
+		write_file(
+			&get_client_meta_path("2.0", &paths),
+			&create_meta(&["shared", "new"]),
+		);
+		let libraries_dir = paths.internal.join("libraries/foo");
+		write_file(&libraries_dir.join("shared.jar"), "shared");
+		write_file(&libraries_dir.join("old.jar"), "old");
+		write_file(&libraries_dir.join("new.jar"), "new");
+		let jar_path = crate::io::minecraft::game_jar::get_path(Side::Client, "1.0", None, &paths);
+		write_file(&jar_path, "client");
+
+		assert_eq!(get_installed_versions(&paths).unwrap(), vec!["1.0", "2.0"]);
+
+		let meta_size = create_meta(&["shared", "old"]).len() as u64;
+		let freed = uninstall("1.0", &paths).unwrap();
+		assert_eq!(freed, meta_size + "old".len() as u64 + "client".len() as u64);
+
+		assert!(libraries_dir.join("shared.jar").exists());
+		assert!(libraries_dir.join("new.jar").exists());
+		assert!(!libraries_dir.join("old.jar").exists());
+		assert!(!jar_path.exists());
+		assert_eq!(get_installed_versions(&paths).unwrap(), vec!["2.0"]);
+
+		assert!(uninstall("1.0", &paths).is_err());
+	}
+}
@@ -0,0 +1,182 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use mcvm_shared::Side;
+use sha1::{Digest, Sha1};
+
+use crate::io::files::paths::Paths;
+use crate::io::json_from_file;
+
+use super::assets::{self, AssetIndex};
+use super::client_meta::ClientMeta;
+use super::libraries;
+
+/// A file of an installed version along with what it should contain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameFile {
+	/// What type of file this is
+	pub kind: GameFileKind,
+	/// The path to the file
+	pub path: PathBuf,
+	/// The URL the file can be downloaded from
+	pub url: String,
+	/// The expected SHA-1 hash of the file, if it is known
+	pub sha1: Option<String>,
+}
+
+/// Different types of game files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameFileKind {
+	/// The game JAR file
+	GameJar,
+	/// A Java library
+	Library,
+	/// A game asset
+	Asset,
+}
+
+/// The state of a file after checking it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+	/// The file exists and matches its expected hash
+	Intact,
+	/// The file does not exist
+	Missing,
+	/// The file exists but does not match its expected hash
+	Corrupt,
+}
+
+/// Gets the files of a version for a side. Libraries and assets are only used by the client.
+/// The asset index should be provided if it is available so that assets can be included
+pub fn get_game_files(
+	version: &str,
+	side: Side,
+	client_meta: &ClientMeta,
+	asset_index: Option<&AssetIndex>,
+	paths: &Paths,
+) -> anyhow::Result<Vec<GameFile>> {
+	let mut out = Vec::new();
+
+	let jar_download = match side {
+		Side::Client => &client_meta.downloads.client,
+		Side::Server => &client_meta.downloads.server,
+	};
+	out.push(GameFile {
+		kind: GameFileKind::GameJar,
+		path: crate::io::minecraft::game_jar::get_path(side, version, None, paths),
+		url: jar_download.url.clone(),
+		sha1: jar_download.sha1.clone(),
+	});
+
+	if let Side::Server = side {
+		return Ok(out);
+	}
+
+	let artifacts =
+		libraries::get_artifacts(client_meta, paths).context("Failed to get library artifacts")?;
+	for (artifact, path) in artifacts {
+		out.push(GameFile {
+			kind: GameFileKind::Library,
+			path,
+			url: artifact.url.clone(),
+			sha1: artifact.sha1.clone(),
+		});
+	}
+
+	if let Some(asset_index) = asset_index {
+		let mut objects: Vec<_> = asset_index.objects.values().collect();
+		objects.sort_by(|a, b| a.hash.cmp(&b.hash));
+		objects.dedup_by(|a, b| a.hash == b.hash);
+		for asset in objects {
+			out.push(GameFile {
+				kind: GameFileKind::Asset,
+				path: assets::get_object_path(paths, asset),
+				url: format!(
+					"https://resources.download.minecraft.net/{}",
+					asset.get_hash_path()
+				),
+				sha1: Some(asset.hash.clone()),
+			});
+		}
+	}
+
+	Ok(out)
+}
+
+/// Gets the files of a version using the client meta and asset index that were stored when it was installed
+pub fn get_stored_game_files(
+	version: &str,
+	side: Side,
+	paths: &Paths,
+) -> anyhow::Result<Vec<GameFile>> {
+	let meta_path = paths
+		.internal
+		.join("versions")
+		.join(version)
+		.join(format!("{version}.json"));
+	let client_meta: ClientMeta = json_from_file(meta_path)
+		.context("Failed to read client meta. Has the version been installed?")?;
+
+	let index_path = assets::get_index_path(paths, version);
+	let asset_index: Option<AssetIndex> = if index_path.exists() {
+		Some(json_from_file(index_path).context("Failed to read asset index")?)
+	} else {
+		None
+	};
+
+	get_game_files(version, side, &client_meta, asset_index.as_ref(), paths)
+}
+
+/// Checks whether a file exists and matches its expected SHA-1 hash.
+/// If there is no expected hash, the file only has to exist
+pub fn check_file(path: &Path, sha1: Option<&str>) -> anyhow::Result<FileStatus> {
+	if !path.exists() {
+		return Ok(FileStatus::Missing);
+	}
+	let Some(expected) = sha1 else {
+		return Ok(FileStatus::Intact);
+	};
+
+	let mut file = BufReader::new(File::open(path).context("Failed to open file")?);
+	let mut hasher = Sha1::new();
+	let mut buf = [0; 8192];
+	loop {
+		let count = file.read(&mut buf).context("Failed to read file")?;
+		if count == 0 {
+			break;
+		}
+		hasher.update(&buf[..count]);
+	}
+	let actual = hex::encode(hasher.finalize());
+
+	if actual.eq_ignore_ascii_case(expected) {
+		Ok(FileStatus::Intact)
+	} else {
+		Ok(FileStatus::Corrupt)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_check_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let path = dir.join("file.txt");
+		std::fs::write(&path, "hello").unwrap();
+
+		let hash = "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d";
+		assert_eq!(check_file(&path, Some(hash)).unwrap(), FileStatus::Intact);
+		assert_eq!(check_file(&path, None).unwrap(), FileStatus::Intact);
+		std::fs::write(&path, "goodbye").unwrap();
+		assert_eq!(check_file(&path, Some(hash)).unwrap(), FileStatus::Corrupt);
+		assert_eq!(
+			check_file(&dir.join("missing.txt"), Some(hash)).unwrap(),
+			FileStatus::Missing
+		);
+	}
+}
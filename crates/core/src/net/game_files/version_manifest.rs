@@ -10,6 +10,10 @@ use crate::io::{json_from_file, json_to_file};
 use crate::net::download::ProgressiveDownload;
 use crate::util::versions::VersionName;
 
+/// The URL to the version manifest
+pub const VERSION_MANIFEST_URL: &str =
+	"https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
 /// JSON format for the version manifest that contains all available Minecraft versions
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct VersionManifest {
@@ -127,15 +131,15 @@ async fn get_contents(
 	let mut path = paths.internal.join("versions");
 	files::create_dir(&path)?;
 	path.push("manifest.json");
-	if manager.allow_offline && !force && path.exists() {
+	let use_cached = manager.is_offline() || (manager.allow_offline() && !force);
+	if use_cached && path.exists() {
 		return json_from_file(path).context("Failed to read manifest contents from file");
 	}
+	manager.ensure_online("version manifest")?;
 
-	let mut download = ProgressiveDownload::bytes(
-		"https://piston-meta.mojang.com/mc/game/version_manifest_v2.json",
-		client,
-	)
-	.await?;
+	let mut download = ProgressiveDownload::bytes(VERSION_MANIFEST_URL, client)
+		.await?
+		.with_bandwidth_limit(manager.get_bandwidth_limiter().cloned());
 
 	while !download.is_finished() {
 		download.poll_download().await?;
@@ -1,5 +1,5 @@
 use crate::net::download;
-use mcvm_shared::util::{ARCH_STRING, OS_STRING, PREFERRED_ARCHIVE};
+use mcvm_shared::util::{ArchiveType, ARCH_STRING, OS_STRING};
 
 use anyhow::{anyhow, Context};
 use reqwest::Client;
@@ -80,12 +80,15 @@ pub mod adoptium {
 pub mod zulu {
 	use super::*;
 
-	use mcvm_shared::util::preferred_archive_extension;
 	use serde::Deserialize;
 
-	/// Gets the newest Zulu package for a major Java version
-	pub async fn get_latest(major_version: &str, client: &Client) -> anyhow::Result<PackageFormat> {
-		let url = json_url(major_version);
+	/// Gets the newest Zulu package for a major Java version that is downloaded as the given type of archive
+	pub async fn get_latest(
+		major_version: &str,
+		archive: ArchiveType,
+		client: &Client,
+	) -> anyhow::Result<PackageFormat> {
+		let url = json_url(major_version, archive);
 		let manifest = download::json::<Vec<PackageFormat>>(&url, client)
 			.await
 			.context("Failed to download manifest of Zulu versions")?;
@@ -97,9 +100,10 @@ pub mod zulu {
 	}
 
 	/// Gets the URL to the JSON file for a major Java version
-	fn json_url(major_version: &str) -> String {
+	fn json_url(major_version: &str, archive: ArchiveType) -> String {
+		let archive = archive.as_str();
 		format!(
-			"https://api.azul.com/metadata/v1/zulu/packages/?java_version={major_version}&os={OS_STRING}&arch={ARCH_STRING}&archive_type={PREFERRED_ARCHIVE}&java_package_type=jre&latest=true&java_package_features=headfull&release_status=ga&availability_types=CA&certifications=tck&page=1&page_size=100"
+			"https://api.azul.com/metadata/v1/zulu/packages/?java_version={major_version}&os={OS_STRING}&arch={ARCH_STRING}&archive_type={archive}&java_package_type=jre&latest=true&java_package_features=headfull&release_status=ga&availability_types=CA&certifications=tck&page=1&page_size=100"
 		)
 	}
 
@@ -113,8 +117,8 @@ pub mod zulu {
 	}
 
 	/// Gets the name of the extracted directory by removing the archive file extension
-	pub fn extract_dir_name(name: &str) -> String {
-		name.replacen(&preferred_archive_extension(), "", 1)
+	pub fn extract_dir_name(name: &str, archive: ArchiveType) -> String {
+		name.replacen(&archive.extension(), "", 1)
 	}
 
 	#[cfg(test)]
@@ -123,8 +127,17 @@ pub mod zulu {
 
 		#[test]
 		fn test_extract_dir_name() {
-			let name = format!("hello.{PREFERRED_ARCHIVE}");
-			assert_eq!(extract_dir_name(&name), "hello");
+			assert_eq!(
+				extract_dir_name("hello.tar.gz", ArchiveType::TarGz),
+				"hello"
+			);
+			assert_eq!(extract_dir_name("hello.zip", ArchiveType::Zip), "hello");
+		}
+
+		#[test]
+		fn test_json_url_archive() {
+			assert!(json_url("17", ArchiveType::TarGz).contains("&archive_type=tar.gz&"));
+			assert!(json_url("17", ArchiveType::Zip).contains("&archive_type=zip&"));
 		}
 	}
 }
@@ -132,23 +145,41 @@ pub mod zulu {
 /// Downloading GraalVM
 pub mod graalvm {
 	use bytes::Bytes;
-	use mcvm_shared::util::preferred_archive_extension;
 
 	use super::*;
 
-	/// Downloads the contents of the GraalVM archive
-	pub async fn get_latest(major_version: &str, client: &Client) -> anyhow::Result<Bytes> {
-		let url = download_url(major_version);
+	/// Downloads the contents of the GraalVM archive as the given type of archive
+	pub async fn get_latest(
+		major_version: &str,
+		archive: ArchiveType,
+		client: &Client,
+	) -> anyhow::Result<Bytes> {
+		let url = download_url(major_version, archive);
 		download::bytes(url, client).await
 	}
 
 	/// Gets the download URL
-	fn download_url(major_version: &str) -> String {
+	fn download_url(major_version: &str, archive: ArchiveType) -> String {
 		format!(
 			"https://download.oracle.com/graalvm/{major_version}/latest/graalvm-jdk-{major_version}_{}-{}_bin{}",
 			OS_STRING,
 			ARCH_STRING,
-			preferred_archive_extension()
+			archive.extension()
 		)
 	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		#[test]
+		fn test_download_url_archive() {
+			let url = download_url("21", ArchiveType::TarGz);
+			assert!(
+				url.starts_with("https://download.oracle.com/graalvm/21/latest/graalvm-jdk-21_")
+			);
+			assert!(url.ends_with("_bin.tar.gz"));
+			assert!(download_url("21", ArchiveType::Zip).ends_with("_bin.zip"));
+		}
+	}
 }
@@ -1,6 +1,9 @@
+use anyhow::Context;
 use mcvm_auth::mc::{call_mc_api, Keypair};
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Struct for a Minecraft Profile from the Minecraft Services API
 #[derive(Deserialize, Serialize, Debug)]
@@ -16,6 +19,23 @@ pub struct MinecraftUserProfile {
 	pub capes: Vec<Cape>,
 }
 
+impl MinecraftUserProfile {
+	/// Get the skin that this user is currently using, if there is one
+	pub fn get_active_skin(&self) -> Option<&Skin> {
+		self.skins
+			.iter()
+			.find(|x| x.cosmetic.state == CosmeticState::Active)
+	}
+
+	/// Pick an owned skin with a variant, preferring the active skin
+	pub fn select_skin(&self, variant: SkinVariant) -> Option<&Skin> {
+		match self.get_active_skin() {
+			Some(active) if active.variant == variant => Some(active),
+			_ => self.skins.iter().find(|x| x.variant == variant),
+		}
+	}
+}
+
 /// A skin for a Minecraft user
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Skin {
@@ -23,12 +43,17 @@ pub struct Skin {
 	#[serde(flatten)]
 	pub cosmetic: Cosmetic,
 	/// What variant of skin this is
+	#[serde(
+		deserialize_with = "deserialize_api_skin_variant",
+		serialize_with = "serialize_api_skin_variant"
+	)]
 	pub variant: SkinVariant,
 }
 
 /// Variant for a skin
 #[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
-#[serde(rename_all = "UPPERCASE")]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(rename_all = "lowercase")]
 pub enum SkinVariant {
 	/// The classic wide-arm player model
 	Classic,
@@ -36,6 +61,39 @@ pub enum SkinVariant {
 	Slim,
 }
 
+impl SkinVariant {
+	/// Get the name of this variant used when changing skins with the API
+	pub fn to_api_str(&self) -> &'static str {
+		match self {
+			Self::Classic => "classic",
+			Self::Slim => "slim",
+		}
+	}
+}
+
+/// Deserializes a skin variant from the API, which uses uppercase names
+fn deserialize_api_skin_variant<'de, D: Deserializer<'de>>(
+	deserializer: D,
+) -> Result<SkinVariant, D::Error> {
+	let name = String::deserialize(deserializer)?;
+	match name.as_str() {
+		"CLASSIC" => Ok(SkinVariant::Classic),
+		"SLIM" => Ok(SkinVariant::Slim),
+		other => Err(serde::de::Error::unknown_variant(
+			other,
+			&["CLASSIC", "SLIM"],
+		)),
+	}
+}
+
+/// Serializes a skin variant with the uppercase names that the API uses
+fn serialize_api_skin_variant<S: Serializer>(
+	variant: &SkinVariant,
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	serializer.serialize_str(&variant.to_api_str().to_uppercase())
+}
+
 /// A cape for a Minecraft user
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Cape {
@@ -80,6 +138,88 @@ pub async fn get_user_profile(
 	.await
 }
 
+/// Set the active skin of a Minecraft user to one of the skins they own
+pub async fn set_active_skin(
+	access_token: &str,
+	skin: &Skin,
+	client: &Client,
+) -> anyhow::Result<()> {
+	let body = serde_json::json!({
+		"variant": skin.variant.to_api_str(),
+		"url": skin.cosmetic.url,
+	});
+	client
+		.post("https://api.minecraftservices.com/minecraft/profile/skins")
+		.header("Authorization", format!("Bearer {access_token}"))
+		.json(&body)
+		.send()
+		.await
+		.context("Failed to send skin change request")?
+		.error_for_status()
+		.context("Skin change request failed")?;
+
+	Ok(())
+}
+
+/// The entitlements of a Minecraft account, which decide whether it can play the game
+#[derive(Deserialize, Debug)]
+pub struct Entitlements {
+	/// The items that the account is entitled to
+	#[serde(default)]
+	pub items: Vec<Entitlement>,
+}
+
+impl Entitlements {
+	/// Checks whether the account has an entitlement with a name
+	pub fn has_item(&self, name: &str) -> bool {
+		self.items.iter().any(|x| x.name == name)
+	}
+
+	/// Gets how the account is able to play Java Edition
+	pub fn get_ownership(&self) -> GameOwnership {
+		// Game Pass gives the game without the product that comes from buying it
+		if self.has_item("product_minecraft") {
+			GameOwnership::Purchased
+		} else if self.has_item("game_minecraft")
+			|| self.has_item("product_game_pass_pc")
+			|| self.has_item("product_game_pass_ultimate")
+		{
+			GameOwnership::GamePass
+		} else {
+			GameOwnership::None
+		}
+	}
+}
+
+/// A single entitlement of a Minecraft account
+#[derive(Deserialize, Debug)]
+pub struct Entitlement {
+	/// The name of the entitlement, such as 'product_minecraft'
+	pub name: String,
+}
+
+/// How an account is able to play Java Edition
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameOwnership {
+	/// The game was bought
+	Purchased,
+	/// The game is available through a Game Pass subscription
+	GamePass,
+	/// The account does not have the game
+	None,
+}
+
+/// Get the entitlements of a Minecraft user
+pub async fn get_entitlements(access_token: &str, client: &Client) -> anyhow::Result<Entitlements> {
+	call_mc_api(
+		"https://api.minecraftservices.com/entitlements/mcstore",
+		access_token,
+		client,
+	)
+	.await
+	.context("Failed to get account entitlements")
+}
+
 /// Response from the player certificate endpoint
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -104,3 +244,121 @@ pub async fn get_user_certificate(
 
 	Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_select_skin_variant() {
+		let skin = |id: &str, variant, state| Skin {
+			cosmetic: Cosmetic {
+				id: id.into(),
+				url: format!("https://textures.minecraft.net/texture/{id}"),
+				state,
+			},
+			variant,
+		};
+		let mut profile = MinecraftUserProfile {
+			name: "Steve".into(),
+			uuid: "uuid".into(),
+			skins: vec![
+				skin("wide", SkinVariant::Classic, CosmeticState::Active),
+				skin("slim", SkinVariant::Slim, CosmeticState::Inactive),
+			],
+			capes: Vec::new(),
+		};
+
+		let selected = profile.select_skin(SkinVariant::Slim).unwrap();
+		assert_eq!(selected.cosmetic.id, "slim");
+		let selected = profile.select_skin(SkinVariant::Classic).unwrap();
+		assert_eq!(selected.cosmetic.id, "wide");
+
+		profile.skins.remove(1);
+		assert!(profile.select_skin(SkinVariant::Slim).is_none());
+	}
+
+	#[test]
+	fn test_skin_variant_names() {
+		let skin: Skin = serde_json::from_value(serde_json::json!({
+			"id": "skin",
+			"url": "https://textures.minecraft.net/texture/skin",
+			"state": "ACTIVE",
+			"variant": "SLIM"
+		}))
+		.unwrap();
+		assert_eq!(skin.variant, SkinVariant::Slim);
+
+		// Config only uses lowercase names
+		assert_eq!(
+			serde_json::to_value(SkinVariant::Slim).unwrap(),
+			serde_json::json!("slim")
+		);
+		assert_eq!(
+			serde_json::from_value::<SkinVariant>(serde_json::json!("classic")).unwrap(),
+			SkinVariant::Classic
+		);
+		assert!(serde_json::from_value::<SkinVariant>(serde_json::json!("CLASSIC")).is_err());
+	}
+
+	#[test]
+	fn test_user_profile_round_trip() {
+		let profile = serde_json::json!({
+			"name": "Steve",
+			"id": "uuid",
+			"skins": [
+				{
+					"id": "skin",
+					"url": "https://textures.minecraft.net/texture/skin",
+					"state": "ACTIVE",
+					"variant": "CLASSIC"
+				}
+			],
+			"capes": [
+				{
+					"id": "cape",
+					"url": "https://textures.minecraft.net/texture/cape",
+					"state": "INACTIVE",
+					"alias": "migrator"
+				}
+			]
+		});
+		let deserialized: MinecraftUserProfile = serde_json::from_value(profile.clone()).unwrap();
+		assert_eq!(serde_json::to_value(&deserialized).unwrap(), profile);
+		let round_tripped: MinecraftUserProfile =
+			serde_json::from_value(serde_json::to_value(&deserialized).unwrap()).unwrap();
+		assert_eq!(round_tripped.skins[0].variant, SkinVariant::Classic);
+	}
+
+	#[test]
+	fn test_entitlement_ownership() {
+		let ownership = |response: &str| {
+			serde_json::from_str::<Entitlements>(response)
+				.unwrap()
+				.get_ownership()
+		};
+
+		let owned = r#"{
+			"items": [
+				{"name": "product_minecraft", "signature": "abc"},
+				{"name": "game_minecraft", "signature": "def"}
+			],
+			"signature": "ghi",
+			"keyId": "1"
+		}"#;
+		assert_eq!(ownership(owned), GameOwnership::Purchased);
+
+		let game_pass = r#"{
+			"items": [
+				{"name": "product_game_pass_pc", "signature": "abc"},
+				{"name": "game_minecraft", "signature": "def"}
+			],
+			"signature": "ghi",
+			"keyId": "1"
+		}"#;
+		assert_eq!(ownership(game_pass), GameOwnership::GamePass);
+
+		let not_owned = r#"{"items": [], "signature": "ghi", "keyId": "1"}"#;
+		assert_eq!(ownership(not_owned), GameOwnership::None);
+	}
+}
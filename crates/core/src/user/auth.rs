@@ -1,9 +1,16 @@
-use anyhow::{bail, Context};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context};
 use mcvm_auth::RsaPrivateKey;
 use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
 use mcvm_shared::translate;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
-use crate::net::minecraft::MinecraftUserProfile;
+use crate::net::minecraft::{GameOwnership, MinecraftUserProfile};
 use crate::Paths;
 use mcvm_auth::db::{AuthDatabase, DatabaseUser, SensitiveUserInfo};
 use mcvm_auth::mc::Keypair;
@@ -12,7 +19,10 @@ use mcvm_auth::mc::{
 	ClientId, RefreshToken,
 };
 
-use super::{CustomAuthFunction, User, UserKind};
+use super::{CustomAuthFunction, User, UserID, UserKind, UserManager};
+
+/// The maximum number of accounts to refresh at once, to stay within the rate limits of the Microsoft APIs
+const MAX_CONCURRENT_REFRESHES: usize = 4;
 
 impl User {
 	/// Authenticate the user
@@ -24,7 +34,7 @@ impl User {
 		match &mut self.kind {
 			UserKind::Microsoft { xbox_uid } => {
 				if params.offline {
-					let db = AuthDatabase::open(&params.paths.auth)
+					let db = AuthDatabase::open(params.auth_dir)
 						.context("Failed to open authentication database")?;
 					let Some((user, sensitive)) = get_full_user(&db, &self.id, o)
 						.await
@@ -80,10 +90,10 @@ impl User {
 	/// Checks if the user still has valid authentication. This does not mean that they are
 	/// authenticated yet. To check if the user is authenticated and ready to be used, use the is_authenticated
 	/// function instead.
-	pub fn is_auth_valid(&self, paths: &Paths) -> bool {
+	pub fn is_auth_valid(&self, auth_dir: &Path) -> bool {
 		match &self.kind {
 			UserKind::Microsoft { .. } => {
-				let Ok(db) = AuthDatabase::open(&paths.auth) else {
+				let Ok(db) = AuthDatabase::open(auth_dir) else {
 					return false;
 				};
 
@@ -106,11 +116,11 @@ impl User {
 	/// Updates this user's passkey using prompts
 	pub async fn update_passkey(
 		&self,
-		paths: &Paths,
+		auth_dir: &Path,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<()> {
 		let mut db =
-			AuthDatabase::open(&paths.auth).context("Failed to open authentication database")?;
+			AuthDatabase::open(auth_dir).context("Failed to open authentication database")?;
 		let user = db.get_user_mut(&self.id).context(
 			"User does not exist in database. Try authenticating first before setting a passkey",
 		)?;
@@ -143,9 +153,9 @@ impl User {
 	}
 
 	/// Logs out this user and removes their data from the auth database (not including passkey)
-	pub fn logout(&mut self, paths: &Paths) -> anyhow::Result<()> {
+	pub fn logout(&mut self, auth_dir: &Path) -> anyhow::Result<()> {
 		let mut db =
-			AuthDatabase::open(&paths.auth).context("Failed to open authentication database")?;
+			AuthDatabase::open(auth_dir).context("Failed to open authentication database")?;
 		db.logout_user(&self.id)
 			.context("Failed to logout user in database")?;
 
@@ -156,6 +166,132 @@ impl User {
 	}
 }
 
+impl UserManager {
+	/// Refreshes the tokens of all of the Microsoft users that are not authenticated, using the refresh
+	/// tokens stored in the database. Accounts are refreshed concurrently, and a failure for one account
+	/// does not stop the others. Returns the result of the refresh for each account
+	pub async fn refresh_all_tokens(
+		&mut self,
+		paths: &Paths,
+		client: &reqwest::Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<Vec<(UserID, anyhow::Result<()>)>> {
+		if self.offline {
+			return Ok(Vec::new());
+		}
+		let db = AuthDatabase::open(self.get_auth_dir(paths))
+			.context("Failed to open authentication database")?;
+
+		let mut out = Vec::new();
+		let mut accounts = Vec::new();
+		let mut profiles = HashMap::new();
+		// Reading the database may prompt for passkeys, so it has to be done one account at a time
+		for (id, user) in &self.users {
+			if !user.is_microsoft() || user.is_authenticated() {
+				continue;
+			}
+			match get_full_user(&db, id, o).await {
+				Ok(Some((db_user, sensitive))) => {
+					let refresh_token = RefreshToken::new(
+						sensitive
+							.refresh_token
+							.expect("Refresh token should be present in a full valid user"),
+					);
+					accounts.push((id.clone(), refresh_token));
+					let profile = (
+						db_user.username.clone(),
+						db_user.uuid.clone(),
+						sensitive.keypair,
+					);
+					profiles.insert(id.clone(), profile);
+				}
+				Ok(None) => out.push((id.clone(), Err(anyhow!("User has to log in again")))),
+				Err(e) => out.push((id.clone(), Err(e))),
+			}
+		}
+
+		let client_id = self.ms_client_id.clone();
+		let client = client.clone();
+		let results = refresh_concurrently(accounts, |refresh_token| {
+			refresh_microsoft_user(refresh_token, client_id.clone(), client.clone())
+		})
+		.await;
+
+		for (id, result) in results {
+			let result = result.map(|(access_token, xbox_uid)| {
+				let (name, uuid, keypair) = profiles.remove(&id).expect("Profile should exist");
+				if let Some(user) = self.users.get_mut(&id) {
+					user.access_token = Some(access_token);
+					user.name = Some(name);
+					user.uuid = Some(uuid);
+					user.keypair = keypair;
+					user.kind = UserKind::Microsoft {
+						xbox_uid: Some(xbox_uid),
+					};
+				}
+			});
+			out.push((id, result));
+		}
+
+		Ok(out)
+	}
+}
+
+/// Refreshes the Minecraft access token of a Microsoft user from their refresh token,
+/// returning the access token and Xbox UID
+async fn refresh_microsoft_user(
+	refresh_token: RefreshToken,
+	client_id: ClientId,
+	client: reqwest::Client,
+) -> anyhow::Result<(AccessToken, String)> {
+	let oauth_client = auth::create_client(client_id).context("Failed to create OAuth client")?;
+	let token = auth::refresh_microsoft_token(&oauth_client, &refresh_token)
+		.await
+		.context("Failed to get refreshed token")?;
+	let mc_token = auth::auth_minecraft(token, &client)
+		.await
+		.context("Failed to get Minecraft token")?;
+
+	Ok((
+		AccessToken(auth::mc_access_token_to_string(&mc_token.access_token)),
+		mc_token.username,
+	))
+}
+
+/// Runs a refresh task for each account, limiting how many run at once,
+/// and collects the result for every account
+async fn refresh_concurrently<T, R, F, Fut>(
+	accounts: Vec<(UserID, T)>,
+	refresh: F,
+) -> Vec<(UserID, anyhow::Result<R>)>
+where
+	F: Fn(T) -> Fut,
+	Fut: Future<Output = anyhow::Result<R>> + Send + 'static,
+	R: Send + 'static,
+{
+	let mut tasks = JoinSet::new();
+	let sem = Arc::new(Semaphore::new(MAX_CONCURRENT_REFRESHES));
+	for (id, account) in accounts {
+		let sem = sem.clone();
+		let fut = refresh(account);
+		tasks.spawn(async move {
+			let _permit = sem.acquire().await;
+			(id, fut.await)
+		});
+	}
+
+	let mut out = Vec::new();
+	while let Some(result) = tasks.join_next().await {
+		match result {
+			Ok(result) => out.push(result),
+			// A panicking task has no account to report the result for
+			Err(e) => std::panic::resume_unwind(e.into_panic()),
+		}
+	}
+
+	out
+}
+
 /// Data for a Microsoft user
 pub struct MicrosoftUserData {
 	access_token: AccessToken,
@@ -171,7 +307,7 @@ async fn update_microsoft_user_auth(
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<MicrosoftUserData> {
 	let mut db =
-		AuthDatabase::open(&params.paths.auth).context("Failed to open authentication database")?;
+		AuthDatabase::open(params.auth_dir).context("Failed to open authentication database")?;
 
 	// Force reauth if specified
 	if params.force {
@@ -234,11 +370,11 @@ async fn reauth_microsoft_user(
 		let client = client.clone();
 		let token = auth_result.access_token.0.clone();
 		async move {
-			let owns_game = auth::account_owns_game(&token, &client)
+			let entitlements = crate::net::minecraft::get_entitlements(&token, &client)
 				.await
 				.context("Failed to check for game ownership")?;
 
-			Ok::<bool, anyhow::Error>(owns_game)
+			Ok::<GameOwnership, anyhow::Error>(entitlements.get_ownership())
 		}
 	};
 
@@ -266,11 +402,13 @@ async fn reauth_microsoft_user(
 		}
 	};
 
-	let (owns_game, profile, certificate) =
+	let (ownership, profile, certificate) =
 		tokio::try_join!(ownership_task, profile_task, certificate_task)?;
 
-	if !owns_game {
-		bail!("Specified account does not own Minecraft");
+	if ownership == GameOwnership::None {
+		bail!(
+			"Specified account does not own Minecraft: Java Edition or have it through Game Pass"
+		);
 	}
 
 	// Calculate expiration time
@@ -384,7 +522,32 @@ pub(crate) struct AuthParameters<'a> {
 	pub force: bool,
 	pub offline: bool,
 	pub client_id: ClientId,
-	pub paths: &'a Paths,
+	pub auth_dir: &'a Path,
 	pub req_client: &'a reqwest::Client,
 	pub custom_auth_fn: Option<CustomAuthFunction>,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_refresh_concurrently() {
+		let accounts = vec![("good".into(), true), ("bad".into(), false)];
+		let mut results = refresh_concurrently(accounts, |succeeds| async move {
+			if succeeds {
+				Ok("token")
+			} else {
+				bail!("invalid_grant")
+			}
+		})
+		.await;
+		results.sort_by(|a, b| a.0.cmp(&b.0));
+
+		assert_eq!(results.len(), 2);
+		assert_eq!(&*results[0].0, "bad");
+		assert!(results[0].1.is_err());
+		assert_eq!(&*results[1].0, "good");
+		assert_eq!(results[1].1.as_ref().unwrap(), &"token");
+	}
+}
@@ -3,9 +3,15 @@ pub mod auth;
 /// Tools for working with UUIDs
 pub mod uuid;
 
-use std::{collections::HashMap, ops::Deref, sync::Arc};
-
-use anyhow::bail;
+use std::{
+	collections::HashMap,
+	ops::Deref,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use anyhow::{bail, Context};
+use mcvm_auth::db::AuthDatabase;
 use mcvm_auth::mc::{AccessToken, ClientId, Keypair};
 use mcvm_shared::output::MCVMOutput;
 use reqwest::Client;
@@ -155,6 +161,8 @@ pub struct UserManager {
 	ms_client_id: ClientId,
 	/// Whether the manager has been set as offline for authentication
 	offline: bool,
+	/// A custom directory to store the authentication database in instead of the default one
+	auth_dir: Option<PathBuf>,
 	/// Custom auth function for plugin injection
 	custom_auth_fn: Option<CustomAuthFunction>,
 }
@@ -176,6 +184,7 @@ impl UserManager {
 			users: HashMap::new(),
 			ms_client_id,
 			offline: false,
+			auth_dir: None,
 			custom_auth_fn: None,
 		}
 	}
@@ -278,10 +287,11 @@ impl UserManager {
 				.get_mut(user_id)
 				.expect("User in AuthState does not exist");
 
-			if !user.is_authenticated() || !user.is_auth_valid(paths) {
+			let auth_dir = self.auth_dir.as_deref().unwrap_or(&paths.auth);
+			if !user.is_authenticated() || !user.is_auth_valid(auth_dir) {
 				let params = AuthParameters {
 					req_client: client,
-					paths,
+					auth_dir,
 					force: false,
 					offline: self.offline,
 					client_id: self.ms_client_id.clone(),
@@ -311,6 +321,20 @@ impl UserManager {
 		self.offline = offline;
 	}
 
+	/// Set a custom directory to store the authentication database in.
+	/// Fails if the directory can't be created or written to
+	pub fn set_auth_dir(&mut self, dir: PathBuf) -> anyhow::Result<()> {
+		AuthDatabase::validate_dir(&dir).context("Invalid authentication database directory")?;
+		self.auth_dir = Some(dir);
+		Ok(())
+	}
+
+	/// Get the directory that the authentication database is stored in. This is the
+	/// directory from the paths unless a custom one has been set
+	pub fn get_auth_dir<'a>(&'a self, paths: &'a Paths) -> &'a Path {
+		self.auth_dir.as_deref().unwrap_or(&paths.auth)
+	}
+
 	/// Set the manager's custom auth function
 	pub fn set_custom_auth_function(&mut self, func: CustomAuthFunction) {
 		self.custom_auth_fn = Some(func);
@@ -1,6 +1,6 @@
 /// Utilities for working with serde_json values
 pub mod json;
 /// "Secret" values
-pub(crate) mod secrets;
+pub mod secrets;
 /// Utilities for game versions
 pub mod versions;
@@ -1,6 +1,7 @@
 use mcvm_auth::mc::ClientId;
 
-/// Get the Microsoft client ID
+/// Get the default Microsoft client ID. It can be replaced when building
+/// by setting the MCVM_MS_CLIENT_ID environment variable
 pub fn get_ms_client_id() -> ClientId {
 	ClientId::new(get_raw_ms_client_id().to_string())
 }
@@ -1,10 +1,12 @@
 use std::{fmt::Display, sync::Arc};
 
+use anyhow::bail;
+use mcvm_shared::versions::closest_match;
 #[cfg(feature = "schema")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::net::game_files::version_manifest::VersionManifest;
+use crate::net::game_files::version_manifest::{make_version_list, VersionManifest};
 
 /// Matches for the latest Minecraft version.
 /// We have to separate this so that deserialization works
@@ -54,10 +56,27 @@ pub enum MinecraftVersion {
 }
 
 impl MinecraftVersion {
+	/// Parses a user-supplied version, checking that it exists in the list of available versions.
+	/// The `latest` and `latest_snapshot` symbols are always allowed
+	pub fn parse(version: &str, version_list: &[String]) -> anyhow::Result<Self> {
+		let version = version.trim();
+		match version {
+			"latest" => Ok(Self::Latest),
+			"latest_snapshot" => Ok(Self::LatestSnapshot),
+			_ => {
+				validate_version_name(version, version_list)?;
+				Ok(Self::Version(version.into()))
+			}
+		}
+	}
+
 	/// Get the correct version from the version manifest
 	pub fn get_version(&self, manifest: &VersionManifest) -> anyhow::Result<VersionName> {
 		match self {
-			Self::Version(version) => Ok(version.clone()),
+			Self::Version(version) => {
+				validate_version_name(version, &make_version_list(manifest))?;
+				Ok(version.clone())
+			}
 			Self::Latest => Ok(manifest.latest.release.clone()),
 			Self::LatestSnapshot => Ok(manifest.latest.snapshot.clone()),
 		}
@@ -90,6 +109,43 @@ impl Display for MinecraftVersion {
 /// String name for a Minecraft version
 pub type VersionName = Arc<str>;
 
+/// Checks that a version name is in the list of available versions, returning
+/// an error with a suggestion for a similar version if it is not
+pub fn validate_version_name(version: &str, version_list: &[String]) -> anyhow::Result<()> {
+	if version_list.iter().any(|x| x == version) {
+		return Ok(());
+	}
+
+	if let Some(suggestion) = suggest_version(version, version_list) {
+		bail!("Version '{version}' does not exist. Did you mean '{suggestion}'?");
+	}
+	bail!("Version '{version}' does not exist");
+}
+
+/// Finds a version that matches the given one once separators and case are normalized,
+/// falling back to the closest version by edit distance
+fn suggest_version(version: &str, version_list: &[String]) -> Option<String> {
+	let normalized = normalize_version_name(version);
+	version_list
+		.iter()
+		.find(|x| normalize_version_name(x) == normalized)
+		.cloned()
+		.or_else(|| closest_match(version, version_list))
+}
+
+/// Normalizes a version name so that common typos compare equal
+fn normalize_version_name(version: &str) -> String {
+	version
+		.trim()
+		.to_lowercase()
+		.chars()
+		.map(|c| match c {
+			',' | '-' | '_' | ' ' => '.',
+			c => c,
+		})
+		.collect()
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -122,4 +178,31 @@ mod tests {
 			MinecraftVersionDeser::Latest(MinecraftLatestVersion::Snapshot)
 		);
 	}
+
+	#[test]
+	fn test_version_validation() {
+		let list = vec!["1.19".to_string(), "1.20".to_string(), "1.20.1".to_string()];
+
+		assert!(matches!(
+			MinecraftVersion::parse("1.20.1", &list),
+			Ok(MinecraftVersion::Version(version)) if &*version == "1.20.1"
+		));
+		assert!(matches!(
+			MinecraftVersion::parse(" latest ", &list),
+			Ok(MinecraftVersion::Latest)
+		));
+		assert!(matches!(
+			MinecraftVersion::parse("latest_snapshot", &list),
+			Ok(MinecraftVersion::LatestSnapshot)
+		));
+
+		let err = MinecraftVersion::parse("1.20,1", &list).unwrap_err();
+		assert!(err.to_string().contains("Did you mean '1.20.1'?"));
+
+		let err = MinecraftVersion::parse("1.19.1", &list).unwrap_err();
+		assert!(err.to_string().contains("Did you mean '1.19'?"));
+
+		let err = MinecraftVersion::parse("foo", &list).unwrap_err();
+		assert!(!err.to_string().contains("Did you mean"));
+	}
 }
@@ -5,14 +5,17 @@ use anyhow::Context;
 use mcvm_shared::later::Later;
 use mcvm_shared::output::MCVMOutput;
 use mcvm_shared::output::{MessageContents, MessageLevel};
+use mcvm_shared::util::ArchiveType;
 use mcvm_shared::versions::VersionInfo;
 
 use crate::config::BrandingProperties;
 use crate::instance::{Instance, InstanceConfiguration, InstanceParameters};
 use crate::io::files::paths::Paths;
+use crate::io::json_from_file;
 use crate::io::persistent::PersistentData;
 use crate::io::update::UpdateManager;
 use crate::net::game_files::client_meta::{self, ClientMeta};
+use crate::net::game_files::download_plan::DownloadPlan;
 use crate::net::game_files::version_manifest::{self, VersionEntry, VersionManifestAndList};
 use crate::net::game_files::{assets, libraries};
 use crate::user::UserManager;
@@ -45,6 +48,19 @@ impl<'inner, 'params> InstalledVersion<'inner, 'params> {
 		}
 	}
 
+	/// Computes what installing the client files for this version would download,
+	/// without downloading anything
+	pub fn compute_download_plan(&self) -> DownloadPlan {
+		let index_path = assets::get_index_path(self.params.paths, &self.inner.version);
+		let asset_index = json_from_file(index_path).ok();
+		DownloadPlan::compute(
+			&self.inner.version,
+			&self.inner.client_meta,
+			asset_index.as_ref(),
+			self.params.paths,
+		)
+	}
+
 	/// Create an instance and its files using this version,
 	/// ready to be launched
 	pub async fn get_instance(
@@ -64,6 +80,7 @@ impl<'inner, 'params> InstalledVersion<'inner, 'params> {
 			client_assets_and_libs: &mut self.inner.client_assets_and_libs,
 			censor_secrets: self.params.censor_secrets,
 			disable_hardlinks: self.params.disable_hardlinks,
+			java_archive_type: self.params.java_archive_type,
 			branding: self.params.branding,
 		};
 		let instance = Instance::load(config, params, o)
@@ -221,6 +238,11 @@ impl VersionRegistry {
 		self.version_manifest.get()
 	}
 
+	/// Remove a loaded version from the registry
+	pub fn remove_version(&mut self, version: &str) {
+		self.versions.remove(version);
+	}
+
 	/// Add additional versions to the manifest. Must be called before the manifest is obtained.
 	pub fn add_additional_versions(&mut self, versions: Vec<VersionEntry>) {
 		self.additional_versions.extend(versions);
@@ -236,6 +258,7 @@ pub(crate) struct VersionParameters<'a> {
 	pub users: &'a mut UserManager,
 	pub censor_secrets: bool,
 	pub disable_hardlinks: bool,
+	pub java_archive_type: ArchiveType,
 	pub branding: &'a BrandingProperties,
 }
 
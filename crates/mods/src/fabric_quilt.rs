@@ -1,12 +1,14 @@
 use std::fmt::Display;
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
+use mcvm_core::instance::InstanceConfiguration;
 use mcvm_core::io::java::classpath::Classpath;
 use mcvm_core::io::java::maven::MavenLibraryParts;
 use mcvm_core::io::json_from_file;
 use mcvm_core::io::update::UpdateManager;
 use mcvm_core::io::{files, json_to_file};
 use mcvm_core::net::download;
+use mcvm_core::util::versions::MinecraftVersion;
 use mcvm_core::{MCVMCore, Paths};
 use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel, OutputProcess};
 use mcvm_shared::versions::VersionInfo;
@@ -49,9 +51,63 @@ pub async fn install_from_core(
 	side: Side,
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<(Classpath, String)> {
-	let meta = get_meta(
-		&version_info.version,
+	let meta = install_meta(core, &version_info.version, mode, side, None, o).await?;
+
+	let classpath =
+		get_classpath(&meta, core.get_paths(), side).context("Failed to get classpath")?;
+
+	Ok((
+		classpath,
+		meta.launcher_meta
+			.main_class
+			.get_main_class_string(side)
+			.into(),
+	))
+}
+
+/// Install the game version along with Fabric/Quilt using the core, and get everything needed to launch it.
+/// For the client, the assets and libraries of the game are installed as well.
+/// If no loader version is given, the newest one will be used
+pub async fn install_profile(
+	core: &mut MCVMCore,
+	version: &MinecraftVersion,
+	mode: Mode,
+	side: Side,
+	loader_version: Option<&str>,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<LaunchProfile> {
+	let version_info = {
+		let mut installed_version = core
+			.get_version(version, o)
+			.await
+			.context("Failed to install version")?;
+		if let Side::Client = side {
+			installed_version
+				.ensure_client_assets_and_libs(o)
+				.await
+				.context("Failed to install client assets and libraries")?;
+		}
+		installed_version.get_version_info()
+	};
+
+	let meta = install_meta(core, &version_info.version, mode, side, loader_version, o).await?;
+
+	LaunchProfile::from_meta(&meta, core.get_paths(), side)
+}
+
+/// Get the meta for a loader version and install all of the files from it
+async fn install_meta(
+	core: &MCVMCore,
+	version: &str,
+	mode: Mode,
+	side: Side,
+	loader_version: Option<&str>,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<FabricQuiltMeta> {
+	let meta = get_meta_for_loader_version(
+		version,
 		&mode,
+		loader_version,
 		core.get_paths(),
 		core.get_update_manager(),
 		core.get_client(),
@@ -77,18 +133,50 @@ pub async fn install_from_core(
 		core.get_client(),
 	)
 	.await
-	.context("Failed to download {mode} files for {side}")?;
+	.with_context(|| format!("Failed to download {mode} files for {side}"))?;
 
-	let classpath =
-		get_classpath(&meta, core.get_paths(), side).context("Failed to get classpath")?;
+	Ok(meta)
+}
 
-	Ok((
-		classpath,
-		meta.launcher_meta
-			.main_class
-			.get_main_class_string(side)
-			.into(),
-	))
+/// Everything needed to launch an instance with Fabric or Quilt installed
+#[derive(Debug, Clone)]
+pub struct LaunchProfile {
+	/// The loader libraries to add to the classpath
+	pub classpath: Classpath,
+	/// The main class to launch with
+	pub main_class: String,
+	/// Additional game arguments that the loader requires
+	pub game_args: Vec<String>,
+}
+
+impl LaunchProfile {
+	/// Create the launch profile for a side from the meta
+	pub fn from_meta(meta: &FabricQuiltMeta, paths: &Paths, side: Side) -> anyhow::Result<Self> {
+		let classpath = get_classpath(meta, paths, side).context("Failed to get classpath")?;
+		let main_class = meta.launcher_meta.main_class.get_main_class_string(side);
+		let arguments = &meta.launcher_meta.arguments;
+		let side_args = match side {
+			Side::Client => &arguments.client,
+			Side::Server => &arguments.server,
+		};
+		let game_args = arguments.common.iter().chain(side_args).cloned().collect();
+
+		Ok(Self {
+			classpath,
+			main_class: main_class.into(),
+			game_args,
+		})
+	}
+
+	/// Apply this profile to the configuration of the instance that will be launched
+	pub fn apply_to_config(&self, config: &mut InstanceConfiguration) {
+		config.main_class = Some(self.main_class.clone());
+		config.additional_libs.extend(self.classpath.get_paths());
+		config
+			.launch
+			.game_args
+			.extend(self.game_args.iter().cloned());
+	}
 }
 
 /// Metadata for Fabric or Quilt
@@ -103,6 +191,17 @@ pub struct FabricQuiltMeta {
 	pub intermediary: MainLibrary,
 }
 
+impl FabricQuiltMeta {
+	/// Gets the version of the loader from its Maven coordinate
+	pub fn get_loader_version(&self) -> &str {
+		self.loader
+			.maven
+			.rsplit_once(':')
+			.map(|(_, version)| version)
+			.unwrap_or(&self.loader.maven)
+	}
+}
+
 /// Metadata for the launcher
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct LauncherMeta {
@@ -110,6 +209,9 @@ pub struct LauncherMeta {
 	/// The main class to override with when launching
 	#[serde(rename = "mainClass")]
 	pub main_class: MainClass,
+	/// Additional game arguments, which are only used by some older versions
+	#[serde(default)]
+	arguments: Arguments,
 }
 
 /// A library in the Fabric/Quilt meta
@@ -139,6 +241,17 @@ pub struct Libraries {
 	server: Vec<Library>,
 }
 
+/// The game arguments for different sides
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Arguments {
+	#[serde(default)]
+	common: Vec<String>,
+	#[serde(default)]
+	client: Vec<String>,
+	#[serde(default)]
+	server: Vec<String>,
+}
+
 /// A Java main class override provided by the meta
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(untagged)]
@@ -167,13 +280,26 @@ impl MainClass {
 	}
 }
 
-/// Get the Fabric/Quilt metadata file
+/// Get the Fabric/Quilt metadata file for the newest loader version
 pub async fn get_meta(
 	version: &str,
 	mode: &Mode,
 	paths: &Paths,
 	manager: &UpdateManager,
 	client: &Client,
+) -> anyhow::Result<FabricQuiltMeta> {
+	get_meta_for_loader_version(version, mode, None, paths, manager, client).await
+}
+
+/// Get the Fabric/Quilt metadata file for a specific loader version,
+/// or the newest one if no version is given
+pub async fn get_meta_for_loader_version(
+	version: &str,
+	mode: &Mode,
+	loader_version: Option<&str>,
+	paths: &Paths,
+	manager: &UpdateManager,
+	client: &Client,
 ) -> anyhow::Result<FabricQuiltMeta> {
 	let meta_url = match mode {
 		Mode::Fabric => format!("https://meta.fabricmc.net/v2/versions/loader/{version}"),
@@ -191,6 +317,7 @@ pub async fn get_meta(
 	let meta = if manager.allow_offline() && path.exists() {
 		json_from_file(path).with_context(|| format!("Failed to parse {mode} meta from file"))?
 	} else {
+		manager.ensure_online(&format!("{mode} metadata"))?;
 		let bytes = download::bytes(&meta_url, client)
 			.await
 			.with_context(|| format!("Failed to download {mode} metadata file"))?;
@@ -202,9 +329,16 @@ pub async fn get_meta(
 		out
 	};
 
-	let meta = meta
-		.first()
-		.ok_or(anyhow!("Could not find a valid {mode} version"))?;
+	let meta = if let Some(loader_version) = loader_version {
+		meta.iter()
+			.find(|x| x.get_loader_version() == loader_version)
+			.ok_or(anyhow!(
+				"Could not find {mode} loader version {loader_version} for Minecraft {version}"
+			))?
+	} else {
+		meta.first()
+			.ok_or(anyhow!("Could not find a valid {mode} version"))?
+	};
 
 	Ok(meta.clone())
 }
@@ -219,6 +353,7 @@ pub async fn download_files(
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<()> {
 	let force = manager.force_reinstall();
+	let offline = manager.is_offline();
 
 	let process = OutputProcess::new(o);
 	process.0.display(
@@ -230,10 +365,9 @@ pub async fn download_files(
 	let paths_clone = paths.clone();
 
 	let client_clone = client.clone();
-	let common_task =
-		tokio::spawn(
-			async move { download_libraries(&libs, &paths_clone, &client_clone, force).await },
-		);
+	let common_task = tokio::spawn(async move {
+		download_libraries(&libs, &paths_clone, &client_clone, force, offline).await
+	});
 
 	let paths_clone = paths.clone();
 	let loader_clone = meta.loader.clone();
@@ -251,6 +385,7 @@ pub async fn download_files(
 			&paths_clone,
 			&client_clone,
 			force,
+			offline,
 		);
 		let task2 = download_main_library(
 			&intermediary_clone,
@@ -258,6 +393,7 @@ pub async fn download_files(
 			&paths_clone,
 			&client_clone,
 			force,
+			offline,
 		);
 
 		tokio::try_join!(task1, task2)?;
@@ -290,7 +426,14 @@ pub async fn download_side_specific_files(
 		Side::Server => &meta.launcher_meta.libraries.server,
 	};
 
-	download_libraries(libs, paths, client, manager.force_reinstall()).await?;
+	download_libraries(
+		libs,
+		paths,
+		client,
+		manager.force_reinstall(),
+		manager.is_offline(),
+	)
+	.await?;
 
 	Ok(())
 }
@@ -313,11 +456,13 @@ fn get_lib_path(name: &str) -> Option<String> {
 }
 
 /// Download all Fabric/Quilt libraries. Returns the resulting classpath.
+/// Fails if a library is missing while offline
 async fn download_libraries(
 	libs: &[Library],
 	paths: &Paths,
 	client: &Client,
 	force: bool,
+	offline: bool,
 ) -> anyhow::Result<Classpath> {
 	let mut classpath = Classpath::new();
 	let mut tasks = JoinSet::new();
@@ -329,6 +474,9 @@ async fn download_libraries(
 			if !force && lib_path.exists() {
 				continue;
 			}
+			if offline {
+				bail!("Offline mode: library {path} is not cached");
+			}
 			let url = lib.url.clone() + &path;
 
 			let client = client.clone();
@@ -350,19 +498,24 @@ async fn download_libraries(
 	Ok(classpath)
 }
 
-/// Download a main library from Fabric or Quilt, such as the loader or mappings
+/// Download a main library from Fabric or Quilt, such as the loader or mappings.
+/// Fails if the library is missing while offline
 async fn download_main_library(
 	lib: &MainLibrary,
 	url: &str,
 	paths: &Paths,
 	client: &Client,
 	force: bool,
+	offline: bool,
 ) -> anyhow::Result<()> {
 	let path = get_lib_path(&lib.maven).expect("Expected a valid path");
 	let lib_path = paths.libraries.join(&path);
 	if !force && lib_path.exists() {
 		return Ok(());
 	}
+	if offline {
+		bail!("Offline mode: library {path} is not cached");
+	}
 	let url = url.to_owned() + &path;
 	let resp = download::bytes(url, client).await?;
 
@@ -415,3 +568,65 @@ pub fn get_classpath(
 
 	Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_launch_profile() {
+		let meta: FabricQuiltMeta = serde_json::from_str(
+			r#"{
+				"loader": {
+					"separator": ".",
+					"build": 11,
+					"maven": "net.fabricmc:fabric-loader:0.15.11",
+					"version": "0.15.11",
+					"stable": true
+				},
+				"intermediary": {
+					"maven": "net.fabricmc:intermediary:1.20.1",
+					"version": "1.20.1",
+					"stable": true
+				},
+				"launcherMeta": {
+					"version": 2,
+					"libraries": {
+						"client": [],
+						"common": [
+							{
+								"name": "net.fabricmc:sponge-mixin:0.13.3+mixin.0.8.5",
+								"url": "https://maven.fabricmc.net/"
+							}
+						],
+						"server": []
+					},
+					"mainClass": {
+						"client": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+						"server": "net.fabricmc.loader.impl.launch.knot.KnotServer"
+					}
+				}
+			}"#,
+		)
+		.unwrap();
+		assert_eq!(meta.get_loader_version(), "0.15.11");
+
+		let dir = tempfile::tempdir().unwrap();
+		let paths = Paths::with_data_dir_no_create(dir.path().to_path_buf()).unwrap();
+		let profile = LaunchProfile::from_meta(&meta, &paths, Side::Client).unwrap();
+		assert_eq!(
+			profile.main_class,
+			"net.fabricmc.loader.impl.launch.knot.KnotClient"
+		);
+		assert!(profile.game_args.is_empty());
+
+		let classpath = profile.classpath.get_paths();
+		for lib in [
+			"net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar",
+			"net/fabricmc/intermediary/1.20.1/intermediary-1.20.1.jar",
+			"net/fabricmc/sponge-mixin/0.13.3+mixin.0.8.5/sponge-mixin-0.13.3+mixin.0.8.5.jar",
+		] {
+			assert!(classpath.contains(&paths.libraries.join(lib)), "{lib}");
+		}
+	}
+}
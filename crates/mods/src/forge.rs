@@ -0,0 +1,1120 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use mcvm_core::instance::InstanceConfiguration;
+use mcvm_core::io::files;
+use mcvm_core::io::java::classpath::{Classpath, CLASSPATH_SEP};
+use mcvm_core::io::java::install::JavaInstallationKind;
+use mcvm_core::io::update::UpdateManager;
+use mcvm_core::net::download;
+use mcvm_core::net::game_files::client_meta::get_required_java_version;
+use mcvm_core::net::game_files::game_jar;
+use mcvm_core::net::game_files::verify::{check_file, FileStatus};
+use mcvm_core::util::versions::MinecraftVersion;
+use mcvm_core::{MCVMCore, Paths};
+use mcvm_net::neoforge;
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel, OutputProcess};
+use mcvm_shared::Side;
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::task::JoinSet;
+use zip::ZipArchive;
+
+/// Path to the install profile in the installer jar
+const INSTALL_PROFILE_PATH: &str = "install_profile.json";
+/// Path to the version JSON in the installer jar
+const VERSION_JSON_PATH: &str = "version.json";
+/// Name of the file that marks that the processors have already been run
+const PROCESSED_MARKER: &str = "processed";
+/// URL to the list of promoted Forge versions for each Minecraft version
+const FORGE_PROMOTIONS_URL: &str =
+	"https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+
+/// Which of the loaders we are installing. Forge and NeoForge share the same installer format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+	/// Minecraft Forge
+	Forge,
+	/// NeoForge
+	NeoForge,
+}
+
+impl Mode {
+	/// Get the URL to the installer jar for a loader version
+	fn get_installer_url(&self, minecraft_version: &str, loader_version: &str) -> String {
+		match self {
+			Self::Forge => format!("https://maven.minecraftforge.net/net/minecraftforge/forge/{minecraft_version}-{loader_version}/forge-{minecraft_version}-{loader_version}-installer.jar"),
+			Self::NeoForge => format!("https://maven.neoforged.net/releases/net/neoforged/neoforge/{loader_version}/neoforge-{loader_version}-installer.jar"),
+		}
+	}
+}
+
+impl Display for Mode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{}",
+			match self {
+				Self::Forge => "Forge",
+				Self::NeoForge => "NeoForge",
+			}
+		)
+	}
+}
+
+/// Install Forge/NeoForge for the client using the core. This downloads the installer for the loader version,
+/// downloads its libraries, and runs the processors from its install profile to create the patched game files.
+/// The processed files are cached so that the processors only run once for each loader version.
+/// Only installers for Minecraft 1.13 and above are supported
+pub async fn install_from_core(
+	core: &mut MCVMCore,
+	version: &MinecraftVersion,
+	mode: Mode,
+	loader_version: &str,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<LaunchProfile> {
+	let (version_name, client_meta) = {
+		let installed_version = core
+			.get_version(version, o)
+			.await
+			.context("Failed to install version")?;
+		(
+			installed_version.get_version().clone(),
+			installed_version.get_client_meta().clone(),
+		)
+	};
+
+	// The processors need the vanilla jar to patch it
+	game_jar::get(
+		Side::Client,
+		&client_meta,
+		&version_name,
+		core.get_paths(),
+		core.get_update_manager(),
+		core.get_client(),
+		o,
+	)
+	.await
+	.context("Failed to get the game JAR file")?;
+
+	let java_version = get_required_java_version(&version_name, &client_meta);
+	let java = core
+		.get_java_installation(java_version, JavaInstallationKind::Auto, o)
+		.await
+		.context("Failed to get Java installation for running the installer")?;
+
+	let process = OutputProcess::new(o);
+	process.0.display(
+		MessageContents::StartProcess(format!("Installing {mode}")),
+		MessageLevel::Important,
+	);
+
+	let installer = Installer::download(
+		&version_name,
+		mode,
+		loader_version,
+		core.get_paths(),
+		core.get_update_manager(),
+		core.get_client(),
+	)
+	.await
+	.with_context(|| format!("Failed to download {mode} installer"))?;
+
+	installer
+		.download_libraries(
+			core.get_paths(),
+			core.get_update_manager(),
+			core.get_client(),
+		)
+		.await
+		.with_context(|| format!("Failed to download {mode} libraries"))?;
+
+	installer
+		.run_processors(
+			&java.get_jvm_path(),
+			core.get_paths(),
+			core.get_update_manager(),
+			process.0,
+		)
+		.await
+		.with_context(|| format!("Failed to run {mode} install processors"))?;
+
+	process.0.display(
+		MessageContents::Success(format!("{mode} installed")),
+		MessageLevel::Important,
+	);
+
+	installer.get_launch_profile(core.get_paths())
+}
+
+/// Get the newest loader version for a Minecraft version. For Forge, the recommended
+/// version is preferred over the latest one
+pub async fn get_newest_loader_version(
+	mode: Mode,
+	minecraft_version: &str,
+	client: &Client,
+) -> anyhow::Result<String> {
+	let version = match mode {
+		Mode::Forge => {
+			let promotions: ForgePromotions = download::json(FORGE_PROMOTIONS_URL, client)
+				.await
+				.context("Failed to download Forge promotions")?;
+			get_forge_promotion(&promotions, minecraft_version)
+		}
+		Mode::NeoForge => {
+			let versions = neoforge::get_loader_versions(minecraft_version, client)
+				.await
+				.context("Failed to get NeoForge versions")?;
+			// Prefer stable releases, but new Minecraft versions only have betas at first
+			versions
+				.iter()
+				.find(|x| x.stable)
+				.or(versions.first())
+				.map(|x| x.version.clone())
+		}
+	};
+
+	version.with_context(|| {
+		format!("Could not find a {mode} version for Minecraft {minecraft_version}")
+	})
+}
+
+/// The promoted Forge versions for each Minecraft version
+#[derive(Deserialize, Debug)]
+struct ForgePromotions {
+	/// Map of keys like `1.20.4-recommended` to loader versions
+	promos: HashMap<String, String>,
+}
+
+/// Get the recommended Forge version for a Minecraft version, or the latest one if there isn't one
+fn get_forge_promotion(promotions: &ForgePromotions, minecraft_version: &str) -> Option<String> {
+	promotions
+		.promos
+		.get(&format!("{minecraft_version}-recommended"))
+		.or_else(|| {
+			promotions
+				.promos
+				.get(&format!("{minecraft_version}-latest"))
+		})
+		.cloned()
+}
+
+/// Everything needed to launch an instance with Forge or NeoForge installed
+#[derive(Debug, Clone)]
+pub struct LaunchProfile {
+	/// The version of the loader that was installed
+	pub loader_version: String,
+	/// The loader libraries to add to the classpath
+	pub classpath: Classpath,
+	/// The main class to launch with
+	pub main_class: String,
+	/// Additional JVM arguments that the loader requires
+	pub jvm_args: Vec<String>,
+	/// Additional game arguments that the loader requires
+	pub game_args: Vec<String>,
+	/// The patched client jar created by the processors, if the install profile has one
+	pub patched_jar: Option<PathBuf>,
+}
+
+impl LaunchProfile {
+	/// Apply this profile to the configuration of the instance that will be launched
+	pub fn apply_to_config(&self, config: &mut InstanceConfiguration) {
+		config.main_class = Some(self.main_class.clone());
+		config.additional_libs.extend(self.classpath.get_paths());
+		config.launch.jvm_args.extend(self.jvm_args.iter().cloned());
+		config
+			.launch
+			.game_args
+			.extend(self.game_args.iter().cloned());
+	}
+}
+
+/// A downloaded Forge/NeoForge installer along with its install profile
+pub struct Installer {
+	/// The Minecraft version being installed for
+	minecraft_version: String,
+	/// The version of the loader being installed
+	loader_version: String,
+	/// The directory where the installer and processed files are stored
+	dir: PathBuf,
+	/// Path to the installer jar
+	jar_path: PathBuf,
+	install_profile: InstallProfile,
+	version_json: VersionJson,
+}
+
+impl Installer {
+	/// Download the installer for a loader version, or use the cached one if it was already downloaded
+	pub async fn download(
+		minecraft_version: &str,
+		mode: Mode,
+		loader_version: &str,
+		paths: &Paths,
+		manager: &UpdateManager,
+		client: &Client,
+	) -> anyhow::Result<Self> {
+		let dir = get_install_dir(mode, minecraft_version, loader_version, paths);
+		let jar_path = dir.join("installer.jar");
+		if manager.force_reinstall() || !jar_path.exists() {
+			manager.ensure_online(&format!("{mode} installer"))?;
+			files::create_leading_dirs_async(&jar_path)
+				.await
+				.context("Failed to create installer directory")?;
+			let url = mode.get_installer_url(minecraft_version, loader_version);
+			download::file(url, &jar_path, client)
+				.await
+				.context("Failed to download installer jar")?;
+		}
+
+		Self::open(minecraft_version, loader_version, dir, jar_path)
+	}
+
+	/// Open an installer jar that has already been downloaded
+	pub fn open(
+		minecraft_version: &str,
+		loader_version: &str,
+		dir: PathBuf,
+		jar_path: PathBuf,
+	) -> anyhow::Result<Self> {
+		let file = BufReader::new(File::open(&jar_path).context("Failed to open installer jar")?);
+		let mut zip = ZipArchive::new(file).context("Failed to read installer jar")?;
+
+		let install_profile = read_zip_file(&mut zip, INSTALL_PROFILE_PATH)?;
+		let install_profile: InstallProfile = serde_json::from_str(&install_profile)
+			.context("Failed to deserialize install profile. Installers for versions older than 1.13 are not supported")?;
+		let version_json = read_zip_file(&mut zip, VERSION_JSON_PATH)?;
+		let version_json: VersionJson =
+			serde_json::from_str(&version_json).context("Failed to deserialize version JSON")?;
+
+		Ok(Self {
+			minecraft_version: minecraft_version.to_string(),
+			loader_version: loader_version.to_string(),
+			dir,
+			jar_path,
+			install_profile,
+			version_json,
+		})
+	}
+
+	/// Download all of the libraries needed by the processors and the game.
+	/// Libraries without a URL are extracted from the installer instead
+	pub async fn download_libraries(
+		&self,
+		paths: &Paths,
+		manager: &UpdateManager,
+		client: &Client,
+	) -> anyhow::Result<()> {
+		let libraries = self
+			.install_profile
+			.libraries
+			.iter()
+			.chain(&self.version_json.libraries);
+
+		let mut tasks = JoinSet::new();
+		let mut bundled = Vec::new();
+		for library in libraries {
+			let artifact_path = library.get_path()?;
+			let path = paths.libraries.join(&artifact_path);
+			if !manager.force_reinstall() && path.exists() {
+				continue;
+			}
+
+			let sha1 = library.get_sha1().map(String::from);
+			match library.get_url() {
+				Some(url) => {
+					manager.ensure_online(&format!("library {artifact_path}"))?;
+					let url = url.to_string();
+					let client = client.clone();
+					tasks.spawn(async move {
+						files::create_leading_dirs_async(&path).await?;
+						download::file(url, &path, &client).await?;
+						verify_library(&path, sha1.as_deref(), &artifact_path)
+					});
+				}
+				None => bundled.push((artifact_path, path, sha1)),
+			}
+		}
+
+		if !bundled.is_empty() {
+			let mut zip = self.open_jar()?;
+			for (artifact_path, path, sha1) in bundled {
+				let contents = read_zip_bytes(&mut zip, &format!("maven/{artifact_path}"))
+					.with_context(|| {
+						format!("Library {artifact_path} has no URL and is not bundled in the installer")
+					})?;
+				files::create_leading_dirs(&path)?;
+				std::fs::write(&path, contents)
+					.with_context(|| format!("Failed to write library {artifact_path}"))?;
+				verify_library(&path, sha1.as_deref(), &artifact_path)?;
+			}
+		}
+
+		while let Some(result) = tasks.join_next().await {
+			result??;
+		}
+
+		Ok(())
+	}
+
+	/// Run the processors from the install profile, which create the patched game files.
+	/// This is skipped if they have already been run and all of their outputs still match their hashes
+	pub async fn run_processors(
+		&self,
+		java: &Path,
+		paths: &Paths,
+		manager: &UpdateManager,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<()> {
+		let marker_path = self.dir.join(PROCESSED_MARKER);
+		let force = manager.force_reinstall() || !marker_path.exists();
+		let data = self.get_data(paths, force)?;
+		let processors = self.install_profile.get_processors(Side::Client);
+
+		if !force && self.outputs_valid(&processors, &data, paths)? {
+			return Ok(());
+		}
+
+		for (i, processor) in processors.iter().enumerate() {
+			o.display(
+				MessageContents::StartProcess(format!(
+					"Running processor {}/{}",
+					i + 1,
+					processors.len()
+				)),
+				MessageLevel::Debug,
+			);
+
+			let jar_path = paths.libraries.join(get_maven_path(&processor.jar)?);
+			let main_class = get_main_class(&jar_path).with_context(|| {
+				format!("Failed to get main class of processor {}", processor.jar)
+			})?;
+			let mut classpath = Classpath::new();
+			classpath.add_path(&jar_path)?;
+			for library in &processor.classpath {
+				classpath.add_path(&paths.libraries.join(get_maven_path(library)?))?;
+			}
+			let args = processor.get_args(&data, paths)?;
+
+			let output = tokio::process::Command::new(java)
+				.arg("-cp")
+				.arg(classpath.get_str())
+				.arg(&main_class)
+				.args(&args)
+				.current_dir(&self.dir)
+				.output()
+				.await
+				.with_context(|| format!("Failed to run processor {}", processor.jar))?;
+			if !output.status.success() {
+				bail!(
+					"Processor {} failed with {}:\n{}",
+					processor.jar,
+					output.status,
+					String::from_utf8_lossy(&output.stderr)
+				);
+			}
+
+			let invalid = processor.get_invalid_outputs(&data, paths)?;
+			if !invalid.is_empty() {
+				bail!(
+					"Processor {} created outputs that do not match their expected hashes: {}",
+					processor.jar,
+					invalid.join(", ")
+				);
+			}
+		}
+
+		std::fs::write(&marker_path, "").context("Failed to write processed marker")?;
+
+		Ok(())
+	}
+
+	/// Get the launch profile from the version JSON of the installer
+	pub fn get_launch_profile(&self, paths: &Paths) -> anyhow::Result<LaunchProfile> {
+		let mut classpath = Classpath::new();
+		for library in &self.version_json.libraries {
+			classpath.add_path(&paths.libraries.join(library.get_path()?))?;
+		}
+
+		// The ignore list in the JVM arguments uses the version name to keep the vanilla
+		// jar out of the module path, so it has to be the name of our jar
+		let jar_path = mcvm_core::io::minecraft::game_jar::get_path(
+			Side::Client,
+			&self.minecraft_version,
+			None,
+			paths,
+		);
+		let jar_name = jar_path
+			.file_stem()
+			.and_then(|x| x.to_str())
+			.unwrap_or_default();
+		let library_dir = paths
+			.libraries
+			.to_str()
+			.context("Failed to convert library directory to a string")?;
+		let substitute = |arg: &String| {
+			arg.replace("${library_directory}", library_dir)
+				.replace("${classpath_separator}", &CLASSPATH_SEP.to_string())
+				.replace("${version_name}", jar_name)
+		};
+
+		let arguments = &self.version_json.arguments;
+		let patched_jar = match self.install_profile.data.get("PATCHED") {
+			Some(value) => {
+				let value = value.get(Side::Client);
+				match value.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+					Some(coord) => Some(paths.libraries.join(get_maven_path(coord)?)),
+					None => None,
+				}
+			}
+			None => None,
+		};
+
+		Ok(LaunchProfile {
+			loader_version: self.loader_version.clone(),
+			classpath,
+			main_class: self.version_json.main_class.clone(),
+			jvm_args: arguments.get_jvm_args().map(substitute).collect(),
+			game_args: arguments.get_game_args().map(substitute).collect(),
+			patched_jar,
+		})
+	}
+
+	/// Get the data variables for the processors, resolved for the client side.
+	/// Files from the installer are only extracted again if they are missing or `force` is set
+	fn get_data(&self, paths: &Paths, force: bool) -> anyhow::Result<HashMap<String, String>> {
+		let mut out = HashMap::new();
+		let vanilla_jar = mcvm_core::io::minecraft::game_jar::get_path(
+			Side::Client,
+			&self.minecraft_version,
+			None,
+			paths,
+		);
+		let builtins = [
+			("SIDE", "client".into()),
+			("MINECRAFT_JAR", path_to_string(&vanilla_jar)?),
+			("MINECRAFT_VERSION", self.minecraft_version.clone()),
+			("ROOT", path_to_string(&self.dir)?),
+			("INSTALLER", path_to_string(&self.jar_path)?),
+			("LIBRARY_DIR", path_to_string(&paths.libraries)?),
+		];
+		for (key, value) in builtins {
+			out.insert(key.to_string(), value);
+		}
+
+		let mut zip = None;
+		for (key, value) in &self.install_profile.data {
+			let value = value.get(Side::Client);
+			let value =
+				if let Some(coord) = value.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+					path_to_string(&paths.libraries.join(get_maven_path(coord)?))?
+				} else if let Some(literal) =
+					value.strip_prefix('\'').and_then(|x| x.strip_suffix('\''))
+				{
+					literal.to_string()
+				} else if let Some(inner_path) = value.strip_prefix('/') {
+					// Files in the installer have to be extracted so that the processors can read them
+					let path = self.extract_file(&mut zip, inner_path, force)?;
+					path_to_string(&path)?
+				} else {
+					value.to_string()
+				};
+			out.insert(key.clone(), value);
+		}
+
+		Ok(out)
+	}
+
+	/// Check whether all of the outputs of the processors exist and match their expected hashes
+	fn outputs_valid(
+		&self,
+		processors: &[&Processor],
+		data: &HashMap<String, String>,
+		paths: &Paths,
+	) -> anyhow::Result<bool> {
+		for processor in processors {
+			if !processor.get_invalid_outputs(data, paths)?.is_empty() {
+				return Ok(false);
+			}
+		}
+
+		Ok(true)
+	}
+
+	/// Extract a file from the installer jar, opening the jar if it has not been opened yet.
+	/// The file is only extracted again if it is missing or `force` is set
+	fn extract_file(
+		&self,
+		zip: &mut Option<ZipArchive<BufReader<File>>>,
+		inner_path: &str,
+		force: bool,
+	) -> anyhow::Result<PathBuf> {
+		let path = self.dir.join("extracted").join(inner_path);
+		if !force && path.exists() {
+			return Ok(path);
+		}
+
+		let zip = match zip {
+			Some(zip) => zip,
+			None => zip.insert(self.open_jar()?),
+		};
+		let contents = read_zip_bytes(zip, inner_path)?;
+		files::create_leading_dirs(&path)?;
+		std::fs::write(&path, contents)
+			.with_context(|| format!("Failed to extract {inner_path} from installer"))?;
+
+		Ok(path)
+	}
+
+	/// Open the installer jar
+	fn open_jar(&self) -> anyhow::Result<ZipArchive<BufReader<File>>> {
+		let file =
+			BufReader::new(File::open(&self.jar_path).context("Failed to open installer jar")?);
+		ZipArchive::new(file).context("Failed to read installer jar")
+	}
+}
+
+/// The install_profile.json file in the installer
+#[derive(Deserialize, Debug)]
+struct InstallProfile {
+	/// Variables for the processors, each with a value for the client and server
+	#[serde(default)]
+	data: HashMap<String, SidedValue>,
+	#[serde(default)]
+	processors: Vec<Processor>,
+	#[serde(default)]
+	libraries: Vec<Library>,
+}
+
+impl InstallProfile {
+	/// Get the processors that should be run for a side
+	fn get_processors(&self, side: Side) -> Vec<&Processor> {
+		let side = side.to_string();
+		self.processors
+			.iter()
+			.filter(|x| x.sides.as_ref().map_or(true, |sides| sides.contains(&side)))
+			.collect()
+	}
+}
+
+/// A value in the install profile data with a different value for each side
+#[derive(Deserialize, Debug)]
+struct SidedValue {
+	client: String,
+	server: String,
+}
+
+impl SidedValue {
+	fn get(&self, side: Side) -> &str {
+		match side {
+			Side::Client => &self.client,
+			Side::Server => &self.server,
+		}
+	}
+}
+
+/// A processor in the install profile, which is a Java program that is run to create game files
+#[derive(Deserialize, Debug)]
+struct Processor {
+	/// The sides to run this processor on. If not present, the processor runs on all sides
+	#[serde(default)]
+	sides: Option<Vec<String>>,
+	/// Maven coordinate of the processor jar
+	jar: String,
+	/// Maven coordinates of the libraries for the processor
+	#[serde(default)]
+	classpath: Vec<String>,
+	#[serde(default)]
+	args: Vec<String>,
+	/// The files created by the processor, along with their expected hashes
+	#[serde(default)]
+	outputs: HashMap<String, String>,
+}
+
+impl Processor {
+	/// Get the arguments for this processor with all of the variables resolved
+	fn get_args(
+		&self,
+		data: &HashMap<String, String>,
+		paths: &Paths,
+	) -> anyhow::Result<Vec<String>> {
+		self.args
+			.iter()
+			.map(|arg| resolve_arg(arg, data, paths))
+			.collect()
+	}
+
+	/// Get the paths of the outputs of this processor that are missing or do not match their expected SHA-1 hashes
+	fn get_invalid_outputs(
+		&self,
+		data: &HashMap<String, String>,
+		paths: &Paths,
+	) -> anyhow::Result<Vec<String>> {
+		let mut out = Vec::new();
+		for (output, hash) in &self.outputs {
+			let output = resolve_arg(output, data, paths)?;
+			// Hashes are either data variables or quoted literals
+			let hash = resolve_arg(hash, data, paths)?;
+			let hash = hash.trim_matches('\'');
+			let status = check_file(Path::new(&output), Some(hash))
+				.with_context(|| format!("Failed to check processor output {output}"))?;
+			if status != FileStatus::Intact {
+				out.push(output);
+			}
+		}
+
+		Ok(out)
+	}
+}
+
+/// Resolve a processor argument, which is either a data variable,
+/// a Maven coordinate to a library, or a literal value
+fn resolve_arg(arg: &str, data: &HashMap<String, String>, paths: &Paths) -> anyhow::Result<String> {
+	if let Some(key) = arg.strip_prefix('{').and_then(|x| x.strip_suffix('}')) {
+		data.get(key)
+			.cloned()
+			.ok_or(anyhow!("Processor data variable {key} does not exist"))
+	} else if let Some(coord) = arg.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+		path_to_string(&paths.libraries.join(get_maven_path(coord)?))
+	} else {
+		Ok(arg.to_string())
+	}
+}
+
+/// The version.json file in the installer, which is in the format of the vanilla client meta
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct VersionJson {
+	main_class: String,
+	#[serde(default)]
+	arguments: Arguments,
+	#[serde(default)]
+	libraries: Vec<Library>,
+}
+
+/// Launch arguments in the version JSON
+#[derive(Deserialize, Debug, Default)]
+struct Arguments {
+	#[serde(default)]
+	game: Vec<Argument>,
+	#[serde(default)]
+	jvm: Vec<Argument>,
+}
+
+impl Arguments {
+	fn get_game_args(&self) -> impl Iterator<Item = &String> {
+		self.game.iter().filter_map(Argument::get_plain)
+	}
+
+	fn get_jvm_args(&self) -> impl Iterator<Item = &String> {
+		self.jvm.iter().filter_map(Argument::get_plain)
+	}
+}
+
+/// A launch argument in the version JSON
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Argument {
+	/// A plain argument
+	Plain(String),
+	/// An argument with rules. These are not used by Forge or NeoForge, so they are ignored
+	Conditional(serde_json::Value),
+}
+
+impl Argument {
+	fn get_plain(&self) -> Option<&String> {
+		match self {
+			Self::Plain(arg) => Some(arg),
+			Self::Conditional(..) => None,
+		}
+	}
+}
+
+/// A library in the install profile or version JSON
+#[derive(Deserialize, Debug)]
+struct Library {
+	/// Maven coordinate of the library
+	name: String,
+	#[serde(default)]
+	downloads: Option<LibraryDownloads>,
+}
+
+impl Library {
+	/// Get the path of this library relative to the libraries directory
+	fn get_path(&self) -> anyhow::Result<String> {
+		match self.downloads.as_ref().and_then(|x| x.artifact.as_ref()) {
+			Some(artifact) if !artifact.path.is_empty() => Ok(artifact.path.clone()),
+			_ => get_maven_path(&self.name),
+		}
+	}
+
+	/// Get the expected SHA-1 hash of this library, if there is one
+	fn get_sha1(&self) -> Option<&str> {
+		let artifact = self.downloads.as_ref()?.artifact.as_ref()?;
+		artifact.sha1.as_deref().filter(|x| !x.is_empty())
+	}
+
+	/// Get the URL to download this library from. If there isn't one, the library is bundled in the installer
+	fn get_url(&self) -> Option<&str> {
+		let artifact = self.downloads.as_ref()?.artifact.as_ref()?;
+		if artifact.url.is_empty() {
+			None
+		} else {
+			Some(&artifact.url)
+		}
+	}
+}
+
+/// Downloads for a library
+#[derive(Deserialize, Debug)]
+struct LibraryDownloads {
+	#[serde(default)]
+	artifact: Option<LibraryArtifact>,
+}
+
+/// The main artifact for a library
+#[derive(Deserialize, Debug)]
+struct LibraryArtifact {
+	#[serde(default)]
+	path: String,
+	#[serde(default)]
+	url: String,
+	#[serde(default)]
+	sha1: Option<String>,
+}
+
+/// Get the path relative to a Maven repository for a Maven coordinate, which
+/// can have a classifier and an extension like `group:artifact:version:classifier@extension`
+fn get_maven_path(coord: &str) -> anyhow::Result<String> {
+	let (coord, extension) = coord.split_once('@').unwrap_or((coord, "jar"));
+	let mut parts = coord.split(':');
+	let (Some(group), Some(artifact), Some(version)) = (parts.next(), parts.next(), parts.next())
+	else {
+		bail!("Invalid Maven coordinate '{coord}'");
+	};
+	let classifier = parts.next().map(|x| format!("-{x}")).unwrap_or_default();
+	let group = group.replace('.', "/");
+
+	Ok(format!(
+		"{group}/{artifact}/{version}/{artifact}-{version}{classifier}.{extension}"
+	))
+}
+
+/// Check that a library matches its expected hash, removing it if it does not so that it is downloaded again
+fn verify_library(path: &Path, sha1: Option<&str>, artifact_path: &str) -> anyhow::Result<()> {
+	let status = check_file(path, sha1)
+		.with_context(|| format!("Failed to check hash of library {artifact_path}"))?;
+	if status != FileStatus::Intact {
+		let _ = std::fs::remove_file(path);
+		bail!("Library {artifact_path} does not match its expected hash");
+	}
+
+	Ok(())
+}
+
+/// Get the directory where the installer and processed files for a loader version are stored
+fn get_install_dir(
+	mode: Mode,
+	minecraft_version: &str,
+	loader_version: &str,
+	paths: &Paths,
+) -> PathBuf {
+	paths
+		.internal
+		.join("forge")
+		.join(mode.to_string().to_lowercase())
+		.join(format!("{minecraft_version}-{loader_version}"))
+}
+
+/// Get the main class from the manifest of a jar
+fn get_main_class(jar_path: &Path) -> anyhow::Result<String> {
+	let file = BufReader::new(File::open(jar_path).context("Failed to open jar")?);
+	let mut zip = ZipArchive::new(file).context("Failed to read jar")?;
+	let manifest = read_zip_file(&mut zip, "META-INF/MANIFEST.MF")?;
+	manifest
+		.lines()
+		.find_map(|line| line.strip_prefix("Main-Class:"))
+		.map(|x| x.trim().to_string())
+		.context("Jar manifest does not have a main class")
+}
+
+/// Read a text file from a zip archive
+fn read_zip_file<R: Read + Seek>(zip: &mut ZipArchive<R>, path: &str) -> anyhow::Result<String> {
+	let contents = read_zip_bytes(zip, path)?;
+	String::from_utf8(contents).with_context(|| format!("{path} is not valid UTF-8"))
+}
+
+/// Read a file from a zip archive
+fn read_zip_bytes<R: Read + Seek>(zip: &mut ZipArchive<R>, path: &str) -> anyhow::Result<Vec<u8>> {
+	let mut file = zip
+		.by_name(path)
+		.with_context(|| format!("Failed to find {path} in archive"))?;
+	let mut out = Vec::new();
+	file.read_to_end(&mut out)
+		.with_context(|| format!("Failed to read {path} from archive"))?;
+
+	Ok(out)
+}
+
+/// Convert a path to a string for use in arguments
+fn path_to_string(path: &Path) -> anyhow::Result<String> {
+	path.to_str()
+		.map(String::from)
+		.context("Failed to convert path to a string")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const INSTALL_PROFILE: &str = r#"{
+		"spec": 1,
+		"profile": "NeoForge",
+		"version": "neoforge-20.4.80-beta",
+		"minecraft": "1.20.4",
+		"data": {
+			"MOJMAPS": {
+				"client": "[net.minecraft:client:1.20.4-20231207.154220:mappings@txt]",
+				"server": "[net.minecraft:server:1.20.4-20231207.154220:mappings@txt]"
+			},
+			"PATCHED": {
+				"client": "[net.neoforged:minecraft-client-patched:20.4.80-beta]",
+				"server": "[net.neoforged:minecraft-server-patched:20.4.80-beta]"
+			},
+			"MCP_VERSION": {
+				"client": "'20231207.154220'",
+				"server": "'20231207.154220'"
+			}
+		},
+		"processors": [
+			{
+				"sides": ["server"],
+				"jar": "net.neoforged.installertools:installertools:2.1.2",
+				"classpath": [],
+				"args": ["--task", "EXTRACT_FILES"]
+			},
+			{
+				"sides": ["client"],
+				"jar": "net.neoforged.installertools:installertools:2.1.2",
+				"classpath": ["net.md-5:SpecialSource:1.11.0"],
+				"args": ["--task", "DOWNLOAD_MOJMAPS", "--version", "{MINECRAFT_VERSION}", "--side", "{SIDE}", "--output", "{MOJMAPS}"],
+				"outputs": {}
+			},
+			{
+				"jar": "net.neoforged.installertools:binarypatcher:2.1.2",
+				"classpath": [],
+				"args": ["--clean", "{MINECRAFT_JAR}", "--output", "{PATCHED}", "--neoform", "[net.neoforged:neoform:1.20.4-20231207.154220@zip]"],
+				"outputs": {
+					"{PATCHED}": "'0000000000000000000000000000000000000000'"
+				}
+			}
+		],
+		"libraries": [
+			{
+				"name": "net.neoforged.installertools:installertools:2.1.2",
+				"downloads": {
+					"artifact": {
+						"path": "net/neoforged/installertools/installertools/2.1.2/installertools-2.1.2.jar",
+						"url": "https://maven.neoforged.net/releases/net/neoforged/installertools/installertools/2.1.2/installertools-2.1.2.jar"
+					}
+				}
+			}
+		]
+	}"#;
+
+	const VERSION_JSON: &str = r#"{
+		"id": "neoforge-20.4.80-beta",
+		"inheritsFrom": "1.20.4",
+		"type": "release",
+		"mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+		"arguments": {
+			"game": ["--fml.neoForgeVersion", "20.4.80-beta", "--launchTarget", "forgeclient"],
+			"jvm": [
+				"-DignoreList=client-extra,${version_name}.jar",
+				"-DlibraryDirectory=${library_directory}",
+				"-p",
+				"${library_directory}/cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar${classpath_separator}${library_directory}/cpw/mods/securejarhandler/2.1.24/securejarhandler-2.1.24.jar"
+			]
+		},
+		"libraries": [
+			{
+				"name": "cpw.mods:bootstraplauncher:1.1.2",
+				"downloads": {
+					"artifact": {
+						"path": "cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar",
+						"url": "https://maven.neoforged.net/releases/cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar"
+					}
+				}
+			},
+			{
+				"name": "net.neoforged:neoforge:20.4.80-beta:universal",
+				"downloads": {
+					"artifact": {
+						"path": "net/neoforged/neoforge/20.4.80-beta/neoforge-20.4.80-beta-universal.jar",
+						"url": ""
+					}
+				}
+			}
+		]
+	}"#;
+
+	fn create_installer(paths: &Paths) -> Installer {
+		let dir = get_install_dir(Mode::NeoForge, "1.20.4", "20.4.80-beta", paths);
+		Installer {
+			minecraft_version: "1.20.4".into(),
+			loader_version: "20.4.80-beta".into(),
+			jar_path: dir.join("installer.jar"),
+			dir,
+			install_profile: serde_json::from_str(INSTALL_PROFILE).unwrap(),
+			version_json: serde_json::from_str(VERSION_JSON).unwrap(),
+		}
+	}
+
+	#[test]
+	fn test_launch_profile() {
+		let dir = tempfile::tempdir().unwrap();
+		let paths = Paths::with_data_dir_no_create(dir.path().to_path_buf()).unwrap();
+		let installer = create_installer(&paths);
+		let profile = installer.get_launch_profile(&paths).unwrap();
+
+		assert_eq!(profile.loader_version, "20.4.80-beta");
+		assert_eq!(
+			profile.main_class,
+			"cpw.mods.bootstraplauncher.BootstrapLauncher"
+		);
+		assert_eq!(
+			profile.classpath.get_paths(),
+			vec![
+				paths
+					.libraries
+					.join("cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar"),
+				paths.libraries.join(
+					"net/neoforged/neoforge/20.4.80-beta/neoforge-20.4.80-beta-universal.jar"
+				),
+			]
+		);
+		assert_eq!(
+			profile.patched_jar,
+			Some(paths.libraries.join(
+				"net/neoforged/minecraft-client-patched/20.4.80-beta/minecraft-client-patched-20.4.80-beta.jar"
+			))
+		);
+
+		let library_dir = paths.libraries.to_str().unwrap();
+		assert_eq!(
+			profile.jvm_args[0],
+			"-DignoreList=client-extra,1.20.4_client.jar"
+		);
+		assert_eq!(
+			profile.jvm_args[1],
+			format!("-DlibraryDirectory={library_dir}")
+		);
+		assert!(!profile.jvm_args[3].contains("${"));
+		assert_eq!(profile.game_args.last().unwrap(), "forgeclient");
+	}
+
+	#[test]
+	fn test_processor_args() {
+		let dir = tempfile::tempdir().unwrap();
+		let paths = Paths::with_data_dir_no_create(dir.path().to_path_buf()).unwrap();
+		let installer = create_installer(&paths);
+		let processors = installer.install_profile.get_processors(Side::Client);
+		assert_eq!(processors.len(), 2);
+
+		let data = installer.get_data(&paths, false).unwrap();
+		let args = processors[0].get_args(&data, &paths).unwrap();
+		let mojmaps = paths.libraries.join(
+			"net/minecraft/client/1.20.4-20231207.154220/client-1.20.4-20231207.154220-mappings.txt",
+		);
+		assert_eq!(
+			args,
+			vec![
+				"--task",
+				"DOWNLOAD_MOJMAPS",
+				"--version",
+				"1.20.4",
+				"--side",
+				"client",
+				"--output",
+				mojmaps.to_str().unwrap(),
+			]
+		);
+
+		let args = processors[1].get_args(&data, &paths).unwrap();
+		let vanilla_jar =
+			mcvm_core::io::minecraft::game_jar::get_path(Side::Client, "1.20.4", None, &paths);
+		assert_eq!(args[1], vanilla_jar.to_str().unwrap());
+		assert!(args[5].ends_with("neoform-1.20.4-20231207.154220.zip"));
+		assert_eq!(data["MCP_VERSION"], "20231207.154220");
+	}
+
+	#[test]
+	fn test_processor_output_hashes() {
+		let dir = tempfile::tempdir().unwrap();
+		let paths = Paths::with_data_dir_no_create(dir.path().to_path_buf()).unwrap();
+		let installer = create_installer(&paths);
+		let processors = installer.install_profile.get_processors(Side::Client);
+		let data = installer.get_data(&paths, false).unwrap();
+
+		let patched = PathBuf::from(&data["PATCHED"]);
+		assert_eq!(
+			processors[1].get_invalid_outputs(&data, &paths).unwrap(),
+			vec![data["PATCHED"].clone()]
+		);
+
+		let processor = Processor {
+			sides: None,
+			jar: "test:test:1.0".into(),
+			classpath: Vec::new(),
+			args: Vec::new(),
+			outputs: HashMap::from([(
+				"{PATCHED}".to_string(),
+				"'aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d'".to_string(),
+			)]),
+		};
+		files::create_leading_dirs(&patched).unwrap();
+		std::fs::write(&patched, "hello").unwrap();
+		assert!(processor
+			.get_invalid_outputs(&data, &paths)
+			.unwrap()
+			.is_empty());
+		std::fs::write(&patched, "goodbye").unwrap();
+		assert_eq!(
+			processor.get_invalid_outputs(&data, &paths).unwrap().len(),
+			1
+		);
+	}
+
+	#[test]
+	fn test_newest_loader_version() {
+		let promotions: ForgePromotions = serde_json::from_str(
+			r#"{"promos": {"1.20.4-latest": "49.0.30", "1.20.4-recommended": "49.0.26", "1.20.6-latest": "50.0.0"}}"#,
+		)
+		.unwrap();
+		assert_eq!(
+			get_forge_promotion(&promotions, "1.20.4").as_deref(),
+			Some("49.0.26")
+		);
+		assert_eq!(
+			get_forge_promotion(&promotions, "1.20.6").as_deref(),
+			Some("50.0.0")
+		);
+		assert_eq!(get_forge_promotion(&promotions, "1.12.2"), None);
+	}
+
+	#[test]
+	fn test_maven_path() {
+		assert_eq!(
+			get_maven_path("net.neoforged:neoforge:20.4.80-beta:universal").unwrap(),
+			"net/neoforged/neoforge/20.4.80-beta/neoforge-20.4.80-beta-universal.jar"
+		);
+		assert_eq!(
+			get_maven_path("net.neoforged:neoform:1.20.4-20231207.154220@zip").unwrap(),
+			"net/neoforged/neoform/1.20.4-20231207.154220/neoform-1.20.4-20231207.154220.zip"
+		);
+		assert!(get_maven_path("invalid").is_err());
+	}
+}
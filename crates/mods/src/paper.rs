@@ -61,6 +61,14 @@ pub async fn install_from_core(
 		bail!("Velocity is a proxy and cannot be used in the install_from_core function");
 	}
 
+	// Offline mode can only use a JAR that has already been downloaded
+	let jar_path = get_local_jar_path(mode, &version_info.version, core.get_paths());
+	let manager = core.get_update_manager();
+	if manager.is_offline() && jar_path.exists() {
+		return Ok((jar_path, PAPER_SERVER_MAIN_CLASS.into()));
+	}
+	manager.ensure_online(&format!("{mode} server JAR"))?;
+
 	let build_num = get_newest_build(mode, &version_info.version, core.get_client())
 		.await
 		.context(format!("Failed to get newest {mode} build"))?;
@@ -79,10 +87,7 @@ pub async fn install_from_core(
 	.await
 	.context(format!("Failed to download {mode} JAR file"))?;
 
-	Ok((
-		get_local_jar_path(mode, &version_info.version, core.get_paths()),
-		PAPER_SERVER_MAIN_CLASS.into(),
-	))
+	Ok((jar_path, PAPER_SERVER_MAIN_CLASS.into()))
 }
 
 /// Install Velocity, returning the path to the JAR file and the main class
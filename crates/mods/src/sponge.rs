@@ -38,6 +38,14 @@ pub async fn install_from_core(
 ) -> anyhow::Result<(PathBuf, String)> {
 	let _ = o;
 
+	// Offline mode can only use a JAR that has already been downloaded
+	let jar_path = get_local_jar_path(mode, &version_info.version, core.get_paths());
+	let manager = core.get_update_manager();
+	if manager.is_offline() && jar_path.exists() {
+		return Ok((jar_path, SPONGE_SERVER_MAIN_CLASS.into()));
+	}
+	manager.ensure_online("Sponge server JAR")?;
+
 	let sponge_version = get_newest_version(mode, &version_info.version, core.get_client())
 		.await
 		.context("Failed to get latest version for Sponge")?;
@@ -51,10 +59,7 @@ pub async fn install_from_core(
 	.await
 	.context("Failed to download Sponge JAR file")?;
 
-	Ok((
-		get_local_jar_path(mode, &version_info.version, core.get_paths()),
-		SPONGE_SERVER_MAIN_CLASS.into(),
-	))
+	Ok((jar_path, SPONGE_SERVER_MAIN_CLASS.into()))
 }
 
 /// Get the newest version of a Sponge project
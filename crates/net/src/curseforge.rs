@@ -0,0 +1,58 @@
+use anyhow::Context;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// API URL
+const API_URL: &str = "https://api.curseforge.com/v1";
+
+/// A CurseForge mod (project)
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Mod {
+	/// The ID of the mod
+	pub id: u32,
+	/// The display name of the mod
+	pub name: String,
+	/// The URL-friendly slug of the mod
+	pub slug: String,
+	/// Whether the files of the mod can be downloaded by third parties like launchers.
+	/// When this is false, the files have to be downloaded manually from the website
+	#[serde(default)]
+	pub allow_mod_distribution: Option<bool>,
+}
+
+/// Response wrapper used by all of the CurseForge API endpoints
+#[derive(Deserialize)]
+struct Response<T> {
+	data: T,
+}
+
+/// Get multiple CurseForge mods. An API key is required for the CurseForge API.
+/// Mods that don't exist are not included in the result
+pub async fn get_multiple_mods(
+	mod_ids: &[u32],
+	api_key: &str,
+	client: &Client,
+) -> anyhow::Result<Vec<Mod>> {
+	if mod_ids.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let body = serde_json::json!({
+		"modIds": mod_ids,
+	});
+	let resp: Response<Vec<Mod>> = client
+		.post(format!("{API_URL}/mods"))
+		.header("x-api-key", api_key)
+		.json(&body)
+		.send()
+		.await
+		.context("Failed to send request")?
+		.error_for_status()
+		.context("Server reported an error")?
+		.json()
+		.await
+		.context("Failed to deserialize mods")?;
+
+	Ok(resp.data)
+}
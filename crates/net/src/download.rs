@@ -1,6 +1,8 @@
 use std::fs::File;
 use std::io::{BufWriter, Cursor, Write};
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::{ensure, Context};
 use mcvm_shared::output::MessageContents;
@@ -58,6 +60,39 @@ pub async fn text(url: impl IntoUrl, client: &Client) -> anyhow::Result<String>
 	Ok(text)
 }
 
+/// Downloads text if it has changed since the version with the given ETag was downloaded.
+/// Returns None if the server reports that it has not been modified, and the text
+/// along with its new ETag otherwise.
+pub async fn text_if_changed(
+	url: impl IntoUrl,
+	etag: Option<&str>,
+	client: &Client,
+) -> anyhow::Result<Option<(String, Option<String>)>> {
+	let mut request = client.get(url).header("User-Agent", user_agent());
+	if let Some(etag) = etag {
+		request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+	}
+	let resp = request.send().await.context("Failed to send request")?;
+	if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+		return Ok(None);
+	}
+
+	let resp = resp
+		.error_for_status()
+		.context("Server reported an error")?;
+	let etag = resp
+		.headers()
+		.get(reqwest::header::ETAG)
+		.and_then(|x| x.to_str().ok())
+		.map(String::from);
+	let text = resp
+		.text()
+		.await
+		.context("Failed to convert download to text")?;
+
+	Ok(Some((text, etag)))
+}
+
 /// Downloads and returns bytes
 pub async fn bytes(url: impl IntoUrl, client: &Client) -> anyhow::Result<bytes::Bytes> {
 	let bytes = download(url, client)
@@ -70,6 +105,26 @@ pub async fn bytes(url: impl IntoUrl, client: &Client) -> anyhow::Result<bytes::
 	Ok(bytes)
 }
 
+/// Downloads and returns bytes, limiting the rate of the download if a limiter is given
+pub async fn bytes_limited(
+	url: impl IntoUrl,
+	client: &Client,
+	limiter: Option<&BandwidthLimiter>,
+) -> anyhow::Result<bytes::Bytes> {
+	let Some(limiter) = limiter else {
+		return bytes(url, client).await;
+	};
+
+	let mut response = download(url, client).await.context("Failed to download")?;
+	let mut out = Vec::with_capacity(response.content_length().unwrap_or_default() as usize);
+	while let Some(chunk) = response.chunk().await.context("Failed to download chunk")? {
+		out.extend_from_slice(&chunk);
+		limiter.consume(chunk.len()).await;
+	}
+
+	Ok(out.into())
+}
+
 /// Downloads and puts the contents in a file
 pub async fn file(
 	url: impl IntoUrl,
@@ -99,6 +154,72 @@ pub async fn json<T: DeserializeOwned>(url: impl IntoUrl, client: &Client) -> an
 		.context("Failed to parse JSON")
 }
 
+/// A token bucket that limits the rate of downloads to a number of bytes per second.
+/// Clones share the same bucket, so one limiter can be used to limit the total rate
+/// of multiple concurrent downloads
+#[derive(Debug, Clone)]
+pub struct BandwidthLimiter {
+	bytes_per_sec: u64,
+	state: Arc<Mutex<LimiterState>>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+	/// The number of bytes that can be downloaded right now. Goes negative
+	/// when downloads have to wait
+	tokens: f64,
+	/// The last time that tokens were added
+	last_refill: Instant,
+}
+
+impl BandwidthLimiter {
+	/// Create a new limiter with a maximum number of bytes per second.
+	/// Returns None if the limit is zero, which means that downloads are unlimited
+	pub fn new(bytes_per_sec: u64) -> Option<Self> {
+		if bytes_per_sec == 0 {
+			return None;
+		}
+
+		Some(Self {
+			bytes_per_sec,
+			state: Arc::new(Mutex::new(LimiterState {
+				tokens: 0.0,
+				last_refill: Instant::now(),
+			})),
+		})
+	}
+
+	/// Get the maximum number of bytes per second
+	pub fn get_limit(&self) -> u64 {
+		self.bytes_per_sec
+	}
+
+	/// Take tokens for a number of downloaded bytes, waiting until the rate
+	/// is back under the limit
+	pub async fn consume(&self, bytes: usize) {
+		let wait = {
+			let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+			let rate = self.bytes_per_sec as f64;
+			let now = Instant::now();
+			let refill = now.duration_since(state.last_refill).as_secs_f64() * rate;
+			// Only allow a burst of up to one second of data
+			state.tokens = (state.tokens + refill).min(rate);
+			state.last_refill = now;
+			state.tokens -= bytes as f64;
+
+			if state.tokens < 0.0 {
+				Duration::from_secs_f64(-state.tokens / rate)
+			} else {
+				Duration::ZERO
+			}
+		};
+
+		if !wait.is_zero() {
+			tokio::time::sleep(wait).await;
+		}
+	}
+}
+
 /// A persistent single download that can be used to track progress
 pub struct ProgressiveDownload<W: Write> {
 	response: reqwest::Response,
@@ -106,6 +227,7 @@ pub struct ProgressiveDownload<W: Write> {
 	content_length: u64,
 	bytes_downloaded: usize,
 	finished: bool,
+	bandwidth_limiter: Option<BandwidthLimiter>,
 }
 
 impl<W: Write> ProgressiveDownload<W> {
@@ -117,9 +239,16 @@ impl<W: Write> ProgressiveDownload<W> {
 			writer,
 			bytes_downloaded: 0,
 			finished: false,
+			bandwidth_limiter: None,
 		}
 	}
 
+	/// Limit the rate of this download. None means that the download is unlimited
+	pub fn with_bandwidth_limit(mut self, limiter: Option<BandwidthLimiter>) -> Self {
+		self.bandwidth_limiter = limiter;
+		self
+	}
+
 	/// Get the number of bytes that have been downloaded
 	pub fn get_downloaded(&self) -> usize {
 		self.bytes_downloaded
@@ -149,6 +278,9 @@ impl<W: Write> ProgressiveDownload<W> {
 				.write_all(&bytes)
 				.context("Failed to write downloaded bytes")?;
 			self.bytes_downloaded += bytes.len();
+			if let Some(limiter) = &self.bandwidth_limiter {
+				limiter.consume(bytes.len()).await;
+			}
 		} else {
 			self.finished = true;
 			// Ensure that we downloaded the correct amount
@@ -215,3 +347,24 @@ pub fn validate_url(url: &str) -> anyhow::Result<()> {
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_unlimited_bandwidth() {
+		assert!(BandwidthLimiter::new(0).is_none());
+	}
+
+	#[tokio::test]
+	async fn test_bandwidth_limit() {
+		let limiter = BandwidthLimiter::new(100_000).unwrap();
+		let start = Instant::now();
+		for _ in 0..25 {
+			limiter.consume(2_000).await;
+		}
+		// 50 kB at 100 kB/s should take at least half a second
+		assert!(start.elapsed() >= Duration::from_millis(500));
+	}
+}
@@ -0,0 +1,101 @@
+use anyhow::Context;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::download;
+
+/// API URL
+const API_URL: &str = "https://meta.fabricmc.net/v2";
+
+/// A version of the Fabric loader
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct LoaderVersion {
+	/// The version string of the loader
+	pub version: String,
+	/// The Maven coordinate of the loader library
+	pub maven: String,
+	/// Whether this is a stable release of the loader
+	pub stable: bool,
+}
+
+/// An entry in the list of loader versions for a Minecraft version
+#[derive(Deserialize)]
+struct LoaderListEntry {
+	loader: LoaderVersion,
+}
+
+/// Get the Fabric loader versions that are available for a Minecraft version, newest first
+pub async fn get_loader_versions(
+	minecraft_version: &str,
+	client: &Client,
+) -> anyhow::Result<Vec<LoaderVersion>> {
+	let url = format!("{API_URL}/versions/loader/{minecraft_version}");
+	let bytes = download::bytes(url, client)
+		.await
+		.context("Failed to download Fabric loader versions")?;
+
+	parse_loader_versions(&bytes)
+}
+
+/// Get the newest stable Fabric loader version for a Minecraft version, if there is one
+pub async fn get_latest_stable_loader(
+	minecraft_version: &str,
+	client: &Client,
+) -> anyhow::Result<Option<LoaderVersion>> {
+	let versions = get_loader_versions(minecraft_version, client).await?;
+	Ok(versions.into_iter().find(|x| x.stable))
+}
+
+/// Parse the loader versions from the loader list returned by the API
+fn parse_loader_versions(data: &[u8]) -> anyhow::Result<Vec<LoaderVersion>> {
+	let entries: Vec<LoaderListEntry> =
+		serde_json::from_slice(data).context("Failed to deserialize Fabric loader versions")?;
+
+	Ok(entries.into_iter().map(|x| x.loader).collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_loader_versions() {
+		let data = br#"[
+			{
+				"loader": {
+					"separator": ".",
+					"build": 6,
+					"maven": "net.fabricmc:fabric-loader:0.16.0",
+					"version": "0.16.0",
+					"stable": false
+				},
+				"intermediary": {
+					"maven": "net.fabricmc:intermediary:1.20.1",
+					"version": "1.20.1",
+					"stable": true
+				}
+			},
+			{
+				"loader": {
+					"separator": ".",
+					"build": 5,
+					"maven": "net.fabricmc:fabric-loader:0.15.11",
+					"version": "0.15.11",
+					"stable": true
+				},
+				"intermediary": {
+					"maven": "net.fabricmc:intermediary:1.20.1",
+					"version": "1.20.1",
+					"stable": true
+				}
+			}
+		]"#;
+
+		let versions = parse_loader_versions(data).unwrap();
+		assert_eq!(versions.len(), 2);
+		assert_eq!(versions[0].version, "0.16.0");
+		assert!(!versions[0].stable);
+		assert_eq!(versions[1].maven, "net.fabricmc:fabric-loader:0.15.11");
+		assert!(versions[1].stable);
+	}
+}
@@ -1,11 +1,17 @@
 //! Note: The asynchronous functions in this library expect the use of the Tokio runtime and may panic
 //! if it is not used
 
+/// Interacting with the CurseForge API
+pub mod curseforge;
 /// Download utilities
 pub mod download;
+/// Getting Fabric loader versions from the Fabric meta API
+pub mod fabric;
 /// GitHub releases API
 pub mod github;
 /// Interacting with the Modrinth API
 pub mod modrinth;
+/// Getting NeoForge versions from the NeoForged Maven
+pub mod neoforge;
 /// Interacting with the Smithed API
 pub mod smithed;
@@ -1,6 +1,10 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
 use crate::download;
 use anyhow::{anyhow, Context};
 use mcvm_shared::modifications::{Modloader, ServerType};
+use regex::{Regex, RegexBuilder};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +13,9 @@ use serde::{Deserialize, Serialize};
 pub struct Project {
 	/// The ID of the project
 	pub id: String,
+	/// The URL-friendly slug of the project
+	#[serde(default)]
+	pub slug: Option<String>,
 	/// The type of this project and its files
 	pub project_type: ProjectType,
 	/// The ID's of the available project versions
@@ -48,7 +55,7 @@ pub struct Project {
 }
 
 /// The type of a Modrinth project
-#[derive(Deserialize, Serialize, Copy, Clone)]
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum ProjectType {
 	/// A mod project
@@ -83,6 +90,88 @@ pub async fn get_project_raw(project_id: &str, client: &Client) -> anyhow::Resul
 	Ok(out)
 }
 
+/// Resolve a project slug, such as the one in the URL of the project page, to the ID of the project.
+/// IDs can also be given, which will be returned as they are. Resolved slugs are cached so that
+/// the same project is only looked up once
+pub async fn resolve_slug(slug: &str, client: &Client) -> anyhow::Result<String> {
+	let cached = get_slug_cache()
+		.lock()
+		.expect("Slug cache poisoned")
+		.get(slug)
+		.cloned();
+	if let Some(id) = cached {
+		return Ok(id);
+	}
+
+	let url = format!("https://api.modrinth.com/v2/project/{slug}/check");
+	let text = download::text(url, client)
+		.await
+		.with_context(|| format!("Failed to resolve Modrinth project '{slug}'"))?;
+	let id = read_project_check(&text)
+		.with_context(|| format!("Failed to resolve Modrinth project '{slug}'"))?;
+	cache_slug(slug, &id);
+
+	Ok(id)
+}
+
+/// Read the project ID from the response of checking if a project exists
+fn read_project_check(text: &str) -> anyhow::Result<String> {
+	let check: ProjectCheck =
+		serde_json::from_str(text).context("Failed to deserialize project check")?;
+	Ok(check.id)
+}
+
+/// Response from checking if a project exists
+#[derive(Deserialize)]
+struct ProjectCheck {
+	/// The ID of the project
+	id: String,
+}
+
+/// Get the cache of resolved project slugs
+fn get_slug_cache() -> &'static Mutex<HashMap<String, String>> {
+	static SLUG_CACHE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+	SLUG_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Store a resolved project slug in the cache
+fn cache_slug(slug: &str, id: &str) {
+	let mut cache = get_slug_cache().lock().expect("Slug cache poisoned");
+	cache.insert(slug.to_string(), id.to_string());
+}
+
+/// Get the ID of the project that a file download from the Modrinth CDN belongs to
+pub fn get_project_id_from_download_url(url: &str) -> Option<&str> {
+	let rest = url.strip_prefix("https://cdn.modrinth.com/data/")?;
+	rest.split('/').next().filter(|x| !x.is_empty())
+}
+
+/// Get the ID of the version that a file download from the Modrinth CDN belongs to
+pub fn get_version_id_from_download_url(url: &str) -> Option<&str> {
+	let rest = url.strip_prefix("https://cdn.modrinth.com/data/")?;
+	let mut parts = rest.split('/').skip(1);
+	if parts.next() != Some("versions") {
+		return None;
+	}
+	parts.next().filter(|x| !x.is_empty())
+}
+
+/// Cleanup a version number to remove things like modloaders, giving the
+/// content version that packages generated from the version use
+pub fn cleanup_version_name(version: &str) -> String {
+	static MODLOADER_REGEX: OnceLock<Regex> = OnceLock::new();
+	let regex = MODLOADER_REGEX.get_or_init(|| {
+		RegexBuilder::new("(-|_|\\+)?(fabric|forge|quilt)")
+			.case_insensitive(true)
+			.build()
+			.expect("Failed to create regex")
+	});
+	let version = regex.replace_all(version, "");
+	let version = version.replace("+", "-");
+
+	version
+}
+
 /// Format the URL for the get_project API
 fn format_get_project_url(project_id: &str) -> String {
 	format!("https://api.modrinth.com/v2/project/{project_id}")
@@ -281,6 +370,64 @@ pub async fn get_multiple_versions(
 	download::json(url, client).await
 }
 
+/// The maximum number of IDs to put in one request to the API, to keep the URL from getting too long
+const MAX_IDS_PER_REQUEST: usize = 200;
+
+/// Get the IDs of all of the projects that a project depends on, directly or through other dependencies.
+/// Every type of dependency is included, but only the dependencies of required and optional ones are followed.
+/// This is useful for finding all of the projects that relations have to be substituted for
+pub async fn get_project_dependencies(
+	project_id: &str,
+	client: &Client,
+) -> anyhow::Result<Vec<String>> {
+	let mut visited = HashSet::from([project_id.to_string()]);
+	let mut out = Vec::new();
+	let mut current = vec![project_id.to_string()];
+	while !current.is_empty() {
+		let mut projects = Vec::new();
+		for chunk in current.chunks(MAX_IDS_PER_REQUEST) {
+			projects.extend(get_multiple_projects(chunk, client).await?);
+		}
+		// The first project could have been given by its slug
+		visited.extend(projects.iter().map(|x| x.id.clone()));
+
+		let version_ids: Vec<_> = projects
+			.iter()
+			.flat_map(|x| x.versions.iter().cloned())
+			.collect();
+		let mut versions = Vec::new();
+		for chunk in version_ids.chunks(MAX_IDS_PER_REQUEST) {
+			versions.extend(get_multiple_versions(chunk, client).await?);
+		}
+
+		current = get_new_dependencies(&versions, &mut visited, &mut out);
+	}
+
+	out.sort();
+	Ok(out)
+}
+
+/// Adds the dependencies of versions that have not been visited yet to the output,
+/// returning the ones that should have their own dependencies checked next
+fn get_new_dependencies(
+	versions: &[Version],
+	visited: &mut HashSet<String>,
+	out: &mut Vec<String>,
+) -> Vec<String> {
+	let mut next = Vec::new();
+	for dep in versions.iter().flat_map(|x| &x.dependencies) {
+		if !visited.insert(dep.project_id.clone()) {
+			continue;
+		}
+		out.push(dep.project_id.clone());
+		if let DependencyType::Required | DependencyType::Optional = dep.dependency_type {
+			next.push(dep.project_id.clone());
+		}
+	}
+
+	next
+}
+
 /// A file download from the Modrinth API
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Download {
@@ -340,6 +487,9 @@ pub struct GalleryEntry {
 	pub url: String,
 	/// Whether the gallery image is a featured banner on the project page
 	pub featured: bool,
+	/// The position of the image in the gallery
+	#[serde(default)]
+	pub ordering: i64,
 }
 
 /// Support status for a project on a specific side
@@ -392,3 +542,77 @@ pub struct User {
 	/// The user's username
 	pub username: String,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_read_project_check() {
+		// Response from https://api.modrinth.com/v2/project/sodium/check
+		let response = r#"{"id":"AANobbMI"}"#;
+		assert_eq!(read_project_check(response).unwrap(), "AANobbMI");
+
+		// Response for a project that doesn't exist, which is not an ID
+		let response =
+			r#"{"error":"not_found","description":"the requested route does not exist"}"#;
+		assert!(read_project_check(response).is_err());
+	}
+
+	#[test]
+	fn test_download_url_ids() {
+		let url = "https://cdn.modrinth.com/data/AANobbMI/versions/OihdIimA/sodium.jar";
+		assert_eq!(get_project_id_from_download_url(url), Some("AANobbMI"));
+		assert_eq!(get_version_id_from_download_url(url), Some("OihdIimA"));
+		let url = "https://github.com/other/other/releases/download/1.0/other.jar";
+		assert_eq!(get_project_id_from_download_url(url), None);
+		assert_eq!(get_version_id_from_download_url(url), None);
+	}
+
+	#[test]
+	fn test_dependency_levels() {
+		let create_version = |project_id: &str, deps: &[(&str, &str)]| -> Version {
+			let deps: Vec<_> = deps
+				.iter()
+				.map(|(id, kind)| {
+					serde_json::json!({"project_id": id, "version_id": null, "dependency_type": kind})
+				})
+				.collect();
+			serde_json::from_value(serde_json::json!({
+				"id": format!("{project_id}-1.0"),
+				"project_id": project_id,
+				"name": "1.0",
+				"version_number": "1.0",
+				"version_type": "release",
+				"loaders": ["fabric"],
+				"files": [],
+				"game_versions": ["1.20.1"],
+				"dependencies": deps,
+				"featured": false,
+				"date_published": "2024-01-01T00:00:00Z"
+			}))
+			.unwrap()
+		};
+
+		let mut visited = HashSet::from(["root".to_string()]);
+		let mut out = Vec::new();
+		let root_versions = [
+			create_version("root", &[("api", "required"), ("other", "incompatible")]),
+			create_version("root", &[("api", "required"), ("extra", "optional")]),
+		];
+		let next = get_new_dependencies(&root_versions, &mut visited, &mut out);
+		assert_eq!(next, vec!["api", "extra"]);
+
+		// The second level depends back on the root, which should not be included
+		let dep_versions = [
+			create_version("api", &[("lib", "embedded"), ("root", "optional")]),
+			create_version("extra", &[("api", "required"), ("config", "required")]),
+		];
+		let next = get_new_dependencies(&dep_versions, &mut visited, &mut out);
+		assert_eq!(next, vec!["config"]);
+
+		let next = get_new_dependencies(&[create_version("config", &[])], &mut visited, &mut out);
+		assert!(next.is_empty());
+		assert_eq!(out, vec!["api", "other", "extra", "lib", "config"]);
+	}
+}
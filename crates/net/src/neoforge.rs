@@ -0,0 +1,120 @@
+use anyhow::Context;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::download;
+
+/// URL to the Maven metadata of the NeoForge artifact
+const MAVEN_METADATA_URL: &str =
+	"https://maven.neoforged.net/releases/net/neoforged/neoforge/maven-metadata.xml";
+
+/// A version of NeoForge
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct LoaderVersion {
+	/// The version string of the loader
+	pub version: String,
+	/// Whether this is a stable release of the loader instead of a beta
+	pub stable: bool,
+}
+
+/// Get the NeoForge versions that are available for a Minecraft version, newest first.
+/// Minecraft 1.20.1 and older are not supported, as NeoForge releases for them were
+/// published under a different artifact
+pub async fn get_loader_versions(
+	minecraft_version: &str,
+	client: &Client,
+) -> anyhow::Result<Vec<LoaderVersion>> {
+	let metadata = download::text(MAVEN_METADATA_URL, client)
+		.await
+		.context("Failed to download NeoForge Maven metadata")?;
+
+	Ok(parse_loader_versions(&metadata, minecraft_version))
+}
+
+/// Get the newest stable NeoForge version for a Minecraft version, if there is one
+pub async fn get_latest_stable_loader(
+	minecraft_version: &str,
+	client: &Client,
+) -> anyhow::Result<Option<LoaderVersion>> {
+	let versions = get_loader_versions(minecraft_version, client).await?;
+	Ok(versions.into_iter().find(|x| x.stable))
+}
+
+/// Parse the versions for a Minecraft version from the Maven metadata, newest first
+fn parse_loader_versions(metadata: &str, minecraft_version: &str) -> Vec<LoaderVersion> {
+	let Some(prefix) = get_version_prefix(minecraft_version) else {
+		return Vec::new();
+	};
+
+	let mut out: Vec<_> = metadata
+		.split("<version>")
+		.skip(1)
+		.filter_map(|x| x.split_once("</version>"))
+		.map(|(version, ..)| version.trim())
+		.filter(|version| version.starts_with(&prefix))
+		.map(|version| LoaderVersion {
+			version: version.to_string(),
+			stable: !version.contains('-'),
+		})
+		.collect();
+	// The Maven metadata lists versions from oldest to newest
+	out.reverse();
+
+	out
+}
+
+/// Get the prefix of the NeoForge versions for a Minecraft version.
+/// NeoForge versions are made from the minor and patch versions of Minecraft,
+/// so 1.20.4 becomes 20.4 and 1.21 becomes 21.0
+fn get_version_prefix(minecraft_version: &str) -> Option<String> {
+	let rest = minecraft_version.strip_prefix("1.")?;
+	let (minor, patch) = rest.split_once('.').unwrap_or((rest, "0"));
+	if minor.parse::<u16>().is_err() || patch.parse::<u16>().is_err() {
+		return None;
+	}
+
+	Some(format!("{minor}.{patch}."))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const METADATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>net.neoforged</groupId>
+  <artifactId>neoforge</artifactId>
+  <versioning>
+    <latest>21.0.10-beta</latest>
+    <release>21.0.10-beta</release>
+    <versions>
+      <version>20.4.80-beta</version>
+      <version>20.4.237</version>
+      <version>20.4.239</version>
+      <version>21.0.0-beta</version>
+      <version>21.0.10-beta</version>
+    </versions>
+    <lastUpdated>20240624120000</lastUpdated>
+  </versioning>
+</metadata>"#;
+
+	#[test]
+	fn test_parse_loader_versions() {
+		let versions = parse_loader_versions(METADATA, "1.20.4");
+		let versions: Vec<_> = versions.iter().map(|x| x.version.as_str()).collect();
+		assert_eq!(versions, vec!["20.4.239", "20.4.237", "20.4.80-beta"]);
+
+		let versions = parse_loader_versions(METADATA, "1.21");
+		assert_eq!(versions.len(), 2);
+		assert!(versions.iter().all(|x| !x.stable));
+
+		assert!(parse_loader_versions(METADATA, "24w14a").is_empty());
+	}
+
+	#[test]
+	fn test_version_prefix() {
+		assert_eq!(get_version_prefix("1.20.4").as_deref(), Some("20.4."));
+		assert_eq!(get_version_prefix("1.21").as_deref(), Some("21.0."));
+		assert_eq!(get_version_prefix("1.20.5-pre1"), None);
+	}
+}
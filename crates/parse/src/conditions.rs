@@ -11,7 +11,7 @@ use mcvm_shared::modifications::{ModloaderMatch, PluginLoaderMatch};
 use mcvm_shared::Side;
 
 use super::instruction::parse_arg;
-use super::lex::{TextPos, Token};
+use super::lex::{self, TextPos, Token};
 use super::vars::Value;
 
 /// A condition that checks some property to create a boolean answer
@@ -43,6 +43,13 @@ pub enum ConditionKind {
 	And(Box<ConditionKind>, Later<Box<ConditionKind>>),
 	/// OR of multiple conditions
 	Or(Box<ConditionKind>, Later<Box<ConditionKind>>),
+	/// A parenthesized group of conditions
+	Group {
+		/// The grouped condition
+		condition: Later<Box<ConditionKind>>,
+		/// Whether the closing parenthesis has been parsed yet
+		closed: bool,
+	},
 	/// Check the Minecraft version
 	Version(Value),
 	/// Check the side
@@ -51,10 +58,22 @@ pub enum ConditionKind {
 	Modloader(Later<ModloaderMatch>),
 	/// Check the plugin loader
 	PluginLoader(Later<PluginLoaderMatch>),
+	/// Check the version of the installed modloader
+	ModloaderVersion(Value),
 	/// Check a configured feature
 	Feature(Value),
 	/// Check a variable
 	Value(Value, Value),
+	/// Check that a variable is not equal to a value
+	ValueNe(Value, Value),
+	/// Check that a string contains another string
+	Contains(Value, Value),
+	/// Check that a string starts with another string
+	StartsWith(Value, Value),
+	/// Check that a string ends with another string
+	EndsWith(Value, Value),
+	/// Check that the system has at least an amount of memory, in megabytes
+	Memory(Value),
 	/// Check if a variable is defined
 	Defined(Later<String>),
 	/// Check a constant boolean, used for testing
@@ -111,7 +130,7 @@ pub enum ArchCondition {
 	X86,
 	/// x86_64
 	X86_64,
-	/// ARM
+	/// ARM, including both 32-bit and 64-bit (aarch64)
 	Arm,
 	/// Any other architecture
 	Other,
@@ -139,8 +158,14 @@ impl ConditionKind {
 			"side" => Some(Self::Side(Later::Empty)),
 			"modloader" => Some(Self::Modloader(Later::Empty)),
 			"plugin_loader" => Some(Self::PluginLoader(Later::Empty)),
+			"modloader_version" => Some(Self::ModloaderVersion(Value::None)),
 			"feature" => Some(Self::Feature(Value::None)),
 			"value" => Some(Self::Value(Value::None, Value::None)),
+			"value_ne" => Some(Self::ValueNe(Value::None, Value::None)),
+			"contains" => Some(Self::Contains(Value::None, Value::None)),
+			"starts_with" => Some(Self::StartsWith(Value::None, Value::None)),
+			"ends_with" => Some(Self::EndsWith(Value::None, Value::None)),
+			"memory" => Some(Self::Memory(Value::None)),
 			"defined" => Some(Self::Defined(Later::Empty)),
 			"os" => Some(Self::OS(Later::Empty)),
 			"stability" => Some(Self::Stability(Later::Empty)),
@@ -149,6 +174,22 @@ impl ConditionKind {
 		}
 	}
 
+	/// Parse the first token of a condition, which is either the name
+	/// of the condition or the start of a group
+	pub fn parse_start(tok: &Token, pos: &TextPos) -> anyhow::Result<Self> {
+		match tok {
+			Token::Ident(name) => match Self::parse_from_str(name) {
+				Some(condition) => Ok(condition),
+				None => bail!("Unknown condition '{name}' {pos}"),
+			},
+			Token::Paren(lex::Side::Left) => Ok(Self::Group {
+				condition: Later::Empty,
+				closed: false,
+			}),
+			_ => unexpected_token!(tok, pos),
+		}
+	}
+
 	/// Checks whether this condition is finished parsing
 	pub fn is_finished_parsing(&self) -> bool {
 		match &self {
@@ -159,7 +200,12 @@ impl ConditionKind {
 				left.is_finished_parsing()
 					&& matches!(right, Later::Full(condition) if condition.is_finished_parsing())
 			}
-			Self::Version(val) | Self::Feature(val) | Self::ContentVersion(val) => val.is_some(),
+			Self::Group { closed, .. } => *closed,
+			Self::Version(val)
+			| Self::Feature(val)
+			| Self::ContentVersion(val)
+			| Self::ModloaderVersion(val)
+			| Self::Memory(val) => val.is_some(),
 			Self::Side(val) => val.is_full(),
 			Self::Modloader(val) => val.is_full(),
 			Self::PluginLoader(val) => val.is_full(),
@@ -169,7 +215,11 @@ impl ConditionKind {
 			Self::Arch(val) => val.is_full(),
 			Self::Stability(val) => val.is_full(),
 			Self::Language(val) => val.is_full(),
-			Self::Value(left, right) => left.is_some() && right.is_some(),
+			Self::Value(left, right)
+			| Self::ValueNe(left, right)
+			| Self::Contains(left, right)
+			| Self::StartsWith(left, right)
+			| Self::EndsWith(left, right) => left.is_some() && right.is_some(),
 		}
 	}
 
@@ -182,7 +232,7 @@ impl ConditionKind {
 					match name.as_str() {
 						"and" => *self = ConditionKind::And(current, Later::Empty),
 						"or" => *self = ConditionKind::Or(current, Later::Empty),
-						_ => bail!("Unknown condition combinator '{name}'"),
+						_ => bail!("Unknown condition combinator '{name}' {pos}"),
 					}
 					return Ok(());
 				}
@@ -199,18 +249,24 @@ impl ConditionKind {
 					Later::Full(condition) => {
 						return condition.parse(tok, pos);
 					}
-					Later::Empty => match tok {
-						Token::Ident(name) => match Self::parse_from_str(name) {
-							Some(nested_cond) => condition.fill(Box::new(nested_cond)),
-							None => {
-								bail!("Unknown condition '{}' {}", name.clone(), pos.clone());
-							}
-						},
-						_ => unexpected_token!(tok, pos),
-					},
+					Later::Empty => condition.fill(Box::new(Self::parse_start(tok, pos)?)),
 				}
 			}
-			Self::Version(val) | Self::Feature(val) | Self::ContentVersion(val) => {
+			Self::Group { condition, closed } => match condition {
+				Later::Full(inner) => {
+					if inner.is_finished_parsing() && tok == &Token::Paren(lex::Side::Right) {
+						*closed = true;
+					} else {
+						return inner.parse(tok, pos);
+					}
+				}
+				Later::Empty => condition.fill(Box::new(Self::parse_start(tok, pos)?)),
+			},
+			Self::Version(val)
+			| Self::Feature(val)
+			| Self::ContentVersion(val)
+			| Self::ModloaderVersion(val)
+			| Self::Memory(val) => {
 				*val = parse_arg(tok, pos)?;
 			}
 			Self::Defined(var) => match tok {
@@ -274,6 +330,17 @@ impl ConditionKind {
 				_ => unexpected_token!(tok, pos),
 			},
 			Self::Value(left, right) => match left {
+				Value::None => *left = parse_arg(tok, pos)?,
+				// Inline not-equals operator
+				_ if tok == &Token::Ident("not_eq".into()) => {
+					*self = Self::ValueNe(left.clone(), Value::None);
+				}
+				_ => *right = parse_arg(tok, pos)?,
+			},
+			Self::ValueNe(left, right)
+			| Self::Contains(left, right)
+			| Self::StartsWith(left, right)
+			| Self::EndsWith(left, right) => match left {
 				Value::None => *left = parse_arg(tok, pos)?,
 				_ => *right = parse_arg(tok, pos)?,
 			},
@@ -311,3 +378,78 @@ fn check_enum_condition_argument<T>(
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::instruction::InstrKind;
+	use crate::parse::lex_and_parse;
+	use crate::routine::INSTALL_ROUTINE;
+
+	#[test]
+	fn test_condition_error_positions() {
+		let text = "@install {\n\tif side client xor side server {}\n}";
+		let err = lex_and_parse(text).unwrap_err();
+		assert!(format!("{err:?}").contains("Unknown condition combinator 'xor' (2:"));
+
+		let text = "@install { if foo {} }";
+		let err = lex_and_parse(text).unwrap_err();
+		assert!(format!("{err:?}").contains("(1:"));
+	}
+
+	#[test]
+	fn test_value_ne_parse() {
+		let expected = ConditionKind::ValueNe(Value::Var("x".into()), Value::Literal("foo".into()));
+		for text in [
+			r#"@install { if value $x not_eq "foo" {} }"#,
+			r#"@install { if value_ne $x "foo" {} }"#,
+		] {
+			let parsed = lex_and_parse(text).unwrap();
+			let block = parsed
+				.blocks
+				.get(parsed.routines.get(INSTALL_ROUTINE).unwrap())
+				.unwrap();
+			let InstrKind::If { condition, .. } = &block.contents.first().unwrap().kind else {
+				panic!("Instruction is not an if");
+			};
+			assert_eq!(condition.kind, expected);
+		}
+
+		let text = r#"@install { if value $x not_eq {} }"#;
+		assert!(lex_and_parse(text).is_err());
+	}
+
+	#[test]
+	fn test_string_condition_parse() {
+		let text = r#"@install {
+			if contains $x "pre" and starts_with $x "1." or ends_with "foo" $y {}
+		}"#;
+		let parsed = lex_and_parse(text).unwrap();
+		let block = parsed
+			.blocks
+			.get(parsed.routines.get(INSTALL_ROUTINE).unwrap())
+			.unwrap();
+		let InstrKind::If { condition, .. } = &block.contents.first().unwrap().kind else {
+			panic!("Instruction is not an if");
+		};
+		assert_eq!(
+			condition.kind,
+			ConditionKind::Or(
+				Box::new(ConditionKind::And(
+					Box::new(ConditionKind::Contains(
+						Value::Var("x".into()),
+						Value::Literal("pre".into())
+					)),
+					Later::Full(Box::new(ConditionKind::StartsWith(
+						Value::Var("x".into()),
+						Value::Literal("1.".into())
+					)))
+				)),
+				Later::Full(Box::new(ConditionKind::EndsWith(
+					Value::Literal("foo".into()),
+					Value::Var("y".into())
+				)))
+			)
+		);
+	}
+}
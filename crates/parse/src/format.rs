@@ -0,0 +1,249 @@
+use anyhow::Context;
+
+use crate::lex::{lex, Side, Token};
+use crate::parse::lex_and_parse;
+
+/// Formats the contents of a package script with consistent indentation and spacing.
+/// Comments and line breaks inside of instructions are preserved. Fails if the script
+/// does not parse, so that the meaning of an invalid script is never changed.
+pub fn format(contents: &str) -> anyhow::Result<String> {
+	lex_and_parse(contents).context("Failed to parse script")?;
+	let tokens = lex(contents)?;
+	let chars: Vec<char> = contents.chars().collect();
+
+	let mut formatter = Formatter::default();
+	let mut last: Option<(&Token, usize)> = None;
+	for (tok, pos) in &tokens {
+		if matches!(tok, Token::None | Token::Whitespace) {
+			continue;
+		}
+
+		// Count the line breaks in the source since the last token
+		let newlines = if let Some((last_tok, last_pos)) = last {
+			let between = chars.get(last_pos..*pos.absolute()).unwrap_or_default();
+			let count = between.iter().filter(|c| **c == '\n').count();
+			count.saturating_sub(count_newlines(last_tok))
+		} else {
+			0
+		};
+
+		formatter.token(tok, newlines);
+		last = Some((tok, *pos.absolute()));
+	}
+	formatter.flush();
+
+	Ok(formatter.out)
+}
+
+/// State for building formatted output
+#[derive(Default)]
+struct Formatter {
+	/// The finished output
+	out: String,
+	/// The contents of the line that is currently being built
+	line: String,
+	/// The indentation of the current line
+	line_depth: usize,
+	/// The previous token on the current line
+	prev: Option<Token>,
+	/// The current block depth
+	depth: usize,
+	/// Whether the current line is finished but can still take a trailing comment or else
+	line_complete: bool,
+	/// Whether we are in the middle of an instruction
+	in_instr: bool,
+}
+
+impl Formatter {
+	/// Adds a token, with the number of line breaks before it in the source
+	fn token(&mut self, tok: &Token, newlines: usize) {
+		if let Token::Comment(text) = tok {
+			let comment = format!("#{}", text.trim_end());
+			if newlines == 0 && !self.line.is_empty() {
+				self.line.push(' ');
+			} else {
+				self.flush();
+				self.start_line(tok, newlines);
+			}
+			self.line.push_str(&comment);
+			self.flush();
+			return;
+		}
+
+		if self.line_complete {
+			let is_else = matches!(tok, Token::Ident(name) if name == "else");
+			if !is_else || self.prev != Some(Token::Curly(Side::Right)) {
+				self.flush();
+			}
+			self.line_complete = false;
+		} else if newlines > 0 {
+			// Keep the line breaks inside of instructions
+			self.flush();
+		}
+
+		if let Token::Curly(Side::Right) = tok {
+			self.flush();
+			self.depth = self.depth.saturating_sub(1);
+		}
+
+		if self.line.is_empty() {
+			self.start_line(tok, newlines);
+		} else if needs_space(self.prev.as_ref(), tok) {
+			self.line.push(' ');
+		}
+		self.line.push_str(&format_token(tok));
+		self.prev = Some(tok.clone());
+
+		match tok {
+			Token::Curly(Side::Left) => {
+				self.depth += 1;
+				self.in_instr = false;
+				self.line_complete = true;
+			}
+			Token::Curly(Side::Right) | Token::Semicolon => {
+				self.in_instr = false;
+				self.line_complete = true;
+			}
+			_ => self.in_instr = true,
+		}
+	}
+
+	/// Starts a new line, adding a blank line before it if needed
+	fn start_line(&mut self, tok: &Token, newlines: usize) {
+		let closing = matches!(
+			tok,
+			Token::Curly(Side::Right)
+				| Token::Paren(Side::Right)
+				| Token::Angle(Side::Right)
+				| Token::Square(Side::Right)
+		);
+
+		let can_be_blank = !self.in_instr
+			&& !closing
+			&& !self.out.is_empty()
+			&& !self.out.ends_with("{\n")
+			&& !self.out.ends_with("\n\n");
+		// Routines are always separated by a blank line
+		let after_routine = self.depth == 0 && self.out.ends_with("}\n");
+		if can_be_blank && (newlines >= 2 || after_routine) {
+			self.out.push('\n');
+		}
+
+		// Lines that continue an instruction are indented once more
+		self.line_depth = if self.in_instr && !closing {
+			self.depth + 1
+		} else {
+			self.depth
+		};
+	}
+
+	/// Writes the current line to the output
+	fn flush(&mut self) {
+		if !self.line.is_empty() {
+			for _ in 0..self.line_depth {
+				self.out.push('\t');
+			}
+			self.out.push_str(&self.line);
+			self.out.push('\n');
+			self.line.clear();
+		}
+		self.prev = None;
+		self.line_complete = false;
+	}
+}
+
+/// Checks if a space should be put between two tokens on the same line
+fn needs_space(prev: Option<&Token>, tok: &Token) -> bool {
+	let after_open = matches!(
+		prev,
+		Some(
+			Token::At
+				| Token::Bang | Token::Paren(Side::Left)
+				| Token::Angle(Side::Left)
+				| Token::Square(Side::Left)
+		)
+	);
+	let before_close = matches!(
+		tok,
+		Token::Semicolon
+			| Token::Comma
+			| Token::Colon
+			| Token::Paren(Side::Right)
+			| Token::Angle(Side::Right)
+			| Token::Square(Side::Right)
+	);
+
+	!after_open && !before_close
+}
+
+/// Prints a token the same way that it would be lexed
+fn format_token(tok: &Token) -> String {
+	match tok {
+		Token::Str(string) => format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\"")),
+		_ => tok.as_string(),
+	}
+}
+
+/// Counts the line breaks inside of a token
+fn count_newlines(tok: &Token) -> usize {
+	match tok {
+		Token::Str(string) => string.matches('\n').count(),
+		_ => 0,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_format() {
+		let text = r#"# Header comment
+@meta{name   "Test" ;
+ description "A \"quoted\" package";}
+@install {
+  if not (side client) { # Servers only
+    require <"foo">   ("bar" "baz");
+
+      recommend !"qux";
+  }
+  else if value $x "y" {
+     finish;
+  } else {
+    addon "mod" "mod.jar" (
+      kind: mod,
+      url: "https://example.com/mod.jar"
+    );
+  }
+}"#;
+		let expected = r#"# Header comment
+@meta {
+	name "Test";
+	description "A \"quoted\" package";
+}
+
+@install {
+	if not (side client) { # Servers only
+		require <"foo"> ("bar" "baz");
+
+		recommend !"qux";
+	} else if value $x "y" {
+		finish;
+	} else {
+		addon "mod" "mod.jar" (
+			kind: mod,
+			url: "https://example.com/mod.jar"
+		);
+	}
+}
+"#;
+		let formatted = format(text).unwrap();
+		assert_eq!(formatted, expected);
+		assert_eq!(format(&formatted).unwrap(), formatted);
+	}
+
+	#[test]
+	fn test_format_invalid() {
+		assert!(format("@install { set x; ").is_err());
+	}
+}
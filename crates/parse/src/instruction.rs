@@ -152,6 +152,8 @@ pub struct ElseBlock {
 	pub block: BlockId,
 	/// An additional condition that might need to be satisfied, used for else if.
 	pub condition: Option<Condition>,
+	/// The textual position of the else
+	pub pos: TextPos,
 }
 
 impl Display for InstrKind {
@@ -8,6 +8,8 @@
 
 /// Parsing for conditions, used in if instructions
 pub mod conditions;
+/// Canonical formatting of package scripts
+pub mod format;
 /// Parsing for most instructions, with the exception of a few complex ones
 pub mod instruction;
 /// Token generation from a string, which is passed into the parser
@@ -19,6 +21,8 @@ pub mod routine;
 /// Things related to script variables
 pub mod vars;
 
+pub use format::format;
+
 use std::fmt::Display;
 
 /// Reason why the package reported a failure
@@ -9,7 +9,7 @@ use crate::routine::RESERVED_ROUTINES;
 use super::conditions::Condition;
 use super::conditions::ConditionKind;
 use super::instruction::{parse_arg, InstrKind, Instruction};
-use super::lex::{lex, reduce_tokens, Side, Token, TokenAndPos};
+use super::lex::{lex, reduce_tokens, Side, TextPos, Token, TokenAndPos};
 use super::vars::Value;
 use mcvm_shared::addon::AddonKind;
 
@@ -59,6 +59,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 							prs.mode = ParseMode::If {
 								condition: None,
 								is_if_else: false,
+								start_pos: pos.clone(),
 							};
 							block_just_ended = false;
 						}
@@ -66,11 +67,12 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 							if !block_just_ended {
 								bail!("'else' used without if block {}", pos.clone());
 							}
-							prs.mode = ParseMode::CheckForElseIf;
+							prs.mode = ParseMode::CheckForElseIf(pos.clone());
 							block_just_ended = false;
 						}
 						"addon" => {
 							prs.mode = ParseMode::Addon {
+								start_pos: pos.clone(),
 								state: addon::State::Id,
 								key: addon::Key::None,
 								id: Value::None,
@@ -90,6 +92,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 						}
 						"require" => {
 							prs.mode = ParseMode::Require {
+								start_pos: pos.clone(),
 								state: require::State::Normal,
 								package_groups: Vec::new(),
 								current_group: None,
@@ -144,6 +147,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 			ParseMode::If {
 				condition,
 				is_if_else,
+				start_pos,
 			} => {
 				match tok {
 					Token::Curly(Side::Left) => {
@@ -156,6 +160,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 								else_to_append = Some(ElseBlock {
 									block,
 									condition: Some(condition.clone()),
+									pos: start_pos.clone(),
 								});
 							} else {
 								block_to_set = Some(block);
@@ -165,7 +170,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 										if_block: block,
 										else_blocks: Vec::new(),
 									},
-									pos.clone(),
+									start_pos.clone(),
 								));
 							}
 							prs.mode = ParseMode::Root;
@@ -176,23 +181,15 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 					Token::Curly(Side::Right) => unexpected_token!(tok, pos),
 					_ => match condition {
 						Some(condition) => condition.parse(tok, pos)?,
-						None => match tok {
-							Token::Ident(name) => match ConditionKind::parse_from_str(name) {
-								Some(new_condition) => {
-									*condition = Some(Condition::new(new_condition))
-								}
-								None => {
-									bail!("Unknown condition {} {}", name.clone(), pos.clone());
-								}
-							},
-							_ => unexpected_token!(tok, pos),
-						},
+						None => {
+							*condition = Some(Condition::new(ConditionKind::parse_start(tok, pos)?))
+						}
 					},
 				}
 
 				Ok(())
 			}
-			ParseMode::CheckForElseIf => {
+			ParseMode::CheckForElseIf(start_pos) => {
 				match tok {
 					Token::Ident(name) => match name.as_str() {
 						// Start an else if
@@ -200,6 +197,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 							prs.mode = ParseMode::If {
 								condition: None,
 								is_if_else: true,
+								start_pos: start_pos.clone(),
 							};
 						}
 						_ => unexpected_token!(tok, pos),
@@ -210,6 +208,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 						else_to_append = Some(ElseBlock {
 							block,
 							condition: None,
+							pos: start_pos.clone(),
 						});
 						prs.mode = ParseMode::Root;
 					}
@@ -230,6 +229,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 				Ok(())
 			}
 			ParseMode::Addon {
+				start_pos,
 				state,
 				key,
 				id,
@@ -319,7 +319,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 									version: filled_keys.version.clone(),
 									hashes: filled_keys.hashes.clone(),
 								},
-								pos.clone(),
+								start_pos.clone(),
 							));
 							prs.mode = ParseMode::Root;
 						}
@@ -330,6 +330,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 				Ok(())
 			}
 			ParseMode::Require {
+				start_pos,
 				state,
 				package_groups,
 				current_group,
@@ -369,7 +370,7 @@ pub fn parse<'a>(tokens: impl Iterator<Item = &'a TokenAndPos>) -> anyhow::Resul
 						Token::Semicolon => {
 							instr_to_push = Some(Instruction::new(
 								InstrKind::Require(package_groups.clone()),
-								pos.clone(),
+								start_pos.clone(),
 							));
 							prs.mode = ParseMode::Root;
 						}
@@ -517,9 +518,11 @@ enum ParseMode {
 	If {
 		condition: Option<Condition>,
 		is_if_else: bool,
+		start_pos: TextPos,
 	},
-	CheckForElseIf,
+	CheckForElseIf(TextPos),
 	Addon {
+		start_pos: TextPos,
 		state: addon::State,
 		key: addon::Key,
 		id: Value,
@@ -527,6 +530,7 @@ enum ParseMode {
 		filled_keys: addon::FilledKeys,
 	},
 	Require {
+		start_pos: TextPos,
 		state: require::State,
 		package_groups: Vec<Vec<require::Package>>,
 		current_group: Option<Vec<require::Package>>,
@@ -610,6 +614,58 @@ impl Parsed {
 	pub fn routine_exists(&self, name: &str) -> bool {
 		self.routines.contains_key(name)
 	}
+
+	/// Creates a human-readable tree of the parsed routines, instructions, and conditions,
+	/// which is useful for debugging scripts
+	pub fn dump(&self) -> String {
+		let mut out = String::new();
+		let mut routines: Vec<_> = self.routines.iter().collect();
+		routines.sort_by_key(|(name, _)| name.as_str());
+		for (name, block) in routines {
+			let block = self.blocks.get(block).expect("Block does not exist");
+			if name == DEFAULT_ROUTINE && block.contents.is_empty() {
+				continue;
+			}
+			out.push_str(&format!("@{name}\n"));
+			self.dump_block(block, 1, &mut out);
+		}
+
+		out
+	}
+
+	/// Dumps the instructions in a block at an indentation level
+	fn dump_block(&self, block: &Block, indent: usize, out: &mut String) {
+		let prefix = "\t".repeat(indent);
+		for instr in &block.contents {
+			let pos = &instr.pos;
+			match &instr.kind {
+				InstrKind::If {
+					condition,
+					if_block,
+					else_blocks,
+				} => {
+					out.push_str(&format!("{prefix}if {pos} {:?}\n", condition.kind));
+					let if_block = self.blocks.get(if_block).expect("If block does not exist");
+					self.dump_block(if_block, indent + 1, out);
+					for else_block in else_blocks {
+						match &else_block.condition {
+							Some(condition) => out.push_str(&format!(
+								"{prefix}else if {} {:?}\n",
+								else_block.pos, condition.kind
+							)),
+							None => out.push_str(&format!("{prefix}else {}\n", else_block.pos)),
+						}
+						let else_block = self
+							.blocks
+							.get(&else_block.block)
+							.expect("Else block does not exist");
+						self.dump_block(else_block, indent + 1, out);
+					}
+				}
+				kind => out.push_str(&format!("{prefix}{kind} {pos} {kind:?}\n")),
+			}
+		}
+	}
 }
 
 impl Default for Parsed {
@@ -801,6 +857,72 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_grouped_condition_parse() {
+		let text = r#"@install {
+			if (side client or side server) and modloader fabric {}
+		}"#;
+		let parsed = lex_and_parse(text).unwrap();
+		let block = parsed
+			.blocks
+			.get(parsed.routines.get(INSTALL_ROUTINE).unwrap())
+			.unwrap();
+		let instr = block.contents.first().unwrap();
+		let InstrKind::If { condition, .. } = &instr.kind else {
+			panic!("Instruction is not an if");
+		};
+		assert_eq!(
+			condition.kind,
+			ConditionKind::And(
+				Box::new(ConditionKind::Group {
+					condition: Later::Full(Box::new(ConditionKind::Or(
+						Box::new(ConditionKind::Side(Later::Full(mcvm_shared::Side::Client))),
+						Later::Full(Box::new(ConditionKind::Side(Later::Full(
+							mcvm_shared::Side::Server
+						)))),
+					))),
+					closed: true,
+				}),
+				Later::Full(Box::new(ConditionKind::Modloader(Later::Full(
+					ModloaderMatch::Fabric
+				)))),
+			)
+		);
+
+		let text = r#"@install {
+			if not ((side client) or side server) {}
+		}"#;
+		assert!(lex_and_parse(text).is_ok());
+
+		let text = r#"@install {
+			if (side client {}
+		}"#;
+		assert!(lex_and_parse(text).is_err());
+	}
+
+	#[test]
+	fn test_dump() {
+		let text = r#"
+			@meta {
+				name "Test";
+			}
+			@install {
+				if side client {
+					set x "foo";
+				} else {
+					finish;
+				}
+			}
+		"#;
+		let dump = lex_and_parse(text).unwrap().dump();
+		assert!(dump.contains("@meta\n\tname"));
+		assert!(dump.contains("@install\n\tif"));
+		assert!(dump.contains("\t\tset"));
+		assert!(dump.contains("\telse (8:"));
+		assert!(dump.contains("\t\tfinish"));
+		assert!(!dump.contains(DEFAULT_ROUTINE));
+	}
+
 	#[test]
 	fn test_if_else() {
 		let text = r#"@install {
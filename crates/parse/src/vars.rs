@@ -51,7 +51,12 @@ impl Value {
 				}
 				State::Name => {
 					if c == '}' {
-						if let Some(var) = vars.get_var(&name) {
+						// Substitutions can provide a default with the ${name:-default} syntax
+						let (var_name, default) = match name.split_once(":-") {
+							Some((var_name, default)) => (var_name, Some(default)),
+							None => (name.as_str(), None),
+						};
+						if let Some(var) = vars.get_var(var_name).or(default) {
 							out.push_str(var);
 						}
 						name.clear();
@@ -186,4 +191,25 @@ mod tests {
 		let string = Value::substitute_tokens(string, &vars);
 		assert_eq!(string, "One foo skip a who");
 	}
+
+	#[test]
+	fn test_value_substitution_default() {
+		let vars = {
+			let mut vars = HashMapVariableStore::new();
+			vars.set_var("bar".into(), "foo".into());
+			vars.set_var("empty".into(), String::new());
+			vars
+		};
+
+		let value = Value::Literal("${bar:-default}".into());
+		assert_eq!(value.get(&vars).unwrap(), "foo");
+		let value = Value::Literal("${baz:-default}".into());
+		assert_eq!(value.get(&vars).unwrap(), "default");
+		let value = Value::Literal("${empty:-default}".into());
+		assert_eq!(value.get(&vars).unwrap(), "");
+		let value = Value::Literal("${baz:-}".into());
+		assert_eq!(value.get(&vars).unwrap(), "");
+		let value = Value::Literal("v${baz:-1.0}-${bar}".into());
+		assert_eq!(value.get(&vars).unwrap(), "v1.0-foo");
+	}
 }
@@ -1,10 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Context;
 use mcvm_parse::conditions::{ArchCondition, OSCondition};
 use mcvm_shared::addon::AddonKind;
 use mcvm_shared::lang::Language;
-use mcvm_shared::modifications::{ModloaderMatch, PluginLoaderMatch};
+use mcvm_shared::modifications::{ModloaderMatch, PluginLoaderMatch, ProxyMatch};
 use mcvm_shared::pkg::{PackageAddonOptionalHashes, PackageStability};
 use mcvm_shared::util::DeserListOrSingle;
 use mcvm_shared::versions::VersionPattern;
@@ -110,6 +110,9 @@ pub struct DeclarativeConditionSet {
 	/// What plugin loaders to allow
 	#[serde(skip_serializing_if = "DeserListOrSingle::is_option_empty")]
 	pub plugin_loaders: Option<DeserListOrSingle<PluginLoaderMatch>>,
+	/// What server proxies to allow
+	#[serde(skip_serializing_if = "DeserListOrSingle::is_option_empty")]
+	pub proxies: Option<DeserListOrSingle<ProxyMatch>>,
 	/// What stability setting to allow
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub stability: Option<PackageStability>,
@@ -291,6 +294,28 @@ impl DeclarativePackage {
 			}
 		}
 	}
+
+	/// Gets the content versions that can only be installed with the latest stability,
+	/// because every addon version that has them requires it
+	pub fn get_unstable_content_versions(&self) -> HashSet<String> {
+		let mut all = HashSet::new();
+		let mut stable = HashSet::new();
+		for version in self.addons.values().flat_map(|x| &x.versions) {
+			let Some(content_versions) = &version.conditional_properties.content_versions else {
+				continue;
+			};
+			let is_stable =
+				version.conditional_properties.stability != Some(PackageStability::Latest);
+			for content_version in content_versions.iter() {
+				all.insert(content_version.clone());
+				if is_stable {
+					stable.insert(content_version.clone());
+				}
+			}
+		}
+
+		all.difference(&stable).cloned().collect()
+	}
 }
 
 #[cfg(test)]
@@ -328,4 +353,29 @@ mod tests {
 
 		assert_eq!(pkg.meta.name, Some("Test Package".into()));
 	}
+
+	#[test]
+	fn test_unstable_content_versions() {
+		let contents = r#"
+			{
+				"addons": {
+					"test": {
+						"kind": "mod",
+						"versions": [
+							{ "url": "example.com/3", "content_versions": "3.0", "stability": "latest" },
+							{ "url": "example.com/2b", "content_versions": "2.0", "stability": "latest" },
+							{ "url": "example.com/2", "content_versions": ["2.0", "1.0"] }
+						]
+					}
+				}
+			}
+		"#;
+
+		let pkg = deserialize_declarative_package(contents).unwrap();
+
+		assert_eq!(
+			pkg.get_unstable_content_versions(),
+			HashSet::from(["3.0".to_string()])
+		);
+	}
 }
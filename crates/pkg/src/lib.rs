@@ -8,6 +8,8 @@
 
 /// Standard declarative package format
 pub mod declarative;
+/// Lock files for the results of package resolution
+pub mod lock;
 /// Package metadata
 pub mod metadata;
 /// Package properties
@@ -18,8 +20,10 @@ pub mod repo;
 pub mod resolve;
 /// Framework for evaluating script packages
 pub mod script_eval;
+/// Checking installed packages for updates
+pub mod update;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 use declarative::{deserialize_declarative_package, validate_declarative_package};
 use mcvm_shared::pkg::{ArcPkgReq, PackageID};
@@ -35,6 +39,15 @@ pub use mcvm_shared::pkg::{PkgRequest, PkgRequestSource};
 
 /// Parses and validates a package
 pub fn parse_and_validate(contents: &str, content_type: PackageContentType) -> anyhow::Result<()> {
+	parse_and_validate_custom(contents, content_type, &())
+}
+
+/// Parses and validates a package, using a handler for packages with custom content types
+pub fn parse_and_validate_custom(
+	contents: &str,
+	content_type: PackageContentType,
+	custom: &impl CustomContentTypeHandler,
+) -> anyhow::Result<()> {
 	match content_type {
 		PackageContentType::Script => {
 			let parsed = parse::parse::lex_and_parse(contents).context("Parsing failed")?;
@@ -45,13 +58,24 @@ pub fn parse_and_validate(contents: &str, content_type: PackageContentType) -> a
 			let contents = deserialize_declarative_package(contents).context("Parsing failed")?;
 			validate_declarative_package(&contents).context("Package was invalid")?;
 		}
+		PackageContentType::Custom(content_type) => {
+			let parsed = custom
+				.parse_custom_package(&content_type, contents)
+				.context("Parsing failed")?;
+			if parsed.is_none() {
+				bail!("Unsupported package content type '{content_type}'");
+			}
+			custom
+				.validate_custom_package(&content_type, contents)
+				.context("Package was invalid")?;
+		}
 	}
 
 	Ok(())
 }
 
 /// Content type of a package
-#[derive(Deserialize, Serialize, Debug, Copy, Clone, Default)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum PackageContentType {
@@ -60,6 +84,48 @@ pub enum PackageContentType {
 	Script,
 	/// A declarative / JSON package
 	Declarative,
+	/// A content type that is not built in, which has to be handled by a plugin
+	#[serde(untagged)]
+	Custom(String),
+}
+
+/// Handler for packages with custom content types, such as a plugin
+pub trait CustomContentTypeHandler {
+	/// Parse a package with a custom content type. Returns None if the content type is not supported.
+	fn parse_custom_package(
+		&self,
+		content_type: &str,
+		contents: &str,
+	) -> anyhow::Result<Option<CustomPackageContents>>;
+
+	/// Validate a package with a custom content type, returning an error if it is invalid
+	fn validate_custom_package(&self, content_type: &str, contents: &str) -> anyhow::Result<()>;
+}
+
+/// No custom content types are supported
+impl CustomContentTypeHandler for () {
+	fn parse_custom_package(
+		&self,
+		_: &str,
+		_: &str,
+	) -> anyhow::Result<Option<CustomPackageContents>> {
+		Ok(None)
+	}
+
+	fn validate_custom_package(&self, _: &str, _: &str) -> anyhow::Result<()> {
+		Ok(())
+	}
+}
+
+/// The parsed contents of a package with a custom content type
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct CustomPackageContents {
+	/// The metadata of the package
+	#[serde(default)]
+	pub meta: PackageMetadata,
+	/// The properties of the package
+	#[serde(default)]
+	pub properties: PackageProperties,
 }
 
 /// A required package
@@ -89,13 +155,13 @@ fn is_false(x: &bool) -> bool {
 
 /// Trait for a central package registry that can evaluate packages
 #[async_trait]
-pub trait PackageEvaluator<'a> {
+pub trait PackageEvaluator<'a>: Send {
 	/// Type passed to most functions, used for common / cached values
-	type CommonInput;
+	type CommonInput: Sync;
 	/// Type passed to the evaluation function
-	type EvalInput<'b>: Clone;
+	type EvalInput<'b>: Clone + Sync;
 	/// Result from package relationship evaluation
-	type EvalRelationsResult<'b>: PackageEvalRelationsResult;
+	type EvalRelationsResult<'b>: PackageEvalRelationsResult + Send;
 	/// Configured package type
 	type ConfiguredPackage: ConfiguredPackage<EvalInput<'a> = Self::EvalInput<'a>>;
 
@@ -107,12 +173,59 @@ pub trait PackageEvaluator<'a> {
 		common_input: &Self::CommonInput,
 	) -> anyhow::Result<Self::EvalRelationsResult<'a>>;
 
+	/// Evaluate the relationships of multiple packages, where each package has the input
+	/// at the same index in `inputs`. The results must be in the same order as the packages.
+	/// By default, this evaluates each package one at a time, but it can be overridden by
+	/// evaluators that can do the work for multiple packages together.
+	async fn eval_package_relations_batch(
+		&mut self,
+		pkgs: &[ArcPkgReq],
+		inputs: &[Self::EvalInput<'a>],
+		common_input: &Self::CommonInput,
+	) -> Vec<anyhow::Result<Self::EvalRelationsResult<'a>>> {
+		let mut out = Vec::with_capacity(pkgs.len());
+		for (pkg, input) in pkgs.iter().zip(inputs) {
+			out.push(self.eval_package_relations(pkg, input, common_input).await);
+		}
+
+		out
+	}
+
 	/// Get the properties of a package
 	async fn get_package_properties<'b>(
 		&'b mut self,
 		pkg: &ArcPkgReq,
 		common_input: &Self::CommonInput,
 	) -> anyhow::Result<&'b PackageProperties>;
+
+	/// Get the metadata of a package. Implementations should cache the metadata
+	/// of each package so that it is only evaluated the first time that it is requested,
+	/// making it cheap to call this repeatedly for many packages. The cache should not
+	/// be invalidated for the lifetime of the evaluator.
+	async fn get_package_metadata<'b>(
+		&'b mut self,
+		pkg: &ArcPkgReq,
+		common_input: &Self::CommonInput,
+	) -> anyhow::Result<&'b PackageMetadata>;
+
+	/// Get the ID of the repository that a package comes from, if it came from one
+	async fn get_package_repository(
+		&mut self,
+		_pkg: &ArcPkgReq,
+		_common_input: &Self::CommonInput,
+	) -> anyhow::Result<Option<String>> {
+		Ok(None)
+	}
+
+	/// Check if a package exists and can be installed. This is used to choose between
+	/// alternative dependencies. By default, every package is assumed to be available.
+	async fn is_package_available(
+		&mut self,
+		_pkg: &ArcPkgReq,
+		_common_input: &Self::CommonInput,
+	) -> anyhow::Result<bool> {
+		Ok(true)
+	}
 }
 
 /// Trait for a user-configured package
@@ -133,7 +246,8 @@ pub trait ConfiguredPackage: Clone {
 
 /// Trait for the result from evaluating a package, used for resolution
 pub trait PackageEvalRelationsResult {
-	/// Get the evaluated dependencies
+	/// Get the evaluated dependencies. Each inner list is a group of alternatives,
+	/// where only one of them has to be installed.
 	fn get_deps(&self) -> Vec<Vec<RequiredPackage>>;
 	/// Get the evaluated conflicts
 	fn get_conflicts(&self) -> Vec<PackageID>;
@@ -143,7 +257,8 @@ pub trait PackageEvalRelationsResult {
 	fn get_bundled(&self) -> Vec<PackageID>;
 	/// Get the evaluated compats
 	fn get_compats(&self) -> Vec<(PackageID, PackageID)>;
-	/// Get the evaluated extensions
+	/// Get the evaluated extensions. These are the packages that this package extends,
+	/// which are required like dependencies and are installed before this package.
 	fn get_extensions(&self) -> Vec<PackageID>;
 }
 
@@ -163,3 +278,104 @@ pub fn is_open_source(meta: &PackageMetadata, properties: &PackageProperties) ->
 
 	true
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Clone)]
+	pub(crate) struct TestConfiguredPackage(pub ArcPkgReq);
+
+	impl ConfiguredPackage for TestConfiguredPackage {
+		type EvalInput<'a> = ();
+
+		fn get_package(&self) -> ArcPkgReq {
+			self.0.clone()
+		}
+
+		fn override_configured_package_input(
+			&self,
+			_: &PackageProperties,
+			_: &mut Self::EvalInput<'_>,
+		) -> anyhow::Result<()> {
+			Ok(())
+		}
+	}
+
+	/// Relations result with fixed relations
+	#[derive(Default, Clone)]
+	pub(crate) struct TestRelationsResult {
+		pub deps: Vec<Vec<RequiredPackage>>,
+		pub conflicts: Vec<PackageID>,
+		pub recommendations: Vec<RecommendedPackage>,
+		pub bundled: Vec<PackageID>,
+		pub compats: Vec<(PackageID, PackageID)>,
+		pub extensions: Vec<PackageID>,
+	}
+
+	impl PackageEvalRelationsResult for TestRelationsResult {
+		fn get_deps(&self) -> Vec<Vec<RequiredPackage>> {
+			self.deps.clone()
+		}
+
+		fn get_conflicts(&self) -> Vec<PackageID> {
+			self.conflicts.clone()
+		}
+
+		fn get_recommendations(&self) -> Vec<RecommendedPackage> {
+			self.recommendations.clone()
+		}
+
+		fn get_bundled(&self) -> Vec<PackageID> {
+			self.bundled.clone()
+		}
+
+		fn get_compats(&self) -> Vec<(PackageID, PackageID)> {
+			self.compats.clone()
+		}
+
+		fn get_extensions(&self) -> Vec<PackageID> {
+			self.extensions.clone()
+		}
+	}
+
+	/// Handler for a custom content type where the package is just its name
+	struct NameContentType;
+
+	impl CustomContentTypeHandler for NameContentType {
+		fn parse_custom_package(
+			&self,
+			content_type: &str,
+			contents: &str,
+		) -> anyhow::Result<Option<CustomPackageContents>> {
+			if content_type != "name" {
+				return Ok(None);
+			}
+			let mut out = CustomPackageContents::default();
+			out.meta.name = Some(contents.to_string());
+			Ok(Some(out))
+		}
+
+		fn validate_custom_package(&self, _: &str, contents: &str) -> anyhow::Result<()> {
+			if contents.is_empty() {
+				bail!("Name is empty");
+			}
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_custom_content_type() {
+		let content_type: PackageContentType = serde_json::from_str("\"name\"").unwrap();
+		assert_eq!(content_type, PackageContentType::Custom("name".into()));
+		let content_type: PackageContentType = serde_json::from_str("\"script\"").unwrap();
+		assert_eq!(content_type, PackageContentType::Script);
+
+		let name = PackageContentType::Custom("name".into());
+		parse_and_validate_custom("Foo", name.clone(), &NameContentType).unwrap();
+		assert!(parse_and_validate_custom("", name.clone(), &NameContentType).is_err());
+		assert!(parse_and_validate("Foo", name).is_err());
+		let other = PackageContentType::Custom("other".into());
+		assert!(parse_and_validate_custom("Foo", other, &NameContentType).is_err());
+	}
+}
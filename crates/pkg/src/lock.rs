@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use mcvm_shared::pkg::PackageID;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::resolve::ResolutionResult;
+
+/// A record of the exact set of packages from a resolution, used to make sure that
+/// later installs resolve to the same packages
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ResolveLock {
+	/// The locked packages, keyed and sorted by package ID
+	pub packages: BTreeMap<String, LockedPackage>,
+}
+
+/// A single package in a resolve lock
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct LockedPackage {
+	/// The content version that the package resolved to
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub content_version: Option<String>,
+	/// The ID of the repository that the package came from
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub repository: Option<String>,
+	/// The hash of the package contents
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub hash: Option<String>,
+}
+
+impl ResolveLock {
+	/// Creates a lock from the result of a resolution, with the hashes of
+	/// the contents of the resolved packages
+	pub fn from_resolution(result: &ResolutionResult, hashes: &HashMap<PackageID, String>) -> Self {
+		let packages = result
+			.packages
+			.iter()
+			.map(|req| {
+				let package = LockedPackage {
+					content_version: result.content_versions.get(req).cloned(),
+					repository: result.repositories.get(req).cloned(),
+					hash: hashes.get(&req.id).cloned(),
+				};
+				(req.id.to_string(), package)
+			})
+			.collect();
+
+		Self { packages }
+	}
+
+	/// Reads a lock from a JSON file
+	pub fn read(path: &Path) -> anyhow::Result<Self> {
+		let contents = std::fs::read_to_string(path).context("Failed to read lock file")?;
+		serde_json::from_str(&contents).context("Failed to deserialize lock")
+	}
+
+	/// Writes this lock to a JSON file
+	pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+		let contents = serde_json::to_string_pretty(self).context("Failed to serialize lock")?;
+		std::fs::write(path, contents).context("Failed to write lock file")
+	}
+}
+
+/// Checks that the result of a resolution matches a lock, returning an error
+/// that lists all of the differences if it does not
+pub fn verify_lock(
+	lock: &ResolveLock,
+	result: &ResolutionResult,
+	hashes: &HashMap<PackageID, String>,
+) -> anyhow::Result<()> {
+	let current = ResolveLock::from_resolution(result, hashes);
+
+	let mut differences = Vec::new();
+	for (id, locked) in &lock.packages {
+		match current.packages.get(id) {
+			Some(package) if package != locked => {
+				differences.push(format!("Package '{id}' has changed"))
+			}
+			Some(..) => {}
+			None => differences.push(format!("Package '{id}' is no longer required")),
+		}
+	}
+	for id in current.packages.keys() {
+		if !lock.packages.contains_key(id) {
+			differences.push(format!("Package '{id}' is not in the lock"));
+		}
+	}
+
+	if !differences.is_empty() {
+		bail!(
+			"Resolved packages do not match the lock:\n{}",
+			differences.join("\n")
+		);
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use mcvm_shared::pkg::{PkgRequest, PkgRequestSource};
+
+	use super::*;
+
+	fn create_resolution(ids: &[&str]) -> ResolutionResult {
+		let packages: Vec<_> = ids
+			.iter()
+			.map(|id| Arc::new(PkgRequest::parse(id, PkgRequestSource::UserRequire)))
+			.collect();
+		let repositories = packages
+			.iter()
+			.map(|req| (req.clone(), "std".to_string()))
+			.collect();
+		let content_versions = packages
+			.iter()
+			.filter(|req| req.id.as_ref() == "bar")
+			.map(|req| (req.clone(), "1.2.1".to_string()))
+			.collect();
+
+		ResolutionResult {
+			packages,
+			unfulfilled_recommendations: Vec::new(),
+			repositories,
+			content_versions,
+			sources: HashMap::new(),
+		}
+	}
+
+	#[test]
+	fn test_lock_verification() {
+		let hashes = HashMap::from([("foo".into(), "1234".to_string())]);
+		let resolution = create_resolution(&["foo", "bar@1.2.*"]);
+		let lock = ResolveLock::from_resolution(&resolution, &hashes);
+		assert_eq!(
+			lock.packages["bar"].content_version.as_deref(),
+			Some("1.2.1")
+		);
+		assert_eq!(lock.packages["foo"].content_version, None);
+		assert_eq!(lock.packages["foo"].hash.as_deref(), Some("1234"));
+
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("lock.json");
+		lock.write(&path).unwrap();
+		let lock = ResolveLock::read(&path).unwrap();
+
+		verify_lock(&lock, &create_resolution(&["bar@1.2.*", "foo"]), &hashes).unwrap();
+
+		let err = verify_lock(&lock, &create_resolution(&["foo", "baz"]), &hashes).unwrap_err();
+		let err = err.to_string();
+		assert!(err.contains("'bar' is no longer required"));
+		assert!(err.contains("'baz' is not in the lock"));
+
+		assert!(verify_lock(&lock, &resolution, &HashMap::new()).is_err());
+	}
+}
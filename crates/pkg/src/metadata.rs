@@ -1,5 +1,7 @@
+use crate::parse::lex::{lex, Side, Token, TokenAndPos};
+use crate::parse::parse::parse;
 use crate::parse::{instruction::InstrKind, parse::Parsed, routine::METADATA_ROUTINE};
-use anyhow::bail;
+use anyhow::{bail, Context};
 #[cfg(feature = "schema")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -129,3 +131,79 @@ pub fn eval_metadata(parsed: &Parsed) -> anyhow::Result<PackageMetadata> {
 		Ok(PackageMetadata::default())
 	}
 }
+
+/// Collect the metadata from the contents of a package script. Only the metadata routine
+/// is parsed and evaluated, so this is much cheaper than parsing the whole package
+/// and errors in other routines are ignored.
+pub fn eval_metadata_only(contents: &str) -> anyhow::Result<PackageMetadata> {
+	let tokens = lex(contents).context("Lexing failed")?;
+	let Some(routine) = find_routine_tokens(&tokens, METADATA_ROUTINE) else {
+		return Ok(PackageMetadata::default());
+	};
+	let parsed = parse(routine.iter()).context("Parsing failed")?;
+	eval_metadata(&parsed)
+}
+
+/// Finds the tokens of a top-level routine, from the @ to the closing brace
+fn find_routine_tokens<'a>(tokens: &'a [TokenAndPos], routine: &str) -> Option<&'a [TokenAndPos]> {
+	let mut depth = 0usize;
+	let mut start = None;
+	let mut meaningful = tokens
+		.iter()
+		.enumerate()
+		.filter(|(_, (tok, _))| !tok.is_ignored())
+		.peekable();
+	while let Some((i, (tok, _))) = meaningful.next() {
+		match tok {
+			Token::At if depth == 0 => {
+				if let Some((_, (Token::Ident(name), _))) = meaningful.peek() {
+					if name == routine {
+						start = Some(i);
+					}
+				}
+			}
+			Token::Curly(Side::Left) => depth += 1,
+			Token::Curly(Side::Right) => {
+				depth = depth.saturating_sub(1);
+				if depth == 0 {
+					if let Some(start) = start {
+						return tokens.get(start..=i);
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_eval_metadata_only() {
+		let text = r#"
+			@install {
+				this is not valid;
+			}
+			@meta {
+				name "Test";
+				description "Only the metadata is evaluated";
+			}
+			@foo {
+				if {
+			}
+		"#;
+		let metadata = eval_metadata_only(text).unwrap();
+		assert_eq!(metadata.name.as_deref(), Some("Test"));
+		assert_eq!(
+			metadata.description.as_deref(),
+			Some("Only the metadata is evaluated")
+		);
+
+		let metadata = eval_metadata_only("@install { this is not valid; }").unwrap();
+		assert!(metadata.is_empty());
+	}
+}
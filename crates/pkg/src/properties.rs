@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use anyhow::{bail, ensure};
 use mcvm_parse::conditions::{ArchCondition, OSCondition};
 use mcvm_shared::modifications::{ModloaderMatch, PluginLoaderMatch};
@@ -140,3 +142,75 @@ pub fn eval_properties(parsed: &Parsed) -> anyhow::Result<PackageProperties> {
 		Ok(PackageProperties::default())
 	}
 }
+
+/// Builds a map from Modrinth project IDs to the IDs of the packages that declare them in their properties.
+/// This can be used to substitute Modrinth dependencies with packages. If multiple packages have the same
+/// Modrinth ID, the first one is used
+pub fn build_modrinth_id_map<'a>(
+	packages: impl IntoIterator<Item = (&'a str, &'a PackageProperties)>,
+) -> HashMap<String, String> {
+	build_id_map(packages, |x| x.modrinth_id.as_ref())
+}
+
+/// Builds a map from CurseForge mod IDs to the IDs of the packages that declare them in their properties.
+/// If multiple packages have the same CurseForge ID, the first one is used
+pub fn build_curseforge_id_map<'a>(
+	packages: impl IntoIterator<Item = (&'a str, &'a PackageProperties)>,
+) -> HashMap<String, String> {
+	build_id_map(packages, |x| x.curseforge_id.as_ref())
+}
+
+/// Builds a map from an external ID in package properties to the IDs of the packages
+fn build_id_map<'a>(
+	packages: impl IntoIterator<Item = (&'a str, &'a PackageProperties)>,
+	get_id: impl Fn(&PackageProperties) -> Option<&String>,
+) -> HashMap<String, String> {
+	let mut out = HashMap::new();
+	for (package, properties) in packages {
+		if let Some(id) = get_id(properties) {
+			out.entry(id.clone()).or_insert_with(|| package.to_string());
+		}
+	}
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_modrinth_id_map() {
+		let props = |modrinth_id: Option<&str>| PackageProperties {
+			modrinth_id: modrinth_id.map(String::from),
+			..Default::default()
+		};
+		let packages = [
+			("sodium", props(Some("AANobbMI"))),
+			("fabric-api", props(Some("P7dR8mSH"))),
+			("no-modrinth", props(None)),
+			("sodium-fork", props(Some("AANobbMI"))),
+		];
+
+		let map = build_modrinth_id_map(packages.iter().map(|(id, props)| (*id, props)));
+		assert_eq!(map.len(), 2);
+		assert_eq!(map["AANobbMI"], "sodium");
+		assert_eq!(map["P7dR8mSH"], "fabric-api");
+	}
+
+	#[test]
+	fn test_curseforge_id_map() {
+		let props = |curseforge_id: Option<&str>| PackageProperties {
+			curseforge_id: curseforge_id.map(String::from),
+			..Default::default()
+		};
+		let packages = [
+			("jei", props(Some("238222"))),
+			("no-curseforge", props(None)),
+		];
+
+		let map = build_curseforge_id_map(packages.iter().map(|(id, props)| (*id, props)));
+		assert_eq!(map.len(), 1);
+		assert_eq!(map["238222"], "jei");
+	}
+}
@@ -49,6 +49,19 @@ pub struct RepoPkgEntry {
 	/// Flags for this package
 	#[serde(default)]
 	pub flags: HashSet<PackageFlag>,
+	/// Hash of the package file. Changing it makes clients download the package file again
+	/// instead of using their cached copy.
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub hash: Option<String>,
+}
+
+impl RepoPkgEntry {
+	/// Whether this package has been flagged as deprecated or archived and should
+	/// no longer be used
+	pub fn is_deprecated(&self) -> bool {
+		self.flags.contains(&PackageFlag::Deprecated) || self.flags.contains(&PackageFlag::Archived)
+	}
 }
 
 /// Flags that can be applied to packages by repositories to provide information about them
@@ -60,6 +73,8 @@ pub enum PackageFlag {
 	OutOfDate,
 	/// This package has been deprecated in favor of another one
 	Deprecated,
+	/// This package is no longer maintained and will not receive any updates
+	Archived,
 	/// This package has security or safety vulnerabilities
 	Insecure,
 	/// The package provides malicious content
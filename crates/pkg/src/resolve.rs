@@ -1,44 +1,59 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 
-use anyhow::{bail, Context};
+use anyhow::{anyhow, bail, Context};
 use itertools::Itertools;
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
 use mcvm_shared::pkg::{ArcPkgReq, PackageID};
+use mcvm_shared::translate;
+use mcvm_shared::versions::VersionPattern;
+use thiserror::Error;
 
 use crate::properties::PackageProperties;
-use crate::{ConfiguredPackage, PackageEvalRelationsResult, PackageEvaluator};
+use crate::update::get_installed_content_version;
+use crate::{ConfiguredPackage, PackageEvalRelationsResult, PackageEvaluator, RequiredPackage};
 
 use crate::{PkgRequest, PkgRequestSource};
 
-/// Find all package dependencies from a set of required packages
+/// Find all package dependencies from a set of required packages.
+/// Progress is displayed to the output as each package is evaluated.
+/// Resolution fails if the dependency graph grows past the limits.
 pub async fn resolve<'a, E: PackageEvaluator<'a>>(
 	packages: &[E::ConfiguredPackage],
 	mut evaluator: E,
 	constant_eval_input: E::EvalInput<'a>,
 	common_input: &E::CommonInput,
-) -> anyhow::Result<ResolutionResult> {
+	limits: &ResolutionLimits,
+	o: &mut impl MCVMOutput,
+) -> Result<ResolutionResult, ResolutionError> {
 	let mut resolver = Resolver {
 		tasks: VecDeque::new(),
 		constraints: Vec::new(),
 		constant_input: constant_eval_input,
+		repositories: HashMap::new(),
+		content_versions: HashMap::new(),
+		queued: HashMap::new(),
+		num_resolved: 0,
+		sources: HashMap::new(),
+		limits: limits.clone(),
 	};
 
 	// Create the initial EvalPackage from the installed packages
 	for config in packages.iter().sorted_by_key(|x| x.get_package()) {
 		let req = config.get_package();
 
+		resolver.add_source(&req);
 		resolver.constraints.push(Constraint {
 			kind: ConstraintKind::UserRequire(req.clone()),
 		});
-		resolver.tasks.push_back(Task::EvalPackage {
-			dest: req.clone(),
-			config: Some(config.clone()),
-		});
+		resolver.queue_eval(req.clone(), Some(config.clone()), 0)?;
 	}
 
-	while let Some(task) = resolver.tasks.pop_front() {
-		resolve_task(task, common_input, &mut evaluator, &mut resolver).await?;
-		resolver.check_compats();
+	// Evaluate all of the queued packages together so that evaluators can batch them
+	while !resolver.tasks.is_empty() {
+		let tasks: Vec<_> = resolver.tasks.drain(..).collect();
+		resolve_tasks(tasks, common_input, &mut evaluator, &mut resolver, o).await?;
+		resolver.check_compats()?;
 	}
 
 	let mut unfulfilled_recommendations = Vec::new();
@@ -60,30 +75,32 @@ pub async fn resolve<'a, E: PackageEvaluator<'a>>(
 					});
 				}
 			}
-			ConstraintKind::Extend(package) => {
-				if !resolver.is_required(package) {
-					let source = package.source.get_source();
-					if let Some(source) = source {
-						bail!(
-							"The package '{}' extends the functionality of the package '{}', which is not installed.",
-							source.debug_sources(),
-							package
-						);
-					} else {
-						bail!(
-							"A package extends the functionality of the package '{}', which is not installed.",
-							package
-						);
-					}
-				}
-			}
 			_ => {}
 		}
 	}
 
+	let repositories = std::mem::take(&mut resolver.repositories);
+	let content_versions = std::mem::take(&mut resolver.content_versions);
+	let sources = std::mem::take(&mut resolver.sources);
+	let packages = resolver.collect_packages();
+	let repositories = repositories
+		.into_iter()
+		.filter(|(package, _)| packages.contains(package))
+		.collect();
+	let content_versions = content_versions
+		.into_iter()
+		.filter(|(package, _)| packages.contains(package))
+		.collect();
+	let sources = sources
+		.into_iter()
+		.filter(|(id, _)| packages.iter().any(|x| &x.id == id))
+		.collect();
 	let out = ResolutionResult {
-		packages: resolver.collect_packages(),
+		packages,
 		unfulfilled_recommendations,
+		repositories,
+		content_versions,
+		sources,
 	};
 
 	Ok(out)
@@ -95,6 +112,92 @@ pub struct ResolutionResult {
 	pub packages: Vec<ArcPkgReq>,
 	/// Package recommendations that were not satisfied
 	pub unfulfilled_recommendations: Vec<RecommendedPackage>,
+	/// The ID of the repository that each package came from.
+	/// Packages that did not come from a repository are not included.
+	pub repositories: HashMap<ArcPkgReq, String>,
+	/// The content version that each package resolved to.
+	/// Packages without content versions are not included.
+	pub content_versions: HashMap<ArcPkgReq, String>,
+	/// Every reason that each package was requested, in the order that they were found.
+	/// A package can be requested by multiple sources but is only evaluated once.
+	pub sources: HashMap<PackageID, Vec<PkgRequestSource>>,
+}
+
+/// Limits on the size of the dependency graph, which protect against repositories
+/// with runaway dependencies using up resources during resolution
+#[derive(Debug, Clone)]
+pub struct ResolutionLimits {
+	/// The maximum number of packages that can be resolved in total
+	pub max_packages: usize,
+	/// The maximum length of a chain of dependencies. User-required packages have a depth of zero.
+	pub max_depth: u32,
+}
+
+impl Default for ResolutionLimits {
+	fn default() -> Self {
+		Self {
+			max_packages: 10000,
+			max_depth: 256,
+		}
+	}
+}
+
+/// An error from package resolution. The specific failures that UIs might want to
+/// handle differently have their own variants, and everything else is kept as an
+/// anyhow error.
+#[derive(Debug, Error)]
+pub enum ResolutionError {
+	/// A package does not exist in any of the repositories
+	#[error("Package '{0}' does not exist")]
+	PackageNotFound(PackageID),
+	/// A package is refused by other packages that are being installed
+	#[error("Package '{package}' is incompatible with existing packages {}", .refusers.join(", "))]
+	Conflict {
+		/// The package that is refused
+		package: PackageID,
+		/// The packages that refuse it
+		refusers: Vec<PackageID>,
+	},
+	/// The content version that a package was requested with does not match any of the content versions of the package
+	#[error("Package '{package}' does not have a content version that matches '{version}'")]
+	UnsatisfiableVersion {
+		/// The package that was requested
+		package: PackageID,
+		/// The requested content version
+		version: VersionPattern,
+	},
+	/// Compat relations between packages form a cycle
+	#[error("Compat relations between packages form a cycle: {}", .0.join(" -> "))]
+	Cycle(Vec<PackageID>),
+	/// More packages were found than the limit allows
+	#[error("Resolution found more than the limit of {0} packages")]
+	TooManyPackages(usize),
+	/// A chain of dependencies is longer than the limit allows
+	#[error(
+		"Package '{package}' is nested deeper than the limit of {limit} dependencies: {chain}"
+	)]
+	TooDeep {
+		/// The package that is too deep
+		package: PackageID,
+		/// The maximum depth
+		limit: u32,
+		/// The chain of packages that led to the package
+		chain: String,
+	},
+	/// Any other failure, such as a package failing to evaluate
+	#[error(transparent)]
+	Other(#[from] anyhow::Error),
+}
+
+impl ResolutionError {
+	/// Adds the package that the error happened in as context for errors
+	/// that are not one of the specific resolution failures
+	fn in_package(self, package: &PkgRequest) -> Self {
+		match self {
+			Self::Other(e) => Self::Other(e.context(package_context_error_message(package))),
+			other => other,
+		}
+	}
 }
 
 /// Recommended package that has a PkgRequest instead of a String
@@ -105,54 +208,206 @@ pub struct RecommendedPackage {
 	pub invert: bool,
 }
 
-/// Resolve a single task
-async fn resolve_task<'a, E: PackageEvaluator<'a>>(
-	task: Task<'a, E>,
+/// Resolve a set of tasks
+async fn resolve_tasks<'a, E: PackageEvaluator<'a>>(
+	tasks: Vec<Task<'a, E>>,
 	common_input: &E::CommonInput,
 	evaluator: &mut E,
 	resolver: &mut Resolver<'a, E>,
-) -> anyhow::Result<()> {
-	match task {
-		Task::EvalPackage { dest, config } => {
-			resolve_eval_package(
-				dest.clone(),
-				config.as_ref(),
-				common_input,
-				evaluator,
-				resolver,
-			)
-			.await
-			.with_context(|| package_context_error_message(&dest))?;
+	o: &mut impl MCVMOutput,
+) -> Result<(), ResolutionError> {
+	let mut packages = Vec::with_capacity(tasks.len());
+	let mut inputs = Vec::with_capacity(tasks.len());
+	for task in tasks {
+		match task {
+			Task::EvalPackage { dest, config } => {
+				let (input, content_version) =
+					get_eval_input(&dest, config.as_ref(), common_input, evaluator, resolver)
+						.await
+						.map_err(|e| e.in_package(&dest))?;
+				if let Some(content_version) = content_version {
+					resolver
+						.content_versions
+						.insert(dest.clone(), content_version);
+				}
+				let repository = evaluator
+					.get_package_repository(&dest, common_input)
+					.await
+					.context("Failed to get the repository of the package")
+					.with_context(|| package_context_error_message(&dest))?;
+				if let Some(repository) = repository {
+					resolver.repositories.insert(dest.clone(), repository);
+				}
+				packages.push(dest);
+				inputs.push(input);
+			}
 		}
 	}
 
+	let results = evaluator
+		.eval_package_relations_batch(&packages, &inputs, common_input)
+		.await;
+	for (package, result) in packages.into_iter().zip(results) {
+		let result = result
+			.context("Failed to evaluate package")
+			.with_context(|| package_context_error_message(&package))?;
+		let deps = choose_dependencies(&package, &result, common_input, evaluator, resolver)
+			.await
+			.with_context(|| package_context_error_message(&package))?;
+		resolve_eval_package(package.clone(), &result, &deps, resolver)
+			.map_err(|e| e.in_package(&package))?;
+
+		// The total includes every package that has been found so far, so it grows as new dependencies are found
+		resolver.num_resolved += 1;
+		o.display(
+			MessageContents::Associated(
+				Box::new(MessageContents::Progress {
+					current: resolver.num_resolved,
+					total: resolver.queued.len() as u32,
+				}),
+				Box::new(MessageContents::Simple(translate!(
+					o,
+					ResolvedPackage,
+					"pkg" = &package.id
+				))),
+			),
+			MessageLevel::Important,
+		);
+	}
+
 	Ok(())
 }
 
-/// Resolve an EvalPackage task
-async fn resolve_eval_package<'a, E: PackageEvaluator<'a>>(
-	package: ArcPkgReq,
+/// Get the EvalInput for an EvalPackage task, along with the content version that the package resolves to
+async fn get_eval_input<'a, E: PackageEvaluator<'a>>(
+	package: &ArcPkgReq,
 	config: Option<&E::ConfiguredPackage>,
 	common_input: &E::CommonInput,
 	evaluator: &mut E,
-	resolver: &mut Resolver<'a, E>,
-) -> anyhow::Result<()> {
+	resolver: &Resolver<'a, E>,
+) -> Result<(E::EvalInput<'a>, Option<String>), ResolutionError> {
 	// Make sure that this package fits the constraints as well
-	resolver
-		.check_constraints(&package)
-		.context("Package did not fit existing constraints")?;
+	resolver.check_constraints(package)?;
 
-	// Get the correct EvalInput
-	let properties = evaluator
-		.get_package_properties(&package, common_input)
+	let properties = match evaluator
+		.get_package_properties(package, common_input)
 		.await
-		.context("Failed to get package properties")?;
+	{
+		Ok(properties) => properties,
+		Err(e) => {
+			// Report the package as missing if that is the reason that it failed
+			if let Ok(false) = evaluator.is_package_available(package, common_input).await {
+				return Err(ResolutionError::PackageNotFound(package.id.clone()));
+			}
+			return Err(e.context("Failed to get package properties").into());
+		}
+	};
+	check_content_version(package, properties)?;
+	let content_version =
+		get_installed_content_version(properties, Some(&package.content_version)).map(String::from);
+
 	let input = override_eval_input::<E>(properties, &resolver.constant_input, config)?;
 
-	let result = evaluator
-		.eval_package_relations(&package, &input, common_input)
-		.await
-		.context("Failed to evaluate package")?;
+	Ok((input, content_version))
+}
+
+/// Checks that a package has a content version that matches the one it was requested with
+fn check_content_version(
+	package: &PkgRequest,
+	properties: &PackageProperties,
+) -> Result<(), ResolutionError> {
+	if package.content_version == VersionPattern::Any {
+		return Ok(());
+	}
+
+	let empty = Vec::new();
+	let available = properties.content_versions.as_ref().unwrap_or(&empty);
+	if !available
+		.iter()
+		.any(|x| package.content_version.matches_single(x, available))
+	{
+		return Err(ResolutionError::UnsatisfiableVersion {
+			package: package.id.clone(),
+			version: package.content_version.clone(),
+		});
+	}
+
+	Ok(())
+}
+
+/// Picks the package to depend on from each group of alternative dependencies.
+/// If one of the alternatives is already required, that one is used. Otherwise,
+/// the first alternative in the group that is available is chosen.
+async fn choose_dependencies<'a, E: PackageEvaluator<'a>>(
+	package: &ArcPkgReq,
+	result: &E::EvalRelationsResult<'a>,
+	common_input: &E::CommonInput,
+	evaluator: &mut E,
+	resolver: &Resolver<'a, E>,
+) -> anyhow::Result<Vec<RequiredPackage>> {
+	let mut out = Vec::new();
+	for group in result.get_deps() {
+		// There is nothing to choose between
+		if group.len() <= 1 {
+			out.extend(group);
+			continue;
+		}
+
+		let requests: Vec<_> = group
+			.iter()
+			.map(|dep| {
+				Arc::new(PkgRequest::parse(
+					&dep.value,
+					PkgRequestSource::Dependency(package.clone()),
+				))
+			})
+			.collect();
+
+		let required = group
+			.iter()
+			.zip(&requests)
+			.find(|(_, req)| resolver.is_required(req));
+		if let Some((dep, _)) = required {
+			out.push(dep.clone());
+			continue;
+		}
+
+		let mut chosen = None;
+		for (dep, req) in group.iter().zip(&requests) {
+			if resolver.is_refused(req) {
+				continue;
+			}
+			let available = evaluator
+				.is_package_available(req, common_input)
+				.await
+				.with_context(|| format!("Failed to check if package '{req}' is available"))?;
+			if available {
+				chosen = Some(dep.clone());
+				break;
+			}
+		}
+
+		let Some(chosen) = chosen else {
+			bail!(
+				"None of the alternatives for a dependency are available: {}",
+				group.iter().map(|x| &x.value).join(", ")
+			);
+		};
+		out.push(chosen);
+	}
+
+	Ok(out)
+}
+
+/// Resolve an EvalPackage task using the evaluated relations of the package
+/// and the dependencies that were chosen from them
+fn resolve_eval_package<'a, E: PackageEvaluator<'a>>(
+	package: ArcPkgReq,
+	result: &E::EvalRelationsResult<'a>,
+	deps: &[RequiredPackage],
+	resolver: &mut Resolver<'a, E>,
+) -> Result<(), ResolutionError> {
+	let depth = resolver.get_depth(&package) + 1;
 
 	for conflict in result.get_conflicts().iter().sorted() {
 		let req = Arc::new(PkgRequest::parse(
@@ -160,33 +415,31 @@ async fn resolve_eval_package<'a, E: PackageEvaluator<'a>>(
 			PkgRequestSource::Refused(package.clone()),
 		));
 		if resolver.is_required(&req) {
-			bail!(
-				"Package '{}' is incompatible with this package.",
-				req.debug_sources()
-			);
+			return Err(ResolutionError::Conflict {
+				package: req.id.clone(),
+				refusers: vec![package.id.clone()],
+			});
 		}
 		resolver.constraints.push(Constraint {
 			kind: ConstraintKind::Refuse(req),
 		});
 	}
 
-	for dep in result.get_deps().iter().flatten().sorted() {
+	for dep in deps.iter().sorted() {
 		let req = Arc::new(PkgRequest::parse(
 			&dep.value,
 			PkgRequestSource::Dependency(package.clone()),
 		));
 		if dep.explicit && !resolver.is_user_required(&req) {
-			bail!("Package '{req}' has been explicitly required by this package. This means it must be required by the user in their config.");
+			return Err(anyhow!("Package '{req}' has been explicitly required by this package. This means it must be required by the user in their config.").into());
 		}
 		resolver.check_constraints(&req)?;
+		resolver.add_source(&req);
 		if !resolver.is_required(&req) {
 			resolver.constraints.push(Constraint {
 				kind: ConstraintKind::Require(req.clone()),
 			});
-			resolver.tasks.push_back(Task::EvalPackage {
-				dest: req,
-				config: None,
-			});
+			resolver.queue_eval(req, None, depth)?;
 		}
 	}
 
@@ -196,14 +449,12 @@ async fn resolve_eval_package<'a, E: PackageEvaluator<'a>>(
 			PkgRequestSource::Bundled(package.clone()),
 		));
 		resolver.check_constraints(&req)?;
+		resolver.add_source(&req);
 		resolver.remove_require_constraint(&req);
 		resolver.constraints.push(Constraint {
 			kind: ConstraintKind::Bundle(req.clone()),
 		});
-		resolver.tasks.push_back(Task::EvalPackage {
-			dest: req,
-			config: None,
-		});
+		resolver.queue_eval(req, None, depth)?;
 	}
 
 	for (check_package, compat_package) in result.get_compats().iter().sorted() {
@@ -222,11 +473,20 @@ async fn resolve_eval_package<'a, E: PackageEvaluator<'a>>(
 		}
 	}
 
+	// Extended packages are required like dependencies, but are also ordered before this package
 	for extension in result.get_extensions().iter().sorted() {
 		let req = Arc::new(PkgRequest::parse(
 			extension,
 			PkgRequestSource::Dependency(package.clone()),
 		));
+		resolver.check_constraints(&req)?;
+		resolver.add_source(&req);
+		if !resolver.is_required(&req) {
+			resolver.constraints.push(Constraint {
+				kind: ConstraintKind::Require(req.clone()),
+			});
+			resolver.queue_eval(req.clone(), None, depth)?;
+		}
 		resolver.constraints.push(Constraint {
 			kind: ConstraintKind::Extend(req),
 		});
@@ -235,8 +495,11 @@ async fn resolve_eval_package<'a, E: PackageEvaluator<'a>>(
 	for recommendation in result.get_recommendations().iter().sorted() {
 		let req = Arc::new(PkgRequest::parse(
 			&recommendation.value,
-			PkgRequestSource::Dependency(package.clone()),
+			PkgRequestSource::Recommended(package.clone()),
 		));
+		if !recommendation.invert {
+			resolver.add_source(&req);
+		}
 		resolver.constraints.push(Constraint {
 			kind: ConstraintKind::Recommend(req, recommendation.invert),
 		});
@@ -267,6 +530,17 @@ struct Resolver<'a, E: PackageEvaluator<'a>> {
 	tasks: VecDeque<Task<'a, E>>,
 	constraints: Vec<Constraint>,
 	constant_input: E::EvalInput<'a>,
+	repositories: HashMap<ArcPkgReq, String>,
+	/// The content version that each evaluated package resolved to
+	content_versions: HashMap<ArcPkgReq, String>,
+	/// The packages that have been queued for evaluation, so that each one is only evaluated once,
+	/// along with the depth that they were found at
+	queued: HashMap<PackageID, u32>,
+	/// The number of packages that have been evaluated and resolved
+	num_resolved: u32,
+	/// Every reason that each package was requested
+	sources: HashMap<PackageID, Vec<PkgRequestSource>>,
+	limits: ResolutionLimits,
 }
 
 impl<'a, E> Resolver<'a, E>
@@ -282,6 +556,49 @@ where
 		)
 	}
 
+	/// Queues a package to be evaluated if it has not been already,
+	/// checking that it stays within the limits
+	pub fn queue_eval(
+		&mut self,
+		req: ArcPkgReq,
+		config: Option<E::ConfiguredPackage>,
+		depth: u32,
+	) -> Result<(), ResolutionError> {
+		if self.queued.contains_key(&req.id) {
+			return Ok(());
+		}
+
+		if depth > self.limits.max_depth {
+			return Err(ResolutionError::TooDeep {
+				package: req.id.clone(),
+				limit: self.limits.max_depth,
+				chain: req.debug_sources(),
+			});
+		}
+		if self.queued.len() >= self.limits.max_packages {
+			return Err(ResolutionError::TooManyPackages(self.limits.max_packages));
+		}
+
+		self.queued.insert(req.id.clone(), depth);
+		self.tasks
+			.push_back(Task::EvalPackage { dest: req, config });
+
+		Ok(())
+	}
+
+	/// Gets the depth that a queued package was found at
+	pub fn get_depth(&self, req: &ArcPkgReq) -> u32 {
+		self.queued.get(&req.id).copied().unwrap_or_default()
+	}
+
+	/// Records a reason that a package was requested
+	pub fn add_source(&mut self, req: &ArcPkgReq) {
+		let sources = self.sources.entry(req.id.clone()).or_default();
+		if !sources.contains(&req.source) {
+			sources.push(req.source.clone());
+		}
+	}
+
 	/// Whether a package has been required by an existing constraint
 	pub fn is_required(&self, req: &ArcPkgReq) -> bool {
 		self.constraints
@@ -356,40 +673,83 @@ where
 	}
 
 	/// Creates an error if this package is disallowed in the constraints
-	pub fn check_constraints(&self, req: &ArcPkgReq) -> anyhow::Result<()> {
+	pub fn check_constraints(&self, req: &ArcPkgReq) -> Result<(), ResolutionError> {
 		if self.is_refused(req) {
-			let refusers = self.get_refusers(req);
-			bail!(
-				"Package '{req}' is incompatible with existing packages {}",
-				refusers.join(", ")
-			);
+			return Err(ResolutionError::Conflict {
+				package: req.id.clone(),
+				refusers: self.get_refusers(req),
+			});
 		}
 
 		Ok(())
 	}
 
-	/// Checks compat constraints to see if new constraints are needed
-	pub fn check_compats(&mut self) {
-		let mut constraints_to_add = Vec::new();
+	/// Checks compat constraints to see if new constraints are needed.
+	/// When the first package of a compat is required, the second one is required as well,
+	/// which is an error if the second package is refused.
+	pub fn check_compats(&mut self) -> Result<(), ResolutionError> {
+		if let Some(cycle) = self.find_compat_cycle() {
+			return Err(ResolutionError::Cycle(
+				cycle.into_iter().map(|x| x.id.clone()).collect(),
+			));
+		}
+
+		let mut to_require = Vec::new();
 		for constraint in &self.constraints {
 			if let ConstraintKind::Compat(package, compat_package) = &constraint.kind {
 				if self.is_required(package) && !self.is_required(compat_package) {
-					constraints_to_add.push(Constraint {
-						kind: ConstraintKind::Require(compat_package.clone()),
-					});
-					self.tasks.push_back(Task::EvalPackage {
-						dest: compat_package.clone(),
-						config: None,
-					});
+					to_require.push(compat_package.clone());
 				}
 			}
 		}
-		self.constraints.extend(constraints_to_add);
+		for compat_package in to_require {
+			self.check_constraints(&compat_package)?;
+			self.add_source(&compat_package);
+			// Multiple compats can require the same package
+			if self.is_required(&compat_package) {
+				continue;
+			}
+			self.constraints.push(Constraint {
+				kind: ConstraintKind::Require(compat_package.clone()),
+			});
+			let depth = compat_package
+				.source
+				.get_source()
+				.map(|source| self.get_depth(&source) + 1)
+				.unwrap_or_default();
+			self.queue_eval(compat_package, None, depth)?;
+		}
+
+		Ok(())
 	}
 
-	/// Collect all needed packages for final output
+	/// Finds a cycle in the compat constraints, returning the packages in it. Mutual compats
+	/// between two packages are a common way to say that they go together, so they aren't counted
+	fn find_compat_cycle(&self) -> Option<Vec<ArcPkgReq>> {
+		let edges: Vec<_> = self
+			.constraints
+			.iter()
+			.filter_map(|x| match &x.kind {
+				ConstraintKind::Compat(package, compat_package) => Some((package, compat_package)),
+				_ => None,
+			})
+			.collect();
+
+		for (start, _) in &edges {
+			let mut path = vec![*start];
+			if find_cycle_from(&edges, &mut path) {
+				return Some(path.into_iter().cloned().collect());
+			}
+		}
+
+		None
+	}
+
+	/// Collect all needed packages for final output. Packages that are extended by
+	/// other packages are put before the packages that extend them.
 	pub fn collect_packages(self) -> Vec<ArcPkgReq> {
-		self.constraints
+		let mut out: Vec<_> = self
+			.constraints
 			.iter()
 			.filter_map(|x| match &x.kind {
 				ConstraintKind::Require(dest)
@@ -397,10 +757,79 @@ where
 				| ConstraintKind::Bundle(dest) => Some(dest.clone()),
 				_ => None,
 			})
-			.collect()
+			.collect();
+
+		let extensions: Vec<_> = self
+			.constraints
+			.iter()
+			.filter_map(|x| match &x.kind {
+				ConstraintKind::Extend(extended) => {
+					Some((extended.source.get_source()?, extended.clone()))
+				}
+				_ => None,
+			})
+			.collect();
+		order_extended_packages(&mut out, &extensions);
+
+		out
+	}
+}
+
+/// Moves packages so that they come before the packages that extend them
+fn order_extended_packages(packages: &mut Vec<ArcPkgReq>, extensions: &[(ArcPkgReq, ArcPkgReq)]) {
+	// Limit the number of passes in case extensions form a cycle
+	for _ in 0..=packages.len() {
+		let mut changed = false;
+		for (extender, extended) in extensions {
+			let extender_pos = packages.iter().position(|x| x == extender);
+			let extended_pos = packages.iter().position(|x| x == extended);
+			if let (Some(extender_pos), Some(extended_pos)) = (extender_pos, extended_pos) {
+				if extended_pos > extender_pos {
+					let extended = packages.remove(extended_pos);
+					packages.insert(extender_pos, extended);
+					changed = true;
+				}
+			}
+		}
+		if !changed {
+			break;
+		}
 	}
 }
 
+/// Searches for a path through the edges that leads from the end of the path back to its start,
+/// through at least two other packages. If one is found, the path is filled with the cycle.
+fn find_cycle_from<'b>(
+	edges: &[(&'b ArcPkgReq, &'b ArcPkgReq)],
+	path: &mut Vec<&'b ArcPkgReq>,
+) -> bool {
+	let Some(last) = path.last().copied() else {
+		return false;
+	};
+	for (src, dest) in edges {
+		if *src != last {
+			continue;
+		}
+		if *dest == path[0] {
+			if path.len() > 2 {
+				path.push(dest);
+				return true;
+			}
+			continue;
+		}
+		if path.contains(dest) {
+			continue;
+		}
+		path.push(dest);
+		if find_cycle_from(edges, path) {
+			return true;
+		}
+		path.pop();
+	}
+
+	false
+}
+
 /// A requirement for the installation of the packages
 #[derive(Debug)]
 struct Constraint {
@@ -432,3 +861,747 @@ enum Task<'a, E: PackageEvaluator<'a>> {
 fn package_context_error_message(package: &PkgRequest) -> String {
 	format!("In package '{}'", package.debug_sources())
 }
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::sync::Mutex;
+
+	use async_trait::async_trait;
+	use mcvm_shared::output::{Message, NoOp};
+
+	use super::*;
+	use crate::metadata::PackageMetadata;
+	use crate::tests::{TestConfiguredPackage, TestRelationsResult};
+	use crate::{RecommendedPackage, RequiredPackage};
+
+	/// Evaluator that only supports evaluating relations in batches
+	struct BatchingEvaluator {
+		batch_calls: Arc<AtomicUsize>,
+		properties: PackageProperties,
+		metadata: PackageMetadata,
+	}
+
+	#[async_trait]
+	impl<'a> PackageEvaluator<'a> for BatchingEvaluator {
+		type CommonInput = ();
+		type EvalInput<'b> = ();
+		type EvalRelationsResult<'b> = TestRelationsResult;
+		type ConfiguredPackage = TestConfiguredPackage;
+
+		async fn eval_package_relations(
+			&mut self,
+			_: &ArcPkgReq,
+			_: &Self::EvalInput<'a>,
+			_: &Self::CommonInput,
+		) -> anyhow::Result<Self::EvalRelationsResult<'a>> {
+			bail!("Packages should be evaluated in a batch")
+		}
+
+		async fn eval_package_relations_batch(
+			&mut self,
+			pkgs: &[ArcPkgReq],
+			_: &[Self::EvalInput<'a>],
+			_: &Self::CommonInput,
+		) -> Vec<anyhow::Result<Self::EvalRelationsResult<'a>>> {
+			self.batch_calls.fetch_add(1, Ordering::SeqCst);
+			pkgs.iter()
+				.map(|_| Ok(TestRelationsResult::default()))
+				.collect()
+		}
+
+		async fn get_package_properties<'b>(
+			&'b mut self,
+			_: &ArcPkgReq,
+			_: &Self::CommonInput,
+		) -> anyhow::Result<&'b PackageProperties> {
+			Ok(&self.properties)
+		}
+
+		async fn get_package_metadata<'b>(
+			&'b mut self,
+			_: &ArcPkgReq,
+			_: &Self::CommonInput,
+		) -> anyhow::Result<&'b PackageMetadata> {
+			Ok(&self.metadata)
+		}
+	}
+
+	/// Evaluator that gives each package a fixed set of relations
+	#[derive(Default)]
+	struct RelationsEvaluator {
+		relations: HashMap<String, TestRelationsResult>,
+		/// Repositories and the packages in them, in order of priority
+		repos: Vec<(&'static str, Vec<&'static str>)>,
+		/// The packages that have been evaluated, in order
+		evaluated: Arc<Mutex<Vec<String>>>,
+		/// Packages that fail to load
+		missing: Vec<&'static str>,
+		properties: PackageProperties,
+		metadata: PackageMetadata,
+	}
+
+	#[async_trait]
+	impl<'a> PackageEvaluator<'a> for RelationsEvaluator {
+		type CommonInput = ();
+		type EvalInput<'b> = ();
+		type EvalRelationsResult<'b> = TestRelationsResult;
+		type ConfiguredPackage = TestConfiguredPackage;
+
+		async fn eval_package_relations(
+			&mut self,
+			pkg: &ArcPkgReq,
+			_: &Self::EvalInput<'a>,
+			_: &Self::CommonInput,
+		) -> anyhow::Result<Self::EvalRelationsResult<'a>> {
+			self.evaluated.lock().unwrap().push(pkg.id.to_string());
+			Ok(self
+				.relations
+				.get(&pkg.id.to_string())
+				.cloned()
+				.unwrap_or_default())
+		}
+
+		async fn get_package_properties<'b>(
+			&'b mut self,
+			pkg: &ArcPkgReq,
+			_: &Self::CommonInput,
+		) -> anyhow::Result<&'b PackageProperties> {
+			if self.missing.contains(&pkg.id.as_ref()) {
+				bail!("Failed to load package");
+			}
+			Ok(&self.properties)
+		}
+
+		async fn get_package_metadata<'b>(
+			&'b mut self,
+			_: &ArcPkgReq,
+			_: &Self::CommonInput,
+		) -> anyhow::Result<&'b PackageMetadata> {
+			Ok(&self.metadata)
+		}
+
+		async fn get_package_repository(
+			&mut self,
+			pkg: &ArcPkgReq,
+			_: &Self::CommonInput,
+		) -> anyhow::Result<Option<String>> {
+			let repo = self
+				.repos
+				.iter()
+				.find(|(_, packages)| packages.contains(&pkg.id.as_ref()));
+			Ok(repo.map(|(id, _)| id.to_string()))
+		}
+
+		async fn is_package_available(
+			&mut self,
+			pkg: &ArcPkgReq,
+			_: &Self::CommonInput,
+		) -> anyhow::Result<bool> {
+			Ok(self
+				.repos
+				.iter()
+				.any(|(_, packages)| packages.contains(&pkg.id.as_ref())))
+		}
+	}
+
+	fn create_user_packages(ids: &[&str]) -> Vec<TestConfiguredPackage> {
+		ids.iter()
+			.map(|id| {
+				TestConfiguredPackage(Arc::new(PkgRequest::parse(
+					id,
+					PkgRequestSource::UserRequire,
+				)))
+			})
+			.collect()
+	}
+
+	async fn resolve_ids(
+		ids: &[&str],
+		relations: HashMap<String, TestRelationsResult>,
+	) -> anyhow::Result<Vec<String>> {
+		let evaluator = RelationsEvaluator {
+			relations,
+			..Default::default()
+		};
+		let result = resolve(
+			&create_user_packages(ids),
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await?;
+		Ok(result
+			.packages
+			.iter()
+			.map(|x| x.id.to_string())
+			.sorted()
+			.collect())
+	}
+
+	fn extensions(extensions: &[&str]) -> TestRelationsResult {
+		TestRelationsResult {
+			extensions: extensions.iter().map(|x| x.to_string().into()).collect(),
+			..Default::default()
+		}
+	}
+
+	fn compats(compats: &[(&str, &str)]) -> TestRelationsResult {
+		TestRelationsResult {
+			compats: compats
+				.iter()
+				.map(|(package, compat)| (package.to_string().into(), compat.to_string().into()))
+				.collect(),
+			..Default::default()
+		}
+	}
+
+	#[tokio::test]
+	async fn test_compats() {
+		let relations = HashMap::from([("x".to_string(), compats(&[("a", "b")]))]);
+		let packages = resolve_ids(&["a", "x"], relations.clone()).await.unwrap();
+		assert_eq!(packages, vec!["a", "b", "x"]);
+
+		let packages = resolve_ids(&["x"], relations).await.unwrap();
+		assert_eq!(packages, vec!["x"]);
+	}
+
+	#[tokio::test]
+	async fn test_extensions() {
+		let relations = HashMap::from([("addon".to_string(), extensions(&["base"]))]);
+		// The extended package is required even if the user doesn't require it
+		let packages = resolve_ids(&["addon"], relations.clone()).await.unwrap();
+		assert_eq!(packages, vec!["addon", "base"]);
+
+		// The extended package should be installed first
+		let evaluator = RelationsEvaluator {
+			relations: relations.clone(),
+			..Default::default()
+		};
+		let result = resolve(
+			&create_user_packages(&["addon"]),
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.unwrap();
+		let packages: Vec<_> = result.packages.iter().map(|x| x.id.to_string()).collect();
+		assert_eq!(packages, vec!["base", "addon"]);
+
+		let evaluator = RelationsEvaluator {
+			relations,
+			repos: vec![("std", vec!["addon"])],
+			missing: vec!["base"],
+			..Default::default()
+		};
+		let err = resolve(
+			&create_user_packages(&["addon"]),
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.err()
+		.unwrap();
+		assert!(matches!(err, ResolutionError::PackageNotFound(id) if id.as_ref() == "base"));
+	}
+
+	#[tokio::test]
+	async fn test_package_repositories() {
+		let evaluator = RelationsEvaluator {
+			relations: HashMap::from([(
+				"foo".to_string(),
+				TestRelationsResult {
+					deps: vec![vec![RequiredPackage {
+						value: "bar".into(),
+						explicit: false,
+					}]],
+					..Default::default()
+				},
+			)]),
+			repos: vec![("first", vec!["foo"]), ("second", vec!["foo", "bar"])],
+			..Default::default()
+		};
+		let packages = create_user_packages(&["foo", "local"]);
+		let result = resolve(
+			&packages,
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.unwrap();
+
+		let repositories: HashMap<_, _> = result
+			.repositories
+			.iter()
+			.map(|(package, repo)| (package.id.to_string(), repo.as_str()))
+			.collect();
+		assert_eq!(
+			repositories,
+			HashMap::from([("foo".to_string(), "first"), ("bar".to_string(), "second")])
+		);
+	}
+
+	#[tokio::test]
+	async fn test_resolved_content_versions() {
+		let evaluator = RelationsEvaluator {
+			properties: PackageProperties {
+				content_versions: Some(vec!["1.1".into(), "1.0".into()]),
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let packages = create_user_packages(&["foo", "bar@1.0"]);
+		let result = resolve(
+			&packages,
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.unwrap();
+
+		let versions: HashMap<_, _> = result
+			.content_versions
+			.iter()
+			.map(|(package, version)| (package.id.to_string(), version.as_str()))
+			.collect();
+		assert_eq!(
+			versions,
+			HashMap::from([("foo".to_string(), "1.1"), ("bar".to_string(), "1.0")])
+		);
+	}
+
+	#[tokio::test]
+	async fn test_compat_cycle() {
+		// Mutual compats are fine
+		let relations = HashMap::from([("x".to_string(), compats(&[("a", "b"), ("b", "a")]))]);
+		let packages = resolve_ids(&["a", "x"], relations).await.unwrap();
+		assert_eq!(packages, vec!["a", "b", "x"]);
+
+		let relations = HashMap::from([(
+			"x".to_string(),
+			compats(&[("a", "b"), ("b", "c"), ("c", "a")]),
+		)]);
+		let err = resolve_ids(&["a", "x"], relations).await.unwrap_err();
+		assert!(err.to_string().contains("a -> b -> c -> a"));
+	}
+
+	#[tokio::test]
+	async fn test_compat_conflict() {
+		let mut relations = compats(&[("a", "b")]);
+		relations.conflicts = vec!["b".into()];
+		let relations = HashMap::from([("x".to_string(), relations)]);
+		let err = resolve_ids(&["a", "x"], relations).await.unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<ResolutionError>(),
+			Some(ResolutionError::Conflict { package, .. }) if package.as_ref() == "b"
+		));
+	}
+
+	#[tokio::test]
+	async fn test_resolution_errors() {
+		let dependency = TestRelationsResult {
+			deps: vec![vec![RequiredPackage {
+				value: "b".into(),
+				explicit: false,
+			}]],
+			..Default::default()
+		};
+		let evaluator = RelationsEvaluator {
+			relations: HashMap::from([("a".to_string(), dependency)]),
+			repos: vec![("std", vec!["a"])],
+			missing: vec!["b"],
+			..Default::default()
+		};
+		let err = resolve(
+			&create_user_packages(&["a"]),
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.err()
+		.unwrap();
+		assert!(matches!(err, ResolutionError::PackageNotFound(id) if id.as_ref() == "b"));
+
+		let conflict = TestRelationsResult {
+			conflicts: vec!["b".into()],
+			..Default::default()
+		};
+		let evaluator = RelationsEvaluator {
+			relations: HashMap::from([("a".to_string(), conflict)]),
+			..Default::default()
+		};
+		let err = resolve(
+			&create_user_packages(&["a", "b"]),
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.err()
+		.unwrap();
+		assert!(matches!(
+			err,
+			ResolutionError::Conflict { package, refusers }
+				if package.as_ref() == "b" && refusers == vec![PackageID::from("a")]
+		));
+
+		let evaluator = RelationsEvaluator {
+			properties: PackageProperties {
+				content_versions: Some(vec!["1.0".into()]),
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let err = resolve(
+			&create_user_packages(&["a@2.0"]),
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.err()
+		.unwrap();
+		assert!(matches!(
+			err,
+			ResolutionError::UnsatisfiableVersion { package, version }
+				if package.as_ref() == "a" && version == VersionPattern::Single("2.0".into())
+		));
+
+		let evaluator = RelationsEvaluator {
+			relations: HashMap::from([(
+				"x".to_string(),
+				compats(&[("a", "b"), ("b", "c"), ("c", "a")]),
+			)]),
+			..Default::default()
+		};
+		let err = resolve(
+			&create_user_packages(&["a", "x"]),
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.err()
+		.unwrap();
+		assert!(matches!(err, ResolutionError::Cycle(packages) if packages.len() == 4));
+
+		// Other errors can still be used as anyhow errors
+		let explicit_dependency = TestRelationsResult {
+			deps: vec![vec![RequiredPackage {
+				value: "b".into(),
+				explicit: true,
+			}]],
+			..Default::default()
+		};
+		let err: anyhow::Error = resolve_ids(
+			&["a"],
+			HashMap::from([("a".to_string(), explicit_dependency)]),
+		)
+		.await
+		.unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<ResolutionError>(),
+			Some(ResolutionError::Other(..))
+		));
+	}
+
+	/// Output that records all of the progress messages that it gets
+	#[derive(Default)]
+	struct ProgressOutput {
+		progress: Vec<(u32, u32)>,
+	}
+
+	impl MCVMOutput for ProgressOutput {
+		fn display_text(&mut self, _: String, _: MessageLevel) {}
+
+		fn display_message(&mut self, message: Message) {
+			if let MessageContents::Associated(progress, _) = message.contents {
+				if let MessageContents::Progress { current, total } = *progress {
+					self.progress.push((current, total));
+				}
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn test_resolution_progress() {
+		let dependency = |id: &str| TestRelationsResult {
+			deps: vec![vec![RequiredPackage {
+				value: id.into(),
+				explicit: false,
+			}]],
+			..Default::default()
+		};
+		let evaluator = RelationsEvaluator {
+			relations: HashMap::from([
+				("a".to_string(), dependency("c")),
+				("c".to_string(), dependency("d")),
+			]),
+			..Default::default()
+		};
+		let mut o = ProgressOutput::default();
+		let result = resolve(
+			&create_user_packages(&["a", "b"]),
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut o,
+		)
+		.await
+		.unwrap();
+		assert_eq!(result.packages.len(), 4);
+		// The total grows as dependencies are found
+		assert_eq!(o.progress, vec![(1, 3), (2, 3), (3, 4), (4, 4)]);
+	}
+
+	#[tokio::test]
+	async fn test_resolution_limits() {
+		// Create a long chain of packages where each one depends on the next
+		let relations: HashMap<_, _> = (0..20)
+			.map(|i| {
+				let result = TestRelationsResult {
+					deps: vec![vec![RequiredPackage {
+						value: format!("p{}", i + 1).into(),
+						explicit: false,
+					}]],
+					..Default::default()
+				};
+				(format!("p{i}"), result)
+			})
+			.collect();
+		let resolve_chain = |limits: ResolutionLimits| {
+			let evaluator = RelationsEvaluator {
+				relations: relations.clone(),
+				..Default::default()
+			};
+			async move {
+				resolve(
+					&create_user_packages(&["p0"]),
+					evaluator,
+					(),
+					&(),
+					&limits,
+					&mut NoOp,
+				)
+				.await
+			}
+		};
+
+		let result = resolve_chain(ResolutionLimits::default()).await.unwrap();
+		assert_eq!(result.packages.len(), 21);
+
+		let err = resolve_chain(ResolutionLimits {
+			max_depth: 10,
+			..Default::default()
+		})
+		.await
+		.err()
+		.unwrap();
+		let ResolutionError::TooDeep { package, limit, .. } = &err else {
+			panic!("Unexpected error: {err}");
+		};
+		assert_eq!(package.as_ref(), "p11");
+		assert_eq!(*limit, 10);
+		assert!(err.to_string().contains("p0 -> p1 -> p2"));
+
+		let err = resolve_chain(ResolutionLimits {
+			max_packages: 5,
+			..Default::default()
+		})
+		.await
+		.err()
+		.unwrap();
+		assert!(matches!(err, ResolutionError::TooManyPackages(5)));
+	}
+
+	#[tokio::test]
+	async fn test_batch_evaluation() {
+		let batch_calls = Arc::new(AtomicUsize::new(0));
+		let evaluator = BatchingEvaluator {
+			batch_calls: batch_calls.clone(),
+			properties: PackageProperties::default(),
+			metadata: PackageMetadata::default(),
+		};
+		let packages = create_user_packages(&["foo", "bar", "baz"]);
+
+		let result = resolve(
+			&packages,
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.unwrap();
+		assert_eq!(result.packages.len(), 3);
+		assert_eq!(batch_calls.load(Ordering::SeqCst), 1);
+	}
+
+	#[tokio::test]
+	async fn test_request_dedup() {
+		let evaluated = Arc::new(Mutex::new(Vec::new()));
+		let evaluator = RelationsEvaluator {
+			relations: HashMap::from([
+				(
+					"a".to_string(),
+					TestRelationsResult {
+						deps: vec![vec![RequiredPackage {
+							value: "lib".into(),
+							explicit: false,
+						}]],
+						..Default::default()
+					},
+				),
+				(
+					"b".to_string(),
+					TestRelationsResult {
+						recommendations: vec![RecommendedPackage {
+							value: "lib".into(),
+							invert: false,
+						}],
+						..Default::default()
+					},
+				),
+				(
+					"c".to_string(),
+					TestRelationsResult {
+						bundled: vec!["lib".into()],
+						..Default::default()
+					},
+				),
+			]),
+			evaluated: evaluated.clone(),
+			..Default::default()
+		};
+		let packages = create_user_packages(&["a", "b", "c"]);
+		let result = resolve(
+			&packages,
+			evaluator,
+			(),
+			&(),
+			&ResolutionLimits::default(),
+			&mut NoOp,
+		)
+		.await
+		.unwrap();
+
+		let evaluated = evaluated.lock().unwrap();
+		assert_eq!(evaluated.iter().filter(|x| *x == "lib").count(), 1);
+		assert_eq!(
+			result
+				.packages
+				.iter()
+				.filter(|x| x.id.as_ref() == "lib")
+				.count(),
+			1
+		);
+		assert!(result.unfulfilled_recommendations.is_empty());
+
+		let sources: Vec<_> = result.sources["lib"]
+			.iter()
+			.map(|x| match x {
+				PkgRequestSource::Dependency(source) => format!("dependency of {source}"),
+				PkgRequestSource::Recommended(source) => format!("recommended by {source}"),
+				PkgRequestSource::Bundled(source) => format!("bundled by {source}"),
+				_ => "other".into(),
+			})
+			.collect();
+		assert_eq!(
+			sources,
+			vec!["dependency of a", "recommended by b", "bundled by c"]
+		);
+		assert_eq!(result.sources["a"], vec![PkgRequestSource::UserRequire]);
+	}
+
+	fn alternatives(alternatives: &[&str]) -> TestRelationsResult {
+		TestRelationsResult {
+			deps: vec![alternatives
+				.iter()
+				.map(|x| RequiredPackage {
+					value: x.to_string().into(),
+					explicit: false,
+				})
+				.collect()],
+			..Default::default()
+		}
+	}
+
+	#[tokio::test]
+	async fn test_alternative_dependencies() {
+		let resolve_alternatives = |ids: &'static [&'static str], group: &[&str]| {
+			let evaluator = RelationsEvaluator {
+				relations: HashMap::from([("foo".to_string(), alternatives(group))]),
+				repos: vec![("std", vec!["foo", "bar", "baz"])],
+				..Default::default()
+			};
+			async move {
+				let result = resolve(
+					&create_user_packages(ids),
+					evaluator,
+					(),
+					&(),
+					&ResolutionLimits::default(),
+					&mut NoOp,
+				)
+				.await?;
+				Ok::<_, anyhow::Error>(
+					result
+						.packages
+						.iter()
+						.map(|x| x.id.to_string())
+						.collect::<Vec<_>>(),
+				)
+			}
+		};
+
+		// Only one of the alternatives exists
+		let packages = resolve_alternatives(&["foo"], &["missing", "bar"])
+			.await
+			.unwrap();
+		assert_eq!(packages, vec!["foo", "bar"]);
+
+		// The first available alternative is preferred
+		let packages = resolve_alternatives(&["foo"], &["baz", "bar"])
+			.await
+			.unwrap();
+		assert_eq!(packages, vec!["foo", "baz"]);
+
+		// An alternative that is already required satisfies the group
+		let packages = resolve_alternatives(&["bar", "foo"], &["baz", "bar"])
+			.await
+			.unwrap();
+		assert_eq!(packages, vec!["bar", "foo"]);
+
+		let err = resolve_alternatives(&["foo"], &["missing", "other"])
+			.await
+			.unwrap_err();
+		assert!(format!("{err:#}")
+			.contains("alternatives for a dependency are available: missing, other"));
+	}
+}
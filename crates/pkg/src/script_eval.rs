@@ -1,7 +1,7 @@
 use mcvm_parse::conditions::ConditionKind;
 use mcvm_parse::instruction::{InstrKind, Instruction};
 use mcvm_parse::parse::{Block, Parsed};
-use mcvm_parse::routine::INSTALL_ROUTINE;
+use mcvm_parse::routine::{INSTALL_ROUTINE, UNINSTALL_ROUTINE};
 use mcvm_parse::vars::{Value, VariableStore};
 use mcvm_parse::FailReason;
 
@@ -96,29 +96,39 @@ pub struct ScriptEvalConfig {
 
 /// For what reason we are evaluating the script, which determines
 /// what instructions we run
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum EvalReason {
 	/// Installing addons
+	#[default]
 	Install,
 	/// Resolving relations
 	Resolve,
+	/// Running the uninstall routine when the package is removed
+	Uninstall,
 }
 
-/// Evaluate a script package install routine with a script evaluator
+/// Evaluate a script package install or uninstall routine with a script evaluator.
+/// The uninstall routine is optional, so nothing is evaluated if it does not exist
 pub fn eval_script_package<E: ScriptEvaluator>(
 	parsed: &Parsed,
 	e: &mut E,
 	shared: &mut E::Shared<'_>,
 	config: &ScriptEvalConfig,
 ) -> anyhow::Result<()> {
-	let routine_id = parsed
-		.routines
-		.get(INSTALL_ROUTINE)
-		.ok_or(anyhow!("Routine {} does not exist", INSTALL_ROUTINE))?;
+	let routine = match config.reason {
+		EvalReason::Install | EvalReason::Resolve => INSTALL_ROUTINE,
+		EvalReason::Uninstall => UNINSTALL_ROUTINE,
+	};
+	let routine_id = match parsed.routines.get(routine) {
+		Some(routine_id) => routine_id,
+		None if routine == UNINSTALL_ROUTINE => return Ok(()),
+		None => bail!("Routine {routine} does not exist"),
+	};
 	let block = parsed
 		.blocks
 		.get(routine_id)
-		.ok_or(anyhow!("Routine {} does not exist", INSTALL_ROUTINE))?;
+		.ok_or(anyhow!("Routine {routine} does not exist"))?;
 
 	for instr in &block.contents {
 		let result = eval_instr(instr, parsed, e, shared, config)?;
@@ -160,10 +170,12 @@ pub fn eval_instr<E: ScriptEvaluator>(
 ) -> anyhow::Result<EvalResult> {
 	let mut out = EvalResult::new();
 
-	// Used to put a nice anyhow context on all of them
-	let result = {
+	let kind = &instr.kind;
+
+	// Run in a closure so that errors from ? still get the position context
+	let result = (|| {
 		match config.reason {
-			EvalReason::Install | EvalReason::Resolve => match &instr.kind {
+			EvalReason::Install | EvalReason::Resolve | EvalReason::Uninstall => match kind {
 				InstrKind::If {
 					condition,
 					if_block,
@@ -176,7 +188,12 @@ pub fn eval_instr<E: ScriptEvaluator>(
 						// Eval the else block chain
 						for else_block in else_blocks {
 							if let Some(condition) = &else_block.condition {
-								if !e.eval_condition(shared, &condition.kind)? {
+								let result = e
+									.eval_condition(shared, &condition.kind)
+									.with_context(|| {
+										format!("In else if condition at {}", else_block.pos)
+									})?;
+								if !result {
 									continue;
 								}
 							}
@@ -265,7 +282,7 @@ pub fn eval_instr<E: ScriptEvaluator>(
 					e.add_notice(shared, notice)?;
 				}
 				InstrKind::Cmd(command) => {
-					if let EvalReason::Install = config.reason {
+					if let EvalReason::Install | EvalReason::Uninstall = config.reason {
 						let command = get_value_vec(command, e.get_variable_store(shared))?;
 
 						e.add_command(shared, command)?;
@@ -303,7 +320,7 @@ pub fn eval_instr<E: ScriptEvaluator>(
 			},
 		}
 		Ok::<(), anyhow::Error>(())
-	};
+	})();
 
 	result.with_context(|| format!("In {} instruction at {}", instr, instr.pos))?;
 
@@ -355,3 +372,131 @@ pub struct AddonInstructionData {
 	/// The addon's hashes
 	pub hashes: PackageAddonOptionalHashes,
 }
+
+#[cfg(test)]
+mod tests {
+	use mcvm_parse::parse::lex_and_parse;
+	use mcvm_parse::vars::HashMapVariableStore;
+
+	use super::*;
+
+	/// Evaluator that only handles variables and constant conditions
+	struct TestEvaluator;
+
+	impl ScriptEvaluator for TestEvaluator {
+		type Shared<'a> = HashMapVariableStore;
+		type VariableStore = HashMapVariableStore;
+
+		fn get_variable_store<'a>(
+			&self,
+			shared: &'a mut Self::Shared<'_>,
+		) -> &'a mut Self::VariableStore {
+			shared
+		}
+
+		fn eval_condition(
+			&mut self,
+			shared: &mut Self::Shared<'_>,
+			condition: &ConditionKind,
+		) -> anyhow::Result<bool> {
+			match condition {
+				ConditionKind::Value(left, right) => Ok(left.get(shared)? == right.get(shared)?),
+				_ => Ok(true),
+			}
+		}
+
+		fn add_dependency(
+			&mut self,
+			_: &mut Self::Shared<'_>,
+			_: Vec<RequiredPackage>,
+		) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn add_conflict(&mut self, _: &mut Self::Shared<'_>, _: PackageID) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn add_recommendation(
+			&mut self,
+			_: &mut Self::Shared<'_>,
+			_: RecommendedPackage,
+		) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn add_bundled(&mut self, _: &mut Self::Shared<'_>, _: PackageID) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn add_compat(
+			&mut self,
+			_: &mut Self::Shared<'_>,
+			_: (PackageID, PackageID),
+		) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn add_extension(&mut self, _: &mut Self::Shared<'_>, _: PackageID) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn add_notice(&mut self, _: &mut Self::Shared<'_>, _: String) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn add_command(&mut self, _: &mut Self::Shared<'_>, _: Vec<String>) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn add_addon(
+			&mut self,
+			_: &mut Self::Shared<'_>,
+			_: AddonInstructionData,
+		) -> anyhow::Result<()> {
+			Ok(())
+		}
+
+		fn run_custom(&mut self, _: &mut Self::Shared<'_>, _: String) -> anyhow::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn eval_text(text: &str) -> anyhow::Result<()> {
+		eval_text_with_reason(text, EvalReason::Install)
+	}
+
+	fn eval_text_with_reason(text: &str, reason: EvalReason) -> anyhow::Result<()> {
+		let parsed = lex_and_parse(text)?;
+		let config = ScriptEvalConfig { reason };
+		eval_script_package(
+			&parsed,
+			&mut TestEvaluator,
+			&mut HashMapVariableStore::new(),
+			&config,
+		)
+	}
+
+	#[test]
+	fn test_error_position() {
+		let text = "@install {\n\tset x \"foo\";\n\tif value \"a\" \"a\" {\n\t\tset y $undefined;\n\t}\n}";
+		let err = format!("{:?}", eval_text(text).unwrap_err());
+		assert!(err.contains("In if instruction at (3:"));
+		assert!(err.contains("In set instruction at (4:"));
+
+		let text = "@install {\n\tif value \"a\" \"b\" {\n\t\tfinish;\n\t} else if value $undefined \"a\" {\n\t\tfinish;\n\t}\n}";
+		let err = format!("{:?}", eval_text(text).unwrap_err());
+		assert!(err.contains("In else if condition at (4:"));
+	}
+
+	#[test]
+	fn test_uninstall_routine() {
+		let text = "@install {\n\tfinish;\n}\n@uninstall {\n\tfail;\n}";
+		eval_text(text).unwrap();
+		assert!(eval_text_with_reason(text, EvalReason::Uninstall).is_err());
+
+		// Packages do not need an uninstall routine
+		let text = "@install {\n\tfail;\n}";
+		eval_text_with_reason(text, EvalReason::Uninstall).unwrap();
+	}
+}
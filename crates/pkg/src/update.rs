@@ -0,0 +1,208 @@
+use std::collections::{HashMap, HashSet};
+
+use mcvm_shared::pkg::{PackageID, PackageStability};
+use mcvm_shared::versions::VersionPattern;
+
+use crate::properties::PackageProperties;
+
+/// An installed package to check for updates
+#[derive(Debug, Clone)]
+pub struct InstalledPackage {
+	/// The ID of the package
+	pub id: PackageID,
+	/// The content version that is currently installed, if the package has content versions
+	pub content_version: Option<String>,
+	/// The content version that the package is pinned to by the user
+	pub pinned_version: Option<VersionPattern>,
+	/// The stability that the package is configured with
+	pub stability: PackageStability,
+}
+
+/// A package that is available in the repositories
+#[derive(Debug, Clone, Default)]
+pub struct AvailablePackage {
+	/// The properties of the package
+	pub properties: PackageProperties,
+	/// The content versions that can only be installed with the latest stability
+	pub unstable_content_versions: HashSet<String>,
+}
+
+/// An update for an installed package
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageUpdate {
+	/// The ID of the package
+	pub id: PackageID,
+	/// The content version that is currently installed
+	pub installed_version: Option<String>,
+	/// The kind of update
+	pub kind: PackageUpdateKind,
+}
+
+/// Different kinds of updates for a package
+#[derive(Debug, Clone, PartialEq)]
+pub enum PackageUpdateKind {
+	/// A newer content version is available
+	NewVersion(String),
+	/// The package is no longer available from any repository
+	Unavailable,
+}
+
+/// Compares installed packages against the packages that are available in the repositories,
+/// returning the packages that have updates sorted by ID. Only versions that the pin and stability
+/// of each package allow are considered. Packages that are not available are reported as unavailable.
+pub fn get_package_updates(
+	installed: &[InstalledPackage],
+	available: &HashMap<PackageID, AvailablePackage>,
+) -> Vec<PackageUpdate> {
+	let mut out: Vec<_> = installed
+		.iter()
+		.filter_map(|package| {
+			let kind = if let Some(available) = available.get(&package.id) {
+				let newest = get_newest_content_version(available, package)?;
+				PackageUpdateKind::NewVersion(newest.to_string())
+			} else {
+				PackageUpdateKind::Unavailable
+			};
+
+			Some(PackageUpdate {
+				id: package.id.clone(),
+				installed_version: package.content_version.clone(),
+				kind,
+			})
+		})
+		.collect();
+	out.sort_by(|a, b| a.id.cmp(&b.id));
+
+	out
+}
+
+/// Gets the content version that a package is installed with, which is the newest one that the pin allows.
+/// The package's list of content versions goes from newest to oldest.
+pub fn get_installed_content_version<'a>(
+	properties: &'a PackageProperties,
+	pin: Option<&VersionPattern>,
+) -> Option<&'a str> {
+	let available = properties.content_versions.as_ref()?;
+	available
+		.iter()
+		.find(|version| pin.map_or(true, |pin| pin.matches_single(version, available)))
+		.map(String::as_str)
+}
+
+/// Gets the newest content version of a package that is allowed by its pin and stability, if it is newer
+/// than the installed one
+fn get_newest_content_version<'a>(
+	package: &'a AvailablePackage,
+	installed: &InstalledPackage,
+) -> Option<&'a str> {
+	let available = package.properties.content_versions.as_ref()?;
+	let pin = installed.pinned_version.as_ref();
+	let newest = available.iter().find(|version| {
+		pin.map_or(true, |pin| pin.matches_single(version, available))
+			&& (installed.stability == PackageStability::Latest
+				|| !package.unstable_content_versions.contains(*version))
+	});
+	let newest = newest?.as_str();
+	let installed = installed.content_version.as_deref();
+	if installed == Some(newest) {
+		return None;
+	}
+
+	// Don't suggest downgrades if the installed version is newer than the newest allowed one
+	let get_position = |version| available.iter().position(|x| x == version);
+	let installed_position = installed.and_then(get_position);
+	if installed_position.is_some_and(|installed| Some(installed) < get_position(newest)) {
+		return None;
+	}
+
+	Some(newest)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn create_available(content_versions: &[&str], unstable: &[&str]) -> AvailablePackage {
+		AvailablePackage {
+			properties: PackageProperties {
+				content_versions: Some(content_versions.iter().map(|x| x.to_string()).collect()),
+				..Default::default()
+			},
+			unstable_content_versions: unstable.iter().map(|x| x.to_string()).collect(),
+		}
+	}
+
+	#[test]
+	fn test_package_updates() {
+		let installed = [
+			InstalledPackage {
+				id: "outdated".into(),
+				content_version: Some("1.0".into()),
+				pinned_version: None,
+				stability: PackageStability::Stable,
+			},
+			InstalledPackage {
+				id: "pinned".into(),
+				content_version: Some("1.0".into()),
+				pinned_version: Some(VersionPattern::Single("1.0".into())),
+				stability: PackageStability::Stable,
+			},
+			InstalledPackage {
+				id: "current".into(),
+				content_version: Some("2.0".into()),
+				pinned_version: None,
+				stability: PackageStability::Stable,
+			},
+			InstalledPackage {
+				id: "removed".into(),
+				content_version: None,
+				pinned_version: None,
+				stability: PackageStability::Stable,
+			},
+			InstalledPackage {
+				id: "stable".into(),
+				content_version: Some("2.0".into()),
+				pinned_version: None,
+				stability: PackageStability::Stable,
+			},
+			InstalledPackage {
+				id: "unstable".into(),
+				content_version: Some("2.0".into()),
+				pinned_version: None,
+				stability: PackageStability::Latest,
+			},
+		];
+		let available = HashMap::from([
+			("outdated".into(), create_available(&["2.0", "1.0"], &[])),
+			("pinned".into(), create_available(&["2.0", "1.0"], &[])),
+			("current".into(), create_available(&["2.0", "1.0"], &[])),
+			("stable".into(), create_available(&["3.0", "2.0"], &["3.0"])),
+			(
+				"unstable".into(),
+				create_available(&["3.0", "2.0"], &["3.0"]),
+			),
+		]);
+
+		let updates = get_package_updates(&installed, &available);
+		assert_eq!(
+			updates,
+			vec![
+				PackageUpdate {
+					id: "outdated".into(),
+					installed_version: Some("1.0".into()),
+					kind: PackageUpdateKind::NewVersion("2.0".into()),
+				},
+				PackageUpdate {
+					id: "removed".into(),
+					installed_version: None,
+					kind: PackageUpdateKind::Unavailable,
+				},
+				PackageUpdate {
+					id: "unstable".into(),
+					installed_version: Some("2.0".into()),
+					kind: PackageUpdateKind::NewVersion("3.0".into()),
+				},
+			]
+		);
+	}
+}
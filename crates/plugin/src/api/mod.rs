@@ -84,6 +84,11 @@ impl CustomPlugin {
 	hook_interface!(add_versions, "add_versions", AddVersions);
 	hook_interface!(on_instance_setup, "on_instance_setup", OnInstanceSetup);
 	hook_interface!(on_instance_launch, "on_instance_launch", OnInstanceLaunch);
+	hook_interface!(
+		add_classpath_entries,
+		"add_classpath_entries",
+		AddClasspathEntries
+	);
 	hook_interface!(
 		while_instance_launch,
 		"while_instance_launch",
@@ -95,6 +100,21 @@ impl CustomPlugin {
 		"custom_package_instruction",
 		CustomPackageInstruction
 	);
+	hook_interface!(
+		parse_custom_package,
+		"parse_custom_package",
+		ParseCustomPackage
+	);
+	hook_interface!(
+		validate_custom_package,
+		"validate_custom_package",
+		ValidateCustomPackage
+	);
+	hook_interface!(
+		eval_custom_package,
+		"eval_custom_package",
+		EvalCustomPackage
+	);
 	hook_interface!(handle_auth, "handle_auth", HandleAuth);
 	hook_interface!(add_translations, "add_translations", AddTranslations);
 	hook_interface!(
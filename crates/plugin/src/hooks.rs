@@ -8,8 +8,9 @@ use anyhow::{anyhow, bail, Context};
 use mcvm_core::net::minecraft::MinecraftUserProfile;
 use mcvm_core::util::versions::MinecraftVersionDeser;
 use mcvm_core::{net::game_files::version_manifest::VersionEntry, Paths};
-use mcvm_pkg::script_eval::AddonInstructionData;
-use mcvm_pkg::{RecommendedPackage, RequiredPackage};
+use mcvm_pkg::script_eval::{AddonInstructionData, EvalReason};
+use mcvm_pkg::{CustomPackageContents, RecommendedPackage, RequiredPackage};
+use mcvm_shared::addon::AddonKind;
 use mcvm_shared::lang::translate::LanguageMap;
 use mcvm_shared::modifications::{ClientType, ServerType};
 use mcvm_shared::pkg::PackageID;
@@ -381,6 +382,14 @@ def_hook!(
 	(),
 );
 
+def_hook!(
+	AddClasspathEntries,
+	"add_classpath_entries",
+	"Hook for adding extra entries to the classpath of an instance before it is launched",
+	InstanceLaunchArg,
+	Vec<String>,
+);
+
 def_hook!(
 	WhileInstanceLaunch,
 	"while_instance_launch",
@@ -397,7 +406,7 @@ def_hook!(
 	(),
 );
 
-/// Argument for the OnInstanceLaunch and WhileInstanceLaunch hooks
+/// Argument for the OnInstanceLaunch, AddClasspathEntries, and WhileInstanceLaunch hooks
 #[derive(Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct InstanceLaunchArg {
@@ -457,6 +466,108 @@ pub struct CustomPackageInstructionResult {
 	pub notices: Vec<String>,
 }
 
+def_hook!(
+	ParseCustomPackage,
+	"parse_custom_package",
+	"Hook for parsing packages with custom content types",
+	CustomPackageArg,
+	ParseCustomPackageResult,
+);
+
+def_hook!(
+	ValidateCustomPackage,
+	"validate_custom_package",
+	"Hook for validating packages with custom content types",
+	CustomPackageArg,
+	ValidateCustomPackageResult,
+);
+
+/// Argument for the ParseCustomPackage and ValidateCustomPackage hooks
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct CustomPackageArg {
+	/// The custom content type of the package
+	pub content_type: String,
+	/// The contents of the package file
+	pub contents: String,
+}
+
+/// Result from the ParseCustomPackage hook
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ParseCustomPackageResult {
+	/// Whether the content type was handled by this plugin
+	pub handled: bool,
+	/// The parsed contents of the package
+	pub contents: CustomPackageContents,
+}
+
+/// Result from the ValidateCustomPackage hook
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ValidateCustomPackageResult {
+	/// Whether the content type was handled by this plugin
+	pub handled: bool,
+	/// Problems with the package. The package is valid if this is empty.
+	pub errors: Vec<String>,
+}
+
+def_hook!(
+	EvalCustomPackage,
+	"eval_custom_package",
+	"Hook for evaluating packages with custom content types",
+	EvalCustomPackageArg,
+	CustomPackageInstructionResult,
+);
+
+/// Argument for the EvalCustomPackage hook
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct EvalCustomPackageArg {
+	/// The ID of the package
+	pub pkg_id: String,
+	/// The custom content type of the package
+	pub content_type: String,
+	/// The contents of the package file
+	pub contents: String,
+	/// The reason that the package is being evaluated
+	pub reason: EvalReason,
+	/// The Minecraft version
+	pub minecraft_version: String,
+	/// The side of the instance
+	pub side: Option<Side>,
+	/// The features enabled for the package
+	pub features: Vec<String>,
+	/// The content version of the package to install
+	pub content_version: Option<String>,
+}
+
+def_hook!(
+	ProcessDownloadedAddon,
+	"process_downloaded_addon",
+	"Hook for modifying addon files in place after they are downloaded and before they are installed",
+	ProcessDownloadedAddonArg,
+	(),
+);
+
+/// Argument for the ProcessDownloadedAddon hook
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ProcessDownloadedAddonArg {
+	/// The path to the downloaded addon file
+	pub path: String,
+	/// The ID of the package that the addon is from
+	pub pkg_id: String,
+	/// The ID of the addon
+	pub addon_id: String,
+	/// The kind of the addon
+	pub kind: Option<AddonKind>,
+	/// The file name of the addon
+	pub file_name: String,
+	/// The version of the addon
+	pub version: Option<String>,
+}
+
 def_hook!(
 	HandleAuth,
 	"handle_auth",
@@ -47,6 +47,11 @@ impl CorePluginManager {
 		self.mcvm_version = Some(version);
 	}
 
+	/// Get the MCVM version of the manager
+	pub fn get_mcvm_version(&self) -> Option<&'static str> {
+		self.mcvm_version
+	}
+
 	/// Add a plugin to the manager
 	pub fn add_plugin(
 		&mut self,
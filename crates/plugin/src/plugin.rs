@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -123,6 +123,12 @@ pub struct PluginManifest {
 	pub protocol_version: Option<u16>,
 	/// Whether to disable base64 encoding in the protocol
 	pub raw_transfer: bool,
+	/// Hooks that have to succeed. Some hooks only produce a warning when they fail,
+	/// and listing them here makes their failures errors instead
+	pub required_hooks: HashSet<String>,
+	/// Custom package content types that this plugin handles.
+	/// Packages can only use custom content types that are registered by a plugin
+	pub content_types: HashSet<String>,
 }
 
 impl PluginManifest {
@@ -35,14 +35,19 @@ define_translations! {
 	Error, "Header for an error", "Error";
 	StartResolvingDependencies, "When starting to resolve package dependencies", "Resolving package dependencies";
 	FinishResolvingDependencies, "When finishing resolving package dependencies", "Dependencies resolved";
+	ResolvedPackage, "When a package is evaluated while resolving dependencies", "Resolved package %pkg";
 	StartAcquiringAddons, "When starting to acquire addons", "Acquiring addons";
 	FinishAcquiringAddons, "When finishing acquiring addons", "Addons acquired";
 	StartInstallingPackages, "When starting to install packages", "Installing packages";
 	FinishInstallingPackage, "When finishing installing a single package", "Package installed";
+	UninstallRoutineFailed, "When the uninstall routine of a removed package could not be run", "Failed to run the uninstall routine of package %pkg: %error";
 	PackageOutOfDate, "When a package is out of date", "Package %pkg has been flagged as out of date";
 	PackageDeprecated, "When a package is deprecated", "Package %pkg has been flagged as deprecated";
+	PackageArchived, "When a package is archived", "Package %pkg has been archived and will no longer receive updates";
 	PackageInsecure, "When a package is insecure", "Package %pkg has been flagged as insecure";
 	PackageMalicious, "When a package is malicious", "Package %pkg has been flagged as malicious";
+	ResolveLockMismatch, "When the packages of an instance resolve differently than the last time", "Packages for instance %inst changed since the last update: %error";
+	DuplicateMod, "When the same mod is installed more than once on an instance", "Mod '%mod' is installed more than once by packages %pkgs, which can cause crashes";
 	PackageSupportHeader, "Header for packages to consider supporting", "Packages to consider supporting";
 	StartUpdatingPackages, "When starting to update packages on a profile", "Updating packages";
 	FinishUpdatingPackages, "When finishing updating packages on a profile", "All packages installed";
@@ -55,9 +60,13 @@ define_translations! {
 	StartUpdatingInstance, "When starting to update an instance", "Updating instance %inst";
 	PreparingLaunch, "When preparing to launch the game", "Preparing to launch";
 	Launch, "When launching the game", "Launching!";
+	DemoTimeLimitWarning, "Warning when launching the game with a demo user", "Demo users can only play for a limited time. Log in with a Microsoft account to play without a limit";
+	SkinVariantUnavailable, "Warning when the user does not own a skin with the preferred variant", "You do not have a skin with the %variant model, so your skin was not changed";
+	SkinChangeFailed, "Warning when the active skin could not be changed", "Failed to change skin: %error";
 	CoreRepoName, "Name of the core repo", "Core";
 	CoreRepoDescription, "Description of the core repo", "The built-in set of packages";
 	RepoVersionWarning, "Warning when a remote repo version is too high", "Minimum MCVM version for repository %repo is higher than current installation";
+	RepoQueryFailed, "When querying a repository for a package fails", "Failed to query repository %repo: %error";
 	OverwriteAddonFilePrompt, "Prompt when an addon file would be overwriten", "The existing file '%file' has the same path as an addon. Overwrite it?";
 	CustomInstructionsWarning, "When a package uses unrecognized custom instructions", "Package uses custom instructions that MCVM does not recognize";
 	Redownloading, "When something is being redownloaded", "Redownloading";
@@ -78,6 +87,8 @@ define_translations! {
 	StartDownloadingVersionManifest, "When starting to download the version manifest", "Downloading version manifest";
 	StartDownloadingGameJar, "When starting to download the game jar", "Downloading %side jar";
 	FinishDownloadingGameJar, "When finishing downloading the game jar", "%side jar downloaded";
+	StartUninstallingVersion, "When starting to uninstall a version", "Uninstalling version %version";
+	FinishUninstallingVersion, "When finishing uninstalling a version", "Version uninstalled";
 	StartCheckingForJavaUpdates, "When starting to check for Java updates", "Checking for Java updates";
 	FinishCheckingForJavaUpdates, "When finishing checking for Java updates", "Java updated";
 	FinishJavaInstallation, "When finishing installing Java", "Java installation finished";
@@ -97,12 +108,16 @@ define_translations! {
 	AuthenticationSuccessful, "When authentication succeeds", "Authentication successful";
 	StartInstallingPlugin, "When starting to install a plugin", "Installing plugin";
 	FinishInstallingPlugin, "When finishing installing a plugin", "Plugin installed";
+	AddonHookFailed, "When a plugin fails to process a downloaded addon", "Plugin '%plugin' failed to process addon '%addon': %error";
 	StartExporting, "When starting to export an instance", "Exporting instance '%instance' in format '%format' using plugin '%plugin'";
 	ExportPluginNoResult, "When the plugin used for instance export doesn't return anything", "Export plugin did not return a result";
 	FinishExporting, "When finishing exporting an instance", "Export finished";
 	StartImporting, "When starting to import an instance", "Importing instance '%instance' in format '%format' using plugin '%plugin'";
 	ImportPluginNoResult, "When the plugin used for instance import doesn't return anything", "Import plugin did not return a result";
 	FinishImporting, "When finishing importing an instance", "Import finished";
+	ModpackItemSkipped, "When part of a modpack can't be imported", "'%item' from the modpack is not supported and was skipped";
+	ModpackOptionalSkipped, "When an optional mod in a modpack isn't imported", "'%item' from the modpack is optional and was skipped";
+	ModpackManualDownload, "When a mod in a modpack has to be downloaded manually", "'%item' from the modpack can't be downloaded automatically and has to be downloaded manually";
 }
 
 /// Replaces placeholders in a translated key
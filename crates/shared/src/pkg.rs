@@ -1,3 +1,4 @@
+use anyhow::bail;
 #[cfg(feature = "schema")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -34,6 +35,8 @@ pub enum PkgRequestSource {
 	Dependency(ArcPkgReq),
 	/// This package was refused by another package
 	Refused(ArcPkgReq),
+	/// This package was recommended by another package
+	Recommended(ArcPkgReq),
 	/// This package was requested by some automatic system
 	Repository,
 }
@@ -54,7 +57,9 @@ impl PkgRequestSource {
 	/// Gets the source package of this package, if any
 	pub fn get_source(&self) -> Option<ArcPkgReq> {
 		match self {
-			Self::Dependency(source) | Self::Bundled(source) => Some(source.clone()),
+			Self::Dependency(source) | Self::Bundled(source) | Self::Recommended(source) => {
+				Some(source.clone())
+			}
 			_ => None,
 		}
 	}
@@ -71,8 +76,9 @@ impl PkgRequestSource {
 			Self::UserRequire => 0,
 			Self::Bundled(..) => 1,
 			Self::Dependency(..) => 2,
-			Self::Refused(..) => 3,
-			Self::Repository => 4,
+			Self::Recommended(..) => 3,
+			Self::Refused(..) => 4,
+			Self::Repository => 5,
 		}
 	}
 }
@@ -115,6 +121,16 @@ impl PkgRequest {
 		}
 	}
 
+	/// Parse a request from user input, normalizing and validating the package ID
+	pub fn parse_validated(
+		string: impl AsRef<str>,
+		source: PkgRequestSource,
+	) -> anyhow::Result<Self> {
+		let mut req = Self::parse(string, source);
+		req.id = parse_package_id(&req.id)?;
+		Ok(req)
+	}
+
 	/// Create a dependency list for debugging
 	pub fn debug_sources(&self) -> String {
 		self.debug_sources_inner(String::new())
@@ -133,6 +149,9 @@ impl PkgRequest {
 			PkgRequestSource::Bundled(bundler) => {
 				format!("{} => {}", bundler.debug_sources_inner(list), self.id)
 			}
+			PkgRequestSource::Recommended(recommender) => {
+				format!("{} ~> {}", recommender.debug_sources_inner(list), self.id)
+			}
 			PkgRequestSource::Repository => format!("Repository -> {}{list}", self.id),
 		}
 	}
@@ -189,7 +208,7 @@ pub const MAX_PACKAGE_ID_LENGTH: usize = 32;
 
 /// Checks if a package identifier is valid
 pub fn is_valid_package_id(id: &str) -> bool {
-	if !is_valid_identifier(id) {
+	if id.is_empty() || !is_valid_identifier(id) {
 		return false;
 	}
 
@@ -209,6 +228,22 @@ pub fn is_valid_package_id(id: &str) -> bool {
 	true
 }
 
+/// Normalizes a package identifier from user input by trimming it and making it lowercase.
+/// This does not check that the identifier is valid.
+pub fn normalize_package_id(id: &str) -> String {
+	id.trim().to_ascii_lowercase()
+}
+
+/// Normalizes a package identifier from user input and checks that it is valid
+pub fn parse_package_id(id: &str) -> anyhow::Result<PackageID> {
+	let normalized = normalize_package_id(id);
+	if !is_valid_package_id(&normalized) {
+		bail!("Invalid package ID '{id}'");
+	}
+
+	Ok(normalized.into())
+}
+
 /// Hashes used for package addons
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
@@ -247,6 +282,29 @@ mod tests {
 		));
 	}
 
+	#[test]
+	fn test_package_id_parsing() {
+		assert_eq!(parse_package_id("sodium").unwrap().as_ref(), "sodium");
+		assert_eq!(
+			parse_package_id("fabric-api").unwrap().as_ref(),
+			"fabric-api"
+		);
+		assert_eq!(parse_package_id("MyMod").unwrap().as_ref(), "mymod");
+		assert_eq!(
+			parse_package_id(" Fabric-API ").unwrap().as_ref(),
+			"fabric-api"
+		);
+		assert!(parse_package_id("my_mod").is_err());
+		assert!(parse_package_id("my mod").is_err());
+		assert!(parse_package_id("my/mod").is_err());
+		assert!(parse_package_id("").is_err());
+
+		let req = PkgRequest::parse_validated("MyMod@1.2", PkgRequestSource::UserRequire).unwrap();
+		assert_eq!(req.id.as_ref(), "mymod");
+		assert_eq!(req.content_version, VersionPattern::Single("1.2".into()));
+		assert!(PkgRequest::parse_validated("my_mod@1.2", PkgRequestSource::UserRequire).is_err());
+	}
+
 	#[test]
 	fn test_request_source_debug() {
 		let req = PkgRequest::parse(
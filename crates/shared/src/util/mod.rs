@@ -2,6 +2,7 @@
 pub mod print;
 
 use std::process::{Command, Stdio};
+use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use cfg_match::cfg_match;
@@ -73,6 +74,61 @@ pub fn preferred_archive_extension() -> String {
 	format!(".{PREFERRED_ARCHIVE}")
 }
 
+/// A type of archive file that things like Java installations can be downloaded as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveType {
+	/// A gzipped tarball
+	TarGz,
+	/// A zip file
+	Zip,
+}
+
+impl ArchiveType {
+	/// Gets the preferred archive type for the current platform
+	pub fn preferred() -> Self {
+		Self::from_extension(PREFERRED_ARCHIVE).unwrap_or(Self::Zip)
+	}
+
+	/// Parses an archive type from its file extension, without the leading dot
+	pub fn from_extension(extension: &str) -> Option<Self> {
+		match extension {
+			"tar.gz" | "tgz" => Some(Self::TarGz),
+			"zip" => Some(Self::Zip),
+			_ => None,
+		}
+	}
+
+	/// Gets the archive type of a file name or URL from its extension
+	pub fn from_file_name(name: &str) -> Option<Self> {
+		if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+			Some(Self::TarGz)
+		} else if name.ends_with(".zip") {
+			Some(Self::Zip)
+		} else {
+			None
+		}
+	}
+
+	/// Gets the file extension of this archive type, without the leading dot
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::TarGz => "tar.gz",
+			Self::Zip => "zip",
+		}
+	}
+
+	/// Gets the file extension of this archive type with a leading dot
+	pub fn extension(&self) -> String {
+		format!(".{}", self.as_str())
+	}
+}
+
+impl Default for ArchiveType {
+	fn default() -> Self {
+		Self::preferred()
+	}
+}
+
 cfg_match! {
 	target_pointer_width = "64" => {
 		/// String representing the current pointer width
@@ -197,6 +253,102 @@ pub fn open_link(link: &str) -> anyhow::Result<()> {
 	Ok(())
 }
 
+// Checking whether a process is alive
+cfg_match! {
+	target_os = "linux" => {
+		/// Checks whether a process with the given PID is currently alive on the system
+		pub fn is_process_alive(pid: u32) -> bool {
+			std::path::Path::new("/proc").join(pid.to_string()).exists()
+		}
+	}
+	target_os = "windows" => {
+		/// Checks whether a process with the given PID is currently alive on the system
+		pub fn is_process_alive(pid: u32) -> bool {
+			let Ok(output) = Command::new("tasklist")
+				.args(["/FI", &format!("PID eq {pid}"), "/NH"])
+				.stderr(Stdio::null())
+				.output()
+			else {
+				return false;
+			};
+			String::from_utf8_lossy(&output.stdout).contains(&pid.to_string())
+		}
+	}
+	_ => {
+		/// Checks whether a process with the given PID is currently alive on the system
+		pub fn is_process_alive(pid: u32) -> bool {
+			Command::new("kill")
+				.args(["-0", &pid.to_string()])
+				.stdout(Stdio::null())
+				.stderr(Stdio::null())
+				.status()
+				.is_ok_and(|x| x.success())
+		}
+	}
+}
+
+cfg_match! {
+	target_os = "linux" => {
+		/// Reads the total amount of physical memory on the system in bytes from the OS
+		fn detect_system_memory() -> Option<u64> {
+			let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+			let line = meminfo.lines().find(|x| x.starts_with("MemTotal:"))?;
+			let kb: u64 = line
+				.trim_start_matches("MemTotal:")
+				.trim()
+				.trim_end_matches("kB")
+				.trim()
+				.parse()
+				.ok()?;
+			Some(kb * 1024)
+		}
+	}
+	target_os = "windows" => {
+		/// Reads the total amount of physical memory on the system in bytes from the OS
+		fn detect_system_memory() -> Option<u64> {
+			let output = Command::new("powershell")
+				.args([
+					"-NoProfile",
+					"-Command",
+					"(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory",
+				])
+				.stderr(Stdio::null())
+				.output()
+				.ok()?;
+			String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+		}
+	}
+	target_os = "macos" => {
+		/// Reads the total amount of physical memory on the system in bytes from the OS
+		fn detect_system_memory() -> Option<u64> {
+			let output = Command::new("sysctl")
+				.args(["-n", "hw.memsize"])
+				.stderr(Stdio::null())
+				.output()
+				.ok()?;
+			String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+		}
+	}
+	_ => {
+		/// Reads the total amount of physical memory on the system in bytes from the OS
+		fn detect_system_memory() -> Option<u64> {
+			let output = Command::new("sysctl")
+				.args(["-n", "hw.physmem"])
+				.stderr(Stdio::null())
+				.output()
+				.ok()?;
+			String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+		}
+	}
+}
+
+/// Gets the total amount of physical memory on the system in bytes, if it can be detected.
+/// The result is only detected once and cached for the rest of the program
+pub fn get_system_memory() -> Option<u64> {
+	static SYSTEM_MEMORY: OnceLock<Option<u64>> = OnceLock::new();
+	*SYSTEM_MEMORY.get_or_init(detect_system_memory)
+}
+
 #[cfg(feature = "schema")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -233,7 +385,8 @@ pub fn is_valid_identifier(id: &str) -> bool {
 }
 
 /// Utility enum for deserialization that lets you do a list that can be one item
-/// without the braces
+/// without the braces. A list with exactly one item is serialized the same way as a single item,
+/// and the two are considered equal, so values round-trip as the same set of items
 #[derive(Deserialize, Debug, Clone, Eq)]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(untagged)]
@@ -290,6 +443,22 @@ impl<T> DeserListOrSingle<T> {
 			}
 		}
 	}
+
+	/// Get the contained items as a slice
+	pub fn as_slice(&self) -> &[T] {
+		match self {
+			Self::Single(val) => std::slice::from_ref(val),
+			Self::List(list) => list,
+		}
+	}
+
+	/// Converts this DeserListOrSingle into a Vec of its items
+	pub fn into_vec(self) -> Vec<T> {
+		match self {
+			Self::Single(val) => vec![val],
+			Self::List(list) => list,
+		}
+	}
 }
 
 impl<T: Clone> DeserListOrSingle<T> {
@@ -406,6 +575,24 @@ mod tests {
 		assert!(!is_valid_identifier("hello world"));
 	}
 
+	#[test]
+	fn test_archive_type() {
+		assert_eq!(ArchiveType::preferred().as_str(), PREFERRED_ARCHIVE);
+		assert_eq!(
+			ArchiveType::preferred().extension(),
+			preferred_archive_extension()
+		);
+		assert_eq!(
+			ArchiveType::from_file_name("https://example.com/java.tar.gz"),
+			Some(ArchiveType::TarGz)
+		);
+		assert_eq!(
+			ArchiveType::from_file_name("java.zip"),
+			Some(ArchiveType::Zip)
+		);
+		assert_eq!(ArchiveType::from_file_name("java.jar"), None);
+	}
+
 	#[test]
 	fn test_deser_list_or_single_iter() {
 		let item = DeserListOrSingle::Single(7);
@@ -417,4 +604,48 @@ mod tests {
 		assert_eq!(iter.next(), Some(&2));
 		assert_eq!(iter.next(), Some(&3));
 	}
+
+	#[test]
+	fn test_deser_list_or_single_round_trip() {
+		let round_trip = |json: &str| {
+			let value: DeserListOrSingle<String> = serde_json::from_str(json).unwrap();
+			let serialized = serde_json::to_string(&value).unwrap();
+			let deserialized: DeserListOrSingle<String> =
+				serde_json::from_str(&serialized).unwrap();
+			assert_eq!(deserialized, value);
+			(value, serialized)
+		};
+
+		let (value, serialized) = round_trip(r#""foo""#);
+		assert!(matches!(value, DeserListOrSingle::Single(..)));
+		assert_eq!(serialized, r#""foo""#);
+
+		// Lists with one item are written the same way as single items
+		let (value, serialized) = round_trip(r#"["foo"]"#);
+		assert_eq!(value, DeserListOrSingle::Single("foo".into()));
+		assert_eq!(serialized, r#""foo""#);
+
+		let (value, serialized) = round_trip("[]");
+		assert!(value.is_empty());
+		assert_eq!(serialized, "[]");
+
+		let (value, serialized) = round_trip(r#"["foo","bar"]"#);
+		assert_eq!(value.as_slice(), ["foo", "bar"]);
+		assert_eq!(serialized, r#"["foo","bar"]"#);
+	}
+
+	#[test]
+	fn test_deser_list_or_single_conversion() {
+		let single = DeserListOrSingle::Single(1);
+		assert_eq!(single.as_slice(), [1]);
+		assert_eq!(single.into_vec(), vec![1]);
+
+		let empty = DeserListOrSingle::<i32>::List(Vec::new());
+		assert!(empty.as_slice().is_empty());
+		assert!(empty.into_vec().is_empty());
+
+		let list = DeserListOrSingle::List(vec![1, 2, 3]);
+		assert_eq!(list.as_slice(), [1, 2, 3]);
+		assert_eq!(list.into_vec(), vec![1, 2, 3]);
+	}
 }
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
 
 #[cfg(feature = "schema")]
@@ -56,6 +57,35 @@ impl VersionPattern {
 		}
 	}
 
+	/// Resolves this pattern to the concrete versions it matches in a list of versions.
+	/// This is the same as `get_matches`, and is useful for storing a snapshot of a resolution
+	pub fn resolve_to_list(&self, versions: &[String]) -> Vec<String> {
+		self.get_matches(versions)
+	}
+
+	/// Creates the narrowest pattern that matches exactly the resolved versions when used with
+	/// the same list of versions. Returns None if there are no resolved versions, or if they are not
+	/// a contiguous run of versions from the list
+	pub fn from_resolved_list(resolved: &[String], versions: &[String]) -> Option<Self> {
+		let mut positions = resolved
+			.iter()
+			.map(|x| versions.iter().position(|y| y == x))
+			.collect::<Option<Vec<_>>>()?;
+		positions.sort();
+		positions.dedup();
+
+		let (first, last) = (*positions.first()?, *positions.last()?);
+		if last - first + 1 != positions.len() {
+			return None;
+		}
+
+		if first == last {
+			Some(Self::Single(versions[first].clone()))
+		} else {
+			Some(Self::Range(versions[first].clone(), versions[last].clone()))
+		}
+	}
+
 	/// Finds the newest match in a list of versions
 	pub fn get_match(&self, versions: &[String]) -> Option<String> {
 		self.get_matches(versions).last().cloned()
@@ -118,6 +148,23 @@ impl VersionPattern {
 		}
 	}
 
+	/// Compares this pattern to a single version that does not have a list of versions
+	/// to compare against, such as a modloader version. Versions are ordered by comparing
+	/// their dot-separated components
+	pub fn matches_by_components(&self, version: &str) -> bool {
+		match self {
+			Self::Single(vers) | Self::Latest(Some(vers)) => version == vers,
+			Self::Latest(None) => false,
+			Self::Before(vers) => compare_version_components(version, vers).is_le(),
+			Self::After(vers) => compare_version_components(version, vers).is_ge(),
+			Self::Range(start, end) => {
+				compare_version_components(version, start).is_ge()
+					&& compare_version_components(version, end).is_le()
+			}
+			Self::Any => true,
+		}
+	}
+
 	/// Compares this pattern to a version supplied in a VersionInfo
 	pub fn matches_info(&self, version_info: &VersionInfo) -> bool {
 		self.matches_single(&version_info.version, &version_info.versions)
@@ -237,6 +284,61 @@ pub struct VersionInfo {
 	pub versions: Vec<String>,
 }
 
+/// Compares two versions by their components, which are separated by dots, dashes, or pluses.
+/// Numeric components are compared as numbers and missing components count as zero
+fn compare_version_components(left: &str, right: &str) -> Ordering {
+	let left: Vec<_> = left.split(['.', '-', '+']).collect();
+	let right: Vec<_> = right.split(['.', '-', '+']).collect();
+	for i in 0..std::cmp::max(left.len(), right.len()) {
+		let left = left.get(i).copied().unwrap_or("0");
+		let right = right.get(i).copied().unwrap_or("0");
+		let ordering = match (left.parse::<u64>(), right.parse::<u64>()) {
+			(Ok(left), Ok(right)) => left.cmp(&right),
+			_ => left.cmp(right),
+		};
+		if ordering.is_ne() {
+			return ordering;
+		}
+	}
+
+	Ordering::Equal
+}
+
+/// Finds the candidate that is closest to a target version, for suggesting a version
+/// when one is mistyped. Candidates that are too different from the target are not returned
+pub fn closest_match(target: &str, candidates: &[String]) -> Option<String> {
+	let threshold = std::cmp::max(2, target.chars().count() / 3);
+	let mut closest: Option<(&String, usize)> = None;
+	for candidate in candidates {
+		let distance = edit_distance(target, candidate);
+		if distance > threshold {
+			continue;
+		}
+		if closest.is_none() || closest.is_some_and(|(_, closest)| distance < closest) {
+			closest = Some((candidate, distance));
+		}
+	}
+
+	closest.map(|(candidate, _)| candidate.clone())
+}
+
+/// Computes the Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut previous: Vec<usize> = (0..=b.len()).collect();
+	let mut current = vec![0; b.len() + 1];
+	for (i, a_char) in a.chars().enumerate() {
+		current[0] = i + 1;
+		for (j, b_char) in b.iter().enumerate() {
+			let substitution = previous[j] + usize::from(a_char != *b_char);
+			current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+		}
+		std::mem::swap(&mut previous, &mut current);
+	}
+
+	previous[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -289,6 +391,43 @@ mod tests {
 		assert!(VersionPattern::Latest(None).matches_single("1.19.3", &versions));
 	}
 
+	#[test]
+	fn test_version_pattern_resolved_list() {
+		let versions: Vec<String> = ["1.16.5", "1.17", "1.18", "1.19.3", "1.20"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+
+		let pattern = VersionPattern::Range("1.17".into(), "1.19.3".into());
+		let resolved = pattern.resolve_to_list(&versions);
+		assert_eq!(resolved, vec!["1.17", "1.18", "1.19.3"]);
+		assert_eq!(
+			VersionPattern::from_resolved_list(&resolved, &versions),
+			Some(pattern)
+		);
+
+		// Other patterns are narrowed to a range of what they matched
+		let resolved = VersionPattern::After("1.19.3".into()).resolve_to_list(&versions);
+		assert_eq!(
+			VersionPattern::from_resolved_list(&resolved, &versions),
+			Some(VersionPattern::Range("1.19.3".into(), "1.20".into()))
+		);
+		assert_eq!(
+			VersionPattern::from_resolved_list(&["1.18".into()], &versions),
+			Some(VersionPattern::Single("1.18".into()))
+		);
+
+		assert_eq!(VersionPattern::from_resolved_list(&[], &versions), None);
+		assert_eq!(
+			VersionPattern::from_resolved_list(&["1.17".into(), "1.19.3".into()], &versions),
+			None
+		);
+		assert_eq!(
+			VersionPattern::from_resolved_list(&["1.12".into()], &versions),
+			None
+		);
+	}
+
 	#[test]
 	fn test_version_pattern_parse() {
 		assert_eq!(
@@ -331,4 +470,44 @@ mod tests {
 		assert!(!VersionPattern::validate("f*o"));
 		assert!(!VersionPattern::validate("f..o"));
 	}
+
+	#[test]
+	fn test_version_pattern_components() {
+		assert!(VersionPattern::from("0.15+").matches_by_components("0.15.7"));
+		assert!(VersionPattern::from("0.15+").matches_by_components("0.15"));
+		assert!(!VersionPattern::from("0.15+").matches_by_components("0.14.25"));
+		assert!(VersionPattern::from("0.14.9-").matches_by_components("0.14.8"));
+		assert!(!VersionPattern::from("0.14.9-").matches_by_components("0.14.10"));
+		assert!(VersionPattern::from("0.14..0.15.2").matches_by_components("0.14.21"));
+		assert!(!VersionPattern::from("0.14..0.15.2").matches_by_components("0.15.3"));
+		assert!(VersionPattern::from("0.15.7").matches_by_components("0.15.7"));
+		assert!(VersionPattern::from("*").matches_by_components("1.0"));
+		assert!(!VersionPattern::from("latest").matches_by_components("1.0"));
+	}
+
+	#[test]
+	fn test_edit_distance() {
+		assert_eq!(edit_distance("", ""), 0);
+		assert_eq!(edit_distance("1.20.1", "1.20.1"), 0);
+		assert_eq!(edit_distance("1.20,1", "1.20.1"), 1);
+		assert_eq!(edit_distance("1.20", "1.20.1"), 2);
+		assert_eq!(edit_distance("kitten", "sitting"), 3);
+	}
+
+	#[test]
+	fn test_closest_match() {
+		let versions = vec![
+			"1.19.4".to_string(),
+			"1.20".to_string(),
+			"1.20.1".to_string(),
+			"23w31a".to_string(),
+		];
+
+		assert_eq!(closest_match("1.20,1", &versions), Some("1.20.1".into()));
+		assert_eq!(closest_match("1.19.4", &versions), Some("1.19.4".into()));
+		assert_eq!(closest_match("23w31b", &versions), Some("23w31a".into()));
+		assert_eq!(closest_match("1.8.9", &versions), None);
+		assert_eq!(closest_match("foobarbaz", &versions), None);
+		assert_eq!(closest_match("1.20.1", &[]), None);
+	}
 }
@@ -65,6 +65,7 @@ async fn get_launched_game(
 	let plugins = config.plugins.clone();
 	let instance_id = InstanceID::from(instance_id);
 	o.set_instance(instance_id.clone());
+	o.set_censor_secrets(config.prefs.censor_secrets);
 
 	let launch_task = {
 		let instance_id = instance_id.clone();
@@ -78,6 +79,9 @@ async fn get_launched_game(
 			let settings = LaunchSettings {
 				ms_client_id: crate::get_ms_client_id(),
 				offline_auth: offline,
+				offline: false,
+				censor_secrets: config.prefs.censor_secrets,
+				bandwidth_limit: config.prefs.bandwidth_limit,
 			};
 			let handle = instance
 				.launch(&paths, &mut config.users, &plugins, settings, &mut o)
@@ -14,7 +14,7 @@ use std::sync::Arc;
 use anyhow::Context;
 use commands::UpdateRunStateEvent;
 use data::LauncherData;
-use mcvm::core::auth_crate::mc::ClientId;
+use mcvm::core::util::secrets::get_ms_client_id;
 use mcvm::core::{net::download::Client, user::UserManager};
 use mcvm::io::paths::Paths;
 use mcvm::shared::id::InstanceID;
@@ -105,17 +105,3 @@ pub enum RunState {
 	Preparing,
 	Running,
 }
-
-/// Get the Microsoft client ID
-pub fn get_ms_client_id() -> ClientId {
-	ClientId::new(get_raw_ms_client_id().to_string())
-}
-
-const fn get_raw_ms_client_id() -> &'static str {
-	if let Some(id) = option_env!("MCVM_MS_CLIENT_ID") {
-		id
-	} else {
-		// Please don't use my client ID :)
-		"402abc71-43fb-45c1-b230-e7fc9d4485fe"
-	}
-}
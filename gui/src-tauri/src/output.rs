@@ -21,6 +21,8 @@ pub struct LauncherOutput {
 	passkeys: Arc<Mutex<HashMap<String, String>>>,
 	/// The instance launch associated with this specific output
 	instance: Option<InstanceID>,
+	/// Whether to censor secrets in displayed messages
+	censor_secrets: bool,
 }
 
 impl LauncherOutput {
@@ -34,6 +36,7 @@ impl LauncherOutput {
 			password_prompt,
 			passkeys,
 			instance: None,
+			censor_secrets: true,
 		}
 	}
 
@@ -44,6 +47,10 @@ impl LauncherOutput {
 	pub fn set_instance(&mut self, instance: InstanceID) {
 		self.instance = Some(instance);
 	}
+
+	pub fn set_censor_secrets(&mut self, censor_secrets: bool) {
+		self.censor_secrets = censor_secrets;
+	}
 }
 
 #[async_trait::async_trait]
@@ -52,6 +59,10 @@ impl MCVMOutput for LauncherOutput {
 		self.disp(text);
 	}
 
+	fn should_censor_secrets(&self) -> bool {
+		self.censor_secrets
+	}
+
 	fn display_message(&mut self, message: Message) {
 		if !message.level.at_least(&MessageLevel::Extra) {
 			return;
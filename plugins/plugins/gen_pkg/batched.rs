@@ -1,9 +1,11 @@
 use std::fs::File;
-use std::io::BufWriter;
-use std::path::PathBuf;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{cmp::Reverse, collections::HashMap};
 
+use anyhow::Context;
 use iso8601_timestamp::Timestamp;
 use mcvm_core::io::{json_from_file, json_to_file};
 use mcvm_core::net::download::Client;
@@ -15,6 +17,7 @@ use tokio::task::JoinSet;
 
 use mcvm_net::smithed as smithed_api;
 
+use super::modrinth::UnsubstitutedDependencies;
 use super::{PackageGenerationConfig, PackageSource};
 
 /// Configuration for a lot of package generation
@@ -31,6 +34,15 @@ pub struct BatchedConfig {
 	/// Global package config to apply to all packages
 	#[serde(default)]
 	pub global_config: Option<PackageGenerationConfig>,
+	/// Whether to skip packages that were already generated by a previous run,
+	/// so that an interrupted run can be resumed. Progress is always recorded in a
+	/// `.gen_state.json` file in the output directory, which should be ignored by version control
+	#[serde(default)]
+	pub resume: bool,
+	/// The maximum age in seconds of a previously generated package before it is generated again
+	/// when resuming. If this is not set, existing packages are never considered stale
+	#[serde(default)]
+	pub max_output_age: Option<u64>,
 }
 
 /// Configuration for a single batched package generation
@@ -38,7 +50,7 @@ pub struct BatchedConfig {
 pub struct BatchedPackageConfig {
 	/// The source for the package
 	pub source: PackageSource,
-	/// The ID of the package at the source
+	/// The ID of the package at the source. Modrinth projects can also use their slug
 	pub id: String,
 	/// The ID of the generated package
 	pub pkg_id: Option<String>,
@@ -48,7 +60,11 @@ pub struct BatchedPackageConfig {
 }
 
 /// Generate a lot of packages
-pub async fn batched_gen(mut config: BatchedConfig, filter: Vec<String>) {
+pub async fn batched_gen(
+	mut config: BatchedConfig,
+	filter: Vec<String>,
+	known_substitutions: &HashMap<String, String>,
+) {
 	// Read config dir for additional packages
 	if let Some(config_dir) = config.config_dir {
 		let config_dir = PathBuf::from(config_dir);
@@ -75,6 +91,14 @@ pub async fn batched_gen(mut config: BatchedConfig, filter: Vec<String>) {
 		config.packages.extend(additional_pkgs);
 	}
 
+	let mut run_state = RunState::open(&config.output_dir).expect("Failed to open run state");
+	if config.resume {
+		let skipped = skip_generated_packages(&mut config, &run_state);
+		if skipped > 0 {
+			println!("Skipping {skipped} packages that were already generated");
+		}
+	}
+
 	let client = Client::new();
 
 	println!("Requesting API...");
@@ -152,6 +176,20 @@ pub async fn batched_gen(mut config: BatchedConfig, filter: Vec<String>) {
 		tasks.spawn(task);
 	}
 
+	// Get the version list if any packages need it
+	let needs_version_list = config
+		.global_config
+		.iter()
+		.chain(config.packages.iter().map(|x| &x.config))
+		.any(|x| x.min_minecraft_version.is_some());
+	let version_list = if needs_version_list {
+		super::modrinth::get_version_list(&client)
+			.await
+			.expect("Failed to get version list")
+	} else {
+		Vec::new()
+	};
+
 	// Download Smithed packs at the same time
 	let smithed_packs = Arc::new(Mutex::new(Vec::new()));
 	for pkg in &config.packages {
@@ -212,6 +250,8 @@ pub async fn batched_gen(mut config: BatchedConfig, filter: Vec<String>) {
 
 	// Iterate through the packages to generate
 	println!("Generating packages...");
+	// Dependencies without substitutions from all of the packages, so they can be reported at once
+	let mut unsubstituted = Vec::new();
 	for pkg in config.packages {
 		let pkg_id = pkg.pkg_id.as_ref().expect("Package ID should exist");
 		if !filter.is_empty() && !filter.contains(pkg_id) {
@@ -219,11 +259,14 @@ pub async fn batched_gen(mut config: BatchedConfig, filter: Vec<String>) {
 		}
 
 		println!("Generating package {}", pkg_id);
-		let pkg_config = if let Some(global_config) = &config.global_config {
+		let mut pkg_config = if let Some(global_config) = &config.global_config {
 			global_config.clone().merge(pkg.config)
 		} else {
 			pkg.config
 		};
+		if pkg.source == PackageSource::Modrinth {
+			pkg_config.add_known_substitutions(known_substitutions);
+		}
 
 		let mut package = match pkg.source {
 			PackageSource::Smithed => {
@@ -239,15 +282,15 @@ pub async fn batched_gen(mut config: BatchedConfig, filter: Vec<String>) {
 				.await
 			}
 			PackageSource::Modrinth => {
-				// Get the project
+				// Get the project, which can be referenced by either its ID or its slug
 				let project = modrinth_projects
 					.iter()
-					.find(|x| x.id == pkg.id)
+					.find(|x| x.id == pkg.id || x.slug.as_ref() == Some(&pkg.id))
 					.expect("Project should have been fetched");
 
 				// Get the versions for the project
 				let versions = modrinth_version_map
-					.get(&pkg.id)
+					.get(&project.id)
 					.expect("Project versions missing from map");
 
 				// Get the team associated with this project. Teams can have no members, which we handle by just using an empty team
@@ -257,16 +300,24 @@ pub async fn batched_gen(mut config: BatchedConfig, filter: Vec<String>) {
 					.find(|team| team.iter().any(|member| member.team_id == project.team))
 					.unwrap_or(&empty_vec);
 
-				super::modrinth::gen_raw(
+				let result = super::modrinth::gen_raw(
 					project.clone(),
 					versions,
 					team,
-					pkg_config.relation_substitutions,
-					&pkg_config.force_extensions,
-					pkg_config.make_fabriclike.unwrap_or_default(),
-					pkg_config.make_forgelike.unwrap_or_default(),
+					&pkg_config,
+					&version_list,
 				)
-				.await
+				.await;
+				match result {
+					Ok(package) => package,
+					Err(e) => {
+						if let Some(missing) = e.downcast_ref::<UnsubstitutedDependencies>() {
+							unsubstituted.extend(missing.0.iter().cloned());
+						}
+						println!("Skipping package {pkg_id}: {e}");
+						continue;
+					}
+				}
 			}
 		};
 
@@ -282,16 +333,121 @@ pub async fn batched_gen(mut config: BatchedConfig, filter: Vec<String>) {
 		mcvm_core::util::json::merge(&mut package, merge);
 
 		// Write out the package
-		let path = PathBuf::from(&config.output_dir)
-			.join(format!("{}.json", pkg.pkg_id.expect("Package ID missing")));
-		let file =
-			BufWriter::new(File::create(path).expect("Failed to create package output file"));
-
-		let mut serializer = Serializer::with_formatter(file, PrettyFormatter::with_indent(b"\t"));
-		package
-			.serialize(&mut serializer)
-			.expect("Failed to serialize JSON");
+		let pkg_id = pkg.pkg_id.expect("Package ID missing");
+		let path = get_output_path(&config.output_dir, &pkg_id);
+		write_package(&path, &package).expect("Failed to write package");
+		run_state.set_generated(pkg_id, SystemTime::now());
+		run_state
+			.write(&config.output_dir)
+			.expect("Failed to write run state");
+	}
+
+	if !unsubstituted.is_empty() {
+		unsubstituted.sort();
+		unsubstituted.dedup();
+		println!(
+			"Dependencies that need substitutions: {}",
+			unsubstituted.join(", ")
+		);
+	}
+}
+
+/// Removes the packages that already have up-to-date output from the config, returning how many were removed
+fn skip_generated_packages(config: &mut BatchedConfig, run_state: &RunState) -> usize {
+	let max_age = config.max_output_age.map(Duration::from_secs);
+	let count = config.packages.len();
+	config.packages.retain(|pkg| {
+		let pkg_id = pkg.pkg_id.as_ref().expect("Package ID should exist");
+		!run_state.is_fresh(pkg_id, max_age)
+			|| !get_output_path(&config.output_dir, pkg_id).exists()
+	});
+
+	count - config.packages.len()
+}
+
+/// Record of when each package was last generated. This is kept separately from the output files
+/// because unchanged packages are not rewritten, so their modification times can't be used
+#[derive(Serialize, Deserialize, Default)]
+struct RunState {
+	/// The times that packages finished generating, in seconds since the Unix epoch
+	generated: HashMap<String, u64>,
+}
+
+impl RunState {
+	/// Opens the run state in an output directory, or creates a new one if it doesn't exist
+	fn open(output_dir: &str) -> anyhow::Result<Self> {
+		let path = get_run_state_path(output_dir);
+		if path.exists() {
+			json_from_file(path).context("Failed to read run state")
+		} else {
+			Ok(Self::default())
+		}
 	}
+
+	/// Writes the run state to an output directory
+	fn write(&self, output_dir: &str) -> anyhow::Result<()> {
+		// Written to a temporary file first so that an interrupted write can't leave a truncated state
+		let path = get_run_state_path(output_dir);
+		let temp_path = path.with_extension("json.tmp");
+		json_to_file(&temp_path, self).context("Failed to write run state")?;
+		std::fs::rename(temp_path, path).context("Failed to move run state")
+	}
+
+	/// Records that a package finished generating at a time
+	fn set_generated(&mut self, pkg_id: String, time: SystemTime) {
+		let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+		self.generated.insert(pkg_id, time.as_secs());
+	}
+
+	/// Checks whether a package was generated and is newer than the maximum age
+	fn is_fresh(&self, pkg_id: &str, max_age: Option<Duration>) -> bool {
+		let Some(generated) = self.generated.get(pkg_id) else {
+			return false;
+		};
+		let Some(max_age) = max_age else {
+			return true;
+		};
+		let generated = UNIX_EPOCH + Duration::from_secs(*generated);
+		// Packages generated in the future are treated as fresh
+		!matches!(generated.elapsed(), Ok(age) if age > max_age)
+	}
+}
+
+/// Get the path to the run state file in an output directory
+fn get_run_state_path(output_dir: &str) -> PathBuf {
+	PathBuf::from(output_dir).join(".gen_state.json")
+}
+
+/// Writes a generated package to a file, returning whether it was written. The file is left alone
+/// if its contents are already the same as the package, so that unchanged packages keep their
+/// modification times and don't show up in diffs
+fn write_package(path: &Path, package: &serde_json::Value) -> anyhow::Result<bool> {
+	if let Ok(existing) = json_from_file::<serde_json::Value>(path) {
+		if &existing == package {
+			return Ok(false);
+		}
+	}
+
+	// The package is written to a temporary file first so that an interrupted
+	// write is never mistaken for a finished package when resuming
+	let temp_path = path.with_extension("json.tmp");
+	let file = BufWriter::new(File::create(&temp_path).context("Failed to create output file")?);
+	let mut serializer = Serializer::with_formatter(file, PrettyFormatter::with_indent(b"\t"));
+	package
+		.serialize(&mut serializer)
+		.context("Failed to serialize JSON")?;
+	serializer
+		.into_inner()
+		.flush()
+		.context("Failed to write output file")?;
+	std::fs::rename(temp_path, path).context("Failed to move output file")?;
+
+	Ok(true)
+}
+
+/// Get the path to the output file of a generated package
+fn get_output_path(output_dir: &str, pkg_id: &str) -> PathBuf {
+	PathBuf::from(output_dir).join(format!("{pkg_id}.json"))
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
@@ -315,3 +471,78 @@ impl SortVersions {
 fn get_cached_version_path(version: &str) -> PathBuf {
 	format!("./pkg_cache/modrinth_versions/{version}.json").into()
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_resume() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+
+		let create = |pkg_id: &str| BatchedPackageConfig {
+			source: PackageSource::Modrinth,
+			id: pkg_id.into(),
+			pkg_id: Some(pkg_id.into()),
+			config: PackageGenerationConfig::default(),
+		};
+		let mut config = BatchedConfig {
+			packages: vec![create("generated"), create("stale"), create("new")],
+			output_dir: dir.to_string_lossy().to_string(),
+			config_dir: None,
+			global_config: None,
+			resume: true,
+			max_output_age: Some(60),
+		};
+
+		// Simulate the output of a previous run. The unchanged package wasn't rewritten, so its file is old
+		// even though it was generated recently
+		let mut run_state = RunState::default();
+		for (pkg_id, age) in [("generated", 0), ("unchanged", 0), ("stale", 3600)] {
+			let path = dir.join(format!("{pkg_id}.json"));
+			File::create(path)
+				.unwrap()
+				.set_modified(SystemTime::now() - Duration::from_secs(3600))
+				.unwrap();
+			run_state.set_generated(pkg_id.into(), SystemTime::now() - Duration::from_secs(age));
+		}
+		config.packages.push(create("unchanged"));
+		// A package with output but no record of finishing, such as from an interrupted run
+		std::fs::write(dir.join("interrupted.json"), "{}").unwrap();
+		config.packages.push(create("interrupted"));
+		run_state.write(&config.output_dir).unwrap();
+		assert!(!get_run_state_path(&config.output_dir)
+			.with_extension("json.tmp")
+			.exists());
+
+		let run_state = RunState::open(&config.output_dir).unwrap();
+		assert_eq!(skip_generated_packages(&mut config, &run_state), 2);
+		let ids: Vec<_> = config.packages.iter().map(|x| x.id.as_str()).collect();
+		assert_eq!(ids, vec!["stale", "new", "interrupted"]);
+	}
+
+	#[test]
+	fn test_write_unchanged_package() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let path = dir.join("package.json");
+
+		let package = serde_json::json!({"meta": {"name": "Test"}, "properties": {}});
+		assert!(write_package(&path, &package).unwrap());
+		let modified = SystemTime::now() - Duration::from_secs(3600);
+		File::options()
+			.write(true)
+			.open(&path)
+			.unwrap()
+			.set_modified(modified)
+			.unwrap();
+
+		assert!(!write_package(&path, &package).unwrap());
+		assert_eq!(path.metadata().unwrap().modified().unwrap(), modified);
+
+		let changed = serde_json::json!({"meta": {"name": "Changed"}, "properties": {}});
+		assert!(write_package(&path, &changed).unwrap());
+		assert_eq!(json_from_file::<serde_json::Value>(&path).unwrap(), changed);
+	}
+}
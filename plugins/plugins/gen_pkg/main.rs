@@ -1,13 +1,23 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::stdout;
 
+use anyhow::Context;
 use clap::Parser;
-use mcvm_plugin::api::CustomPlugin;
+use mcvm::auth::mc::ClientId;
+use mcvm::config::Config;
+use mcvm::io::paths::Paths;
+use mcvm::plugin::PluginManager;
+use mcvm::shared::versions::VersionPattern;
+use mcvm_core::net::download::Client;
+use mcvm_net::modrinth::ProjectType;
+use mcvm_plugin::api::{CustomPlugin, MCVMOutput, MessageContents, MessageLevel};
 use serde::{Deserialize, Serialize};
 use serde_json::ser::PrettyFormatter;
 use serde_json::Serializer;
 
+use modrinth::UnsubstitutedDependencies;
+
 /// Generation of many packages
 pub mod batched;
 /// Modrinth package generation
@@ -17,7 +27,7 @@ pub mod smithed;
 
 fn main() -> anyhow::Result<()> {
 	let mut plugin = CustomPlugin::new("stats")?;
-	plugin.subcommand(|_, args| {
+	plugin.subcommand(|mut ctx, args| {
 		let Some(subcommand) = args.first() else {
 			return Ok(());
 		};
@@ -36,14 +46,26 @@ fn main() -> anyhow::Result<()> {
 					)
 					.expect("Failed to deserialize config")
 				});
-				gen(cli.source, config, &cli.id).await;
+				// Known substitutions are Modrinth IDs, so they are only useful for Modrinth packages
+				let known_substitutions =
+					if cli.use_repositories && cli.source == PackageSource::Modrinth {
+						load_known_substitutions(ctx.get_output()).await
+					} else {
+						HashMap::new()
+					};
+				gen(cli.source, config, &cli.id, &known_substitutions).await?;
 			} else if subcommand == "gen-pkg-batched" {
 				let cli = GenPkgBatched::parse_from(it);
 				let config = serde_json::from_reader(
 					File::open(cli.config_path).expect("Failed to open config file"),
 				)
 				.expect("Failed to deserialize config");
-				batched::batched_gen(config, cli.filter).await;
+				let known_substitutions = if cli.use_repositories {
+					load_known_substitutions(ctx.get_output()).await
+				} else {
+					HashMap::new()
+				};
+				batched::batched_gen(config, cli.filter, &known_substitutions).await;
 			}
 
 			Ok::<(), anyhow::Error>(())
@@ -62,8 +84,11 @@ struct GenPkg {
 	config_path: Option<String>,
 	/// The source to get the package from
 	source: PackageSource,
-	/// The ID of the package from whatever source it is from
+	/// The ID of the package from whatever source it is from. Modrinth projects can also use their slug
 	id: String,
+	/// Substitute Modrinth dependencies with packages from the configured repositories
+	#[arg(long)]
+	use_repositories: bool,
 }
 
 #[derive(Parser)]
@@ -73,6 +98,9 @@ struct GenPkgBatched {
 	/// Packages to filter and only include
 	#[arg(short, long)]
 	filter: Vec<String>,
+	/// Substitute Modrinth dependencies with packages from the configured repositories
+	#[arg(long)]
+	use_repositories: bool,
 }
 
 /// Different types of package generation
@@ -97,6 +125,10 @@ pub struct PackageGenerationConfig {
 	pub make_fabriclike: Option<bool>,
 	/// Whether to make forge modloaders forgelike instead
 	pub make_forgelike: Option<bool>,
+	/// The oldest Minecraft version to generate addon versions for
+	pub min_minecraft_version: Option<VersionPattern>,
+	/// The Modrinth project types that packages can be generated for. Defaults to all of them
+	pub allowed_project_types: Option<HashSet<ProjectType>>,
 }
 
 impl PackageGenerationConfig {
@@ -109,28 +141,92 @@ impl PackageGenerationConfig {
 		self.force_extensions.extend(other.force_extensions);
 		self.make_fabriclike = other.make_fabriclike.or(self.make_fabriclike);
 		self.make_forgelike = other.make_forgelike.or(self.make_forgelike);
+		self.min_minecraft_version = other.min_minecraft_version.or(self.min_minecraft_version);
+		self.allowed_project_types = other.allowed_project_types.or(self.allowed_project_types);
 
 		self
 	}
+
+	/// Add substitutions for relations that are already known, such as from the Modrinth IDs
+	/// of packages in a repository. Substitutions that are already in the config take precedence
+	pub fn add_known_substitutions(&mut self, known: &HashMap<String, String>) {
+		for (relation, package) in known {
+			self.relation_substitutions
+				.entry(relation.clone())
+				.or_insert_with(|| package.clone());
+		}
+	}
+}
+
+/// Get substitutions for Modrinth dependencies from the packages in the configured repositories,
+/// warning and using none if they can't be loaded
+async fn load_known_substitutions(o: &mut impl MCVMOutput) -> HashMap<String, String> {
+	match get_known_substitutions(o).await {
+		Ok(known) => known,
+		Err(e) => {
+			o.display(
+				MessageContents::Warning(format!(
+					"Failed to get substitutions from existing packages: {e:?}"
+				)),
+				MessageLevel::Important,
+			);
+			HashMap::new()
+		}
+	}
 }
 
-/// Generates a package from a source and config
-pub async fn gen(source: PackageSource, config: Option<PackageGenerationConfig>, id: &str) {
-	let config = config.unwrap_or_default();
+/// Get substitutions for Modrinth dependencies from the packages in the configured repositories
+async fn get_known_substitutions(
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<HashMap<String, String>> {
+	let paths = Paths::new().context("Failed to create paths")?;
+	let plugins = PluginManager::load(&paths, o).context("Failed to load plugins configuration")?;
+	let mut config = Config::load(
+		&Config::get_path(&paths),
+		plugins,
+		false,
+		&paths,
+		ClientId::new(String::new()),
+		o,
+	)
+	.context("Failed to load config")?;
+
+	let client = Client::new();
+	config
+		.packages
+		.get_modrinth_id_map(&paths, &client, o)
+		.await
+		.context("Failed to get Modrinth IDs of packages")
+}
+
+/// Generates a package from a source and config and outputs it. If any dependencies of a Modrinth
+/// project are not substituted, an `UnsubstitutedDependencies` error is returned
+pub async fn gen(
+	source: PackageSource,
+	config: Option<PackageGenerationConfig>,
+	id: &str,
+	known_substitutions: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+	let mut config = config.unwrap_or_default();
+	if source == PackageSource::Modrinth {
+		config.add_known_substitutions(known_substitutions);
+	}
 	let mut pkg = match source {
 		PackageSource::Smithed => {
 			smithed::gen(id, config.relation_substitutions, &config.force_extensions).await
 		}
-		PackageSource::Modrinth => {
-			modrinth::gen(
-				id,
-				config.relation_substitutions,
-				&config.force_extensions,
-				config.make_fabriclike.unwrap_or_default(),
-				config.make_forgelike.unwrap_or_default(),
-			)
-			.await
-		}
+		PackageSource::Modrinth => match modrinth::gen(id, &config).await {
+			Ok(pkg) => pkg,
+			Err(e) => {
+				if let Some(missing) = e.downcast_ref::<UnsubstitutedDependencies>() {
+					println!(
+						"Dependencies that need substitutions: {}",
+						missing.0.join(", ")
+					);
+				}
+				return Err(e.context("Failed to generate package"));
+			}
+		},
 	};
 
 	// Improve the generated package
@@ -138,10 +234,12 @@ pub async fn gen(source: PackageSource, config: Option<PackageGenerationConfig>,
 	pkg.optimize();
 
 	// Merge with config
-	let mut pkg = serde_json::value::to_value(pkg).expect("Failed to convert package to value");
+	let mut pkg = serde_json::value::to_value(pkg).context("Failed to convert package to value")?;
 	mcvm_core::util::json::merge(&mut pkg, config.merge);
 
 	let mut serializer = Serializer::with_formatter(stdout(), PrettyFormatter::with_indent(b"\t"));
 	pkg.serialize(&mut serializer)
-		.expect("Failed to output package");
+		.context("Failed to output package")?;
+
+	Ok(())
 }
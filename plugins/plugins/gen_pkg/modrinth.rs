@@ -1,5 +1,6 @@
 use std::collections::HashMap;
-use std::sync::OnceLock;
+
+use anyhow::{bail, Context};
 
 use mcvm::pkg_crate::declarative::{
 	DeclarativeAddon, DeclarativeAddonVersion, DeclarativeConditionSet, DeclarativePackage,
@@ -8,60 +9,94 @@ use mcvm::pkg_crate::declarative::{
 use mcvm::pkg_crate::metadata::PackageMetadata;
 use mcvm::pkg_crate::properties::PackageProperties;
 use mcvm::pkg_crate::RecommendedPackage;
-use mcvm::shared::addon::AddonKind;
-use mcvm::shared::modifications::{ModloaderMatch, PluginLoaderMatch};
+use mcvm::shared::addon::{is_filename_valid, AddonKind};
+use mcvm::shared::modifications::{ModloaderMatch, PluginLoaderMatch, ProxyMatch};
 use mcvm::shared::pkg::PackageStability;
 use mcvm::shared::util::DeserListOrSingle;
 use mcvm::shared::versions::VersionPattern;
 
 use mcvm::shared::Side;
+use mcvm_core::net::download::Client;
+use mcvm_core::net::game_files::version_manifest::{make_version_list, VERSION_MANIFEST_URL};
 use mcvm_net::modrinth::{
-	self, DependencyType, KnownLoader, Loader, Member, Project, ProjectType, ReleaseChannel,
-	SideSupport, Version,
+	self, cleanup_version_name, DependencyType, GalleryEntry, KnownLoader, Loader, Member, Project,
+	ProjectType, ReleaseChannel, SideSupport, Version,
 };
-use regex::{Regex, RegexBuilder};
-
-pub async fn gen(
-	id: &str,
-	relation_substitutions: HashMap<String, String>,
-	force_extensions: &[String],
-	make_fabriclike: bool,
-	make_forgelike: bool,
-) -> DeclarativePackage {
-	let client = mcvm_core::net::download::Client::new();
-	let project = modrinth::get_project(id, &client)
+use thiserror::Error;
+
+use super::PackageGenerationConfig;
+
+pub async fn gen(id: &str, config: &PackageGenerationConfig) -> anyhow::Result<DeclarativePackage> {
+	let client = Client::new();
+	let id = modrinth::resolve_slug(id, &client)
+		.await
+		.context("Failed to resolve Modrinth project")?;
+	let project = modrinth::get_project(&id, &client)
 		.await
-		.expect("Failed to get Modrinth project");
+		.context("Failed to get Modrinth project")?;
 
 	let versions = modrinth::get_multiple_versions(&project.versions, &client)
 		.await
-		.expect("Failed to get Modrinth project versions");
+		.context("Failed to get Modrinth project versions")?;
 
-	let members = modrinth::get_project_team(id, &client)
+	let members = modrinth::get_project_team(&id, &client)
 		.await
-		.expect("Failed to get project team members from Modrinth");
-
-	gen_raw(
-		project,
-		&versions,
-		&members,
-		relation_substitutions,
-		force_extensions,
-		make_fabriclike,
-		make_forgelike,
-	)
-	.await
+		.context("Failed to get project team members from Modrinth")?;
+
+	let version_list = if config.min_minecraft_version.is_some() {
+		get_version_list(&client).await?
+	} else {
+		Vec::new()
+	};
+
+	gen_raw(project, &versions, &members, config, &version_list).await
+}
+
+/// Gets the list of Minecraft versions from the version manifest, from oldest to newest
+pub async fn get_version_list(client: &Client) -> anyhow::Result<Vec<String>> {
+	let manifest = mcvm_core::net::download::json(VERSION_MANIFEST_URL, client)
+		.await
+		.context("Failed to get version manifest")?;
+	Ok(make_version_list(&manifest))
 }
 
+/// Error returned when generating a package from a project with dependencies that don't have substitutions.
+/// Contains all of the missing Modrinth project IDs so that they can be substituted at once
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Dependencies were not substituted: {}", .0.join(", "))]
+pub struct UnsubstitutedDependencies(pub Vec<String>);
+
+/// Generates a package from a Modrinth project. Fails if the project can't be generated,
+/// such as when its type isn't allowed by the config. If any dependencies are not substituted,
+/// an `UnsubstitutedDependencies` error is returned.
 pub async fn gen_raw(
 	project: Project,
 	versions: &[Version],
 	members: &[Member],
-	relation_substitutions: HashMap<String, String>,
-	force_extensions: &[String],
-	make_fabriclike: bool,
-	make_forgelike: bool,
-) -> DeclarativePackage {
+	config: &PackageGenerationConfig,
+	version_list: &[String],
+) -> anyhow::Result<DeclarativePackage> {
+	if let Some(allowed_project_types) = &config.allowed_project_types {
+		if !allowed_project_types.contains(&project.project_type) {
+			bail!("Project type {:?} is not allowed", project.project_type);
+		}
+	}
+
+	let make_fabriclike = config.make_fabriclike.unwrap_or_default();
+	let make_forgelike = config.make_forgelike.unwrap_or_default();
+
+	// Get the position of the oldest Minecraft version to support in the version list
+	let min_version_pos = config
+		.min_minecraft_version
+		.as_ref()
+		.map(|pattern| {
+			version_list
+				.iter()
+				.position(|x| pattern.matches_single(x, version_list))
+				.context("Minimum Minecraft version does not match any versions")
+		})
+		.transpose()?;
+
 	// Get supported sides
 	let supported_sides = get_supported_sides(&project);
 
@@ -95,11 +130,13 @@ pub async fn gen_raw(
 	if let Some(support_link) = donation_urls.first() {
 		meta.support_link = Some(support_link.url.clone());
 	}
-	if let Some(gallery) = project.gallery {
-		// Get the banner image from the featured gallery image
-		if let Some(banner) = gallery.iter().find(|x| x.featured) {
-			meta.banner = Some(banner.url.clone());
-		}
+	// Sort the gallery so that the banner and gallery don't depend on the order from the API
+	let mut gallery = project.gallery;
+	if let Some(gallery) = &mut gallery {
+		gallery.sort_by(|a, b| a.ordering.cmp(&b.ordering).then_with(|| a.url.cmp(&b.url)));
+	}
+	meta.banner = get_banner(gallery.as_deref().unwrap_or_default(), meta.icon.as_deref());
+	if let Some(gallery) = gallery {
 		meta.gallery = Some(gallery.into_iter().map(|x| x.url).collect());
 	}
 
@@ -140,7 +177,7 @@ pub async fn gen_raw(
 		ProjectType::Plugin => AddonKind::Plugin,
 		ProjectType::ResourcePack => AddonKind::ResourcePack,
 		ProjectType::Shader => AddonKind::Shader,
-		ProjectType::Modpack => panic!("Modpack projects are unsupported"),
+		ProjectType::Modpack => bail!("Modpack projects are unsupported"),
 	};
 	let mut addon = DeclarativeAddon {
 		kind: addon_kind,
@@ -148,10 +185,39 @@ pub async fn gen_raw(
 		conditions: Vec::new(),
 		optional: false,
 	};
+	// Versions that are only available as a datapack for projects of other types
+	let mut datapack_addon = DeclarativeAddon {
+		kind: AddonKind::Datapack,
+		versions: Vec::new(),
+		conditions: Vec::new(),
+		optional: false,
+	};
+	// Versions that are plugins for server proxies
+	let mut proxy_addon = DeclarativeAddon {
+		kind: AddonKind::Plugin,
+		versions: Vec::new(),
+		conditions: Vec::new(),
+		optional: false,
+	};
 
 	let mut content_versions = Vec::with_capacity(versions.len());
+	let mut unsubstituted = Vec::new();
 
 	for version in versions {
+		// Skip versions where all of the Minecraft versions are older than the minimum.
+		// Minecraft versions that aren't in the version list are never considered older
+		if let Some(min_version_pos) = min_version_pos {
+			let is_old = |mc_version: &String| {
+				version_list
+					.iter()
+					.position(|x| x == mc_version)
+					.is_some_and(|pos| pos < min_version_pos)
+			};
+			if !version.game_versions.is_empty() && version.game_versions.iter().all(is_old) {
+				continue;
+			}
+		}
+
 		let version_name = version.id.clone();
 		// Collect Minecraft versions
 		let mc_versions: Vec<VersionPattern> = version
@@ -163,7 +229,8 @@ pub async fn gen_raw(
 		// Look at loaders
 		let mut modloaders = Vec::new();
 		let mut plugin_loaders = Vec::new();
-		let mut skip = false;
+		let mut proxies = Vec::new();
+		let mut is_datapack = false;
 		for loader in &version.loaders {
 			match loader {
 				Loader::Known(loader) => match loader {
@@ -188,20 +255,19 @@ pub async fn gen_raw(
 					KnownLoader::Sponge => plugin_loaders.push(PluginLoaderMatch::Sponge),
 					KnownLoader::Paper => plugin_loaders.push(PluginLoaderMatch::Paper),
 					KnownLoader::Purpur => plugin_loaders.push(PluginLoaderMatch::Purpur),
-					// Skip over these versions for now
-					KnownLoader::Datapack
-					| KnownLoader::BungeeCord
-					| KnownLoader::Velocity
-					| KnownLoader::Waterfall => skip = true,
+					KnownLoader::BungeeCord => proxies.push(ProxyMatch::BungeeCord),
+					KnownLoader::Velocity => proxies.push(ProxyMatch::Velocity),
+					KnownLoader::Waterfall => proxies.push(ProxyMatch::Waterfall),
+					KnownLoader::Datapack => is_datapack = true,
 					// We don't care about these
 					KnownLoader::Iris | KnownLoader::Optifine | KnownLoader::Minecraft => {}
 				},
 				Loader::Unknown(other) => panic!("Unknown loader {other}"),
 			}
 		}
-		if skip {
-			continue;
-		}
+		let no_game_loaders = modloaders.is_empty() && plugin_loaders.is_empty();
+		// Datapacks work on any loader, so they are only used when no loaders are supported
+		let is_datapack = is_datapack && no_game_loaders && proxies.is_empty();
 
 		// Get stability
 		let stability = match version.version_type {
@@ -215,10 +281,11 @@ pub async fn gen_raw(
 		let mut conflicts = Vec::new();
 
 		for dep in &version.dependencies {
-			let pkg_id = if let Some(dep_id) = relation_substitutions.get(&dep.project_id) {
-				dep_id.clone()
-			} else {
-				panic!("Dependency {} was not substituted", dep.project_id)
+			let Some(pkg_id) = config.relation_substitutions.get(&dep.project_id).cloned() else {
+				if !unsubstituted.contains(&dep.project_id) {
+					unsubstituted.push(dep.project_id.clone());
+				}
+				continue;
 			};
 			// Don't count none relations
 			if pkg_id == "none" {
@@ -226,7 +293,7 @@ pub async fn gen_raw(
 			}
 			match dep.dependency_type {
 				DependencyType::Required => {
-					if force_extensions.contains(&pkg_id) {
+					if config.force_extensions.contains(&pkg_id) {
 						extensions.push(pkg_id);
 					} else {
 						deps.push(pkg_id)
@@ -279,8 +346,31 @@ pub async fn gen_raw(
 			.get_primary_download()
 			.expect("Version has no available downloads");
 		pkg_version.url = Some(download.url.clone());
+		pkg_version.filename = Some(download.filename.clone());
 
-		addon.versions.push(pkg_version);
+		// Proxy plugins are put in their own addon so that they don't need
+		// to match the game loaders as well
+		if !proxies.is_empty() {
+			let mut proxy_version = pkg_version.clone();
+			proxy_version.conditional_properties.modloaders = None;
+			proxy_version.conditional_properties.plugin_loaders = None;
+			proxy_version.conditional_properties.proxies = Some(DeserListOrSingle::List(proxies));
+			proxy_addon.versions.push(proxy_version);
+			if no_game_loaders {
+				continue;
+			}
+		}
+
+		if is_datapack && !matches!(addon.kind, AddonKind::Datapack) {
+			datapack_addon.versions.push(pkg_version);
+		} else {
+			addon.versions.push(pkg_version);
+		}
+	}
+
+	if !unsubstituted.is_empty() {
+		unsubstituted.sort();
+		return Err(UnsubstitutedDependencies(unsubstituted).into());
 	}
 
 	// Try to sort content versions by semver if possible
@@ -299,15 +389,31 @@ pub async fn gen_raw(
 
 	props.content_versions = Some(content_versions);
 
+	// Fall back to generated filenames when the file has the wrong extension for its addon
+	for addon in [&mut addon, &mut datapack_addon, &mut proxy_addon] {
+		for version in &mut addon.versions {
+			version.filename = version
+				.filename
+				.take()
+				.filter(|x| is_filename_valid(addon.kind, x));
+		}
+	}
+
 	let mut addon_map = HashMap::new();
 	addon_map.insert("addon".into(), addon);
+	if !datapack_addon.versions.is_empty() {
+		addon_map.insert("datapack".into(), datapack_addon);
+	}
+	if !proxy_addon.versions.is_empty() {
+		addon_map.insert("proxy_plugin".into(), proxy_addon);
+	}
 
-	DeclarativePackage {
+	Ok(DeclarativePackage {
 		meta,
 		properties: props,
 		addons: addon_map,
 		..Default::default()
-	}
+	})
 }
 
 /// Gets the list of supported sides from the project
@@ -322,17 +428,245 @@ fn get_supported_sides(project: &Project) -> Vec<Side> {
 	out
 }
 
-/// Cleanup a version name to remove things like modloaders
-fn cleanup_version_name(version: &str) -> String {
-	static MODLOADER_REGEX: OnceLock<Regex> = OnceLock::new();
-	let regex = MODLOADER_REGEX.get_or_init(|| {
-		RegexBuilder::new("(-|_|\\+)?(fabric|forge|quilt)")
-			.case_insensitive(true)
-			.build()
-			.expect("Failed to create regex")
-	});
-	let version = regex.replace_all(version, "");
-	let version = version.replace("+", "-");
+/// Picks the banner image for a project. The first featured gallery image is preferred,
+/// falling back to the first gallery image and then the icon
+fn get_banner(gallery: &[GalleryEntry], icon: Option<&str>) -> Option<String> {
+	gallery
+		.iter()
+		.find(|x| x.featured)
+		.or(gallery.first())
+		.map(|x| x.url.clone())
+		.or(icon.map(String::from))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+
+	use super::*;
+
+	fn create_project(project_type: &str) -> Project {
+		serde_json::from_value(serde_json::json!({
+			"id": "test",
+			"slug": "test",
+			"project_type": project_type,
+			"versions": [],
+			"game_versions": ["1.20.1"],
+			"loaders": [],
+			"client_side": "optional",
+			"server_side": "optional",
+			"team": "team",
+			"title": "Test",
+			"description": "A test project",
+			"donation_urls": [],
+			"license": {"id": "MIT"}
+		}))
+		.unwrap()
+	}
+
+	fn create_version(id: &str, loaders: &[&str]) -> Version {
+		serde_json::from_value(serde_json::json!({
+			"id": id,
+			"project_id": "test",
+			"name": id,
+			"version_number": id,
+			"version_type": "release",
+			"loaders": loaders,
+			"files": [{
+				"url": format!("https://example.com/{id}.zip"),
+				"filename": format!("{id}.zip"),
+				"primary": true
+			}],
+			"game_versions": ["1.20.1"],
+			"dependencies": [],
+			"featured": false,
+			"date_published": "2024-01-01T00:00:00Z"
+		}))
+		.unwrap()
+	}
+
+	fn gen_test(project: Project, versions: &[Version]) -> DeclarativePackage {
+		gen_test_with_config(project, versions, &PackageGenerationConfig::default(), &[]).unwrap()
+	}
+
+	fn gen_test_with_config(
+		project: Project,
+		versions: &[Version],
+		config: &PackageGenerationConfig,
+		version_list: &[String],
+	) -> anyhow::Result<DeclarativePackage> {
+		let runtime = tokio::runtime::Runtime::new().unwrap();
+		runtime.block_on(gen_raw(project, versions, &[], config, version_list))
+	}
+
+	#[test]
+	fn test_datapack_versions() {
+		let versions = [
+			create_version("mod", &["fabric"]),
+			create_version("datapack", &["datapack"]),
+		];
+		let pkg = gen_test(create_project("mod"), &versions);
+		assert_eq!(pkg.addons["addon"].versions.len(), 1);
+		let datapack = &pkg.addons["datapack"];
+		assert!(matches!(datapack.kind, AddonKind::Datapack));
+		assert_eq!(datapack.versions.len(), 1);
+		assert_eq!(datapack.versions[0].version.as_deref(), Some("datapack"));
+
+		// Datapack projects keep their versions in the main addon
+		let pkg = gen_test(create_project("datapack"), &versions[1..]);
+		assert!(matches!(pkg.addons["addon"].kind, AddonKind::Datapack));
+		assert_eq!(pkg.addons["addon"].versions.len(), 1);
+		assert!(!pkg.addons.contains_key("datapack"));
+	}
+
+	#[test]
+	fn test_proxy_versions() {
+		let versions = [
+			create_version("paper", &["paper"]),
+			create_version("velocity", &["velocity"]),
+			create_version("both", &["bungeecord", "spigot"]),
+		];
+		let pkg = gen_test(create_project("plugin"), &versions);
+
+		let addon = &pkg.addons["addon"];
+		let ids: Vec<_> = addon.versions.iter().map(|x| x.version.clone()).collect();
+		assert_eq!(ids, vec![Some("paper".into()), Some("both".into())]);
+		assert!(addon.versions[1].conditional_properties.proxies.is_none());
+
+		let proxy = &pkg.addons["proxy_plugin"];
+		assert!(matches!(proxy.kind, AddonKind::Plugin));
+		assert_eq!(proxy.versions.len(), 2);
+		let velocity = &proxy.versions[0];
+		assert_eq!(velocity.version.as_deref(), Some("velocity"));
+		let proxies: Vec<_> = velocity
+			.conditional_properties
+			.proxies
+			.iter()
+			.flat_map(|x| x.iter())
+			.collect();
+		assert_eq!(proxies, vec![&ProxyMatch::Velocity]);
+		assert!(proxy.versions[1]
+			.conditional_properties
+			.plugin_loaders
+			.is_none());
+	}
+
+	#[test]
+	fn test_version_filenames() {
+		let versions = [create_version("mod", &["fabric"])];
+		let pkg = gen_test(create_project("mod"), &versions);
+		// The file has the wrong extension for a mod
+		assert_eq!(pkg.addons["addon"].versions[0].filename, None);
+
+		let pkg = gen_test(create_project("resourcepack"), &versions);
+		assert_eq!(
+			pkg.addons["addon"].versions[0].filename.as_deref(),
+			Some("mod.zip")
+		);
+	}
+
+	#[test]
+	fn test_min_minecraft_version() {
+		let version_list: Vec<String> = ["1.12", "1.12.2", "1.16", "1.16.5", "1.20.1"]
+			.into_iter()
+			.map(String::from)
+			.collect();
+		let create = |id: &str, game_versions: &[&str]| {
+			let mut version = create_version(id, &["fabric"]);
+			version.game_versions = game_versions.iter().map(|x| x.to_string()).collect();
+			version
+		};
+		let versions = [
+			create("old", &["1.12", "1.12.2"]),
+			create("both", &["1.12.2", "1.16.5"]),
+			create("new", &["1.20.1"]),
+			create("unknown", &["1.12-snapshot"]),
+		];
+
+		let config = PackageGenerationConfig {
+			min_minecraft_version: Some(VersionPattern::Single("1.16".into())),
+			..Default::default()
+		};
+		let pkg =
+			gen_test_with_config(create_project("mod"), &versions, &config, &version_list).unwrap();
+		let ids: Vec<_> = pkg.addons["addon"]
+			.versions
+			.iter()
+			.map(|x| x.version.clone().unwrap())
+			.collect();
+		assert_eq!(ids, vec!["both", "new", "unknown"]);
+	}
+
+	#[test]
+	fn test_banner() {
+		let create = |gallery: serde_json::Value| {
+			let mut project = create_project("mod");
+			project.icon_url = Some("icon.png".into());
+			project.gallery = serde_json::from_value(gallery).unwrap();
+			project
+		};
+
+		let project = create(serde_json::json!([
+			{"url": "first.png", "featured": false, "ordering": 0},
+			{"url": "featured.png", "featured": true, "ordering": 1}
+		]));
+		let pkg = gen_test(project, &[]);
+		assert_eq!(pkg.meta.banner.as_deref(), Some("featured.png"));
+
+		// The gallery is sorted by its ordering, regardless of the order from the API
+		let project = create(serde_json::json!([
+			{"url": "second.png", "featured": false, "ordering": 1},
+			{"url": "first.png", "featured": false, "ordering": 0}
+		]));
+		let pkg = gen_test(project, &[]);
+		assert_eq!(pkg.meta.banner.as_deref(), Some("first.png"));
+		assert_eq!(
+			pkg.meta.gallery,
+			Some(vec!["first.png".into(), "second.png".into()])
+		);
 
-	version
+		let pkg = gen_test(create(serde_json::Value::Null), &[]);
+		assert_eq!(pkg.meta.banner.as_deref(), Some("icon.png"));
+		assert_eq!(pkg.meta.gallery, None);
+	}
+
+	#[test]
+	fn test_unsubstituted_dependencies() {
+		let mut version = create_version("1", &["fabric"]);
+		version.dependencies = serde_json::from_value(serde_json::json!([
+			{"project_id": "sodium", "dependency_type": "required"},
+			{"project_id": "fabric-api", "dependency_type": "required"},
+			{"project_id": "iris", "dependency_type": "optional"}
+		]))
+		.unwrap();
+		let config = PackageGenerationConfig {
+			relation_substitutions: HashMap::from([("iris".into(), "iris".into())]),
+			..Default::default()
+		};
+
+		let err =
+			gen_test_with_config(create_project("mod"), &[version], &config, &[]).unwrap_err();
+		assert_eq!(
+			err.downcast_ref::<UnsubstitutedDependencies>(),
+			Some(&UnsubstitutedDependencies(vec![
+				"fabric-api".into(),
+				"sodium".into()
+			]))
+		);
+	}
+
+	#[test]
+	fn test_allowed_project_types() {
+		let config = PackageGenerationConfig {
+			allowed_project_types: Some(HashSet::from([ProjectType::Mod])),
+			..Default::default()
+		};
+		let versions = [create_version("1", &["iris"])];
+
+		let result = gen_test_with_config(create_project("shader"), &versions, &config, &[]);
+		assert!(result.is_err());
+
+		let result = gen_test_with_config(create_project("mod"), &versions, &config, &[]);
+		assert!(result.is_ok());
+	}
 }
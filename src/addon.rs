@@ -4,10 +4,15 @@ use mcvm_shared::pkg::PackageAddonOptionalHashes;
 use reqwest::Client;
 
 use crate::io::paths::Paths;
-use crate::util::hash::{get_best_hash, hash_file_with_best_hash};
+use crate::io::transaction::get_staging_path;
+use crate::plugin::PluginManager;
+use crate::util::hash::{get_best_hash, hash_file_sha256, hash_file_with_best_hash};
 use mcvm_core::io::files::{create_leading_dirs, update_hardlink};
 use mcvm_core::net::download;
+use mcvm_plugin::hooks::{Hook, ProcessDownloadedAddon, ProcessDownloadedAddonArg};
 use mcvm_shared::modifications::{Modloader, ServerType};
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
+use mcvm_shared::translate;
 
 use std::future::Future;
 use std::path::{Path, PathBuf};
@@ -111,6 +116,11 @@ impl AddonRequest {
 		self.addon.get_unique_id(instance_id)
 	}
 
+	/// Whether acquiring this addon requires downloading it
+	pub fn is_remote(&self) -> bool {
+		matches!(self.location, AddonLocation::Remote(..))
+	}
+
 	/// Get the addon and store it
 	pub async fn acquire(
 		&self,
@@ -122,16 +132,19 @@ impl AddonRequest {
 			.get_acquire_task(paths, instance_id, client)
 			.context("Failed to prepare to acquire addon")?;
 
-		task.await.context("Failed to acquire addon")
+		task.await.context("Failed to acquire addon")?;
+
+		Ok(())
 	}
 
-	/// Get the task to acquire the addon for use in concurrent operations
+	/// Get the task to acquire the addon for use in concurrent operations.
+	/// The task returns the number of bytes that were downloaded
 	pub fn get_acquire_task(
 		&self,
 		paths: &Paths,
 		instance_id: &str,
 		client: &Client,
-	) -> anyhow::Result<impl Future<Output = anyhow::Result<()>> + Send + 'static> {
+	) -> anyhow::Result<impl Future<Output = anyhow::Result<u64>> + Send + 'static> {
 		let path = self.addon.get_path(paths, instance_id);
 		create_leading_dirs(&path)?;
 
@@ -139,26 +152,44 @@ impl AddonRequest {
 		let client = client.clone();
 		let hashes = self.addon.hashes.clone();
 		let task = async move {
-			match location {
+			let downloaded = match location {
 				AddonLocation::Remote(url) => {
-					download::file(url, &path, &client)
+					// Download to a staging path so that a failed download never replaces the stored addon
+					let staged = get_staging_path(&path);
+					download::file(url, &staged, &client)
 						.await
 						.context("Failed to download addon")?;
+
+					let result = Self::check_hashes_impl(hashes, &staged);
+					if result.is_err() {
+						std::fs::remove_file(&staged)
+							.context("Failed to remove staged addon file")?;
+					}
+					result?;
+
+					std::fs::rename(&staged, &path).context("Failed to move stored addon file")?;
+					std::fs::metadata(&path)
+						.context("Failed to get size of stored addon file")?
+						.len()
 				}
 				AddonLocation::Local(actual_path) => {
 					update_hardlink(&actual_path, &path)
 						.context("Failed to hardlink local addon")?;
-				}
-			}
+					// Local addons are not processed by plugins
+					let _ = std::fs::remove_file(get_processed_hash_path(&path));
 
-			let result = Self::check_hashes_impl(hashes, &path);
-			// Remove the addon file if it fails the checksum
-			if result.is_err() {
-				std::fs::remove_file(path).context("Failed to remove stored addon file")?;
-			}
-			result?;
+					let result = Self::check_hashes_impl(hashes, &path);
+					// Remove the addon file if it fails the checksum
+					if result.is_err() {
+						std::fs::remove_file(path).context("Failed to remove stored addon file")?;
+					}
+					result?;
 
-			Ok(())
+					0
+				}
+			};
+
+			Ok(downloaded)
 		};
 
 		Ok(task)
@@ -185,6 +216,102 @@ impl AddonRequest {
 	}
 }
 
+/// Runs the plugin hooks for processing an addon file that was just downloaded, which can modify
+/// the file in place before it is installed. A hook that fails is skipped with a warning, unless
+/// its plugin requires the hook to succeed. If the file is modified, its new hash is recorded
+/// so that the installed addon can still be verified
+pub fn process_downloaded_addon(
+	addon: &Addon,
+	path: &Path,
+	plugins: &PluginManager,
+	paths: &Paths,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<()> {
+	// The file was just downloaded, so any recorded hash is for an older version of it
+	let hash_path = get_processed_hash_path(path);
+	if hash_path.exists() {
+		std::fs::remove_file(&hash_path).context("Failed to remove processed addon hash")?;
+	}
+	let original_hash = hash_file_sha256(path).context("Failed to hash addon file")?;
+
+	let arg = ProcessDownloadedAddonArg {
+		path: path.to_string_lossy().to_string(),
+		pkg_id: addon.pkg_id.to_string(),
+		addon_id: addon.id.clone(),
+		kind: Some(addon.kind),
+		file_name: addon.file_name.clone(),
+		version: addon.version.clone(),
+	};
+
+	let lock = plugins.get_lock()?;
+	let mcvm_version = lock.manager.get_mcvm_version();
+	for plugin in lock.manager.iter_plugins() {
+		let result = plugin
+			.call_hook(&ProcessDownloadedAddon, &arg, &paths.core, mcvm_version, o)
+			.and_then(|handle| handle.map(|x| x.result(o)).transpose());
+		let Err(e) = result else {
+			continue;
+		};
+
+		let required = plugin
+			.get_manifest()
+			.required_hooks
+			.contains(ProcessDownloadedAddon::get_name_static());
+		if required {
+			return Err(e).with_context(|| {
+				format!(
+					"Plugin '{}' failed to process addon '{}'",
+					plugin.get_id(),
+					addon.id
+				)
+			});
+		}
+
+		o.display(
+			MessageContents::Warning(translate!(
+				o,
+				AddonHookFailed,
+				"plugin" = plugin.get_id(),
+				"addon" = &addon.id,
+				"error" = &format!("{e:#}")
+			)),
+			MessageLevel::Important,
+		);
+	}
+
+	let hash = hash_file_sha256(path).context("Failed to hash processed addon file")?;
+	if hash != original_hash {
+		std::fs::write(hash_path, hash).context("Failed to write processed addon hash")?;
+	}
+
+	Ok(())
+}
+
+/// Gets the hashes that an installed addon file is expected to have. This is the hash of the file
+/// after it was modified by plugins if it was, and the hashes given by the package otherwise
+pub fn get_installed_hashes(
+	addon: &Addon,
+	stored_path: &Path,
+) -> anyhow::Result<PackageAddonOptionalHashes> {
+	let hash_path = get_processed_hash_path(stored_path);
+	if !hash_path.exists() {
+		return Ok(addon.hashes.clone());
+	}
+
+	let hash = std::fs::read_to_string(hash_path).context("Failed to read processed addon hash")?;
+	Ok(PackageAddonOptionalHashes {
+		sha256: Some(hash.trim().to_string()),
+		sha512: None,
+	})
+}
+
+/// Get the path to the file that records the hash of a stored addon after it was processed by plugins
+pub fn get_processed_hash_path(stored_path: &Path) -> PathBuf {
+	let mut file_name = stored_path.file_name().unwrap_or_default().to_owned();
+	file_name.push(".processed.sha256");
+	stored_path.with_file_name(file_name)
+}
+
 /// Checks if the modloader and plugin loader are compatible with each other
 pub fn game_modifications_compatible(modloader: &Modloader, plugin_loader: &ServerType) -> bool {
 	matches!(
@@ -195,9 +322,15 @@ pub fn game_modifications_compatible(modloader: &Modloader, plugin_loader: &Serv
 
 #[cfg(test)]
 mod tests {
+	use std::collections::HashMap;
+	use std::sync::Arc;
+
+	use mcvm_plugin::plugin::{HookHandler, NativeHookHandler, PluginManifest};
+	use mcvm_shared::output::NoOp;
 	use mcvm_shared::pkg::{PackageAddonOptionalHashes, PackageID};
 
 	use super::*;
+	use crate::config::plugin::PluginConfig;
 
 	#[test]
 	fn test_game_mods_compat() {
@@ -227,4 +360,71 @@ mod tests {
 		};
 		assert_eq!(addon.split_filename(), ("FooBar", ".baz.jar"));
 	}
+
+	#[test]
+	fn test_process_downloaded_addon() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let paths = Paths::with_data_dir_no_create(dir.to_owned()).unwrap();
+		let path = dir.join("addon.jar");
+		std::fs::write(&path, "original").unwrap();
+
+		let addon = Addon {
+			kind: AddonKind::Mod,
+			id: "foo".into(),
+			file_name: "foo.jar".into(),
+			pkg_id: PackageID::from("package"),
+			version: Some("1".into()),
+			hashes: PackageAddonOptionalHashes::default(),
+		};
+
+		let create_plugins = |required: bool, function: NativeHookHandler| {
+			let mut manifest = PluginManifest::new();
+			manifest.hooks = HashMap::from([(
+				ProcessDownloadedAddon::get_name_static().to_string(),
+				HookHandler::Native { function },
+			)]);
+			if required {
+				manifest
+					.required_hooks
+					.insert(ProcessDownloadedAddon::get_name_static().to_string());
+			}
+			let config = PluginConfig {
+				id: "patcher".into(),
+				custom_config: None,
+			};
+			let mut plugins = PluginManager::new();
+			plugins
+				.add_plugin(config, manifest, &paths, None, &mut NoOp)
+				.unwrap();
+			plugins
+		};
+
+		// A hook that patches the file
+		let patch = Arc::new(|arg: String| -> anyhow::Result<String> {
+			let arg: ProcessDownloadedAddonArg = serde_json::from_str(&arg)?;
+			assert_eq!(arg.addon_id, "foo");
+			let contents = std::fs::read_to_string(&arg.path)?;
+			std::fs::write(&arg.path, contents.replace("original", "patched"))?;
+			Ok("null".into())
+		});
+		let plugins = create_plugins(false, patch);
+		process_downloaded_addon(&addon, &path, &plugins, &paths, &mut NoOp).unwrap();
+		assert_eq!(std::fs::read_to_string(&path).unwrap(), "patched");
+		// The hash of the patched file is the one that the installed addon is checked against
+		assert_eq!(
+			get_installed_hashes(&addon, &path)
+				.unwrap()
+				.sha256
+				.as_deref(),
+			Some("d7017ebcd65455e76e953d5b42fa96c3df28c7c3b616c7f069ed930fb4fae5fd")
+		);
+
+		// A failing hook is only an error if it is required
+		let fail = Arc::new(|_: String| -> anyhow::Result<String> { bail!("Hook failed") });
+		let plugins = create_plugins(false, fail.clone());
+		process_downloaded_addon(&addon, &path, &plugins, &paths, &mut NoOp).unwrap();
+		let plugins = create_plugins(true, fail);
+		assert!(process_downloaded_addon(&addon, &path, &plugins, &paths, &mut NoOp).is_err());
+	}
 }
@@ -40,14 +40,19 @@ pub struct ConfigBuilder {
 impl ConfigBuilder {
 	/// Construct a new ConfigBuilder
 	pub fn new(prefs: ConfigPreferences, repos: Vec<PkgRepo>) -> Self {
-		let packages = PkgRegistry::new(repos, prefs.package_caching_strategy.clone());
+		let plugins = PluginManager::new();
+		let packages = PkgRegistry::new(
+			repos,
+			prefs.package_caching_strategy.clone(),
+			plugins.clone(),
+		);
 		Self {
 			users: UserManager::new(ClientId::new("".into())),
 			instances: HashMap::new(),
 			instance_groups: HashMap::new(),
 			packages,
 			preferences: prefs,
-			plugins: PluginManager::new(),
+			plugins,
 			default_user: None,
 		}
 	}
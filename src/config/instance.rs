@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 
 use anyhow::{bail, ensure, Context};
-use mcvm_core::io::java::args::MemoryNum;
+use mcvm_core::io::java::args::{ArgsPreset, MemoryNum};
 use mcvm_core::io::java::install::JavaInstallationKind;
+use mcvm_core::net::minecraft::SkinVariant;
 use mcvm_core::util::versions::MinecraftVersionDeser;
 use mcvm_plugin::hooks::ModifyInstanceConfig;
 use mcvm_shared::id::{InstanceID, ProfileID};
@@ -148,6 +149,11 @@ pub struct LaunchArgs {
 	#[serde(default)]
 	#[serde(skip_serializing_if = "DefaultExt::is_default")]
 	pub game: Args,
+	/// Whether these arguments should replace the ones inherited from profiles
+	/// instead of being appended to them
+	#[serde(default)]
+	#[serde(skip_serializing_if = "DefaultExt::is_default")]
+	pub replace: bool,
 }
 
 /// Different representations of both memory arguments for the JVM
@@ -239,6 +245,43 @@ pub struct LaunchConfig {
 	#[serde(default)]
 	#[serde(skip_serializing_if = "DefaultExt::is_default")]
 	pub use_log4j_config: bool,
+	/// A directory to run the game in instead of the game directory
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub cwd: Option<String>,
+	/// Settings for capturing the output of the game to log files
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub log_capture: Option<LogCaptureConfig>,
+	/// Whether to suppress the warning that demo users can only play for a limited time
+	#[serde(default)]
+	#[serde(skip_serializing_if = "DefaultExt::is_default")]
+	pub suppress_demo_warning: bool,
+	/// A skin variant to switch the active skin of the user to before launching
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub preferred_skin_variant: Option<SkinVariant>,
+}
+
+/// Settings for capturing the output of the game to log files
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+#[serde(default)]
+pub struct LogCaptureConfig {
+	/// The maximum size of a log file in bytes before it is rotated
+	pub max_file_size: u64,
+	/// The maximum number of rotated log files to keep
+	pub max_files: u32,
+}
+
+impl Default for LogCaptureConfig {
+	fn default() -> Self {
+		let default = mcvm_core::launch::LogCaptureConfig::default();
+		Self {
+			max_file_size: default.max_file_size,
+			max_files: default.max_files,
+		}
+	}
 }
 
 impl LaunchConfig {
@@ -262,8 +305,15 @@ impl LaunchConfig {
 				);
 			}
 		}
+		let preset = ArgsPreset::parse(&self.preset)
+			.with_context(|| format!("Unknown JVM arguments preset '{}'", self.preset))?;
+		let jvm_args = self.args.jvm.parse();
+		preset
+			.check_conflicts(&jvm_args)
+			.context("JVM arguments preset conflicts with custom JVM arguments")?;
 		Ok(LaunchOptions {
-			jvm_args: self.args.jvm.parse(),
+			jvm_args,
+			preset,
 			game_args: self.args.game.parse(),
 			min_mem,
 			max_mem,
@@ -272,13 +322,26 @@ impl LaunchConfig {
 			wrapper: self.wrapper,
 			quick_play: self.quick_play,
 			use_log4j_config: self.use_log4j_config,
+			cwd_override: self.cwd.map(PathBuf::from),
+			log_capture: self
+				.log_capture
+				.map(|x| mcvm_core::launch::LogCaptureConfig {
+					max_file_size: x.max_file_size,
+					max_files: x.max_files,
+				}),
+			suppress_demo_warning: self.suppress_demo_warning,
+			preferred_skin_variant: self.preferred_skin_variant,
 		})
 	}
 
 	/// Merge multiple LaunchConfigs
 	pub fn merge(&mut self, other: Self) -> &mut Self {
-		self.args.jvm.merge(other.args.jvm);
-		self.args.game.merge(other.args.game);
+		if other.args.replace {
+			self.args = other.args;
+		} else {
+			self.args.jvm.merge(other.args.jvm);
+			self.args.game.merge(other.args.game);
+		}
 		if !matches!(other.memory, LaunchMemory::None) {
 			self.memory = other.memory;
 		}
@@ -293,6 +356,18 @@ impl LaunchConfig {
 		if !matches!(other.quick_play, QuickPlay::None) {
 			self.quick_play = other.quick_play;
 		}
+		if other.cwd.is_some() {
+			self.cwd = other.cwd;
+		}
+		if other.log_capture.is_some() {
+			self.log_capture = other.log_capture;
+		}
+		if other.suppress_demo_warning {
+			self.suppress_demo_warning = true;
+		}
+		if other.preferred_skin_variant.is_some() {
+			self.preferred_skin_variant = other.preferred_skin_variant;
+		}
 
 		self
 	}
@@ -304,6 +379,7 @@ impl Default for LaunchConfig {
 			args: LaunchArgs {
 				jvm: Args::default(),
 				game: Args::default(),
+				replace: false,
 			},
 			memory: LaunchMemory::default(),
 			java: default_java(),
@@ -312,6 +388,10 @@ impl Default for LaunchConfig {
 			wrapper: None,
 			quick_play: QuickPlay::default(),
 			use_log4j_config: false,
+			cwd: None,
+			log_capture: None,
+			suppress_demo_warning: false,
+			preferred_skin_variant: None,
 		}
 	}
 }
@@ -505,6 +585,8 @@ fn consolidate_package_configs(
 
 #[cfg(test)]
 mod tests {
+	use mcvm_shared::output::NoOp;
+
 	use super::*;
 
 	#[test]
@@ -534,4 +616,115 @@ mod tests {
 			}
 		);
 	}
+
+	#[test]
+	fn test_args_inheritance() {
+		let profile: ProfileConfig = serde_json::from_value(serde_json::json!({
+			"type": "client",
+			"version": "1.19.2",
+			"launch": {
+				"args": {
+					"jvm": ["-Dprofile=true"],
+					"game": "--profile"
+				}
+			}
+		}))
+		.unwrap();
+		let profiles = HashMap::from([(ProfileID::from("profile"), profile)]);
+		let plugins = PluginManager::new();
+		let paths = Paths::new_no_create().unwrap();
+
+		let config = |replace: bool| -> InstanceConfig {
+			serde_json::from_value(serde_json::json!({
+				"from": "profile",
+				"launch": {
+					"args": {
+						"jvm": ["-Dinstance=true"],
+						"replace": replace
+					}
+				}
+			}))
+			.unwrap()
+		};
+
+		let instance = read_instance_config(
+			InstanceID::from("append"),
+			config(false),
+			&profiles,
+			&plugins,
+			&paths,
+			&mut NoOp,
+		)
+		.unwrap();
+		assert_eq!(
+			instance.config.launch.jvm_args,
+			vec!["-Dprofile=true".to_string(), "-Dinstance=true".to_string()]
+		);
+		assert_eq!(
+			instance.config.launch.game_args,
+			vec!["--profile".to_string()]
+		);
+
+		let instance = read_instance_config(
+			InstanceID::from("replace"),
+			config(true),
+			&profiles,
+			&plugins,
+			&paths,
+			&mut NoOp,
+		)
+		.unwrap();
+		assert_eq!(
+			instance.config.launch.jvm_args,
+			vec!["-Dinstance=true".to_string()]
+		);
+		assert!(instance.config.launch.game_args.is_empty());
+	}
+
+	#[test]
+	fn test_launch_env_precedence() {
+		let profile: ProfileConfig = serde_json::from_value(serde_json::json!({
+			"type": "client",
+			"version": "1.19.2",
+			"launch": {
+				"env": {
+					"MCVM_TEST_ENV_OVERRIDDEN": "profile",
+					"MCVM_TEST_ENV_PROFILE": "profile",
+					"MCVM_TEST_ENV_SYSTEM": "profile"
+				}
+			}
+		}))
+		.unwrap();
+		let profiles = HashMap::from([(ProfileID::from("profile"), profile)]);
+		let config: InstanceConfig = serde_json::from_value(serde_json::json!({
+			"from": "profile",
+			"launch": {
+				"env": {
+					"MCVM_TEST_ENV_OVERRIDDEN": "instance"
+				}
+			}
+		}))
+		.unwrap();
+		let instance = read_instance_config(
+			InstanceID::from("env"),
+			config,
+			&profiles,
+			&PluginManager::new(),
+			&Paths::new_no_create().unwrap(),
+			&mut NoOp,
+		)
+		.unwrap();
+		let inherited = HashMap::from([
+			("MCVM_TEST_ENV_SYSTEM".to_string(), "system".to_string()),
+			("MCVM_TEST_ENV_INHERITED".to_string(), "system".to_string()),
+		]);
+		let core_env = HashMap::from([("MCVM_TEST_ENV_CORE".to_string(), "core".to_string())]);
+		let env = instance.config.launch.build_launch_env(inherited, core_env);
+		let get = |key: &str| env.get(key).map(String::as_str);
+		assert_eq!(get("MCVM_TEST_ENV_OVERRIDDEN"), Some("instance"));
+		assert_eq!(get("MCVM_TEST_ENV_PROFILE"), Some("profile"));
+		assert_eq!(get("MCVM_TEST_ENV_SYSTEM"), Some("profile"));
+		assert_eq!(get("MCVM_TEST_ENV_INHERITED"), Some("system"));
+		assert_eq!(get("MCVM_TEST_ENV_CORE"), Some("core"));
+	}
 }
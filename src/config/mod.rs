@@ -117,7 +117,11 @@ impl Config {
 		let (prefs, repositories) =
 			ConfigPreferences::read(&config.preferences).context("Failed to read preferences")?;
 
-		let packages = PkgRegistry::new(repositories, prefs.package_caching_strategy.clone());
+		let packages = PkgRegistry::new(
+			repositories,
+			prefs.package_caching_strategy.clone(),
+			plugins.clone(),
+		);
 
 		// Users
 		for (user_id, user_config) in config.users.iter() {
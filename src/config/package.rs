@@ -4,8 +4,11 @@ use std::sync::Arc;
 
 use anyhow::{bail, ensure};
 use mcvm_pkg::properties::PackageProperties;
-use mcvm_shared::pkg::{is_valid_package_id, ArcPkgReq, PackageID, PackageStability};
+use mcvm_shared::pkg::{
+	normalize_package_id, parse_package_id, ArcPkgReq, PackageID, PackageStability,
+};
 use mcvm_shared::util::is_valid_identifier;
+use mcvm_shared::versions::VersionPattern;
 #[cfg(feature = "schema")]
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -73,11 +76,40 @@ impl PackageConfig {
 		Ok(out)
 	}
 
+	/// Calculate the content version that the package is pinned to, making sure
+	/// that the package has a content version that matches it
+	pub fn calculate_content_version(
+		&self,
+		properties: &PackageProperties,
+	) -> anyhow::Result<Option<VersionPattern>> {
+		let Some(content_version) = &self.content_version else {
+			return Ok(None);
+		};
+		let pattern = VersionPattern::from(content_version.as_str());
+
+		let empty = Vec::new();
+		let available = properties.content_versions.as_ref().unwrap_or(&empty);
+		ensure!(
+			available
+				.iter()
+				.any(|x| pattern.matches_single(x, available)),
+			"Configured content version '{content_version}' does not match any content version of the package"
+		);
+
+		Ok(Some(pattern))
+	}
+
 	/// Get the request of the config
 	pub fn get_request(&self) -> ArcPkgReq {
-		Arc::new(PkgRequest::parse(
+		let content_version = self
+			.content_version
+			.as_deref()
+			.map(VersionPattern::from)
+			.unwrap_or(VersionPattern::Any);
+		Arc::new(PkgRequest::new(
 			self.id.clone(),
 			PkgRequestSource::UserRequire,
+			content_version,
 		))
 	}
 }
@@ -166,13 +198,13 @@ impl PackageConfigDeser {
 		let content_version = self.get_content_version().cloned();
 
 		let (id, content_version) = if let Some((real_id, version)) = id.split_once('@') {
-			(real_id.into(), Some(version.into()))
+			(real_id, Some(version.into()))
 		} else {
-			(id, content_version)
+			(id.as_ref(), content_version)
 		};
 
 		PackageConfig {
-			id,
+			id: normalize_package_id(id).into(),
 			source,
 			features: self.get_features(),
 			use_default_features: self.get_use_default_features(),
@@ -242,9 +274,8 @@ impl PackageConfigDeser {
 	/// Validate this config
 	pub fn validate(&self) -> anyhow::Result<()> {
 		let id = self.get_pkg_id();
-		if !is_valid_package_id(&id) {
-			bail!("Invalid package ID '{id}'");
-		}
+		let id = id.split_once('@').map(|x| x.0).unwrap_or(&id);
+		parse_package_id(id)?;
 
 		for feature in self.get_features() {
 			if !is_valid_identifier(&feature) {
@@ -255,3 +286,49 @@ impl PackageConfigDeser {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_content_version_pinning() {
+		let properties = PackageProperties {
+			content_versions: Some(vec!["1.0".into(), "2.0".into()]),
+			..Default::default()
+		};
+
+		let mut config = PackageConfig::from_id("foo".into());
+		assert_eq!(config.calculate_content_version(&properties).unwrap(), None);
+
+		config.content_version = Some("1.0".into());
+		assert_eq!(
+			config.calculate_content_version(&properties).unwrap(),
+			Some(VersionPattern::Single("1.0".into()))
+		);
+		assert_eq!(
+			config.get_request().content_version,
+			VersionPattern::Single("1.0".into())
+		);
+
+		config.content_version = Some("3.0".into());
+		assert!(config.calculate_content_version(&properties).is_err());
+		assert!(config
+			.calculate_content_version(&PackageProperties::default())
+			.is_err());
+	}
+
+	#[test]
+	fn test_package_id_normalization() {
+		let config = PackageConfigDeser::Basic("MyMod@1.0".into());
+		config.validate().unwrap();
+		let config =
+			config.to_package_config(PackageStability::Stable, PackageConfigSource::Instance);
+		assert_eq!(config.id.as_ref(), "mymod");
+		assert_eq!(config.content_version.as_deref(), Some("1.0"));
+
+		assert!(PackageConfigDeser::Basic("my_mod".into())
+			.validate()
+			.is_err());
+	}
+}
@@ -2,7 +2,7 @@ use std::collections::HashSet;
 use std::path::PathBuf;
 
 use crate::pkg::reg::CachingStrategy;
-use crate::pkg::repo::{PkgRepo, PkgRepoLocation};
+use crate::pkg::repo::{sort_repos, PkgRepo, PkgRepoLocation};
 use mcvm_core::net::download::validate_url;
 
 use anyhow::{bail, Context};
@@ -18,10 +18,16 @@ pub struct ConfigPreferences {
 	pub package_caching_strategy: CachingStrategy,
 	/// The global language
 	pub language: Language,
+	/// Whether to warn about packages that are shadowed by other repositories
+	pub warn_shadowed_packages: bool,
+	/// Whether to censor secrets such as access tokens in output
+	pub censor_secrets: bool,
+	/// The maximum number of bytes per second for downloads
+	pub bandwidth_limit: Option<u64>,
 }
 
 /// Deserialization struct for user preferences
-#[derive(Deserialize, Serialize, Default)]
+#[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
 #[serde(default)]
 pub struct PrefDeser {
@@ -31,6 +37,27 @@ pub struct PrefDeser {
 	pub package_caching_strategy: CachingStrategy,
 	/// The user's configured language
 	pub language: Language,
+	/// Whether to warn about packages that are shadowed by other repositories
+	/// when the config is loaded
+	pub warn_shadowed_packages: bool,
+	/// Whether to censor secrets such as access tokens in output and logs
+	pub censor_secrets: bool,
+	/// The maximum number of bytes per second for downloads. Unlimited if not set
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub bandwidth_limit: Option<u64>,
+}
+
+impl Default for PrefDeser {
+	fn default() -> Self {
+		Self {
+			repositories: RepositoriesDeser::default(),
+			package_caching_strategy: CachingStrategy::default(),
+			language: Language::default(),
+			warn_shadowed_packages: false,
+			censor_secrets: true,
+			bandwidth_limit: None,
+		}
+	}
 }
 
 /// Deserialization struct for a package repo
@@ -48,6 +75,11 @@ pub struct RepoDeser {
 	/// Whether to disable the repo and not add it to the list
 	#[serde(default)]
 	pub disable: bool,
+	/// The priority of the repo. Repos with higher priorities override
+	/// packages from ones with lower priorities. Defaults to a priority
+	/// based on whether the repo is preferred or a backup
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub priority: Option<i32>,
 }
 
 /// Deserialization struct for all configured package repositories
@@ -65,6 +97,9 @@ pub struct RepositoriesDeser {
 	pub enable_core: bool,
 	/// Whether to enable the std repository
 	pub enable_std: bool,
+	/// The maximum size in bytes of a remote repository index to download
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_index_size: Option<u64>,
 }
 
 impl Default for RepositoriesDeser {
@@ -74,6 +109,7 @@ impl Default for RepositoriesDeser {
 			backup: Vec::new(),
 			enable_core: true,
 			enable_std: true,
+			max_index_size: None,
 		}
 	}
 }
@@ -85,7 +121,7 @@ impl ConfigPreferences {
 		let mut repositories = Vec::new();
 		for repo in prefs.repositories.preferred.iter() {
 			if !repo.disable {
-				add_repo(&mut repositories, repo)?;
+				add_repo(&mut repositories, repo, PkgRepo::PREFERRED_PRIORITY)?;
 			}
 		}
 		repositories.extend(PkgRepo::default_repos(
@@ -94,9 +130,13 @@ impl ConfigPreferences {
 		));
 		for repo in prefs.repositories.backup.iter() {
 			if !repo.disable {
-				add_repo(&mut repositories, repo)?;
+				add_repo(&mut repositories, repo, PkgRepo::BACKUP_PRIORITY)?;
 			}
 		}
+		sort_repos(&mut repositories);
+		for repo in &mut repositories {
+			repo.max_index_size = prefs.repositories.max_index_size;
+		}
 
 		// Check for duplicate IDs
 		let mut existing = HashSet::new();
@@ -111,14 +151,21 @@ impl ConfigPreferences {
 			Self {
 				package_caching_strategy: prefs.package_caching_strategy.clone(),
 				language: prefs.language,
+				warn_shadowed_packages: prefs.warn_shadowed_packages,
+				censor_secrets: prefs.censor_secrets,
+				bandwidth_limit: prefs.bandwidth_limit,
 			},
 			repositories,
 		))
 	}
 }
 
-/// Add a repo to the list
-fn add_repo(repos: &mut Vec<PkgRepo>, repo: &RepoDeser) -> anyhow::Result<()> {
+/// Add a repo to the list, with a priority to use if it does not set its own
+fn add_repo(
+	repos: &mut Vec<PkgRepo>,
+	repo: &RepoDeser,
+	default_priority: i32,
+) -> anyhow::Result<()> {
 	let location = if let Some(url) = &repo.url {
 		validate_url(url).with_context(|| {
 			format!("Invalid url '{}' in package repository '{}'", url, repo.id)
@@ -129,6 +176,57 @@ fn add_repo(repos: &mut Vec<PkgRepo>, repo: &RepoDeser) -> anyhow::Result<()> {
 	} else {
 		bail!("Niether path nor URL was set for repository {}", repo.id);
 	};
-	repos.push(PkgRepo::new(&repo.id, location));
+	let priority = repo.priority.unwrap_or(default_priority);
+	repos.push(PkgRepo::new(&repo.id, location).with_priority(priority));
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_repo_ids(prefs: serde_json::Value) -> Vec<String> {
+		let prefs: PrefDeser = serde_json::from_value(prefs).unwrap();
+		let (_, repos) = ConfigPreferences::read(&prefs).unwrap();
+		repos.into_iter().map(|x| x.id).collect()
+	}
+
+	#[test]
+	fn test_censor_secrets_default() {
+		let prefs: PrefDeser = serde_json::from_value(serde_json::json!({})).unwrap();
+		assert!(prefs.censor_secrets);
+		let prefs: PrefDeser =
+			serde_json::from_value(serde_json::json!({"censor_secrets": false})).unwrap();
+		let (prefs, _) = ConfigPreferences::read(&prefs).unwrap();
+		assert!(!prefs.censor_secrets);
+	}
+
+	#[test]
+	fn test_default_repo_order() {
+		let ids = get_repo_ids(serde_json::json!({
+			"repositories": {
+				"preferred": [{"id": "a", "path": "a"}, {"id": "b", "path": "b"}],
+				"backup": [{"id": "c", "path": "c"}, {"id": "d", "path": "d"}]
+			}
+		}));
+		assert_eq!(ids, vec!["a", "b", "core", "std", "c", "d"]);
+	}
+
+	#[test]
+	fn test_repo_priority() {
+		let ids = get_repo_ids(serde_json::json!({
+			"repositories": {
+				"preferred": [
+					{"id": "a", "path": "a", "priority": -200},
+					{"id": "b", "path": "b"}
+				],
+				"backup": [
+					{"id": "c", "path": "c", "priority": 500},
+					{"id": "d", "path": "d", "priority": 25},
+					{"id": "e", "path": "e", "priority": 0}
+				]
+			}
+		}));
+		assert_eq!(ids, vec!["c", "b", "core", "d", "std", "e", "a"]);
+	}
+}
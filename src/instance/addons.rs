@@ -6,17 +6,19 @@ use mcvm_shared::versions::{VersionInfo, VersionPattern};
 
 use crate::addon::{self, AddonExt};
 use crate::io::paths::Paths;
+use crate::io::transaction::FileTransaction;
 
 use super::{InstKind, Instance};
 
 impl Instance {
-	/// Creates an addon on the instance
+	/// Stages an addon to be created on the instance when the transaction is committed
 	pub fn create_addon(
 		&mut self,
 		addon: &Addon,
 		selected_worlds: &[String],
 		paths: &Paths,
 		version_info: &VersionInfo,
+		transaction: &mut FileTransaction,
 	) -> anyhow::Result<()> {
 		self.ensure_dirs(paths)?;
 
@@ -24,7 +26,7 @@ impl Instance {
 			.get_linked_addon_paths(addon, selected_worlds, paths, version_info)
 			.context("Failed to get linked directory")?
 		{
-			Self::link_addon(&path, addon, paths, &self.id)
+			Self::link_addon(&path, addon, paths, &self.id, transaction)
 				.with_context(|| format!("Failed to link addon {}", addon.id))?;
 		}
 
@@ -103,25 +105,20 @@ impl Instance {
 		})
 	}
 
-	/// Hardlinks the addon from the path in addon storage to the correct in the instance,
-	/// under the specified directory
+	/// Stages a hardlink of the addon from the path in addon storage to the correct in the instance,
+	/// under the specified directory. The current link is replaced when the transaction is committed
 	fn link_addon(
 		dir: &Path,
 		addon: &Addon,
 		paths: &Paths,
 		instance_id: &str,
+		transaction: &mut FileTransaction,
 	) -> anyhow::Result<()> {
 		let link = dir.join(addon.file_name.clone());
 		let addon_path = addon.get_path(paths, instance_id);
-		mcvm_core::io::files::create_leading_dirs(&link)?;
-		// These checks are to make sure that we properly link the hardlink to the right location
-		// We have to remove the current link since it doesnt let us update it in place
+		// This check is to make sure that we properly link the hardlink to the right location
 		ensure!(addon_path.exists(), "Addon path does not exist");
-		if link.exists() {
-			std::fs::remove_file(&link).context("Failed to remove instance addon file")?;
-		}
-		mcvm_core::io::files::update_hardlink(&addon_path, &link)
-			.context("Failed to create hard link")?;
+		transaction.stage_hardlink(&addon_path, &link)?;
 		Ok(())
 	}
 
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use mcvm_shared::addon::AddonKind;
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
+use mcvm_shared::pkg::PackageID;
+use mcvm_shared::translate;
+
+use crate::io::lock::Lockfile;
+use crate::io::mod_metadata::read_mod_ids;
+
+use super::Instance;
+
+/// A mod that is provided by more than one installed file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateMod {
+	/// The ID of the mod
+	pub mod_id: String,
+	/// The packages that provide the mod, sorted by ID
+	pub packages: Vec<PackageID>,
+}
+
+/// Finds the mods that are provided by more than one of the given mod files, which are paired
+/// with the packages that installed them. Files that can't be read as mods are ignored.
+/// The output is sorted by mod ID
+pub fn find_duplicate_mods(files: &[(PackageID, PathBuf)]) -> Vec<DuplicateMod> {
+	let mut providers: HashMap<String, Vec<(&PackageID, &Path)>> = HashMap::new();
+	for (package, path) in files {
+		let Ok(mod_ids) = read_mod_ids(path) else {
+			continue;
+		};
+		for mod_id in mod_ids {
+			providers.entry(mod_id).or_default().push((package, path));
+		}
+	}
+
+	let mut out: Vec<_> = providers
+		.into_iter()
+		.filter(|(_, providers)| providers.len() > 1)
+		.map(|(mod_id, providers)| DuplicateMod {
+			mod_id,
+			packages: providers
+				.into_iter()
+				.map(|(package, _)| package.clone())
+				.sorted()
+				.dedup()
+				.collect(),
+		})
+		.collect();
+	out.sort_by(|a, b| a.mod_id.cmp(&b.mod_id));
+
+	out
+}
+
+impl Instance {
+	/// Checks the mods installed by packages on this instance for any that are installed
+	/// more than once, and warns about them
+	pub fn check_duplicate_mods(&self, lock: &Lockfile, o: &mut impl MCVMOutput) {
+		let files = lock.get_installed_addon_files(&self.id, AddonKind::Mod);
+		for duplicate in find_duplicate_mods(&files) {
+			o.display(
+				MessageContents::Warning(translate!(
+					o,
+					DuplicateMod,
+					"mod" = &duplicate.mod_id,
+					"pkgs" = &duplicate.packages.iter().join(", ")
+				)),
+				MessageLevel::Important,
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs::File;
+	use std::io::Write;
+
+	use zip::write::FileOptions;
+	use zip::ZipWriter;
+
+	use super::*;
+
+	fn create_jar(path: &Path, metadata_path: &str, metadata: &str) {
+		let mut zip = ZipWriter::new(File::create(path).unwrap());
+		zip.start_file(metadata_path, FileOptions::<()>::default())
+			.unwrap();
+		zip.write_all(metadata.as_bytes()).unwrap();
+		zip.finish().unwrap();
+	}
+
+	#[test]
+	fn test_find_duplicate_mods() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let mods_dir = dir.join("mods");
+		std::fs::create_dir_all(&mods_dir).unwrap();
+
+		create_jar(
+			&mods_dir.join("sodium.jar"),
+			"fabric.mod.json",
+			r#"{ "id": "sodium", "version": "0.5.8" }"#,
+		);
+		create_jar(
+			&mods_dir.join("sodium-old.jar"),
+			"fabric.mod.json",
+			r#"{ "id": "sodium", "version": "0.4.10" }"#,
+		);
+		create_jar(
+			&mods_dir.join("jei.jar"),
+			"META-INF/mods.toml",
+			"modLoader = \"javafml\"\n[[mods]]\nmodId = \"jei\"\nversion = \"1.0\"\n",
+		);
+		std::fs::write(mods_dir.join("broken.jar"), "not a jar").unwrap();
+
+		let files = vec![
+			(PackageID::from("sodium"), mods_dir.join("sodium.jar")),
+			(PackageID::from("old"), mods_dir.join("sodium-old.jar")),
+			(PackageID::from("jei"), mods_dir.join("jei.jar")),
+			(PackageID::from("broken"), mods_dir.join("broken.jar")),
+		];
+		assert_eq!(
+			find_duplicate_mods(&files),
+			vec![DuplicateMod {
+				mod_id: "sodium".into(),
+				packages: vec![PackageID::from("old"), PackageID::from("sodium")],
+			}]
+		);
+	}
+}
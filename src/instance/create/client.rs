@@ -31,6 +31,10 @@ impl Instance {
 					.context("Failed to install Fabric/Quilt")?,
 			);
 		}
+		self.modification_data.forge_profile = manager
+			.forge_profile
+			.is_full()
+			.then(|| manager.forge_profile.get_clone());
 
 		// Create keypair file
 		if users.is_authenticated() {
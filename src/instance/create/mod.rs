@@ -16,7 +16,7 @@ use mcvm_core::user::uuid::hyphenate_uuid;
 use mcvm_core::user::{User, UserManager};
 use mcvm_core::version::InstalledVersion;
 use mcvm_core::QuickPlayType;
-use mcvm_mods::fabric_quilt;
+use mcvm_mods::{fabric_quilt, forge};
 use mcvm_plugin::hooks::{OnInstanceSetup, OnInstanceSetupArg};
 use mcvm_shared::modifications::Modloader;
 use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
@@ -53,6 +53,13 @@ impl Instance {
 					self.kind.to_side(),
 				));
 			}
+			// Forge and NeoForge can only be installed on the client
+			Modloader::Forge if self.kind.to_side() == Side::Client => {
+				out.insert(UpdateRequirement::ForgeLike(forge::Mode::Forge));
+			}
+			Modloader::NeoForged if self.kind.to_side() == Side::Client => {
+				out.insert(UpdateRequirement::ForgeLike(forge::Mode::NeoForge));
+			}
 			_ => {}
 		};
 		match &self.kind {
@@ -190,6 +197,7 @@ impl Instance {
 		let launch_config = LaunchConfiguration {
 			java: self.config.launch.java.clone(),
 			jvm_args: self.config.launch.jvm_args.clone(),
+			preset: self.config.launch.preset,
 			game_args: self.config.launch.game_args.clone(),
 			min_mem: self.config.launch.min_mem.clone(),
 			max_mem: self.config.launch.max_mem.clone(),
@@ -197,8 +205,12 @@ impl Instance {
 			wrappers: Vec::from_iter(wrapper),
 			quick_play,
 			use_log4j_config: self.config.launch.use_log4j_config,
+			cwd_override: self.config.launch.cwd_override.clone(),
+			log_capture: self.config.launch.log_capture.clone(),
+			suppress_demo_warning: self.config.launch.suppress_demo_warning,
+			preferred_skin_variant: self.config.launch.preferred_skin_variant,
 		};
-		let config = mcvm_core::InstanceConfiguration {
+		let mut config = mcvm_core::InstanceConfiguration {
 			side,
 			path: self.dirs.get().game_dir.clone(),
 			launch: launch_config,
@@ -206,6 +218,9 @@ impl Instance {
 			main_class: self.modification_data.main_class_override.clone(),
 			additional_libs: self.modification_data.classpath_extension.get_paths(),
 		};
+		if let Some(profile) = &self.modification_data.forge_profile {
+			profile.apply_to_config(&mut config);
+		}
 		let inst = version
 			.get_instance(config, o)
 			.await
@@ -339,6 +354,8 @@ pub struct ModificationData {
 	pub jar_path_override: Option<PathBuf>,
 	/// Extension for the classpath from modifications
 	pub classpath_extension: Classpath,
+	/// The Forge or NeoForge launch profile, which changes the main class, classpath, and arguments
+	pub forge_profile: Option<forge::LaunchProfile>,
 }
 
 impl ModificationData {
@@ -348,6 +365,7 @@ impl ModificationData {
 			main_class_override: None,
 			jar_path_override: None,
 			classpath_extension: Classpath::new(),
+			forge_profile: None,
 		}
 	}
 }
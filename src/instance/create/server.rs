@@ -77,6 +77,14 @@ impl Instance {
 	) -> anyhow::Result<UpdateMethodResult> {
 		let version = &manager.version_info.get().version;
 
+		// Offline mode can only use a JAR that has already been downloaded
+		let paper_jar_path = paper::get_local_jar_path(mode, version, &paths.core);
+		if manager.settings.offline && paper_jar_path.exists() {
+			self.modification_data.jar_path_override = Some(paper_jar_path.clone());
+			return Ok(UpdateMethodResult::from_path(paper_jar_path));
+		}
+		manager.ensure_online(&format!("{mode} server JAR"))?;
+
 		let process = OutputProcess::new(o);
 		process.0.display(
 			MessageContents::StartProcess("Checking for {mode} updates".into()),
@@ -89,7 +97,6 @@ impl Instance {
 		let file_name = paper::get_jar_file_name(mode, version, build_num, client)
 			.await
 			.context("Failed to get the {mode} file name")?;
-		let paper_jar_path = paper::get_local_jar_path(mode, version, &paths.core);
 		if !manager.should_update_file(&paper_jar_path) {
 			process.0.display(
 				MessageContents::Success(format!("{mode} is up to date")),
@@ -131,6 +138,15 @@ impl Instance {
 	) -> anyhow::Result<UpdateMethodResult> {
 		let version = &manager.version_info.get().version;
 
+		// Offline mode can only use a JAR that has already been downloaded
+		let sponge_jar_path =
+			sponge::get_local_jar_path(sponge::Mode::Vanilla, version, &paths.core);
+		if manager.settings.offline && sponge_jar_path.exists() {
+			self.modification_data.jar_path_override = Some(sponge_jar_path.clone());
+			return Ok(UpdateMethodResult::from_path(sponge_jar_path));
+		}
+		manager.ensure_online("Sponge server JAR")?;
+
 		let process = OutputProcess::new(o);
 		process.0.display(
 			MessageContents::StartProcess("Checking for Sponge updates".into()),
@@ -140,8 +156,6 @@ impl Instance {
 		let sponge_version = sponge::get_newest_version(sponge::Mode::Vanilla, version, client)
 			.await
 			.context("Failed to get newest Sponge version")?;
-		let sponge_jar_path =
-			sponge::get_local_jar_path(sponge::Mode::Vanilla, version, &paths.core);
 		if !manager.should_update_file(&sponge_jar_path) {
 			process.0.display(
 				MessageContents::Success("Sponge is up to date".into()),
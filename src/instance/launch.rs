@@ -1,12 +1,17 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use anyhow::Context;
 use mcvm_core::auth_crate::mc::ClientId;
-use mcvm_core::io::java::args::MemoryNum;
+use mcvm_core::io::java::args::{ArgsPreset, MemoryNum};
+use mcvm_core::io::java::classpath::Classpath;
 use mcvm_core::io::java::install::JavaInstallationKind;
+use mcvm_core::launch::{get_last_launch_command, LaunchCommand, LogCaptureConfig};
+use mcvm_core::net::minecraft::SkinVariant;
 use mcvm_core::user::UserManager;
 use mcvm_plugin::hooks::{
-	HookHandle, InstanceLaunchArg, OnInstanceLaunch, OnInstanceStop, WhileInstanceLaunch,
+	AddClasspathEntries, HookHandle, InstanceLaunchArg, OnInstanceLaunch, OnInstanceStop,
+	WhileInstanceLaunch,
 };
 use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
 use mcvm_shared::translate;
@@ -17,8 +22,9 @@ use serde::{Deserialize, Serialize};
 
 use super::update::manager::UpdateManager;
 use crate::config::instance::QuickPlay;
-use crate::plugin::PluginManager;
+use crate::io::lock::Lockfile;
 use crate::io::paths::Paths;
+use crate::plugin::PluginManager;
 
 use super::Instance;
 
@@ -32,6 +38,22 @@ impl Instance {
 		settings: LaunchSettings,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<InstanceHandle> {
+		let prepared = self
+			.prepare_launch(paths, users, plugins, settings, o)
+			.await?;
+		self.spawn(prepared, paths, plugins, o)
+	}
+
+	/// Update the instance and prepare everything needed to launch it, without starting the
+	/// game process. Launch hooks are not run until the prepared launch is spawned
+	pub async fn prepare_launch(
+		&mut self,
+		paths: &Paths,
+		users: &mut UserManager,
+		plugins: &PluginManager,
+		settings: LaunchSettings,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<PreparedLaunch> {
 		o.display(
 			MessageContents::StartProcess(translate!(o, StartUpdatingInstance, "inst" = &self.id)),
 			MessageLevel::Important,
@@ -45,6 +67,17 @@ impl Instance {
 		if settings.offline_auth {
 			manager.offline_auth();
 		}
+		if settings.offline {
+			manager.offline();
+		}
+		manager.set_censor_secrets(settings.censor_secrets);
+		manager.set_bandwidth_limit(settings.bandwidth_limit);
+		// Launch with the Forge/NeoForge version from the last update instead of installing a newer one
+		let lock = Lockfile::open(paths).context("Failed to open lockfile")?;
+		manager.set_forge_version(
+			lock.get_instance_modloader_version(&self.id)
+				.map(String::from),
+		);
 		manager
 			.fulfill_requirements(users, plugins, paths, &client, o)
 			.await
@@ -66,6 +99,15 @@ impl Instance {
 			pid: None,
 		};
 
+		// Add classpath entries from plugins
+		let results = plugins
+			.call_hook(AddClasspathEntries, &hook_arg, paths, o)
+			.context("Failed to call add classpath entries hook")?;
+		for result in results {
+			let entries = result.result(o)?;
+			add_classpath_entries(&mut self.modification_data.classpath_extension, entries);
+		}
+
 		let mut installed_version = manager
 			.get_core_version(o)
 			.await
@@ -84,6 +126,24 @@ impl Instance {
 			MessageLevel::Important,
 		);
 
+		let inner = instance
+			.prepare_launch(o)
+			.await
+			.context("Failed to prepare core instance launch")?;
+
+		Ok(PreparedLaunch { inner, hook_arg })
+	}
+
+	/// Start the game process of a prepared launch, running the launch hooks
+	pub fn spawn(
+		&self,
+		prepared: PreparedLaunch,
+		paths: &Paths,
+		plugins: &PluginManager,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<InstanceHandle> {
+		let PreparedLaunch { inner, hook_arg } = prepared;
+
 		// Run pre-launch hooks
 		let results = plugins
 			.call_hook(OnInstanceLaunch, &hook_arg, paths, o)
@@ -93,14 +153,13 @@ impl Instance {
 		}
 
 		// Launch the instance using core
-		let handle = instance
-			.launch_with_handle(o)
-			.await
-			.map_err(|e| {
-				eprintln!("🔥 Instance launch failure: {:#}", e);
-				e
-			})
-			.context("Failed to launch core instance")?;
+		let handle = inner.spawn(o).context("Failed to launch core instance")?;
+
+		// Record the PID so that the running state can be checked later
+		let mut lock = Lockfile::open(paths).context("Failed to open lockfile")?;
+		lock.update_instance_pid(&self.id, Some(handle.get_pid()));
+		lock.finish(paths)
+			.context("Failed to finish using lockfile")?;
 
 		// Run while_instance_launch hooks alongside
 		let hook_handles = plugins
@@ -114,6 +173,28 @@ impl Instance {
 
 		Ok(handle)
 	}
+
+	/// Get the command that was last used to launch this instance, with secrets censored
+	pub fn get_last_launch_command(
+		&mut self,
+		paths: &Paths,
+	) -> anyhow::Result<Option<LaunchCommand>> {
+		self.ensure_dirs(paths)?;
+		get_last_launch_command(&self.dirs.get().game_dir)
+	}
+
+	/// Checks whether this instance is currently running, using the PID recorded in the lockfile.
+	/// This works even if the instance was launched by another process
+	pub fn is_running(&self, paths: &Paths) -> bool {
+		Lockfile::open(paths).is_ok_and(|lock| lock.is_instance_running(&self.id))
+	}
+}
+
+/// Appends classpath entries supplied by plugins, skipping ones that are already present
+fn add_classpath_entries(classpath: &mut Classpath, entries: Vec<String>) {
+	for entry in entries {
+		classpath.add_unique(&entry);
+	}
 }
 
 /// Settings for launch provided to the instance launch function
@@ -122,6 +203,12 @@ pub struct LaunchSettings {
 	pub ms_client_id: ClientId,
 	/// Whether to do offline auth
 	pub offline_auth: bool,
+	/// Whether to skip all network operations and only use cached files
+	pub offline: bool,
+	/// Whether to censor secrets such as access tokens in output
+	pub censor_secrets: bool,
+	/// The maximum number of bytes per second for downloads
+	pub bandwidth_limit: Option<u64>,
 }
 
 /// Options for launching after conversion from the deserialized version
@@ -131,6 +218,8 @@ pub struct LaunchOptions {
 	pub java: JavaInstallationKind,
 	/// JVM arguments
 	pub jvm_args: Vec<String>,
+	/// Preset for JVM arguments
+	pub preset: ArgsPreset,
 	/// Game arguments
 	pub game_args: Vec<String>,
 	/// Minimum JVM memory
@@ -145,6 +234,42 @@ pub struct LaunchOptions {
 	pub quick_play: QuickPlay,
 	/// Whether or not to use the Log4J configuration
 	pub use_log4j_config: bool,
+	/// A directory to run the game in instead of the game directory
+	pub cwd_override: Option<PathBuf>,
+	/// Settings for capturing the output of the game to log files
+	pub log_capture: Option<LogCaptureConfig>,
+	/// Whether to suppress the warning that demo users can only play for a limited time
+	pub suppress_demo_warning: bool,
+	/// A skin variant to switch the active skin of the user to before launching
+	pub preferred_skin_variant: Option<SkinVariant>,
+}
+
+impl LaunchOptions {
+	/// Build the environment that the game process ends up with, for inspecting it. From lowest to
+	/// highest precedence, this is made of the inherited variables, usually from `get_process_env`,
+	/// the variables from the profiles the instance derives from, the variables from the instance
+	/// itself, and the variables that core adds for some game versions. The game process itself
+	/// inherits the environment implicitly and is only passed the configured variables
+	pub fn build_launch_env(
+		&self,
+		inherited: HashMap<String, String>,
+		core_env: HashMap<String, String>,
+	) -> HashMap<String, String> {
+		let mut out = inherited;
+		// The profile and instance variables have already been merged in order
+		out.extend(self.env.clone());
+		out.extend(core_env);
+
+		out
+	}
+}
+
+/// Get the environment variables of the current process, which are inherited by the game process.
+/// Variables that aren't valid Unicode are left out instead of being mangled
+pub fn get_process_env() -> HashMap<String, String> {
+	std::env::vars_os()
+		.filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+		.collect()
 }
 
 /// A wrapper command
@@ -158,6 +283,14 @@ pub struct WrapperCommand {
 	pub args: Vec<String>,
 }
 
+/// A launch of an instance that has been prepared, but not started yet
+pub struct PreparedLaunch {
+	/// The prepared launch from core, with the full launch command
+	pub inner: mcvm_core::launch::PreparedLaunch,
+	/// Arg to pass to the launch hooks when the launch is started
+	hook_arg: InstanceLaunchArg,
+}
+
 /// A handle for an instance
 pub struct InstanceHandle {
 	/// Core InstanceHandle with the process
@@ -184,6 +317,7 @@ impl InstanceHandle {
 				.context("Failed to kill plugin sibling process")?;
 		}
 
+		Self::clear_pid(&self.hook_arg, paths)?;
 		Self::call_stop_hooks(&self.hook_arg, plugins, paths, o)?;
 
 		Ok(result)
@@ -205,6 +339,7 @@ impl InstanceHandle {
 			.kill()
 			.context("Failed to kill inner instance handle")?;
 
+		Self::clear_pid(&self.hook_arg, paths)?;
 		Self::call_stop_hooks(&self.hook_arg, plugins, paths, o)?;
 
 		Ok(())
@@ -216,6 +351,16 @@ impl InstanceHandle {
 		self.inner.get_process()
 	}
 
+	/// Removes the recorded PID of the instance from the lockfile now that it has stopped
+	fn clear_pid(arg: &InstanceLaunchArg, paths: &Paths) -> anyhow::Result<()> {
+		let mut lock = Lockfile::open(paths).context("Failed to open lockfile")?;
+		lock.update_instance_pid(&arg.id, None);
+		lock.finish(paths)
+			.context("Failed to finish using lockfile")?;
+
+		Ok(())
+	}
+
 	/// Calls on stop hooks
 	fn call_stop_hooks(
 		arg: &InstanceLaunchArg,
@@ -232,3 +377,26 @@ impl InstanceHandle {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_add_classpath_entries() {
+		let mut classpath = Classpath::new();
+		classpath.add("loader.jar");
+		add_classpath_entries(
+			&mut classpath,
+			vec![
+				"plugin.jar".into(),
+				"loader.jar".into(),
+				"plugin.jar".into(),
+			],
+		);
+		assert_eq!(
+			classpath.get_paths(),
+			vec![PathBuf::from("loader.jar"), PathBuf::from("plugin.jar")]
+		);
+	}
+}
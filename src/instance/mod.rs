@@ -1,15 +1,21 @@
 /// Addon-related functions for instances
 mod addons;
+/// Detecting conflicts between the mods installed on an instance
+pub mod conflicts;
 /// Creation of instance contents
 pub mod create;
 /// Launching an instance
 pub mod launch;
+/// Exporting and importing modpacks
+pub mod modpack;
 /// Managing and installing packages on an instance
 pub mod packages;
 /// Import and export of instances to other formats
 pub mod transfer;
 /// Updating an instance
 pub mod update;
+/// Checking and repairing the files of an instance
+pub mod verify;
 
 use mcvm_core::util::versions::MinecraftVersion;
 use mcvm_shared::later::Later;
@@ -0,0 +1,1213 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, ensure, Context};
+use mcvm_core::io::files::create_leading_dirs;
+use mcvm_core::net::download::{self, Client};
+use mcvm_core::util::versions::{MinecraftVersion, MinecraftVersionDeser};
+use mcvm_net::{curseforge, modrinth};
+use mcvm_shared::modifications::{ClientType, Modloader, ServerType};
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
+use mcvm_shared::pkg::{PackageAddonOptionalHashes, PackageID};
+use mcvm_shared::translate;
+use mcvm_shared::Side;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::config::instance::{CommonInstanceConfig, InstanceConfig};
+use crate::config::package::{FullPackageConfig, PackageConfigDeser, PackageConfigSource};
+use crate::io::lock::Lockfile;
+use crate::io::paths::Paths;
+use crate::pkg::reg::PkgRegistry;
+
+use super::create::InstanceDirs;
+use super::Instance;
+
+/// The name of the manifest file in MCVM modpacks
+pub const MCVM_MANIFEST_NAME: &str = "mcvm_modpack.json";
+/// The name of the index file in Modrinth modpacks
+pub const MODRINTH_INDEX_NAME: &str = "modrinth.index.json";
+/// The name of the manifest file in CurseForge modpacks
+pub const CURSEFORGE_MANIFEST_NAME: &str = "manifest.json";
+/// The directory in modpacks with files that are copied into the game directory
+pub const OVERRIDES_DIR: &str = "overrides";
+/// The directory in Modrinth modpacks with files that are only copied into the game directory of clients
+pub const MODRINTH_CLIENT_OVERRIDES_DIR: &str = "client-overrides";
+
+/// Files and directories in the game directory that are included in exported modpacks.
+/// Everything else, like worlds, logs, and files that could contain secrets, is left out
+const EXPORTED_GAME_FILES: &[&str] = &["config", "defaultconfigs", "kubejs", "options.txt"];
+/// Addon directories that are included in Modrinth modpacks, since they can't reference packages
+const MODRINTH_ADDON_DIRS: &[&str] = &["mods", "resourcepacks", "shaderpacks"];
+/// The dependency names of modloaders in Modrinth modpacks
+const MODRINTH_LOADERS: &[(&str, Modloader)] = &[
+	("fabric-loader", Modloader::Fabric),
+	("quilt-loader", Modloader::Quilt),
+	("forge", Modloader::Forge),
+	("neoforge", Modloader::NeoForged),
+];
+
+/// A modpack format that instances can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModpackFormat {
+	/// MCVM's own format, which references packages so that they are installed on import
+	MCVM,
+	/// Modrinth's .mrpack format. Packages can't be referenced, so the installed addon
+	/// files are included in the overrides instead
+	Modrinth,
+}
+
+impl ModpackFormat {
+	/// Get the file extension for modpacks of this format
+	pub fn extension(&self) -> &'static str {
+		match self {
+			Self::MCVM => "mcvmpack",
+			Self::Modrinth => "mrpack",
+		}
+	}
+}
+
+/// Manifest for a modpack in the MCVM format
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MCVMModpackManifest {
+	/// The display name of the instance
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub name: Option<String>,
+	/// The side of the instance
+	pub side: Side,
+	/// The Minecraft version of the instance
+	pub minecraft_version: MinecraftVersionDeser,
+	/// The modloader of the instance
+	pub modloader: Modloader,
+	/// The client type of the instance
+	pub client_type: ClientType,
+	/// The server type of the instance
+	pub server_type: ServerType,
+	/// The packages installed on the instance
+	pub packages: Vec<ModpackPackage>,
+	/// The override files in the modpack
+	pub files: Vec<ModpackFile>,
+}
+
+/// A package in an MCVM modpack
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ModpackPackage {
+	/// The ID of the package
+	pub id: String,
+	/// Whether the package was configured on a profile instead of the instance
+	#[serde(default)]
+	pub from_profile: bool,
+	/// The enabled features of the package
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub features: Vec<String>,
+	/// Whether the default features of the package are used
+	pub use_default_features: bool,
+	/// The content version that the package is pinned to
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub content_version: Option<String>,
+	/// The ID of the repository that the package was installed from
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub repository: Option<String>,
+	/// The addons that the package installed on the instance
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub addons: Vec<ModpackAddon>,
+}
+
+/// An addon installed by a package in an MCVM modpack
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ModpackAddon {
+	/// The ID of the addon
+	pub id: String,
+	/// The version of the addon
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub version: Option<String>,
+	/// The hashes of the addon file
+	#[serde(default)]
+	#[serde(skip_serializing_if = "PackageAddonOptionalHashes::is_empty")]
+	pub hashes: PackageAddonOptionalHashes,
+}
+
+/// An override file in an MCVM modpack
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ModpackFile {
+	/// The path of the file relative to the game directory
+	pub path: String,
+	/// The SHA-256 hash of the file
+	pub sha256: String,
+}
+
+/// The index file of a Modrinth modpack
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ModrinthModpackIndex {
+	/// The version of the format
+	pub format_version: u32,
+	/// The game the modpack is for
+	pub game: String,
+	/// The version of the modpack
+	pub version_id: String,
+	/// The name of the modpack
+	pub name: String,
+	/// A short description of the modpack
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub summary: Option<String>,
+	/// Files to download into the game directory
+	#[serde(default)]
+	pub files: Vec<ModrinthModpackFile>,
+	/// Versions of Minecraft and the modloader
+	pub dependencies: HashMap<String, String>,
+}
+
+/// A file to download in a Modrinth modpack
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ModrinthModpackFile {
+	/// The path of the file relative to the game directory
+	pub path: String,
+	/// Hashes of the file by algorithm
+	pub hashes: HashMap<String, String>,
+	/// Which sides the file is used on
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub env: Option<HashMap<String, String>>,
+	/// URLs the file can be downloaded from
+	pub downloads: Vec<String>,
+	/// The size of the file in bytes
+	#[serde(default)]
+	pub file_size: u64,
+}
+
+/// The manifest file of a CurseForge modpack
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeModpackManifest {
+	/// Minecraft version and modloader info
+	pub minecraft: CurseForgeModpackMinecraft,
+	/// The type of the manifest
+	pub manifest_type: String,
+	/// The name of the modpack
+	pub name: String,
+	/// The mod files in the modpack
+	#[serde(default)]
+	pub files: Vec<CurseForgeModpackFile>,
+	/// The directory in the modpack with files that are copied into the game directory
+	#[serde(default = "default_curseforge_overrides")]
+	pub overrides: String,
+}
+
+/// Minecraft version and modloader info in a CurseForge modpack
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CurseForgeModpackMinecraft {
+	/// The Minecraft version
+	pub version: String,
+	/// The modloaders, with IDs like forge-47.2.0
+	#[serde(default)]
+	pub mod_loaders: Vec<CurseForgeModpackLoader>,
+}
+
+/// A modloader in a CurseForge modpack
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CurseForgeModpackLoader {
+	/// The ID of the modloader and its version
+	pub id: String,
+	/// Whether this is the main modloader for the modpack
+	#[serde(default)]
+	pub primary: bool,
+}
+
+/// A mod file in a CurseForge modpack
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CurseForgeModpackFile {
+	/// The ID of the mod
+	#[serde(rename = "projectID")]
+	pub project_id: u32,
+	/// The ID of the file
+	#[serde(rename = "fileID")]
+	pub file_id: u32,
+	/// Whether the file is required for the modpack
+	#[serde(default = "default_curseforge_required")]
+	pub required: bool,
+}
+
+/// Default value for the overrides directory of a CurseForge modpack
+fn default_curseforge_overrides() -> String {
+	OVERRIDES_DIR.into()
+}
+
+/// Default value for whether a file in a CurseForge modpack is required
+fn default_curseforge_required() -> bool {
+	true
+}
+
+impl Instance {
+	/// Export this instance as a modpack that can be shared with other people.
+	/// The instance should be updated first so that the lockfile has its installed addons and modloader version.
+	/// Returns the path to the created modpack file.
+	pub async fn export_modpack(
+		&self,
+		paths: &Paths,
+		format: ModpackFormat,
+		lock: &Lockfile,
+		reg: &mut PkgRegistry,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<PathBuf> {
+		let game_dir = if self.dirs.is_full() {
+			self.dirs.get().game_dir.clone()
+		} else {
+			InstanceDirs::new(paths, &self.id, &self.get_side()).game_dir
+		};
+
+		let export_dir = paths.data.join("exports");
+		std::fs::create_dir_all(&export_dir).context("Failed to create export directory")?;
+		let path = export_dir.join(format!("{}.{}", self.id, format.extension()));
+		let file = File::create(&path).context("Failed to create modpack file")?;
+		let mut zip = ZipWriter::new(file);
+
+		// Write the override files
+		let mut included = EXPORTED_GAME_FILES.to_vec();
+		if format == ModpackFormat::Modrinth {
+			included.extend(MODRINTH_ADDON_DIRS);
+		}
+		let mut files = Vec::new();
+		for name in included {
+			add_override(&game_dir.join(name), &game_dir, &mut zip, &mut files)
+				.with_context(|| format!("Failed to add '{name}' to modpack"))?;
+		}
+
+		// Write the manifest
+		match format {
+			ModpackFormat::MCVM => {
+				let installed_addons = lock.get_installed_addons(&self.id);
+				let mut packages = Vec::new();
+				for package in &self.config.packages {
+					let repository = reg
+						.get_repository(&package.get_request(), paths, client, o)
+						.await
+						.with_context(|| {
+							format!("Failed to get repository of package '{}'", package.id)
+						})?;
+					let addons = installed_addons
+						.iter()
+						.filter(|(id, _)| id == &package.id)
+						.map(|(id, addon)| {
+							let addon = addon.to_addon(id.clone())?;
+							Ok(ModpackAddon {
+								id: addon.id,
+								version: addon.version,
+								hashes: addon.hashes,
+							})
+						})
+						.collect::<anyhow::Result<_>>()?;
+
+					packages.push(ModpackPackage {
+						id: package.id.to_string(),
+						from_profile: package.source == PackageConfigSource::Profile,
+						features: package.features.clone(),
+						use_default_features: package.use_default_features,
+						content_version: package.content_version.clone(),
+						repository,
+						addons,
+					});
+				}
+				packages.sort_by(|a, b| a.id.cmp(&b.id));
+
+				let manifest = MCVMModpackManifest {
+					name: self.config.name.clone(),
+					side: self.get_side(),
+					minecraft_version: self.config.version.clone().to_serialized(),
+					modloader: self.config.modifications.get_modloader(self.get_side()),
+					client_type: self.config.modifications.client_type.clone(),
+					server_type: self.config.modifications.server_type.clone(),
+					packages,
+					files,
+				};
+				zip.start_file(MCVM_MANIFEST_NAME, FileOptions::<()>::default())?;
+				serde_json::to_writer_pretty(&mut zip, &manifest)
+					.context("Failed to write modpack manifest")?;
+			}
+			ModpackFormat::Modrinth => {
+				let MinecraftVersion::Version(version) = &self.config.version else {
+					bail!("Modrinth modpacks require a specific Minecraft version");
+				};
+				let mut dependencies = HashMap::from([("minecraft".into(), version.to_string())]);
+				let modloader = self.config.modifications.get_modloader(self.get_side());
+				if modloader != Modloader::Vanilla {
+					let name = MODRINTH_LOADERS
+						.iter()
+						.find(|x| x.1 == modloader)
+						.with_context(|| {
+							format!("Modloader {modloader} is not supported by Modrinth modpacks")
+						})?
+						.0;
+					let loader_version = lock.get_instance_modloader_version(&self.id).context(
+						"The modloader version of the instance is not known. Update the instance before exporting it",
+					)?;
+					dependencies.insert(name.into(), loader_version.into());
+				}
+
+				let index = ModrinthModpackIndex {
+					format_version: 1,
+					game: "minecraft".into(),
+					version_id: "1.0.0".into(),
+					name: self.config.name.clone().unwrap_or(self.id.to_string()),
+					summary: None,
+					files: Vec::new(),
+					dependencies,
+				};
+				zip.start_file(MODRINTH_INDEX_NAME, FileOptions::<()>::default())?;
+				serde_json::to_writer_pretty(&mut zip, &index)
+					.context("Failed to write modpack index")?;
+			}
+		}
+
+		zip.finish().context("Failed to finish modpack file")?;
+
+		Ok(path)
+	}
+}
+
+/// Import a Modrinth modpack as the config for a client instance. Override files are extracted
+/// into the given game directory, and the files in the modpack are mapped to the packages that
+/// declare the Modrinth IDs of the projects they come from, pinned to the versions of the files.
+/// Files that can't be mapped are downloaded into the game directory instead, and are checked
+/// against their hashes.
+pub async fn import_mrpack(
+	path: &Path,
+	target: &Path,
+	reg: &mut PkgRegistry,
+	paths: &Paths,
+	client: &Client,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<InstanceConfig> {
+	let index = read_mrpack(path, target)?;
+
+	let packages = reg
+		.get_modrinth_id_map(paths, client, o)
+		.await
+		.context("Failed to get the Modrinth IDs of packages")?;
+
+	// Get the version numbers of the files, which are the content versions of their packages
+	let version_ids = get_download_url_ids(&index, modrinth::get_version_id_from_download_url);
+	let versions = modrinth::get_multiple_versions(&version_ids, client)
+		.await
+		.context("Failed to get Modrinth versions for modpack files")?
+		.into_iter()
+		.map(|x| (x.id, modrinth::cleanup_version_name(&x.version_number)))
+		.collect();
+
+	let (config, files) = mrpack_to_config(index, &packages, &versions, o)?;
+	for file in files {
+		download_mrpack_file(&file, target, client)
+			.await
+			.with_context(|| format!("Failed to download modpack file '{}'", file.path))?;
+	}
+
+	Ok(config)
+}
+
+/// Gets the unique IDs found in the download URLs of the files in a Modrinth modpack
+fn get_download_url_ids(
+	index: &ModrinthModpackIndex,
+	get_id: impl Fn(&str) -> Option<&str>,
+) -> Vec<String> {
+	let ids: HashSet<_> = index
+		.files
+		.iter()
+		.flat_map(|x| &x.downloads)
+		.filter_map(|x| get_id(x))
+		.map(String::from)
+		.collect();
+
+	ids.into_iter().collect()
+}
+
+/// Reads and validates the index of a Modrinth modpack and extracts its overrides into the target directory
+fn read_mrpack(path: &Path, target: &Path) -> anyhow::Result<ModrinthModpackIndex> {
+	let file = BufReader::new(File::open(path).context("Failed to open modpack")?);
+	let mut zip = ZipArchive::new(file).context("Failed to read modpack archive")?;
+	let index_file = zip
+		.by_name(MODRINTH_INDEX_NAME)
+		.context("Modpack index is missing")?;
+	let index: ModrinthModpackIndex =
+		serde_json::from_reader(index_file).context("Failed to deserialize modpack index")?;
+	ensure!(
+		index.game == "minecraft",
+		"Modpack is for an unsupported game '{}'",
+		index.game
+	);
+	for file in &index.files {
+		validate_mrpack_file(file)
+			.with_context(|| format!("Invalid modpack file '{}'", file.path))?;
+	}
+
+	// Client overrides are extracted last so that they replace the common ones
+	extract_overrides(&mut zip, OVERRIDES_DIR, target)?;
+	extract_overrides(&mut zip, MODRINTH_CLIENT_OVERRIDES_DIR, target)?;
+
+	Ok(index)
+}
+
+/// Checks the path and hashes of a file in a Modrinth modpack
+fn validate_mrpack_file(file: &ModrinthModpackFile) -> anyhow::Result<()> {
+	let path = Path::new(&file.path);
+	ensure!(
+		path.components()
+			.all(|x| matches!(x, Component::Normal(..))),
+		"File path must be relative and stay inside of the game directory"
+	);
+	for (algorithm, length) in [("sha1", 40), ("sha512", 128)] {
+		let hash = file
+			.hashes
+			.get(algorithm)
+			.with_context(|| format!("File is missing a {algorithm} hash"))?;
+		ensure!(
+			hash.len() == length && hash.chars().all(|x| x.is_ascii_hexdigit()),
+			"File has an invalid {algorithm} hash"
+		);
+	}
+
+	Ok(())
+}
+
+/// Downloads a file in a Modrinth modpack into the game directory and checks its hash
+async fn download_mrpack_file(
+	file: &ModrinthModpackFile,
+	target: &Path,
+	client: &Client,
+) -> anyhow::Result<()> {
+	let url = file.downloads.first().context("File has no downloads")?;
+	let contents = download::bytes(url, client).await?;
+	check_mrpack_file_hash(file, &contents)?;
+
+	let path = target.join(&file.path);
+	create_leading_dirs(&path)?;
+	std::fs::write(&path, contents)
+		.with_context(|| format!("Failed to write file {}", path.display()))?;
+
+	Ok(())
+}
+
+/// Checks the contents of a file in a Modrinth modpack against its SHA-512 hash in the index
+fn check_mrpack_file_hash(file: &ModrinthModpackFile, contents: &[u8]) -> anyhow::Result<()> {
+	let expected = file
+		.hashes
+		.get("sha512")
+		.context("File is missing a sha512 hash")?;
+	let actual = hex::encode(Sha512::digest(contents));
+	ensure!(
+		actual.eq_ignore_ascii_case(expected),
+		"Hash of the file does not match the one in the modpack"
+	);
+
+	Ok(())
+}
+
+/// Extracts all of the files in a directory of a modpack archive into the target directory
+fn extract_overrides<R: Read + Seek>(
+	zip: &mut ZipArchive<R>,
+	dir: &str,
+	target: &Path,
+) -> anyhow::Result<()> {
+	for i in 0..zip.len() {
+		let mut file = zip.by_index(i)?;
+		// Entries that would escape the target are ignored
+		let Some(name) = file.enclosed_name() else {
+			continue;
+		};
+		let Ok(rel) = name.strip_prefix(dir) else {
+			continue;
+		};
+		let path = target.join(rel);
+		if file.is_dir() {
+			std::fs::create_dir_all(&path)?;
+		} else {
+			create_leading_dirs(&path)?;
+			let mut out = File::create(&path)
+				.with_context(|| format!("Failed to create file {}", path.display()))?;
+			std::io::copy(&mut file, &mut out).context("Failed to extract modpack file")?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Converts the index of a Modrinth modpack into an instance config, using a map of
+/// Modrinth project IDs to package IDs to find the packages for files, and a map of
+/// Modrinth version IDs to content versions to pin them. Returns the config along with
+/// the files that aren't from packages and have to be downloaded directly
+fn mrpack_to_config(
+	index: ModrinthModpackIndex,
+	packages: &HashMap<String, String>,
+	versions: &HashMap<String, String>,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<(InstanceConfig, Vec<ModrinthModpackFile>)> {
+	let version = index
+		.dependencies
+		.get("minecraft")
+		.context("Modpack does not specify a Minecraft version")?;
+
+	let mut modloader = None;
+	for dependency in index.dependencies.keys() {
+		if dependency == "minecraft" {
+			continue;
+		}
+		if let Some((_, loader)) = MODRINTH_LOADERS.iter().find(|x| x.0 == dependency) {
+			modloader = Some(loader.clone());
+		} else {
+			display_skipped(dependency, o);
+		}
+	}
+
+	let mut configs: Vec<(PackageID, Option<String>)> = Vec::new();
+	let mut downloads = Vec::new();
+	for file in index.files {
+		// Files that are only for servers aren't needed
+		let client_support = file.env.as_ref().and_then(|x| x.get("client"));
+		if client_support.is_some_and(|x| x == "unsupported") {
+			continue;
+		}
+
+		let package = file.downloads.iter().find_map(|url| {
+			let project_id = modrinth::get_project_id_from_download_url(url)?;
+			let package = packages.get(project_id)?;
+			let content_version = modrinth::get_version_id_from_download_url(url)
+				.and_then(|x| versions.get(x))
+				.cloned();
+			Some((PackageID::from(package.as_str()), content_version))
+		});
+		let Some(package) = package else {
+			downloads.push(file);
+			continue;
+		};
+		if !configs.iter().any(|x| x.0 == package.0) {
+			configs.push(package);
+		}
+	}
+
+	let config = InstanceConfig {
+		side: Some(Side::Client),
+		name: Some(index.name),
+		common: CommonInstanceConfig {
+			version: Some(MinecraftVersionDeser::Version(version.as_str().into())),
+			modloader,
+			packages: configs
+				.into_iter()
+				.map(|(id, content_version)| create_package_config(id, content_version))
+				.collect(),
+			..Default::default()
+		},
+		window: Default::default(),
+	};
+
+	Ok((config, downloads))
+}
+
+/// Creates the config for a package from a modpack, pinned to a content version if it is known
+fn create_package_config(id: PackageID, content_version: Option<String>) -> PackageConfigDeser {
+	let Some(content_version) = content_version else {
+		return PackageConfigDeser::Basic(id);
+	};
+
+	PackageConfigDeser::Full(FullPackageConfig {
+		id,
+		features: Vec::new(),
+		use_default_features: true,
+		permissions: Default::default(),
+		stability: None,
+		worlds: Vec::new(),
+		content_version: Some(content_version),
+	})
+}
+
+/// Import a CurseForge modpack as the config for a client instance. Override files are extracted
+/// into the given game directory, and the mods in the modpack are mapped to the packages that declare
+/// their CurseForge IDs. The packages aren't pinned, as CurseForge file IDs aren't content versions.
+/// The CurseForge API, which requires an API key, is used to get the names of mods that can't be
+/// mapped so that they can be reported.
+pub async fn import_curseforge_modpack(
+	path: &Path,
+	target: &Path,
+	api_key: &str,
+	reg: &mut PkgRegistry,
+	paths: &Paths,
+	client: &Client,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<InstanceConfig> {
+	let manifest = read_curseforge_modpack(path, target)?;
+
+	let packages = reg
+		.get_curseforge_id_map(paths, client, o)
+		.await
+		.context("Failed to get the CurseForge IDs of packages")?;
+
+	let mod_ids: Vec<_> = manifest
+		.files
+		.iter()
+		.map(|x| x.project_id)
+		.filter(|x| !packages.contains_key(&x.to_string()))
+		.collect();
+	let mods = curseforge::get_multiple_mods(&mod_ids, api_key, client)
+		.await
+		.context("Failed to get CurseForge mods for modpack files")?
+		.into_iter()
+		.map(|x| (x.id, x))
+		.collect();
+
+	curseforge_to_config(manifest, &packages, &mods, o)
+}
+
+/// Reads the manifest of a CurseForge modpack and extracts its overrides into the target directory
+fn read_curseforge_modpack(
+	path: &Path,
+	target: &Path,
+) -> anyhow::Result<CurseForgeModpackManifest> {
+	let file = BufReader::new(File::open(path).context("Failed to open modpack")?);
+	let mut zip = ZipArchive::new(file).context("Failed to read modpack archive")?;
+	let manifest_file = zip
+		.by_name(CURSEFORGE_MANIFEST_NAME)
+		.context("Modpack manifest is missing")?;
+	let manifest: CurseForgeModpackManifest =
+		serde_json::from_reader(manifest_file).context("Failed to deserialize modpack manifest")?;
+	ensure!(
+		manifest.manifest_type == "minecraftModpack",
+		"Modpack has an unsupported manifest type '{}'",
+		manifest.manifest_type
+	);
+
+	extract_overrides(&mut zip, &manifest.overrides, target)?;
+
+	Ok(manifest)
+}
+
+/// Converts the manifest of a CurseForge modpack into an instance config, using a map of
+/// CurseForge mod IDs to package IDs to find the packages for files, and a map of CurseForge
+/// mod IDs to mods to report the ones that can't be imported
+fn curseforge_to_config(
+	manifest: CurseForgeModpackManifest,
+	packages: &HashMap<String, String>,
+	mods: &HashMap<u32, curseforge::Mod>,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<InstanceConfig> {
+	let loaders = &manifest.minecraft.mod_loaders;
+	let loader = loaders.iter().find(|x| x.primary).or(loaders.first());
+	let modloader = if let Some(loader) = loader {
+		let name = loader.id.split('-').next().unwrap_or_default();
+		match name {
+			"forge" => Some(Modloader::Forge),
+			"neoforge" => Some(Modloader::NeoForged),
+			"fabric" => Some(Modloader::Fabric),
+			"quilt" => Some(Modloader::Quilt),
+			_ => {
+				display_skipped(&loader.id, o);
+				None
+			}
+		}
+	} else {
+		None
+	};
+
+	// File IDs are not package content versions, so the packages are left unpinned
+	let mut configs: Vec<PackageID> = Vec::new();
+	for file in &manifest.files {
+		let package = packages.get(&file.project_id.to_string());
+		let cf_mod = mods.get(&file.project_id);
+		let name = match (package, cf_mod) {
+			(Some(package), _) => package.clone(),
+			(None, Some(cf_mod)) => cf_mod.name.clone(),
+			(None, None) => format!("CurseForge mod {}", file.project_id),
+		};
+
+		if !file.required {
+			o.display(
+				MessageContents::Warning(translate!(o, ModpackOptionalSkipped, "item" = &name)),
+				MessageLevel::Important,
+			);
+			continue;
+		}
+
+		let Some(package) = package else {
+			if cf_mod.is_some_and(|x| x.allow_mod_distribution == Some(false)) {
+				o.display(
+					MessageContents::Warning(translate!(o, ModpackManualDownload, "item" = &name)),
+					MessageLevel::Important,
+				);
+			} else {
+				display_skipped(&name, o);
+			}
+			continue;
+		};
+
+		let id = PackageID::from(package.as_str());
+		if !configs.contains(&id) {
+			configs.push(id);
+		}
+	}
+
+	Ok(InstanceConfig {
+		side: Some(Side::Client),
+		name: Some(manifest.name),
+		common: CommonInstanceConfig {
+			version: Some(MinecraftVersionDeser::Version(
+				manifest.minecraft.version.as_str().into(),
+			)),
+			modloader,
+			packages: configs
+				.into_iter()
+				.map(|id| create_package_config(id, None))
+				.collect(),
+			..Default::default()
+		},
+		window: Default::default(),
+	})
+}
+
+/// Reports part of a modpack that couldn't be imported
+fn display_skipped(item: &str, o: &mut impl MCVMOutput) {
+	o.display(
+		MessageContents::Warning(translate!(o, ModpackItemSkipped, "item" = item)),
+		MessageLevel::Important,
+	);
+}
+
+/// Recursively adds a file or directory in the game directory to the overrides of a modpack
+fn add_override(
+	path: &Path,
+	game_dir: &Path,
+	zip: &mut ZipWriter<File>,
+	files: &mut Vec<ModpackFile>,
+) -> anyhow::Result<()> {
+	if path.is_dir() {
+		let mut entries = path
+			.read_dir()
+			.context("Failed to read directory")?
+			.collect::<std::io::Result<Vec<_>>>()?;
+		// Sort so that exports are reproducible
+		entries.sort_by_key(|x| x.file_name());
+		for entry in entries {
+			add_override(&entry.path(), game_dir, zip, files)?;
+		}
+	} else if path.is_file() {
+		let contents = std::fs::read(path).context("Failed to read file")?;
+		let rel = path.strip_prefix(game_dir)?;
+		zip.start_file_from_path(
+			Path::new(OVERRIDES_DIR).join(rel),
+			FileOptions::<()>::default(),
+		)?;
+		zip.write_all(&contents)
+			.context("Failed to copy file into modpack")?;
+		files.push(ModpackFile {
+			path: rel.to_string_lossy().replace('\\', "/"),
+			sha256: hex::encode(Sha256::digest(&contents)),
+		});
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use mcvm_shared::addon::{Addon, AddonKind};
+	use mcvm_shared::id::{InstanceID, ProfileID};
+	use mcvm_shared::output::{NoOp, Recording};
+
+	use crate::config::instance::read_instance_config;
+	use crate::io::lock::LockfileAddon;
+	use crate::pkg::reg::CachingStrategy;
+	use crate::pkg::repo::{PkgRepo, PkgRepoLocation};
+	use crate::plugin::PluginManager;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_export_modpack() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+
+		let index = serde_json::json!({
+			"packages": {
+				"sodium": { "path": "sodium.json" },
+				"lithium": { "path": "lithium.json" }
+			}
+		});
+		let index_path = data_dir.join("index.json");
+		std::fs::write(&index_path, index.to_string()).unwrap();
+		let repos = vec![PkgRepo::new("local", PkgRepoLocation::Local(index_path))];
+		let mut reg = PkgRegistry::new(repos, CachingStrategy::default(), PluginManager::new());
+		let client = Client::new();
+
+		let config: InstanceConfig = serde_json::from_value(serde_json::json!({
+			"type": "client",
+			"name": "Test Pack",
+			"version": "1.20.1",
+			"modloader": "fabric",
+			"packages": [
+				"sodium",
+				{ "id": "lithium", "features": ["extra"] }
+			]
+		}))
+		.unwrap();
+		let mut instance = read_instance_config(
+			InstanceID::from("test"),
+			config,
+			&HashMap::<ProfileID, _>::new(),
+			&PluginManager::new(),
+			&paths,
+			&mut NoOp,
+		)
+		.unwrap();
+
+		let game_dir = data_dir.join("game");
+		std::fs::create_dir_all(game_dir.join("config")).unwrap();
+		std::fs::create_dir_all(game_dir.join("saves/world")).unwrap();
+		std::fs::create_dir_all(game_dir.join("mods")).unwrap();
+		std::fs::write(game_dir.join("config/sodium.json"), "{}").unwrap();
+		std::fs::write(game_dir.join("options.txt"), "fov:0.5").unwrap();
+		std::fs::write(game_dir.join("saves/world/level.dat"), "world").unwrap();
+		std::fs::write(game_dir.join("mods/sodium.jar"), "jar").unwrap();
+		instance.set_custom_dirs(game_dir).unwrap();
+
+		let mut lock = Lockfile::open(&paths).unwrap();
+		lock.update_instance_version("test", "1.20.1");
+		let addon = Addon {
+			kind: AddonKind::Mod,
+			id: "lithium".into(),
+			file_name: "lithium.jar".into(),
+			pkg_id: "lithium".into(),
+			version: Some("1".into()),
+			hashes: PackageAddonOptionalHashes {
+				sha256: Some("a".repeat(64)),
+				sha512: None,
+			},
+		};
+		let addons = [LockfileAddon::from_addon(&addon, Vec::new())];
+		lock.update_package("lithium", "test", &addons, &mut NoOp)
+			.unwrap();
+
+		let path = instance
+			.export_modpack(
+				&paths,
+				ModpackFormat::MCVM,
+				&lock,
+				&mut reg,
+				&client,
+				&mut NoOp,
+			)
+			.await
+			.unwrap();
+		assert_eq!(path.extension().unwrap(), "mcvmpack");
+		let mut zip = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+		let mut names: Vec<_> = zip.file_names().map(String::from).collect();
+		names.sort();
+		assert_eq!(
+			names,
+			vec![
+				MCVM_MANIFEST_NAME.to_string(),
+				"overrides/config/sodium.json".to_string(),
+				"overrides/options.txt".to_string(),
+			]
+		);
+		let mut manifest = String::new();
+		zip.by_name(MCVM_MANIFEST_NAME)
+			.unwrap()
+			.read_to_string(&mut manifest)
+			.unwrap();
+		let manifest: MCVMModpackManifest = serde_json::from_str(&manifest).unwrap();
+		assert_eq!(manifest.name.as_deref(), Some("Test Pack"));
+		assert_eq!(manifest.side, Side::Client);
+		assert_eq!(manifest.modloader, Modloader::Fabric);
+		assert_eq!(manifest.packages.len(), 2);
+		assert_eq!(manifest.packages[0].id, "lithium");
+		assert_eq!(manifest.packages[0].features, vec!["extra".to_string()]);
+		assert_eq!(manifest.packages[0].repository.as_deref(), Some("local"));
+		assert_eq!(
+			manifest.packages[0].addons,
+			vec![ModpackAddon {
+				id: "lithium".into(),
+				version: Some("1".into()),
+				hashes: addon.hashes,
+			}]
+		);
+		assert!(manifest.packages[1].addons.is_empty());
+		assert_eq!(
+			manifest.files[1],
+			ModpackFile {
+				path: "options.txt".into(),
+				sha256: hex::encode(Sha256::digest("fov:0.5")),
+			}
+		);
+
+		// The modloader version has to be known for Modrinth modpacks
+		assert!(instance
+			.export_modpack(
+				&paths,
+				ModpackFormat::Modrinth,
+				&lock,
+				&mut reg,
+				&client,
+				&mut NoOp
+			)
+			.await
+			.is_err());
+		lock.update_instance_modloader_version("test", Some("0.15.7".into()));
+		let path = instance
+			.export_modpack(
+				&paths,
+				ModpackFormat::Modrinth,
+				&lock,
+				&mut reg,
+				&client,
+				&mut NoOp,
+			)
+			.await
+			.unwrap();
+		let mut zip = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+		assert!(zip.by_name("overrides/mods/sodium.jar").is_ok());
+		assert!(zip.by_name("overrides/saves/world/level.dat").is_err());
+		let index: ModrinthModpackIndex =
+			serde_json::from_reader(zip.by_name(MODRINTH_INDEX_NAME).unwrap()).unwrap();
+		assert_eq!(index.name, "Test Pack");
+		assert_eq!(index.dependencies["minecraft"], "1.20.1");
+		assert_eq!(index.dependencies["fabric-loader"], "0.15.7");
+	}
+
+	/// Creates a Modrinth modpack file with the given index
+	fn create_mrpack(path: &Path, index: serde_json::Value) {
+		let mut zip = ZipWriter::new(File::create(path).unwrap());
+		zip.start_file(MODRINTH_INDEX_NAME, FileOptions::<()>::default())
+			.unwrap();
+		serde_json::to_writer(&mut zip, &index).unwrap();
+		zip.start_file("overrides/config/sodium.json", FileOptions::<()>::default())
+			.unwrap();
+		zip.write_all(b"{}").unwrap();
+		zip.start_file("overrides/options.txt", FileOptions::<()>::default())
+			.unwrap();
+		zip.write_all(b"fov:0.5").unwrap();
+		zip.start_file("client-overrides/options.txt", FileOptions::<()>::default())
+			.unwrap();
+		zip.write_all(b"fov:1.0").unwrap();
+		zip.finish().unwrap();
+	}
+
+	/// Creates an entry for a file in a Modrinth modpack index
+	fn create_file(path: &str, url: &str, env: Option<serde_json::Value>) -> serde_json::Value {
+		serde_json::json!({
+			"path": path,
+			"hashes": {
+				"sha1": "a".repeat(40),
+				"sha512": "b".repeat(128)
+			},
+			"env": env,
+			"downloads": [url],
+			"fileSize": 10
+		})
+	}
+
+	#[test]
+	fn test_import_mrpack() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let path = dir.join("pack.mrpack");
+		let game_dir = dir.join("game");
+
+		create_mrpack(
+			&path,
+			serde_json::json!({
+				"formatVersion": 1,
+				"game": "minecraft",
+				"versionId": "1.0.0",
+				"name": "Test Pack",
+				"files": [
+					create_file(
+						"mods/sodium.jar",
+						"https://cdn.modrinth.com/data/AANobbMI/versions/abc/sodium.jar",
+						None,
+					),
+					create_file(
+						"mods/other.jar",
+						"https://github.com/other/other/releases/download/1.0/other.jar",
+						None,
+					),
+					create_file(
+						"mods/unmapped.jar",
+						"https://cdn.modrinth.com/data/unmapped/versions/abc/unmapped.jar",
+						None,
+					),
+					create_file(
+						"mods/server.jar",
+						"https://cdn.modrinth.com/data/server/versions/abc/server.jar",
+						Some(serde_json::json!({"client": "unsupported", "server": "required"})),
+					),
+				],
+				"dependencies": {
+					"minecraft": "1.20.1",
+					"fabric-loader": "0.15.7"
+				}
+			}),
+		);
+
+		let index = read_mrpack(&path, &game_dir).unwrap();
+		assert_eq!(
+			std::fs::read_to_string(game_dir.join("config/sodium.json")).unwrap(),
+			"{}"
+		);
+		assert_eq!(
+			std::fs::read_to_string(game_dir.join("options.txt")).unwrap(),
+			"fov:1.0"
+		);
+
+		// Package IDs don't have to match the slugs of the projects
+		let packages = HashMap::from([
+			("AANobbMI".to_string(), "sodium-renderer".to_string()),
+			("server".to_string(), "server".to_string()),
+		]);
+		let versions = HashMap::from([("abc".to_string(), "0.5.3".to_string())]);
+		let (config, files) = mrpack_to_config(index, &packages, &versions, &mut NoOp).unwrap();
+		assert_eq!(config.side, Some(Side::Client));
+		assert_eq!(config.name.as_deref(), Some("Test Pack"));
+		assert_eq!(
+			config.common.version,
+			Some(MinecraftVersionDeser::Version("1.20.1".into()))
+		);
+		assert_eq!(config.common.modloader, Some(Modloader::Fabric));
+		let packages: Vec<_> = config
+			.common
+			.packages
+			.iter()
+			.map(|x| x.to_string())
+			.collect();
+		assert_eq!(packages, vec!["sodium-renderer".to_string()]);
+		assert_eq!(
+			config.common.packages[0].get_content_version(),
+			Some(&"0.5.3".to_string())
+		);
+		let files: Vec<_> = files.iter().map(|x| x.path.as_str()).collect();
+		assert_eq!(files, vec!["mods/other.jar", "mods/unmapped.jar"]);
+	}
+
+	#[test]
+	fn test_mrpack_file_hash() {
+		let mut file: ModrinthModpackFile = serde_json::from_value(create_file(
+			"mods/other.jar",
+			"https://example.com/other.jar",
+			None,
+		))
+		.unwrap();
+		file.hashes.insert(
+			"sha512".into(),
+			hex::encode(Sha512::digest(b"foo")).to_uppercase(),
+		);
+		assert!(check_mrpack_file_hash(&file, b"foo").is_ok());
+		assert!(check_mrpack_file_hash(&file, b"bar").is_err());
+	}
+
+	#[test]
+	fn test_mrpack_file_validation() {
+		let file = |path: &str| -> ModrinthModpackFile {
+			serde_json::from_value(create_file(path, "https://example.com", None)).unwrap()
+		};
+		validate_mrpack_file(&file("mods/sodium.jar")).unwrap();
+		assert!(validate_mrpack_file(&file("../sodium.jar")).is_err());
+		assert!(validate_mrpack_file(&file("/mods/sodium.jar")).is_err());
+
+		let mut invalid_hash = file("mods/sodium.jar");
+		invalid_hash.hashes.insert("sha1".into(), "xyz".into());
+		assert!(validate_mrpack_file(&invalid_hash).is_err());
+		invalid_hash.hashes.remove("sha1");
+		assert!(validate_mrpack_file(&invalid_hash).is_err());
+	}
+
+	#[test]
+	fn test_import_curseforge_modpack() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let path = dir.join("pack.zip");
+		let game_dir = dir.join("game");
+
+		let manifest = serde_json::json!({
+			"minecraft": {
+				"version": "1.20.1",
+				"modLoaders": [{"id": "forge-47.2.0", "primary": true}]
+			},
+			"manifestType": "minecraftModpack",
+			"manifestVersion": 1,
+			"name": "Test Pack",
+			"version": "1.0.0",
+			"author": "Tester",
+			"files": [
+				{"projectID": 1, "fileID": 10, "required": true},
+				{"projectID": 2, "fileID": 20, "required": true},
+				{"projectID": 3, "fileID": 30, "required": true},
+				{"projectID": 4, "fileID": 40, "required": false},
+				{"projectID": 5, "fileID": 50, "required": true}
+			],
+			"overrides": "overrides"
+		});
+		let mut zip = ZipWriter::new(File::create(&path).unwrap());
+		zip.start_file(CURSEFORGE_MANIFEST_NAME, FileOptions::<()>::default())
+			.unwrap();
+		serde_json::to_writer(&mut zip, &manifest).unwrap();
+		zip.start_file("overrides/config/jei.toml", FileOptions::<()>::default())
+			.unwrap();
+		zip.write_all(b"enabled = true").unwrap();
+		zip.finish().unwrap();
+
+		let manifest = read_curseforge_modpack(&path, &game_dir).unwrap();
+		assert_eq!(
+			std::fs::read_to_string(game_dir.join("config/jei.toml")).unwrap(),
+			"enabled = true"
+		);
+
+		let create_mod = |id: u32, slug: &str, allow_distribution: bool| curseforge::Mod {
+			id,
+			name: slug.to_uppercase(),
+			slug: slug.into(),
+			allow_mod_distribution: Some(allow_distribution),
+		};
+		let packages = HashMap::from([("1".to_string(), "just-enough-items".to_string())]);
+		let mods = HashMap::from([
+			(2, create_mod(2, "optifine", false)),
+			(4, create_mod(4, "optional", true)),
+			(5, create_mod(5, "unmapped", true)),
+		]);
+		let mut o = Recording::default();
+		let config = curseforge_to_config(manifest, &packages, &mods, &mut o).unwrap();
+		assert_eq!(config.name.as_deref(), Some("Test Pack"));
+		assert_eq!(
+			config.common.version,
+			Some(MinecraftVersionDeser::Version("1.20.1".into()))
+		);
+		assert_eq!(config.common.modloader, Some(Modloader::Forge));
+		let packages: Vec<_> = config
+			.common
+			.packages
+			.iter()
+			.map(|x| x.to_string())
+			.collect();
+		assert_eq!(packages, vec!["just-enough-items".to_string()]);
+		assert_eq!(config.common.packages[0].get_content_version(), None);
+		assert_eq!(
+			o.messages,
+			vec![
+				"Warning: 'OPTIFINE' from the modpack can't be downloaded automatically and has to be downloaded manually".to_string(),
+				"Warning: 'CurseForge mod 3' from the modpack is not supported and was skipped".to_string(),
+				"Warning: 'OPTIONAL' from the modpack is optional and was skipped".to_string(),
+				"Warning: 'UNMAPPED' from the modpack is not supported and was skipped".to_string(),
+			]
+		);
+	}
+}
@@ -1,22 +1,27 @@
 use anyhow::{bail, Context};
+use mcvm_pkg::update::{get_package_updates, AvailablePackage, InstalledPackage, PackageUpdate};
+use mcvm_pkg::PkgRequest;
 use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
-use mcvm_shared::pkg::ArcPkgReq;
+use mcvm_shared::pkg::{ArcPkgReq, PkgRequestSource};
 use mcvm_shared::translate;
-use mcvm_shared::versions::VersionInfo;
+use mcvm_shared::versions::{VersionInfo, VersionPattern};
 use reqwest::Client;
 
-use crate::addon::AddonExt;
+use crate::addon::{get_installed_hashes, process_downloaded_addon, AddonExt};
 use crate::plugin::PluginManager;
 use crate::io::lock::{Lockfile, LockfileAddon};
 use crate::io::paths::Paths;
+use crate::io::transaction::FileTransaction;
 use crate::pkg::eval::{EvalData, EvalInput, Routine};
 use crate::pkg::reg::PkgRegistry;
+use crate::pkg::PkgContents;
 
 use super::Instance;
 use crate::config::package::PackageConfig;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
+use std::sync::Arc;
 
 impl Instance {
 	/// Installs a package on this instance
@@ -43,8 +48,21 @@ impl Instance {
 			.await
 			.context("Failed to get download tasks for installing package")?;
 
-		for task in tasks.into_values() {
-			task.await.context("Failed to install addon")?;
+		let mut downloaded = HashSet::new();
+		for (task_id, task) in tasks {
+			let bytes = task.await.context("Failed to install addon")?;
+			if bytes > 0 {
+				downloaded.insert(task_id);
+			}
+		}
+
+		// Let plugins process the addons that were downloaded before they are installed
+		for addon in &eval.addon_reqs {
+			if downloaded.contains(&addon.get_unique_id(&self.id)) {
+				let path = addon.addon.get_path(paths, &self.id);
+				process_downloaded_addon(&addon.addon, &path, plugins, paths, o)
+					.with_context(|| format!("Failed to process addon '{}'", addon.addon.id))?;
+			}
 		}
 
 		self.install_eval_data(pkg, &eval, &version_info, paths, lock, o)
@@ -68,7 +86,7 @@ impl Instance {
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<(
 		EvalData<'a>,
-		HashMap<String, impl Future<Output = anyhow::Result<()>> + Send + 'static>,
+		HashMap<String, impl Future<Output = anyhow::Result<u64>> + Send + 'static>,
 	)> {
 		let eval = reg
 			.eval(pkg, paths, Routine::Install, eval_input, client, plugins, o)
@@ -78,6 +96,9 @@ impl Instance {
 		let mut tasks = HashMap::new();
 		for addon in eval.addon_reqs.iter() {
 			if addon.addon.should_update(paths, &self.id) || force {
+				if reg.is_offline() && addon.is_remote() {
+					bail!("Offline mode: addon '{}' is not downloaded", addon.addon.id);
+				}
 				let task = addon
 					.get_acquire_task(paths, &self.id, client)
 					.context("Failed to get task for acquiring addon")?;
@@ -119,27 +140,84 @@ impl Instance {
 			.addon_reqs
 			.iter()
 			.map(|x| {
+				let hashes = get_installed_hashes(&x.addon, &x.addon.get_path(paths, &self.id))?;
 				Ok(LockfileAddon::from_addon(
 					&x.addon,
 					self.get_linked_addon_paths(&x.addon, &pkg_config.worlds, paths, version_info)?
 						.iter()
 						.map(|y| y.join(x.addon.file_name.clone()))
 						.collect(),
-				))
+				)
+				.with_hashes(hashes))
 			})
 			.collect::<anyhow::Result<Vec<LockfileAddon>>>()
 			.context("Failed to convert addons to the lockfile format")?;
 
+		// Stage all of the addons first so that a failure leaves none of them installed
+		let mut transaction = FileTransaction::new();
+		for addon in eval.addon_reqs.iter() {
+			self.create_addon(
+				&addon.addon,
+				&pkg_config.worlds,
+				paths,
+				version_info,
+				&mut transaction,
+			)
+			.with_context(|| format!("Failed to install addon '{}'", addon.addon.id))?;
+		}
+
+		transaction
+			.commit()
+			.context("Failed to move addons into the instance")?;
+
+		// Only record the new addons once they are actually in place
 		let files_to_remove = lock
 			.update_package(&pkg.id, &self.id, &lockfile_addons, o)
 			.context("Failed to update package in lockfile")?;
+		lock.update_package_content_version(&pkg.id, &self.id, eval.content_version.clone());
 
-		for addon in eval.addon_reqs.iter() {
-			self.create_addon(&addon.addon, &pkg_config.worlds, paths, version_info)
-				.with_context(|| format!("Failed to install addon '{}'", addon.addon.id))?;
+		for path in files_to_remove {
+			self.remove_addon_file(&path, paths)
+				.context("Failed to remove addon file from instance")?;
 		}
 
-		for path in files_to_remove {
+		Ok(())
+	}
+
+	/// Uninstalls a package from this instance by running its uninstall routine and then
+	/// removing the addon files that it installed
+	#[allow(clippy::too_many_arguments)]
+	pub async fn uninstall_package<'a>(
+		&mut self,
+		pkg: &ArcPkgReq,
+		eval_input: EvalInput<'a>,
+		reg: &mut PkgRegistry,
+		paths: &'a Paths,
+		lock: &mut Lockfile,
+		client: &Client,
+		plugins: &'a PluginManager,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<()> {
+		// The package might not be available anymore, but its files should still be removed
+		let routine = Routine::Uninstall;
+		match reg
+			.eval(pkg, paths, routine, eval_input, client, plugins, o)
+			.await
+		{
+			Ok(eval) => run_package_commands(&eval.commands, o)
+				.context("Failed to run package uninstall commands")?,
+			Err(e) => o.display(
+				MessageContents::Warning(translate!(
+					o,
+					UninstallRoutineFailed,
+					"pkg" = &pkg.to_string(),
+					"error" = &format!("{e:#}")
+				)),
+				MessageLevel::Important,
+			),
+		}
+
+		for path in lock.remove_package(&pkg.id, &self.id) {
 			self.remove_addon_file(&path, paths)
 				.context("Failed to remove addon file from instance")?;
 		}
@@ -147,6 +225,61 @@ impl Instance {
 		Ok(())
 	}
 
+	/// Checks the packages installed on this instance for newer content versions in the repositories
+	pub async fn get_package_updates(
+		&self,
+		reg: &mut PkgRegistry,
+		paths: &Paths,
+		lock: &Lockfile,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<Vec<PackageUpdate>> {
+		let mut installed = Vec::new();
+		let mut available = HashMap::new();
+		for (id, content_version) in lock.get_installed_packages(&self.id) {
+			let req = Arc::new(PkgRequest::parse(&id, PkgRequestSource::UserRequire));
+			// Packages whose repository has disappeared are reported as unavailable
+			if reg.exists(&req, paths, client, o).await? {
+				let properties = reg
+					.get_properties(&req, paths, client, o)
+					.await
+					.with_context(|| format!("Failed to get properties for package '{id}'"))?;
+				let properties = properties.clone();
+				let unstable_content_versions = if let PkgContents::Declarative(contents) =
+					reg.parse(&req, paths, client, o).await?
+				{
+					contents.get_unstable_content_versions()
+				} else {
+					HashSet::new()
+				};
+				available.insert(
+					id.clone(),
+					AvailablePackage {
+						properties,
+						unstable_content_versions,
+					},
+				);
+			}
+
+			let pinned_version = self
+				.get_package_config(&id)
+				.and_then(|x| x.content_version.as_deref())
+				.map(VersionPattern::from);
+			let stability = self
+				.get_package_config(&id)
+				.map(|x| x.get_stability(self.config.package_stability))
+				.unwrap_or(self.config.package_stability);
+			installed.push(InstalledPackage {
+				id,
+				content_version,
+				pinned_version,
+				stability,
+			});
+		}
+
+		Ok(get_package_updates(&installed, &available))
+	}
+
 	/// Gets all of the configured packages for this instance
 	pub fn get_configured_packages(&self) -> &Vec<PackageConfig> {
 		&self.config.packages
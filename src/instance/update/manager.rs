@@ -2,7 +2,7 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use mcvm_core::auth_crate::mc::ClientId;
 use mcvm_core::config::BrandingProperties;
 use mcvm_core::user::UserManager;
@@ -20,12 +20,16 @@ use reqwest::Client;
 use crate::plugin::PluginManager;
 use crate::io::paths::Paths;
 use mcvm_mods::fabric_quilt::{self, FabricQuiltMeta};
+use mcvm_mods::forge;
+use mcvm_net::fabric;
 
 /// Requirements for operations that may be shared by multiple instances in a profile
 #[derive(Debug, Hash, PartialEq, Eq)]
 pub enum UpdateRequirement {
 	/// Fabric and Quilt
 	FabricQuilt(fabric_quilt::Mode, Side),
+	/// Forge and NeoForge on the client
+	ForgeLike(forge::Mode),
 	/// Client logging configuration
 	ClientLoggingConfig,
 }
@@ -39,6 +43,12 @@ pub struct UpdateSettings {
 	pub allow_offline: bool,
 	/// Whether to do offline authentication
 	pub offline_auth: bool,
+	/// Whether to skip all network operations and only use cached files
+	pub offline: bool,
+	/// Whether to censor secrets such as access tokens in output
+	pub censor_secrets: bool,
+	/// The maximum number of bytes per second for downloads
+	pub bandwidth_limit: Option<u64>,
 }
 
 /// Manager for when we are updating profile files.
@@ -60,6 +70,10 @@ pub struct UpdateManager {
 	pub version_info: Later<VersionInfo>,
 	/// The Fabric/Quilt metadata to be fulfilled later
 	pub fq_meta: Later<FabricQuiltMeta>,
+	/// The Forge/NeoForge launch profile to be fulfilled later
+	pub forge_profile: Later<forge::LaunchProfile>,
+	/// The Forge/NeoForge version to install instead of the newest one
+	forge_version: Option<String>,
 }
 
 impl UpdateManager {
@@ -69,6 +83,9 @@ impl UpdateManager {
 			force,
 			allow_offline,
 			offline_auth: false,
+			offline: false,
+			censor_secrets: true,
+			bandwidth_limit: None,
 		};
 
 		Self {
@@ -79,6 +96,8 @@ impl UpdateManager {
 			files: HashSet::new(),
 			version_info: Later::Empty,
 			fq_meta: Later::new(),
+			forge_profile: Later::new(),
+			forge_version: None,
 			mc_version: Later::Empty,
 		}
 	}
@@ -88,6 +107,37 @@ impl UpdateManager {
 		self.settings.offline_auth = true;
 	}
 
+	/// Set offline mode, which skips all network operations and
+	/// authenticates offline
+	pub fn offline(&mut self) {
+		self.settings.offline = true;
+		self.settings.offline_auth = true;
+	}
+
+	/// Fails with an error if the manager is in offline mode. Should be called
+	/// before any network operation, with a description of what is being downloaded
+	pub fn ensure_online(&self, what: &str) -> anyhow::Result<()> {
+		if self.settings.offline {
+			bail!("Offline mode: {what} is not cached");
+		}
+		Ok(())
+	}
+
+	/// Set whether to censor secrets in output
+	pub fn set_censor_secrets(&mut self, censor_secrets: bool) {
+		self.settings.censor_secrets = censor_secrets;
+	}
+
+	/// Set the maximum number of bytes per second for downloads
+	pub fn set_bandwidth_limit(&mut self, bandwidth_limit: Option<u64>) {
+		self.settings.bandwidth_limit = bandwidth_limit;
+	}
+
+	/// Set the Forge/NeoForge version to install. If it is not set, the newest version is used
+	pub fn set_forge_version(&mut self, version: Option<String>) {
+		self.forge_version = version;
+	}
+
 	/// Set the MS client ID
 	pub fn set_client_id(&mut self, id: ClientId) {
 		self.ms_client_id = Some(id);
@@ -134,6 +184,7 @@ impl UpdateManager {
 		// We have to clear these now since they are out of date
 		self.version_info.clear();
 		self.fq_meta.clear();
+		self.forge_profile.clear();
 	}
 
 	/// Run all of the operations that are part of the requirements.
@@ -165,6 +216,10 @@ impl UpdateManager {
 			.await
 			.context("Failed to update Fabric/Quilt")?;
 
+		self.update_forge(&version_info, client, o)
+			.await
+			.context("Failed to update Forge/NeoForge")?;
+
 		self.version_info.fill(version_info);
 
 		Ok(())
@@ -186,7 +241,10 @@ impl UpdateManager {
 		// Setup the core
 		let mut core_config = mcvm_core::ConfigBuilder::new()
 			.allow_offline(self.settings.allow_offline)
+			.offline(self.settings.offline)
 			.force_reinstall(self.settings.force)
+			.censor_secrets(self.settings.censor_secrets)
+			.bandwidth_limit(self.settings.bandwidth_limit)
 			.branding(BrandingProperties::new(
 				"mcvm".into(),
 				crate::VERSION.into(),
@@ -282,9 +340,22 @@ impl UpdateManager {
 			for req in self.requirements.iter() {
 				if let UpdateRequirement::FabricQuilt(mode, side) = req {
 					if self.fq_meta.is_empty() {
-						let meta = fabric_quilt::get_meta(
+						// The newest Fabric loader can be a beta, so use the latest stable one
+						// unless we are only using cached files
+						let loader_version = if *mode == fabric_quilt::Mode::Fabric
+							&& !core.get_update_manager().allow_offline()
+						{
+							fabric::get_latest_stable_loader(&version_info.version, client)
+								.await
+								.context("Failed to get the latest stable Fabric loader")?
+								.map(|x| x.version)
+						} else {
+							None
+						};
+						let meta = fabric_quilt::get_meta_for_loader_version(
 							&version_info.version,
 							mode,
+							loader_version.as_deref(),
 							&paths.core,
 							core.get_update_manager(),
 							client,
@@ -319,6 +390,49 @@ impl UpdateManager {
 
 		Ok(())
 	}
+
+	/// Install Forge or NeoForge if it is required
+	async fn update_forge(
+		&mut self,
+		version_info: &VersionInfo,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<()> {
+		if self.forge_profile.is_full() {
+			return Ok(());
+		}
+
+		let mode = self.requirements.iter().find_map(|x| match x {
+			UpdateRequirement::ForgeLike(mode) => Some(*mode),
+			_ => None,
+		});
+		let Some(mode) = mode else {
+			return Ok(());
+		};
+
+		let loader_version = match &self.forge_version {
+			Some(version) => version.clone(),
+			None => {
+				self.ensure_online(&format!("{mode} version list"))?;
+				forge::get_newest_loader_version(mode, &version_info.version, client)
+					.await
+					.with_context(|| format!("Failed to get the newest {mode} version"))?
+			}
+		};
+
+		let profile = forge::install_from_core(
+			self.core.get_mut(),
+			self.mc_version.get(),
+			mode,
+			&loader_version,
+			o,
+		)
+		.await
+		.with_context(|| format!("Failed to install {mode} {loader_version}"))?;
+		self.forge_profile.fill(profile);
+
+		Ok(())
+	}
 }
 
 /// Struct returned by updating functions, with data like changed files
@@ -12,8 +12,7 @@ use mcvm_shared::translate;
 #[cfg(not(feature = "disable_profile_update_packages"))]
 use packages::print_package_support_messages;
 use packages::update_instance_packages;
-#[cfg(not(feature = "disable_profile_update_packages"))]
-use std::collections::HashSet;
+use packages::InstallSummary;
 
 use anyhow::Context;
 use mcvm_mods::paper;
@@ -45,22 +44,36 @@ pub struct InstanceUpdateContext<'a, O: MCVMOutput> {
 	pub lock: &'a mut Lockfile,
 	/// The reqwest client
 	pub client: &'a Client,
+	/// Whether to skip all network operations and only use cached files
+	pub offline: bool,
 	/// The output object
 	pub output: &'a mut O,
 }
 
 impl Instance {
-	/// Update this instance
+	/// Update this instance. Returns a summary of the package installation if packages were updated
 	pub async fn update<'a, O: MCVMOutput>(
 		&mut self,
 		update_packages: bool,
 		force: bool,
 		ctx: &mut InstanceUpdateContext<'a, O>,
-	) -> anyhow::Result<()> {
+	) -> anyhow::Result<Option<InstallSummary>> {
 		#[cfg(feature = "disable_profile_update_packages")]
 		let _update_packages = update_packages;
 
 		let mut manager = UpdateManager::new(force, false);
+		manager.set_censor_secrets(ctx.prefs.censor_secrets);
+		manager.set_bandwidth_limit(ctx.prefs.bandwidth_limit);
+		if ctx.offline {
+			manager.offline();
+			// The newest Forge/NeoForge version can't be checked for, so use the installed one
+			manager.set_forge_version(
+				ctx.lock
+					.get_instance_modloader_version(&self.id)
+					.map(String::from),
+			);
+		}
+		ctx.packages.set_offline(ctx.offline);
 
 		ctx.output.display(
 			MessageContents::Header(translate!(
@@ -106,46 +119,58 @@ impl Instance {
 		.await
 		.context("Failed to create instance")?;
 
-		if update_packages {
-			#[cfg(not(feature = "disable_profile_update_packages"))]
-			{
-				let mut all_packages = HashSet::new();
-
-				ctx.output.display(
-					MessageContents::Header(translate!(ctx.output, StartUpdatingPackages)),
-					MessageLevel::Important,
-				);
-
-				let constants = EvalConstants {
-					version: mc_version.to_string(),
-					modifications: self.config.modifications.clone(),
-					version_list: manager.version_info.get().versions.clone(),
-					language: ctx.prefs.language,
-					profile_stability: self.config.package_stability,
-				};
-
-				let packages =
-					update_instance_packages(&mut [self], &constants, ctx, force).await?;
-
-				ctx.output.display(
-					MessageContents::Success(translate!(ctx.output, FinishUpdatingPackages)),
-					MessageLevel::Important,
-				);
-
-				all_packages.extend(packages);
+		// Remember the modloader version so that it can be included in exported modpacks
+		let modloader_version = if manager.fq_meta.is_full() {
+			Some(manager.fq_meta.get().get_loader_version().to_string())
+		} else if manager.forge_profile.is_full() {
+			Some(manager.forge_profile.get().loader_version.clone())
+		} else {
+			None
+		};
+		ctx.lock
+			.update_instance_modloader_version(&self.id, modloader_version.clone());
+
+		#[cfg(not(feature = "disable_profile_update_packages"))]
+		let summary = if update_packages {
+			ctx.output.display(
+				MessageContents::Header(translate!(ctx.output, StartUpdatingPackages)),
+				MessageLevel::Important,
+			);
+
+			let constants = EvalConstants {
+				version: mc_version.to_string(),
+				modifications: self.config.modifications.clone(),
+				modloader_version,
+				version_list: manager.version_info.get().versions.clone(),
+				language: ctx.prefs.language,
+				profile_stability: self.config.package_stability,
+			};
+
+			let install_summary =
+				update_instance_packages(&mut [self], &constants, ctx, force).await?;
+
+			ctx.output.display(
+				MessageContents::Success(translate!(ctx.output, FinishUpdatingPackages)),
+				MessageLevel::Important,
+			);
+
+			ctx.lock
+				.finish(ctx.paths)
+				.context("Failed to finish using lockfile")?;
+
+			let all_packages: Vec<_> = install_summary.packages().cloned().collect();
+			print_package_support_messages(&all_packages, ctx)
+				.await
+				.context("Failed to print support messages")?;
 
-				ctx.lock
-					.finish(ctx.paths)
-					.context("Failed to finish using lockfile")?;
-
-				let all_packages = Vec::from_iter(all_packages);
-				print_package_support_messages(&all_packages, ctx)
-					.await
-					.context("Failed to print support messages")?;
-			}
-		}
+			Some(install_summary)
+		} else {
+			None
+		};
+		#[cfg(feature = "disable_profile_update_packages")]
+		let summary = None;
 
-		Ok(())
+		Ok(summary)
 	}
 }
 
@@ -182,6 +207,11 @@ async fn get_paper_properties<'a, O: MCVMOutput>(
 	mc_version: &str,
 	ctx: &mut InstanceUpdateContext<'a, O>,
 ) -> anyhow::Result<Option<(u16, String)>> {
+	// Updates can't be checked for without the network
+	if ctx.offline {
+		return Ok(None);
+	}
+
 	let out = if let ServerType::Paper = instance.config.modifications.server_type {
 		let build_num = paper::get_newest_build(paper::Mode::Paper, mc_version, ctx.client)
 			.await
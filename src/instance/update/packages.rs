@@ -1,19 +1,22 @@
 use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use itertools::Itertools;
 use mcvm_core::net::download::get_transfer_limit;
+use mcvm_pkg::lock::{verify_lock, ResolveLock};
 use mcvm_pkg::properties::PackageProperties;
 use mcvm_pkg::repo::PackageFlag;
 use mcvm_pkg::PkgRequest;
 use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
-use mcvm_shared::pkg::{ArcPkgReq, PackageID};
+use mcvm_shared::pkg::{ArcPkgReq, PackageID, PkgRequestSource};
 use mcvm_shared::translate;
 use mcvm_shared::versions::VersionInfo;
 use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 
+use crate::addon::{process_downloaded_addon, AddonExt};
 use crate::instance::Instance;
 use crate::pkg::eval::{resolve, EvalConstants, EvalInput, EvalParameters};
 use crate::util::select_random_n_items_from_list;
@@ -21,15 +24,98 @@ use mcvm_shared::id::InstanceID;
 
 use super::InstanceUpdateContext;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
+
+/// The name of the file in the instance directory that records the last resolution of its packages
+pub const RESOLVE_LOCK_FILE: &str = "resolve_lock.json";
+
+/// Summary of the results of installing packages in bulk
+#[derive(Debug, Default)]
+pub struct InstallSummary {
+	/// Packages that were installed or updated
+	pub installed: Vec<ArcPkgReq>,
+	/// Packages that were already up to date and didn't need anything new to be acquired
+	pub skipped: Vec<ArcPkgReq>,
+	/// Packages that failed to install, along with the error that caused it
+	pub failed: Vec<(ArcPkgReq, anyhow::Error)>,
+	/// The total size in bytes of the addons that were downloaded
+	pub bytes_downloaded: u64,
+	/// How long the installation took
+	pub time: Duration,
+}
+
+impl InstallSummary {
+	/// Iterate over all of the packages that were part of the installation
+	pub fn packages(&self) -> impl Iterator<Item = &ArcPkgReq> {
+		self.installed
+			.iter()
+			.chain(self.skipped.iter())
+			.chain(self.failed.iter().map(|x| &x.0))
+	}
 
-/// Install packages on multiple instances. Returns a set of all unique packages
+	/// Checks whether a package has failed to install
+	pub fn has_failed(&self, pkg: &ArcPkgReq) -> bool {
+		self.failed.iter().any(|x| &x.0 == pkg)
+	}
+
+	/// Returns an error listing the failed packages if any of them failed
+	pub fn check_failures(&self) -> anyhow::Result<()> {
+		if self.failed.is_empty() {
+			return Ok(());
+		}
+
+		let failed = self.failed.iter().map(|x| x.0.to_string()).join(", ");
+		bail!("Failed to install packages: {failed}");
+	}
+
+	/// Records the result of a step in installing a package, reporting and storing
+	/// the error if it failed
+	fn record<T>(
+		&mut self,
+		pkg: &ArcPkgReq,
+		result: anyhow::Result<T>,
+		o: &mut impl MCVMOutput,
+	) -> Option<T> {
+		match result {
+			Ok(val) => Some(val),
+			Err(e) => {
+				o.display(
+					format_package_update_message(
+						pkg,
+						None,
+						MessageContents::Error(format!("{e:?}")),
+					),
+					MessageLevel::Important,
+				);
+				if !self.has_failed(pkg) {
+					self.failed.push((pkg.clone(), e));
+				}
+				None
+			}
+		}
+	}
+
+	/// Records a package that finished installing without any failures
+	fn add_finished(&mut self, pkg: &ArcPkgReq, changed: bool) {
+		if changed {
+			self.installed.push(pkg.clone());
+		} else {
+			self.skipped.push(pkg.clone());
+		}
+	}
+}
+
+/// Install packages on multiple instances. A package that fails to install
+/// does not stop the others from being installed, and is reported in the returned summary instead
 pub async fn update_instance_packages<'a, O: MCVMOutput>(
 	instances: &mut [&mut Instance],
 	constants: &EvalConstants,
 	ctx: &mut InstanceUpdateContext<'a, O>,
 	force: bool,
-) -> anyhow::Result<HashSet<ArcPkgReq>> {
+) -> anyhow::Result<InstallSummary> {
+	let start_time = Instant::now();
+	let mut summary = InstallSummary::default();
+
 	// Resolve dependencies
 	ctx.output.start_process();
 	ctx.output.display(
@@ -51,6 +137,7 @@ pub async fn update_instance_packages<'a, O: MCVMOutput>(
 		MessageLevel::Important,
 	);
 	let mut tasks = HashMap::new();
+	let mut task_packages = HashMap::new();
 	let mut evals = HashMap::new();
 	for (package, package_instances) in resolved_packages
 		.package_to_instances
@@ -70,16 +157,12 @@ pub async fn update_instance_packages<'a, O: MCVMOutput>(
 				.find(|x| &x.id == instance_id)
 				.expect("Instance should exist");
 
-			let mut params = EvalParameters::new(instance.kind.to_side());
-			params.stability = instance.config.package_stability;
-			if let Some(config) = instance.get_package_config(&package.to_string()) {
-				params
-					.apply_config(config, &PackageProperties::default())
-					.context("Failed to apply config")?;
-			}
-
+			let params = get_eval_params(instance, package);
+			let Some(params) = summary.record(package, params, ctx.output) else {
+				break;
+			};
 			let input = EvalInput { constants, params };
-			let (eval, new_tasks) = instance
+			let result = instance
 				.get_package_addon_tasks(
 					package,
 					input,
@@ -93,8 +176,14 @@ pub async fn update_instance_packages<'a, O: MCVMOutput>(
 				.await
 				.with_context(|| {
 					format!("Failed to get addon install tasks for package '{package}' on instance")
-				})?;
-			tasks.extend(new_tasks);
+				});
+			let Some((eval, new_tasks)) = summary.record(package, result, ctx.output) else {
+				break;
+			};
+			for (task_id, task) in new_tasks {
+				task_packages.insert(task_id.clone(), package.clone());
+				tasks.insert(task_id, task);
+			}
 
 			// Add any notices to the list
 			notices.extend(
@@ -121,9 +210,33 @@ pub async fn update_instance_packages<'a, O: MCVMOutput>(
 	}
 
 	// Run the acquire tasks
-	run_addon_tasks(tasks, ctx.output)
+	let results = run_addon_tasks(tasks, ctx.output)
 		.await
 		.context("Failed to acquire addons")?;
+	let downloaded: HashSet<_> = results
+		.iter()
+		.filter(|(_, result)| result.as_ref().is_ok_and(|bytes| *bytes > 0))
+		.map(|(task_id, _)| task_id.clone())
+		.collect();
+	let changed_packages = record_addon_results(results, &task_packages, &mut summary, ctx.output);
+
+	// Let plugins process the addons that were downloaded before they are installed
+	for ((package, instance_id), eval) in evals.iter().sorted_by_key(|x| x.0) {
+		for addon in &eval.addon_reqs {
+			if summary.has_failed(package) {
+				break;
+			}
+			if !downloaded.contains(&addon.get_unique_id(instance_id)) {
+				continue;
+			}
+
+			let path = addon.addon.get_path(ctx.paths, instance_id);
+			let result =
+				process_downloaded_addon(&addon.addon, &path, ctx.plugins, ctx.paths, ctx.output)
+					.with_context(|| format!("Failed to process addon '{}'", addon.addon.id));
+			summary.record(package, result, ctx.output);
+		}
+	}
 
 	ctx.output.display(
 		MessageContents::Success(translate!(ctx.output, FinishAcquiringAddons)),
@@ -140,6 +253,10 @@ pub async fn update_instance_packages<'a, O: MCVMOutput>(
 		.iter()
 		.sorted_by_key(|x| x.0)
 	{
+		if summary.has_failed(package) {
+			continue;
+		}
+
 		ctx.output.start_process();
 
 		for instance_id in package_instances {
@@ -155,7 +272,7 @@ pub async fn update_instance_packages<'a, O: MCVMOutput>(
 			let eval = evals
 				.get(&(package, instance_id))
 				.expect("Evaluation should be in map");
-			instance
+			let result = instance
 				.install_eval_data(
 					package,
 					eval,
@@ -165,8 +282,17 @@ pub async fn update_instance_packages<'a, O: MCVMOutput>(
 					ctx.output,
 				)
 				.await
-				.context("Failed to install package on instance")?;
+				.context("Failed to install package on instance");
+			if summary.record(package, result, ctx.output).is_none() {
+				break;
+			}
+		}
+
+		if summary.has_failed(package) {
+			ctx.output.end_process();
+			continue;
 		}
+		summary.add_finished(package, changed_packages.contains(package));
 
 		ctx.output.display(
 			format_package_update_message(
@@ -179,67 +305,93 @@ pub async fn update_instance_packages<'a, O: MCVMOutput>(
 		ctx.output.end_process();
 	}
 
-	// Use the instance-package map to remove unused packages and addons
+	// Use the instance-package map to uninstall unused packages and remove their addons
 	for (instance_id, packages) in resolved_packages.instance_to_packages {
 		let instance = instances
-			.iter()
+			.iter_mut()
 			.find(|x| x.id == instance_id)
 			.expect("Instance should exist");
 
-		let files_to_remove = ctx
-			.lock
-			.remove_unused_packages(
-				&instance_id,
-				&packages
-					.iter()
-					.map(|x| x.id.clone())
-					.collect::<Vec<PackageID>>(),
-			)
-			.context("Failed to remove unused packages")?;
-		for file in files_to_remove {
+		let unused_packages = ctx.lock.get_unused_packages(
+			&instance_id,
+			&packages
+				.iter()
+				.map(|x| x.id.clone())
+				.collect::<Vec<PackageID>>(),
+		);
+		for package in unused_packages {
+			let package = Arc::new(PkgRequest::parse(package, PkgRequestSource::UserRequire));
+			let params = get_eval_params(instance, &package)?;
+			let input = EvalInput { constants, params };
 			instance
-				.remove_addon_file(&file, ctx.paths)
+				.uninstall_package(
+					&package,
+					input,
+					ctx.packages,
+					ctx.paths,
+					ctx.lock,
+					ctx.client,
+					ctx.plugins,
+					ctx.output,
+				)
+				.await
 				.with_context(|| {
-					format!(
-						"Failed to remove addon file {} for instance {}",
-						file.display(),
-						instance_id
-					)
+					format!("Failed to uninstall package '{package}' from instance {instance_id}")
 				})?;
 		}
 	}
 
-	// Get the set of unique packages
-	let mut out = HashSet::new();
-	out.extend(resolved_packages.package_to_instances.keys().cloned());
+	// Check for mods that ended up installed more than once
+	for instance in instances.iter() {
+		instance.check_duplicate_mods(ctx.lock, ctx.output);
+	}
+
+	summary.time = start_time.elapsed();
 
-	Ok(out)
+	Ok(summary)
+}
+
+/// Gets the parameters for evaluating a package on an instance
+fn get_eval_params(instance: &Instance, package: &ArcPkgReq) -> anyhow::Result<EvalParameters> {
+	let mut params = EvalParameters::new(instance.kind.to_side());
+	params.stability = instance.config.package_stability;
+	if let Some(config) = instance.get_package_config(&package.to_string()) {
+		params
+			.apply_config(config, &PackageProperties::default())
+			.context("Failed to apply config")?;
+	}
+
+	Ok(params)
 }
 
-/// Evaluates addon acquire tasks efficiently with a progress display to the user
+/// Evaluates addon acquire tasks efficiently with a progress display to the user.
+/// Returns the result of each task, which is the number of bytes it downloaded
 async fn run_addon_tasks(
-	tasks: HashMap<String, impl Future<Output = anyhow::Result<()>> + Send + 'static>,
+	tasks: HashMap<String, impl Future<Output = anyhow::Result<u64>> + Send + 'static>,
 	o: &mut impl MCVMOutput,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<HashMap<String, anyhow::Result<u64>>> {
 	let total_count = tasks.len();
 	let mut task_set = JoinSet::new();
 
 	let sem = Arc::new(Semaphore::new(get_transfer_limit()));
-	for task in tasks.into_values() {
+	for (task_id, task) in tasks {
 		let permit = sem.clone().acquire_owned().await;
 		let task = async move {
-			let _permit = permit?;
+			let result = match permit {
+				Ok(_permit) => task.await,
+				Err(e) => Err(e.into()),
+			};
 
-			task.await
+			(task_id, result)
 		};
 		task_set.spawn(task);
 	}
 
+	let mut out = HashMap::with_capacity(total_count);
 	o.start_process();
 	while let Some(result) = task_set.join_next().await {
-		result
-			.context("Failed to run addon acquire task")?
-			.context("Failed to acquire addon")?;
+		let (task_id, result) = result.context("Failed to run addon acquire task")?;
+		out.insert(task_id, result.context("Failed to acquire addon"));
 
 		// Update progress bar
 		let progress = MessageContents::Progress {
@@ -252,7 +404,29 @@ async fn run_addon_tasks(
 
 	o.end_process();
 
-	Ok(())
+	Ok(out)
+}
+
+/// Records the results of addon acquire tasks in the summary for the packages they belong to.
+/// Returns the set of packages that had addons acquired
+fn record_addon_results(
+	results: HashMap<String, anyhow::Result<u64>>,
+	task_packages: &HashMap<String, ArcPkgReq>,
+	summary: &mut InstallSummary,
+	o: &mut impl MCVMOutput,
+) -> HashSet<ArcPkgReq> {
+	let mut changed = HashSet::new();
+	for (task_id, result) in results.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)) {
+		let package = task_packages
+			.get(&task_id)
+			.expect("Task should have a package");
+		if let Some(bytes) = summary.record(package, result, o) {
+			summary.bytes_downloaded += bytes;
+			changed.insert(package.clone());
+		}
+	}
+
+	changed
 }
 
 /// Resolve packages and create a mapping of packages to a list of instances.
@@ -288,6 +462,32 @@ async fn resolve_and_batch<'a, O: MCVMOutput>(
 				instance.id
 			)
 		})?;
+
+		let hashes = instance_resolved
+			.packages
+			.iter()
+			.filter_map(|x| Some((x.id.clone(), ctx.packages.get_hash(x)?.to_string())))
+			.collect();
+		// Report any changes from the last resolution before replacing it
+		let lock_path = instance.dirs.get().inst_dir.join(RESOLVE_LOCK_FILE);
+		if lock_path.exists() {
+			let lock = ResolveLock::read(&lock_path).context("Failed to read resolve lock")?;
+			if let Err(e) = verify_lock(&lock, &instance_resolved, &hashes) {
+				ctx.output.display(
+					MessageContents::Warning(translate!(
+						ctx.output,
+						ResolveLockMismatch,
+						"inst" = &instance.id,
+						"error" = &format!("{e:#}")
+					)),
+					MessageLevel::Important,
+				);
+			}
+		}
+		let lock = ResolveLock::from_resolution(&instance_resolved, &hashes);
+		lock.write(&lock_path)
+			.context("Failed to write resolve lock")?;
+
 		for package in &instance_resolved.packages {
 			if let Some(entry) = batched.get_mut(package) {
 				entry.push(instance.id.clone());
@@ -321,35 +521,47 @@ async fn check_package<'a, O: MCVMOutput>(
 		.flags(pkg, ctx.paths, ctx.client, ctx.output)
 		.await
 		.context("Failed to get flags for package")?;
+	display_flag_warnings(&pkg.id, flags, ctx.output);
+
+	Ok(())
+}
+
+/// Displays warnings and errors for the flags on a package
+fn display_flag_warnings(pkg: &PackageID, flags: &HashSet<PackageFlag>, o: &mut impl MCVMOutput) {
 	if flags.contains(&PackageFlag::OutOfDate) {
-		ctx.output.display(
-			MessageContents::Warning(translate!(ctx.output, PackageOutOfDate, "pkg" = &pkg.id)),
+		o.display(
+			MessageContents::Warning(translate!(o, PackageOutOfDate, "pkg" = pkg)),
 			MessageLevel::Important,
 		);
 	}
 
 	if flags.contains(&PackageFlag::Deprecated) {
-		ctx.output.display(
-			MessageContents::Warning(translate!(ctx.output, PackageDeprecated, "pkg" = &pkg.id)),
+		o.display(
+			MessageContents::Warning(translate!(o, PackageDeprecated, "pkg" = pkg)),
+			MessageLevel::Important,
+		);
+	}
+
+	if flags.contains(&PackageFlag::Archived) {
+		o.display(
+			MessageContents::Warning(translate!(o, PackageArchived, "pkg" = pkg)),
 			MessageLevel::Important,
 		);
 	}
 
 	if flags.contains(&PackageFlag::Insecure) {
-		ctx.output.display(
-			MessageContents::Error(translate!(ctx.output, PackageInsecure, "pkg" = &pkg.id)),
+		o.display(
+			MessageContents::Error(translate!(o, PackageInsecure, "pkg" = pkg)),
 			MessageLevel::Important,
 		);
 	}
 
 	if flags.contains(&PackageFlag::Malicious) {
-		ctx.output.display(
-			MessageContents::Error(translate!(ctx.output, PackageMalicious, "pkg" = &pkg.id)),
+		o.display(
+			MessageContents::Error(translate!(o, PackageMalicious, "pkg" = pkg)),
 			MessageLevel::Important,
 		);
 	}
-
-	Ok(())
 }
 
 /// Prints support messages about installed packages when updating
@@ -405,3 +617,65 @@ fn format_package_update_message(
 
 	MessageContents::ListItem(Box::new(msg))
 }
+
+#[cfg(test)]
+mod tests {
+	use mcvm_shared::output::Recording;
+
+	use super::*;
+
+	#[test]
+	fn test_deprecated_package_warning() {
+		let mut o = Recording::default();
+		display_flag_warnings(&PackageID::from("foo"), &HashSet::new(), &mut o);
+		assert!(o.messages.is_empty());
+
+		let flags = HashSet::from([PackageFlag::Deprecated, PackageFlag::Archived]);
+		display_flag_warnings(&PackageID::from("foo"), &flags, &mut o);
+		assert_eq!(
+			o.messages,
+			vec![
+				"Warning: Package foo has been flagged as deprecated".to_string(),
+				"Warning: Package foo has been archived and will no longer receive updates"
+					.to_string(),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_install_summary() {
+		let package = |id: &str| Arc::new(PkgRequest::parse(id, PkgRequestSource::UserRequire));
+		let (foo, bar, baz) = (package("foo"), package("bar"), package("baz"));
+
+		let task =
+			|result: Result<u64, &'static str>| async move { result.map_err(anyhow::Error::msg) };
+		let tasks = HashMap::from([
+			("foo_addon".to_string(), task(Ok(100))),
+			("bar_addon".to_string(), task(Err("Download failed"))),
+		]);
+		let task_packages = HashMap::from([
+			("foo_addon".to_string(), foo.clone()),
+			("bar_addon".to_string(), bar.clone()),
+		]);
+
+		let mut o = Recording::default();
+		let results = run_addon_tasks(tasks, &mut o).await.unwrap();
+		let mut summary = InstallSummary::default();
+		let changed = record_addon_results(results, &task_packages, &mut summary, &mut o);
+
+		for pkg in [&foo, &bar, &baz] {
+			if !summary.has_failed(pkg) {
+				summary.add_finished(pkg, changed.contains(pkg));
+			}
+		}
+
+		assert_eq!(summary.installed, vec![foo]);
+		assert_eq!(summary.skipped, vec![baz]);
+		assert_eq!(summary.failed.len(), 1);
+		assert_eq!(summary.failed[0].0, bar);
+		assert!(format!("{:?}", summary.failed[0].1).contains("Download failed"));
+		assert_eq!(summary.bytes_downloaded, 100);
+		assert_eq!(summary.packages().count(), 3);
+		assert!(summary.check_failures().is_err());
+	}
+}
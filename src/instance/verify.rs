@@ -0,0 +1,347 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use mcvm_core::io::files::create_leading_dirs_async;
+use mcvm_core::net::download::{self, get_transfer_limit};
+use mcvm_core::net::game_files::verify::{
+	check_file, get_stored_game_files, FileStatus, GameFile, GameFileKind,
+};
+use mcvm_shared::modifications::ServerType;
+use mcvm_shared::output::MCVMOutput;
+use mcvm_shared::pkg::PackageID;
+use mcvm_shared::Side;
+use reqwest::Client;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::addon::AddonExt;
+use crate::io::lock::Lockfile;
+use crate::io::paths::Paths;
+use crate::util::hash::{get_best_hash, hash_file_with_best_hash};
+
+use super::update::packages::InstallSummary;
+use super::update::InstanceUpdateContext;
+use super::Instance;
+
+/// A file on an instance that is missing or corrupt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BadFile {
+	/// The path to the file
+	pub path: PathBuf,
+	/// Whether the file is missing or corrupt
+	pub status: FileStatus,
+	/// Where the file came from
+	pub source: FileSource,
+}
+
+/// Where a file on an instance came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileSource {
+	/// A file of the game version
+	Game {
+		/// The type of game file
+		kind: GameFileKind,
+		/// The URL to download the file again from
+		url: String,
+	},
+	/// A file of an addon installed by a package
+	Addon {
+		/// The package that installed the addon
+		package: PackageID,
+		/// The path to where the addon is stored in the internal addons folder
+		stored_path: PathBuf,
+	},
+}
+
+/// The results of checking the files of an instance
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+	/// The number of files that were checked
+	pub checked: usize,
+	/// The files that are missing or corrupt
+	pub bad_files: Vec<BadFile>,
+}
+
+impl VerifyReport {
+	/// Whether all of the checked files are intact
+	pub fn is_ok(&self) -> bool {
+		self.bad_files.is_empty()
+	}
+}
+
+/// The results of repairing the files of an instance
+#[derive(Debug)]
+pub struct RepairReport {
+	/// A summary of the package installation, if any addons had to be downloaded again
+	pub install_summary: Option<InstallSummary>,
+	/// The results of verifying the instance again after it was repaired
+	pub verify_report: VerifyReport,
+}
+
+impl Instance {
+	/// Checks the game JAR, libraries, assets, and package addons of this instance against their
+	/// expected hashes. The instance must have been updated at least once
+	pub fn verify(&self, lock: &Lockfile, paths: &Paths) -> anyhow::Result<VerifyReport> {
+		let version = lock
+			.get_instance_version(&self.id)
+			.context("Instance has not been updated yet")?;
+		let side = self.get_side();
+		let mut game_files = get_stored_game_files(version, side, &paths.core)
+			.context("Failed to get the files of the game version")?;
+		// Some server types replace the vanilla JAR with their own
+		if side == Side::Server && !self.uses_vanilla_server_jar() {
+			game_files.retain(|x| x.kind != GameFileKind::GameJar);
+		}
+
+		verify_files(&self.id, game_files, lock, paths)
+	}
+
+	/// Repairs the bad files found by verifying this instance. Game files are downloaded again,
+	/// and bad addon files are removed and then downloaded again by updating the instance.
+	/// The instance is verified again afterwards so that files that are still bad can be reported
+	pub async fn repair<'a, O: MCVMOutput>(
+		&mut self,
+		report: &VerifyReport,
+		ctx: &mut InstanceUpdateContext<'a, O>,
+	) -> anyhow::Result<RepairReport> {
+		let packages = repair_files(report, ctx.client)
+			.await
+			.context("Failed to repair files")?;
+		let install_summary = if packages.is_empty() {
+			None
+		} else {
+			self.update(true, false, ctx)
+				.await
+				.context("Failed to install addons again")?
+		};
+
+		let verify_report = self
+			.verify(ctx.lock, ctx.paths)
+			.context("Failed to verify instance after repairing it")?;
+
+		Ok(RepairReport {
+			install_summary,
+			verify_report,
+		})
+	}
+
+	/// Whether this instance uses the vanilla server JAR when it is a server
+	fn uses_vanilla_server_jar(&self) -> bool {
+		matches!(
+			self.config.modifications.server_type,
+			ServerType::None
+				| ServerType::Vanilla
+				| ServerType::Fabric
+				| ServerType::Quilt
+				| ServerType::Forge
+				| ServerType::NeoForged
+		)
+	}
+}
+
+/// Checks game files and the addons installed on an instance
+pub fn verify_files(
+	instance_id: &str,
+	game_files: Vec<GameFile>,
+	lock: &Lockfile,
+	paths: &Paths,
+) -> anyhow::Result<VerifyReport> {
+	let mut report = VerifyReport::default();
+
+	for file in game_files {
+		let status = check_file(&file.path, file.sha1.as_deref())
+			.with_context(|| format!("Failed to check file {}", file.path.display()))?;
+		report.checked += 1;
+		if status != FileStatus::Intact {
+			report.bad_files.push(BadFile {
+				path: file.path,
+				status,
+				source: FileSource::Game {
+					kind: file.kind,
+					url: file.url,
+				},
+			});
+		}
+	}
+
+	for (package, lock_addon) in lock.get_installed_addons(instance_id) {
+		let addon = lock_addon.to_addon(package.clone())?;
+		let stored_path = addon.get_path(paths, instance_id);
+		for path in lock_addon.get_files() {
+			report.checked += 1;
+			let status = if !path.exists() {
+				FileStatus::Missing
+			} else if let Some(hash) = get_best_hash(&addon.hashes) {
+				let matches = hash_file_with_best_hash(&path, hash)
+					.with_context(|| format!("Failed to check file {}", path.display()))?;
+				if matches {
+					FileStatus::Intact
+				} else {
+					FileStatus::Corrupt
+				}
+			} else {
+				FileStatus::Intact
+			};
+
+			if status != FileStatus::Intact {
+				report.bad_files.push(BadFile {
+					path,
+					status,
+					source: FileSource::Addon {
+						package: package.clone(),
+						stored_path: stored_path.clone(),
+					},
+				});
+			}
+		}
+	}
+
+	Ok(report)
+}
+
+/// Repairs the bad files in a verification report, returning the packages that have to be updated
+pub async fn repair_files(
+	report: &VerifyReport,
+	client: &Client,
+) -> anyhow::Result<Vec<PackageID>> {
+	let mut packages = Vec::new();
+	let mut tasks = JoinSet::new();
+	// Used to limit the number of open file descriptors
+	let sem = Arc::new(Semaphore::new(get_transfer_limit()));
+	for file in &report.bad_files {
+		match &file.source {
+			FileSource::Game { url, .. } => {
+				let path = file.path.clone();
+				let url = url.clone();
+				let client = client.clone();
+				let sem = sem.clone();
+				tasks.spawn(async move {
+					let _permit = sem.acquire().await;
+					create_leading_dirs_async(&path).await?;
+					download::file(url, &path, &client)
+						.await
+						.with_context(|| format!("Failed to download file {}", path.display()))
+				});
+			}
+			FileSource::Addon {
+				package,
+				stored_path,
+			} => {
+				// The instance file is a link to the stored addon, so the stored one is bad as well
+				for path in [&file.path, stored_path] {
+					if path.exists() {
+						std::fs::remove_file(path).with_context(|| {
+							format!("Failed to remove addon file {}", path.display())
+						})?;
+					}
+				}
+				packages.push(package.clone());
+			}
+		}
+	}
+
+	while let Some(result) = tasks.join_next().await {
+		result??;
+	}
+
+	packages.sort();
+	packages.dedup();
+
+	Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+	use mcvm_core::net::game_files::client_meta::create_test_meta_json;
+	use mcvm_shared::addon::{Addon, AddonKind};
+	use mcvm_shared::output::NoOp;
+	use mcvm_shared::pkg::PackageAddonOptionalHashes;
+
+	use crate::io::lock::LockfileAddon;
+
+	use super::*;
+
+	#[test]
+	fn test_verify_files() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+
+		let mut meta = create_test_meta_json(serde_json::json!([
+			{
+				"name": "foo:lib:1.0",
+				"downloads": {
+					"artifact": {
+						"path": "foo/lib/1.0/lib-1.0.jar",
+						"url": "https://example.com/lib.jar",
+						"sha1": "9d062bafff17ba8b9a1215c4c51485134d509d91"
+					}
+				}
+			}
+		]));
+		meta["downloads"]["client"]["sha1"] =
+			serde_json::json!("f92e777f4341930bad9b2422283c4680d00dbc06");
+		let meta = meta.to_string();
+		let index = r#"{
+			"objects": {
+				"minecraft/asset.png": {"hash": "05fac94380a70241f23780e7aef62b190894238f", "size": 5}
+			}
+		}"#;
+		let write = |path: PathBuf, contents: &str| {
+			std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+			std::fs::write(path, contents).unwrap();
+		};
+
+		let core = &paths.core;
+		write(core.internal.join("versions/1.0/1.0.json"), &meta);
+		write(core.assets.join("indexes/1.0.json"), index);
+		let jar_path =
+			mcvm_core::io::minecraft::game_jar::get_path(Side::Client, "1.0", None, core);
+		write(jar_path, "jar");
+		// The library is corrupted
+		let lib_path = core.libraries.join("foo/lib/1.0/lib-1.0.jar");
+		write(lib_path.clone(), "corrupted");
+		write(
+			core.assets
+				.join("objects/05/05fac94380a70241f23780e7aef62b190894238f"),
+			"asset",
+		);
+
+		let mod_path = data_dir.join("instance/mods/mod.jar");
+		let addon = Addon {
+			kind: AddonKind::Mod,
+			id: "mod".into(),
+			file_name: "mod.jar".into(),
+			pkg_id: "pkg".into(),
+			version: None,
+			hashes: PackageAddonOptionalHashes {
+				sha256: Some(
+					"e55cffc81a5ad8cfe85239d944a3ae9513645a9eed79bc884f51b80b2760fc46".into(),
+				),
+				sha512: None,
+			},
+		};
+		let mut lock = Lockfile::open(&paths).unwrap();
+		lock.update_instance_version("inst", "1.0");
+		let addons = [LockfileAddon::from_addon(&addon, vec![mod_path.clone()])];
+		lock.update_package("pkg", "inst", &addons, &mut NoOp)
+			.unwrap();
+		write(mod_path.clone(), "mod");
+
+		let game_files = get_stored_game_files("1.0", Side::Client, core).unwrap();
+		let report = verify_files("inst", game_files, &lock, &paths).unwrap();
+		assert_eq!(report.checked, 4);
+		assert_eq!(
+			report.bad_files,
+			vec![BadFile {
+				path: lib_path,
+				status: FileStatus::Corrupt,
+				source: FileSource::Game {
+					kind: GameFileKind::Library,
+					url: "https://example.com/lib.jar".into(),
+				},
+			}]
+		);
+	}
+}
@@ -6,6 +6,7 @@ use anyhow::{anyhow, bail, Context};
 use mcvm_core::io::{json_from_file, json_to_file_pretty};
 use mcvm_shared::output::{MCVMOutput, MessageContents};
 use mcvm_shared::translate;
+use mcvm_shared::util::is_process_alive;
 use serde::{Deserialize, Serialize};
 
 use mcvm_shared::addon::{Addon, AddonKind};
@@ -34,12 +35,22 @@ struct LockfileInstance {
 	version: String,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	paper_build: Option<u16>,
+	/// The version of the modloader that the instance was last updated with
+	#[serde(skip_serializing_if = "Option::is_none")]
+	modloader_version: Option<String>,
+	/// The PID of the instance process, if it was launched and has not been recorded as stopped
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pid: Option<u32>,
 }
 
 /// Package stored in the lockfile
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LockfilePackage {
 	addons: Vec<LockfileAddon>,
+	/// The content version that the package was installed with
+	#[serde(default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	content_version: Option<String>,
 }
 
 /// Format for an addon in the lockfile
@@ -79,6 +90,12 @@ impl LockfileAddon {
 		}
 	}
 
+	/// Replaces the hashes that the installed addon files are checked against
+	pub fn with_hashes(mut self, hashes: PackageAddonOptionalHashes) -> Self {
+		self.hashes = hashes;
+		self
+	}
+
 	/// Converts this LockfileAddon to an Addon
 	pub fn to_addon(&self, pkg_id: PackageID) -> anyhow::Result<Addon> {
 		Ok(Addon {
@@ -95,6 +112,11 @@ impl LockfileAddon {
 		})
 	}
 
+	/// Get the paths of the files of this addon in the instance
+	pub fn get_files(&self) -> Vec<PathBuf> {
+		self.files.iter().map(PathBuf::from).collect()
+	}
+
 	/// Remove this addon
 	pub fn remove(&self) -> anyhow::Result<()> {
 		for file in self.files.iter() {
@@ -201,6 +223,7 @@ impl Lockfile {
 					id.to_owned(),
 					LockfilePackage {
 						addons: addons.to_vec(),
+						content_version: None,
 					},
 				);
 				new_files.extend(addons.iter().flat_map(|x| x.files.clone()));
@@ -234,6 +257,100 @@ impl Lockfile {
 		Ok(files_to_remove)
 	}
 
+	/// Updates the content version that a package is installed with
+	pub fn update_package_content_version(
+		&mut self,
+		id: &str,
+		instance: &str,
+		content_version: Option<String>,
+	) {
+		if let Some(pkg) = self
+			.contents
+			.packages
+			.get_mut(instance)
+			.and_then(|x| x.get_mut(id))
+		{
+			pkg.content_version = content_version;
+		}
+	}
+
+	/// Gets the packages installed on an instance along with their content versions, sorted by ID
+	pub fn get_installed_packages(&self, instance: &str) -> Vec<(PackageID, Option<String>)> {
+		let Some(inst) = self.contents.packages.get(instance) else {
+			return Vec::new();
+		};
+		let mut out: Vec<_> = inst
+			.iter()
+			.map(|(id, pkg)| (PackageID::from(id.clone()), pkg.content_version.clone()))
+			.collect();
+		out.sort();
+
+		out
+	}
+
+	/// Gets all of the addons installed on an instance along with the packages that installed them, sorted by package
+	pub fn get_installed_addons(&self, instance: &str) -> Vec<(PackageID, &LockfileAddon)> {
+		let Some(inst) = self.contents.packages.get(instance) else {
+			return Vec::new();
+		};
+		let mut out: Vec<_> = inst
+			.iter()
+			.flat_map(|(id, pkg)| {
+				pkg.addons
+					.iter()
+					.map(|addon| (PackageID::from(id.clone()), addon))
+			})
+			.collect();
+		out.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.id.cmp(&b.1.id)));
+
+		out
+	}
+
+	/// Gets the files of all of the addons of a kind installed on an instance along with
+	/// the packages that installed them, sorted by package
+	pub fn get_installed_addon_files(
+		&self,
+		instance: &str,
+		kind: AddonKind,
+	) -> Vec<(PackageID, PathBuf)> {
+		let Some(inst) = self.contents.packages.get(instance) else {
+			return Vec::new();
+		};
+		let kind = kind.to_string();
+		let mut out: Vec<_> = inst
+			.iter()
+			.flat_map(|(id, pkg)| {
+				pkg.addons
+					.iter()
+					.filter(|addon| addon.kind == kind)
+					.flat_map(|addon| addon.files.iter())
+					.map(|file| (PackageID::from(id.clone()), PathBuf::from(file)))
+			})
+			.collect();
+		out.sort();
+
+		out
+	}
+
+	/// Gets the packages installed on an instance that are no longer used, sorted by ID
+	pub fn get_unused_packages(
+		&self,
+		instance: &str,
+		used_packages: &[PackageID],
+	) -> Vec<PackageID> {
+		let Some(inst) = self.contents.packages.get(instance) else {
+			return Vec::new();
+		};
+		let mut out: Vec<_> = inst
+			.keys()
+			.map(|pkg| PackageID::from(pkg.clone()))
+			.filter(|pkg| !used_packages.contains(pkg))
+			.collect();
+		out.sort();
+
+		out
+	}
+
 	/// Remove any unused packages for an instance.
 	/// Returns any addon files that need to be removed from the instance.
 	pub fn remove_unused_packages(
@@ -241,27 +358,43 @@ impl Lockfile {
 		instance: &str,
 		used_packages: &[PackageID],
 	) -> anyhow::Result<Vec<PathBuf>> {
-		if let Some(inst) = self.contents.packages.get_mut(instance) {
-			let mut pkgs_to_remove = Vec::new();
-			for (pkg, ..) in inst.iter() {
-				if !used_packages.contains(&PackageID::from(pkg.clone())) {
-					pkgs_to_remove.push(pkg.clone());
-				}
-			}
+		let mut files_to_remove = Vec::new();
+		for pkg_id in self.get_unused_packages(instance, used_packages) {
+			files_to_remove.extend(self.remove_package(&pkg_id, instance));
+		}
 
-			let mut files_to_remove = Vec::new();
-			for pkg_id in pkgs_to_remove {
-				if let Some(pkg) = inst.remove(&pkg_id) {
-					for addon in pkg.addons {
-						files_to_remove.extend(addon.files.iter().map(PathBuf::from));
-					}
-				}
-			}
+		Ok(files_to_remove)
+	}
 
-			Ok(files_to_remove)
-		} else {
-			Ok(vec![])
-		}
+	/// Removes a package from an instance. Returns the addon files that it installed and that
+	/// need to be removed from the instance. Files that are also used by another package on the
+	/// instance are not included.
+	pub fn remove_package(&mut self, id: &str, instance: &str) -> Vec<PathBuf> {
+		let Some(inst) = self.contents.packages.get_mut(instance) else {
+			return Vec::new();
+		};
+		let Some(pkg) = inst.remove(id) else {
+			return Vec::new();
+		};
+
+		let used_files: HashSet<&String> = inst
+			.values()
+			.flat_map(|x| x.addons.iter().flat_map(|addon| addon.files.iter()))
+			.collect();
+		pkg.addons
+			.iter()
+			.flat_map(|addon| addon.files.iter())
+			.filter(|file| !used_files.contains(file))
+			.map(PathBuf::from)
+			.collect()
+	}
+
+	/// Gets the Minecraft version that an instance was last updated with
+	pub fn get_instance_version(&self, instance: &str) -> Option<&str> {
+		self.contents
+			.instances
+			.get(instance)
+			.map(|x| x.version.as_str())
 	}
 
 	/// Updates an instance in the lockfile. Returns true if the version has changed.
@@ -279,6 +412,8 @@ impl Lockfile {
 				LockfileInstance {
 					version: version.to_owned(),
 					paper_build: None,
+					modloader_version: None,
+					pid: None,
 				},
 			);
 
@@ -305,6 +440,21 @@ impl Lockfile {
 		}
 	}
 
+	/// Gets the modloader version that an instance was last updated with, if it is known
+	pub fn get_instance_modloader_version(&self, instance: &str) -> Option<&str> {
+		self.contents
+			.instances
+			.get(instance)
+			.and_then(|x| x.modloader_version.as_deref())
+	}
+
+	/// Updates the modloader version that an instance was updated with
+	pub fn update_instance_modloader_version(&mut self, instance: &str, version: Option<String>) {
+		if let Some(instance) = self.contents.instances.get_mut(instance) {
+			instance.modloader_version = version;
+		}
+	}
+
 	/// Check whether an instance has done its first update successfully
 	pub fn has_instance_done_first_update(&self, instance: &str) -> bool {
 		self.contents.created_instances.contains(instance)
@@ -314,4 +464,112 @@ impl Lockfile {
 	pub fn update_instance_has_done_first_update(&mut self, instance: &str) {
 		self.contents.created_instances.insert(instance.to_string());
 	}
+
+	/// Updates the recorded PID of a running instance. Use None when the instance has stopped
+	pub fn update_instance_pid(&mut self, instance: &str, pid: Option<u32>) {
+		if let Some(instance) = self.contents.instances.get_mut(instance) {
+			instance.pid = pid;
+		}
+	}
+
+	/// Gets the recorded PID of an instance
+	pub fn get_instance_pid(&self, instance: &str) -> Option<u32> {
+		self.contents.instances.get(instance).and_then(|x| x.pid)
+	}
+
+	/// Checks whether an instance is running by checking if its recorded PID is still alive
+	pub fn is_instance_running(&self, instance: &str) -> bool {
+		self.get_instance_pid(instance)
+			.is_some_and(is_process_alive)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use mcvm_shared::output::NoOp;
+
+	use super::*;
+
+	#[test]
+	fn test_instance_running() {
+		let mut lock = Lockfile {
+			contents: LockfileContents::default(),
+		};
+		assert!(!lock.is_instance_running("foo"));
+
+		lock.update_instance_version("foo", "1.20.1");
+		lock.update_instance_pid("foo", Some(std::process::id()));
+		assert!(lock.is_instance_running("foo"));
+
+		// Spawn and reap a process so that we have a PID that is no longer alive
+		let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+			.arg("--help")
+			.stdout(std::process::Stdio::null())
+			.spawn()
+			.unwrap();
+		let stale_pid = child.id();
+		child.wait().unwrap();
+		lock.update_instance_pid("foo", Some(stale_pid));
+		assert!(!lock.is_instance_running("foo"));
+
+		lock.update_instance_pid("foo", None);
+		assert!(!lock.is_instance_running("foo"));
+	}
+
+	fn create_addon(id: &str, pkg_id: &str, files: &[PathBuf]) -> LockfileAddon {
+		let addon = Addon {
+			kind: AddonKind::Mod,
+			id: id.into(),
+			file_name: format!("{id}.jar"),
+			pkg_id: pkg_id.into(),
+			version: None,
+			hashes: PackageAddonOptionalHashes::default(),
+		};
+		LockfileAddon::from_addon(&addon, files.to_vec())
+	}
+
+	#[test]
+	fn test_uninstall_package() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+
+		let mut lock = Lockfile {
+			contents: LockfileContents::default(),
+		};
+		let foo = [
+			create_addon("a", "foo", &[dir.join("a.jar")]),
+			create_addon("shared", "foo", &[dir.join("shared.jar")]),
+		];
+		let bar = [
+			create_addon("b", "bar", &[dir.join("b.jar")]),
+			create_addon("shared", "bar", &[dir.join("shared.jar")]),
+		];
+		lock.update_package("foo", "inst", &foo, &mut NoOp).unwrap();
+		lock.update_package("bar", "inst", &bar, &mut NoOp).unwrap();
+		for file in ["a.jar", "b.jar", "shared.jar", "user.jar"] {
+			std::fs::write(dir.join(file), file).unwrap();
+		}
+
+		assert_eq!(
+			lock.get_unused_packages("inst", &["bar".into()]),
+			vec![PackageID::from("foo")]
+		);
+		let files = lock
+			.remove_unused_packages("inst", &["bar".into()])
+			.unwrap();
+		assert_eq!(files, vec![dir.join("a.jar")]);
+		for file in files {
+			std::fs::remove_file(file).unwrap();
+		}
+		assert!(!dir.join("a.jar").exists());
+		assert!(dir.join("b.jar").exists());
+		assert!(dir.join("shared.jar").exists());
+		assert!(dir.join("user.jar").exists());
+		assert!(lock.get_unused_packages("inst", &["bar".into()]).is_empty());
+
+		// Once the last package using the shared file is removed, it is removed too
+		let mut files = lock.remove_package("bar", "inst");
+		files.sort();
+		assert_eq!(files, vec![dir.join("b.jar"), dir.join("shared.jar")]);
+	}
 }
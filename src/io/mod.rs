@@ -1,4 +1,8 @@
 /// Use of the lockfile for persistent data
 pub mod lock;
+/// Reading metadata from mod files
+pub mod mod_metadata;
 /// Standard paths for MCVM
 pub mod paths;
+/// Staging of file changes so that they can be applied or rolled back together
+pub mod transaction;
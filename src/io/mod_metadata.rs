@@ -0,0 +1,652 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::Path;
+
+use anyhow::Context;
+use mcvm_shared::modifications::Modloader;
+use serde::Deserialize;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// Path to the metadata file in Fabric mods
+pub const FABRIC_METADATA_PATH: &str = "fabric.mod.json";
+/// Path to the metadata file in Quilt mods
+pub const QUILT_METADATA_PATH: &str = "quilt.mod.json";
+/// Path to the metadata file in Forge mods
+pub const FORGE_METADATA_PATH: &str = "META-INF/mods.toml";
+/// Path to the metadata file in NeoForge mods
+pub const NEOFORGE_METADATA_PATH: &str = "META-INF/neoforge.mods.toml";
+/// Path to the manifest file in jars
+const JAR_MANIFEST_PATH: &str = "META-INF/MANIFEST.MF";
+/// Placeholder in mods.toml files for the version from the jar manifest
+const JAR_VERSION_PLACEHOLDER: &str = "${file.jarVersion}";
+/// How many levels of jars bundled inside of other jars will be read
+const MAX_NESTED_JAR_DEPTH: u8 = 4;
+
+/// Metadata about a mod, read from the metadata file of one of the modloaders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModMetadata {
+	/// The ID of the mod
+	pub id: String,
+	/// The version of the mod
+	pub version: Option<String>,
+	/// The display name of the mod
+	pub name: Option<String>,
+	/// The dependencies that the mod declares, sorted by ID
+	pub dependencies: Vec<ModDependency>,
+	/// The modloader that the metadata is for
+	pub loader: Modloader,
+	/// Mods from jars that are bundled inside of this mod's jar
+	pub bundled: Vec<ModMetadata>,
+}
+
+/// A dependency declared by a mod
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModDependency {
+	/// The ID of the mod that is depended on
+	pub id: String,
+	/// The accepted versions of the dependency, in the version range format of the modloader
+	pub versions: Option<String>,
+	/// Whether the dependency is required for the mod to load
+	pub required: bool,
+}
+
+/// Reads the metadata of all of the mods declared in a mod jar. A single jar can
+/// declare multiple mods, and can contain metadata for multiple loaders at once.
+/// Jars bundled inside of Fabric and Quilt mods are read as well, skipping any that can't be read
+pub fn read_mod_metadata(path: &Path) -> anyhow::Result<Vec<ModMetadata>> {
+	read_mod_metadata_impl(path, MAX_NESTED_JAR_DEPTH)
+}
+
+/// Reads the IDs of all of the mods declared by the metadata files in a mod jar, sorted and without duplicates.
+/// Bundled jars are not read
+pub fn read_mod_ids(path: &Path) -> anyhow::Result<Vec<String>> {
+	let mut out: Vec<_> = read_mod_metadata_impl(path, 0)?
+		.into_iter()
+		.map(|x| x.id)
+		.collect();
+	out.sort();
+	out.dedup();
+
+	Ok(out)
+}
+
+/// Reads the metadata of the mods in a mod jar, with bundled jars read up to a maximum depth
+fn read_mod_metadata_impl(path: &Path, max_depth: u8) -> anyhow::Result<Vec<ModMetadata>> {
+	let file = BufReader::new(File::open(path).context("Failed to open mod file")?);
+	let mut zip = ZipArchive::new(file).context("Failed to read mod archive")?;
+
+	read_jar_metadata(&mut zip, 0, max_depth)
+}
+
+/// Reads the metadata of the mods in an opened jar
+fn read_jar_metadata<R: Read + Seek>(
+	zip: &mut ZipArchive<R>,
+	depth: u8,
+	max_depth: u8,
+) -> anyhow::Result<Vec<ModMetadata>> {
+	let mut out = Vec::new();
+	if let Some(contents) = read_zip_file(zip, FABRIC_METADATA_PATH)? {
+		let metadata: FabricModJson =
+			serde_json::from_str(&contents).context("Failed to deserialize Fabric mod metadata")?;
+		let jars = metadata.jars.iter().map(|x| x.file.as_str());
+		let bundled = read_nested_jars(zip, jars, depth, max_depth);
+		out.push(metadata.into_metadata(bundled));
+	}
+	if let Some(contents) = read_zip_file(zip, QUILT_METADATA_PATH)? {
+		let metadata: QuiltModJson =
+			serde_json::from_str(&contents).context("Failed to deserialize Quilt mod metadata")?;
+		let jars = metadata.quilt_loader.jars.iter().map(String::as_str);
+		let bundled = read_nested_jars(zip, jars, depth, max_depth);
+		out.push(metadata.quilt_loader.into_metadata(bundled));
+	}
+	for (metadata_path, loader) in [
+		(FORGE_METADATA_PATH, Modloader::Forge),
+		(NEOFORGE_METADATA_PATH, Modloader::NeoForged),
+	] {
+		if let Some(contents) = read_zip_file(zip, metadata_path)? {
+			let metadata: ModsToml =
+				toml::from_str(&contents).context("Failed to deserialize Forge mod metadata")?;
+			let jar_version = read_jar_version(zip)?;
+			out.extend(metadata.into_metadata(&loader, jar_version.as_deref()));
+		}
+	}
+
+	Ok(out)
+}
+
+/// Reads the metadata of the mods in jars that are bundled inside of another jar.
+/// Bundled jars that are missing or can't be read are skipped, as they shouldn't stop the
+/// metadata of the outer mod from being read
+fn read_nested_jars<'a, R: Read + Seek>(
+	zip: &mut ZipArchive<R>,
+	paths: impl Iterator<Item = &'a str>,
+	depth: u8,
+	max_depth: u8,
+) -> Vec<ModMetadata> {
+	if depth >= max_depth {
+		return Vec::new();
+	}
+
+	let mut out = Vec::new();
+	for path in paths {
+		let Ok(Some(contents)) = read_zip_bytes(zip, path) else {
+			continue;
+		};
+		let Ok(mut nested) = ZipArchive::new(Cursor::new(contents)) else {
+			continue;
+		};
+		if let Ok(metadata) = read_jar_metadata(&mut nested, depth + 1, max_depth) {
+			out.extend(metadata);
+		}
+	}
+
+	out
+}
+
+/// Reads the implementation version from the manifest of a jar
+fn read_jar_version<R: Read + Seek>(zip: &mut ZipArchive<R>) -> anyhow::Result<Option<String>> {
+	let Some(manifest) = read_zip_file(zip, JAR_MANIFEST_PATH)? else {
+		return Ok(None);
+	};
+	let version = manifest.lines().find_map(|line| {
+		line.strip_prefix("Implementation-Version:")
+			.map(|x| x.trim().to_string())
+	});
+
+	Ok(version)
+}
+
+/// Reads a text file from a zip archive, returning None if it does not exist
+fn read_zip_file<R: Read + Seek>(
+	zip: &mut ZipArchive<R>,
+	path: &str,
+) -> anyhow::Result<Option<String>> {
+	let Some(contents) = read_zip_bytes(zip, path)? else {
+		return Ok(None);
+	};
+	let contents =
+		String::from_utf8(contents).with_context(|| format!("{path} is not valid UTF-8"))?;
+
+	Ok(Some(contents))
+}
+
+/// Reads a file from a zip archive, returning None if it does not exist
+fn read_zip_bytes<R: Read + Seek>(
+	zip: &mut ZipArchive<R>,
+	path: &str,
+) -> anyhow::Result<Option<Vec<u8>>> {
+	let mut file = match zip.by_name(path) {
+		Ok(file) => file,
+		Err(ZipError::FileNotFound) => return Ok(None),
+		Err(e) => return Err(e).with_context(|| format!("Failed to read {path} from archive")),
+	};
+	let mut out = Vec::new();
+	file.read_to_end(&mut out)
+		.with_context(|| format!("Failed to read {path} from archive"))?;
+
+	Ok(Some(out))
+}
+
+/// The fabric.mod.json metadata file
+#[derive(Deserialize)]
+struct FabricModJson {
+	id: String,
+	version: Option<String>,
+	name: Option<String>,
+	#[serde(default)]
+	depends: HashMap<String, FabricVersionRange>,
+	#[serde(default)]
+	recommends: HashMap<String, FabricVersionRange>,
+	#[serde(default)]
+	jars: Vec<FabricNestedJar>,
+}
+
+impl FabricModJson {
+	fn into_metadata(self, bundled: Vec<ModMetadata>) -> ModMetadata {
+		let required = self.depends.into_iter().map(|x| (x, true));
+		let optional = self.recommends.into_iter().map(|x| (x, false));
+		let mut dependencies: Vec<_> = required
+			.chain(optional)
+			.map(|((id, versions), required)| ModDependency {
+				id,
+				versions: Some(versions.to_string()),
+				required,
+			})
+			.collect();
+		dependencies.sort_by(|a, b| a.id.cmp(&b.id));
+
+		ModMetadata {
+			id: self.id,
+			version: self.version,
+			name: self.name,
+			dependencies,
+			loader: Modloader::Fabric,
+			bundled,
+		}
+	}
+}
+
+/// A version range for a dependency in the fabric.mod.json metadata file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FabricVersionRange {
+	/// A single version range
+	Single(String),
+	/// A list of version ranges where any of them can match
+	Any(Vec<String>),
+}
+
+impl std::fmt::Display for FabricVersionRange {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Single(range) => write!(f, "{range}"),
+			Self::Any(ranges) => write!(f, "{}", ranges.join(" || ")),
+		}
+	}
+}
+
+/// A bundled jar in the fabric.mod.json metadata file
+#[derive(Deserialize)]
+struct FabricNestedJar {
+	file: String,
+}
+
+/// The quilt.mod.json metadata file
+#[derive(Deserialize)]
+struct QuiltModJson {
+	quilt_loader: QuiltLoaderInfo,
+}
+
+/// The quilt_loader section of the quilt.mod.json metadata file
+#[derive(Deserialize)]
+struct QuiltLoaderInfo {
+	id: String,
+	version: Option<String>,
+	#[serde(default)]
+	metadata: QuiltDisplayMetadata,
+	#[serde(default)]
+	depends: Vec<QuiltDependency>,
+	#[serde(default)]
+	jars: Vec<String>,
+}
+
+impl QuiltLoaderInfo {
+	fn into_metadata(self, bundled: Vec<ModMetadata>) -> ModMetadata {
+		let mut dependencies: Vec<_> = self
+			.depends
+			.into_iter()
+			.map(|dependency| match dependency {
+				QuiltDependency::Id(id) => ModDependency {
+					id,
+					versions: None,
+					required: true,
+				},
+				QuiltDependency::Object {
+					id,
+					versions,
+					optional,
+				} => ModDependency {
+					id,
+					versions: versions.and_then(|x| match x {
+						serde_json::Value::String(range) => Some(range),
+						serde_json::Value::Array(ranges) => Some(
+							ranges
+								.iter()
+								.filter_map(|x| x.as_str())
+								.collect::<Vec<_>>()
+								.join(" || "),
+						),
+						_ => None,
+					}),
+					required: !optional,
+				},
+			})
+			.collect();
+		dependencies.sort_by(|a, b| a.id.cmp(&b.id));
+
+		ModMetadata {
+			id: self.id,
+			version: self.version,
+			name: self.metadata.name,
+			dependencies,
+			loader: Modloader::Quilt,
+			bundled,
+		}
+	}
+}
+
+/// The display metadata in the quilt.mod.json metadata file
+#[derive(Deserialize, Default)]
+struct QuiltDisplayMetadata {
+	name: Option<String>,
+}
+
+/// A dependency in the quilt.mod.json metadata file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QuiltDependency {
+	/// Just the ID of the dependency
+	Id(String),
+	/// The ID of the dependency along with more options
+	Object {
+		id: String,
+		/// Either a single version range or a list of them
+		#[serde(default)]
+		versions: Option<serde_json::Value>,
+		#[serde(default)]
+		optional: bool,
+	},
+}
+
+/// The mods.toml metadata file
+#[derive(Deserialize)]
+struct ModsToml {
+	#[serde(default)]
+	mods: Vec<ModsTomlMod>,
+	/// Dependencies for each mod ID
+	#[serde(default)]
+	dependencies: HashMap<String, Vec<ModsTomlDependency>>,
+}
+
+impl ModsToml {
+	fn into_metadata(mut self, loader: &Modloader, jar_version: Option<&str>) -> Vec<ModMetadata> {
+		self.mods
+			.into_iter()
+			.map(|mod_info| {
+				let mut dependencies: Vec<_> = self
+					.dependencies
+					.remove(&mod_info.mod_id)
+					.unwrap_or_default()
+					.into_iter()
+					.filter_map(ModsTomlDependency::into_dependency)
+					.collect();
+				dependencies.sort_by(|a, b| a.id.cmp(&b.id));
+
+				let version = match (mod_info.version, jar_version) {
+					(Some(version), Some(jar_version)) if version == JAR_VERSION_PLACEHOLDER => {
+						Some(jar_version.to_string())
+					}
+					(version, ..) => version,
+				};
+
+				ModMetadata {
+					id: mod_info.mod_id,
+					version,
+					name: mod_info.display_name,
+					dependencies,
+					loader: loader.clone(),
+					bundled: Vec::new(),
+				}
+			})
+			.collect()
+	}
+}
+
+/// A mod in the mods.toml metadata file
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModsTomlMod {
+	mod_id: String,
+	version: Option<String>,
+	display_name: Option<String>,
+}
+
+/// A dependency in the mods.toml metadata file
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModsTomlDependency {
+	mod_id: String,
+	/// Used by Forge
+	mandatory: Option<bool>,
+	/// Used by NeoForge instead of mandatory
+	#[serde(rename = "type")]
+	kind: Option<String>,
+	version_range: Option<String>,
+}
+
+impl ModsTomlDependency {
+	/// Converts this to a dependency, returning None if it is actually an incompatibility
+	fn into_dependency(self) -> Option<ModDependency> {
+		let required = match (self.mandatory, self.kind.as_deref()) {
+			(Some(mandatory), _) => mandatory,
+			(None, Some("required") | None) => true,
+			(None, Some("optional")) => false,
+			(None, Some(_)) => return None,
+		};
+
+		Some(ModDependency {
+			id: self.mod_id,
+			versions: self.version_range,
+			required,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Write;
+
+	use zip::write::FileOptions;
+	use zip::ZipWriter;
+
+	use super::*;
+
+	/// Creates the contents of a jar file with the given files
+	fn create_jar(files: &[(&str, &[u8])]) -> Vec<u8> {
+		let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+		for (path, contents) in files {
+			zip.start_file(*path, FileOptions::<()>::default()).unwrap();
+			zip.write_all(contents).unwrap();
+		}
+		zip.finish().unwrap().into_inner()
+	}
+
+	/// Writes a jar to a temporary file and reads its metadata
+	fn read_fixture(name: &str, jar: Vec<u8>) -> Vec<ModMetadata> {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join(format!("{name}.jar"));
+		std::fs::write(&path, jar).unwrap();
+		read_mod_metadata(&path).unwrap()
+	}
+
+	#[test]
+	fn test_fabric_metadata() {
+		let nested = create_jar(&[(
+			FABRIC_METADATA_PATH,
+			br#"{ "id": "fabric-api-base", "version": "0.4.31" }"#,
+		)]);
+		let jar = create_jar(&[
+			(
+				FABRIC_METADATA_PATH,
+				br#"{
+					"schemaVersion": 1,
+					"id": "sodium",
+					"version": "0.5.8",
+					"name": "Sodium",
+					"depends": { "minecraft": "1.20.1", "fabricloader": ["0.14.x", "0.15.x"] },
+					"recommends": { "indium": "*" },
+					"jars": [
+						{ "file": "META-INF/jars/fabric-api-base.jar" },
+						{ "file": "META-INF/jars/broken.jar" }
+					]
+				}"#,
+			),
+			("META-INF/jars/fabric-api-base.jar", &nested),
+			("META-INF/jars/broken.jar", b"not a jar"),
+		]);
+
+		let metadata = read_fixture("fabric", jar);
+		assert_eq!(metadata.len(), 1);
+		let metadata = &metadata[0];
+		assert_eq!(metadata.id, "sodium");
+		assert_eq!(metadata.version.as_deref(), Some("0.5.8"));
+		assert_eq!(metadata.name.as_deref(), Some("Sodium"));
+		assert_eq!(metadata.loader, Modloader::Fabric);
+		assert_eq!(
+			metadata.dependencies,
+			vec![
+				ModDependency {
+					id: "fabricloader".into(),
+					versions: Some("0.14.x || 0.15.x".into()),
+					required: true,
+				},
+				ModDependency {
+					id: "indium".into(),
+					versions: Some("*".into()),
+					required: false,
+				},
+				ModDependency {
+					id: "minecraft".into(),
+					versions: Some("1.20.1".into()),
+					required: true,
+				},
+			]
+		);
+		// The broken bundled jar is skipped
+		assert_eq!(metadata.bundled.len(), 1);
+		assert_eq!(metadata.bundled[0].id, "fabric-api-base");
+	}
+
+	#[test]
+	fn test_quilt_metadata() {
+		let jar = create_jar(&[(
+			QUILT_METADATA_PATH,
+			br#"{
+				"schema_version": 1,
+				"quilt_loader": {
+					"group": "org.example",
+					"id": "example",
+					"version": "1.0.0",
+					"metadata": { "name": "Example" },
+					"depends": [
+						"quilt_loader",
+						{ "id": "minecraft", "versions": ">=1.20" },
+						{ "id": "modmenu", "optional": true }
+					]
+				}
+			}"#,
+		)]);
+
+		let metadata = read_fixture("quilt", jar);
+		assert_eq!(metadata.len(), 1);
+		let metadata = &metadata[0];
+		assert_eq!(metadata.id, "example");
+		assert_eq!(metadata.version.as_deref(), Some("1.0.0"));
+		assert_eq!(metadata.name.as_deref(), Some("Example"));
+		assert_eq!(metadata.loader, Modloader::Quilt);
+		let dependencies: Vec<_> = metadata
+			.dependencies
+			.iter()
+			.map(|x| (x.id.as_str(), x.versions.as_deref(), x.required))
+			.collect();
+		assert_eq!(
+			dependencies,
+			vec![
+				("minecraft", Some(">=1.20"), true),
+				("modmenu", None, false),
+				("quilt_loader", None, true),
+			]
+		);
+	}
+
+	#[test]
+	fn test_forge_metadata() {
+		let jar = create_jar(&[
+			(
+				FORGE_METADATA_PATH,
+				br#"
+modLoader = "javafml"
+loaderVersion = "[47,)"
+
+[[mods]]
+modId = "jei"
+version = "${file.jarVersion}"
+displayName = "Just Enough Items"
+
+[[dependencies.jei]]
+modId = "forge"
+mandatory = true
+versionRange = "[47.1.3,)"
+
+[[dependencies.jei]]
+modId = "configured"
+mandatory = false
+"#,
+			),
+			(
+				JAR_MANIFEST_PATH,
+				b"Manifest-Version: 1.0\r\nImplementation-Version: 15.3.0.4\r\n",
+			),
+		]);
+
+		let metadata = read_fixture("forge", jar);
+		assert_eq!(metadata.len(), 1);
+		let metadata = &metadata[0];
+		assert_eq!(metadata.id, "jei");
+		assert_eq!(metadata.version.as_deref(), Some("15.3.0.4"));
+		assert_eq!(metadata.name.as_deref(), Some("Just Enough Items"));
+		assert_eq!(metadata.loader, Modloader::Forge);
+		let dependencies: Vec<_> = metadata
+			.dependencies
+			.iter()
+			.map(|x| (x.id.as_str(), x.versions.as_deref(), x.required))
+			.collect();
+		assert_eq!(
+			dependencies,
+			vec![
+				("configured", None, false),
+				("forge", Some("[47.1.3,)"), true)
+			]
+		);
+	}
+
+	#[test]
+	fn test_multi_loader_metadata() {
+		let jar = create_jar(&[
+			(
+				FABRIC_METADATA_PATH,
+				br#"{ "id": "example", "version": "1.0.0" }"#,
+			),
+			(
+				NEOFORGE_METADATA_PATH,
+				br#"
+[[mods]]
+modId = "example"
+version = "1.0.0"
+
+[[dependencies.example]]
+modId = "neoforge"
+type = "required"
+
+[[dependencies.example]]
+modId = "optifine"
+type = "incompatible"
+"#,
+			),
+		]);
+
+		let metadata = read_fixture("multi_loader", jar);
+		let loaders: Vec<_> = metadata.iter().map(|x| x.loader.clone()).collect();
+		assert_eq!(loaders, vec![Modloader::Fabric, Modloader::NeoForged]);
+		assert_eq!(metadata[1].dependencies.len(), 1);
+		assert_eq!(metadata[1].dependencies[0].id, "neoforge");
+
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("ids.jar");
+		let nested = create_jar(&[(FABRIC_METADATA_PATH, br#"{ "id": "b" }"#)]);
+		std::fs::write(
+			&path,
+			create_jar(&[
+				(
+					FABRIC_METADATA_PATH,
+					br#"{ "id": "a", "jars": [{ "file": "b.jar" }] }"#,
+				),
+				("b.jar", &nested),
+			]),
+		)
+		.unwrap();
+		assert_eq!(read_mod_ids(&path).unwrap(), vec!["a".to_string()]);
+	}
+}
@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context};
+use mcvm_core::io::files::create_leading_dirs;
+
+/// The extension added to files while they are staged
+const STAGING_EXTENSION: &str = "mcvm-staged";
+/// The extension added to replaced files while a transaction is being committed
+const BACKUP_EXTENSION: &str = "mcvm-backup";
+
+/// A set of file changes that are staged next to their destinations and then moved into place
+/// all at once. If the transaction is dropped without being committed, all of the staged files
+/// are removed and none of the destinations are changed.
+#[derive(Debug, Default)]
+pub struct FileTransaction {
+	/// Pairs of staged files and their destinations
+	staged: Vec<(PathBuf, PathBuf)>,
+}
+
+impl FileTransaction {
+	/// Create a new empty transaction
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Stage a file for a destination, returning the path that the file should be written to.
+	/// Any existing file at the staging path is removed.
+	pub fn stage(&mut self, dest: &Path) -> anyhow::Result<PathBuf> {
+		let staged = get_staging_path(dest);
+		create_leading_dirs(&staged)?;
+		if staged.exists() {
+			std::fs::remove_file(&staged).context("Failed to remove existing staged file")?;
+		}
+		self.staged.push((staged.clone(), dest.to_owned()));
+
+		Ok(staged)
+	}
+
+	/// Stage a hardlink to an existing file for a destination
+	pub fn stage_hardlink(&mut self, src: &Path, dest: &Path) -> anyhow::Result<()> {
+		ensure!(src.exists(), "File {} does not exist", src.display());
+		let staged = self.stage(dest)?;
+		std::fs::hard_link(src, staged).context("Failed to create hard link")?;
+
+		Ok(())
+	}
+
+	/// Move all of the staged files to their destinations, replacing any existing files.
+	/// If one of them fails to move, the files that were already moved are reverted to
+	/// what they were before and the remaining staged files are removed.
+	pub fn commit(mut self) -> anyhow::Result<()> {
+		// Destinations that have been changed, along with the backup of the file they replaced
+		let mut committed = Vec::new();
+		let mut files = std::mem::take(&mut self.staged).into_iter();
+		while let Some((staged, dest)) = files.next() {
+			match commit_file(&staged, &dest) {
+				Ok(backup) => committed.push((dest, backup)),
+				Err(e) => {
+					let _ = std::fs::remove_file(&staged);
+					revert(committed);
+					// The remaining staged files are removed when the transaction is dropped
+					self.staged.extend(files);
+					return Err(e);
+				}
+			}
+		}
+
+		for (_, backup) in committed {
+			if let Some(backup) = backup {
+				let _ = std::fs::remove_file(backup);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Remove all of the staged files without changing their destinations
+	pub fn rollback(&mut self) {
+		for (staged, _) in self.staged.drain(..) {
+			let _ = std::fs::remove_file(staged);
+		}
+	}
+}
+
+impl Drop for FileTransaction {
+	fn drop(&mut self) {
+		self.rollback();
+	}
+}
+
+/// Move a single staged file to its destination, returning the path of the backup
+/// of the file that it replaced, if there was one
+fn commit_file(staged: &Path, dest: &Path) -> anyhow::Result<Option<PathBuf>> {
+	let backup = if dest.exists() {
+		let backup = get_path_with_extension(dest, BACKUP_EXTENSION);
+		std::fs::rename(dest, &backup)
+			.with_context(|| format!("Failed to back up existing file {}", dest.display()))?;
+		Some(backup)
+	} else {
+		None
+	};
+
+	if let Err(e) = std::fs::rename(staged, dest) {
+		if let Some(backup) = &backup {
+			let _ = std::fs::rename(backup, dest);
+		}
+		return Err(e).with_context(|| format!("Failed to move staged file to {}", dest.display()));
+	}
+
+	Ok(backup)
+}
+
+/// Revert destinations that were changed by a failed commit, restoring their backups
+fn revert(committed: Vec<(PathBuf, Option<PathBuf>)>) {
+	for (dest, backup) in committed.into_iter().rev() {
+		let _ = std::fs::remove_file(&dest);
+		if let Some(backup) = backup {
+			let _ = std::fs::rename(backup, dest);
+		}
+	}
+}
+
+/// Get the path where a file is staged before it is moved to its destination
+pub fn get_staging_path(dest: &Path) -> PathBuf {
+	get_path_with_extension(dest, STAGING_EXTENSION)
+}
+
+/// Add an extra extension to the file name of a path
+fn get_path_with_extension(path: &Path, extension: &str) -> PathBuf {
+	let mut file_name = path.file_name().unwrap_or_default().to_owned();
+	file_name.push(".");
+	file_name.push(extension);
+	path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_failed_transaction() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let stored = dir.join("stored");
+		let mods = dir.join("mods");
+		std::fs::create_dir_all(&stored).unwrap();
+		std::fs::write(stored.join("first.jar"), "first").unwrap();
+		std::fs::write(stored.join("second.jar"), "second").unwrap();
+
+		// The third addon failed to download, so the whole package should not be installed
+		let result = (|| {
+			let mut transaction = FileTransaction::new();
+			for addon in ["first.jar", "second.jar", "third.jar"] {
+				transaction.stage_hardlink(&stored.join(addon), &mods.join(addon))?;
+			}
+			transaction.commit()
+		})();
+		assert!(result.is_err());
+		assert_eq!(mods.read_dir().unwrap().count(), 0);
+
+		let mut transaction = FileTransaction::new();
+		for addon in ["first.jar", "second.jar"] {
+			transaction
+				.stage_hardlink(&stored.join(addon), &mods.join(addon))
+				.unwrap();
+		}
+		assert!(!mods.join("first.jar").exists());
+		transaction.commit().unwrap();
+		assert_eq!(
+			std::fs::read_to_string(mods.join("first.jar")).unwrap(),
+			"first"
+		);
+		assert_eq!(mods.read_dir().unwrap().count(), 2);
+	}
+
+	#[test]
+	fn test_failed_commit() {
+		let dir = tempfile::tempdir().unwrap();
+		let mods = dir.path();
+		std::fs::write(mods.join("first.jar"), "old first").unwrap();
+		std::fs::write(mods.join("second.jar"), "old second").unwrap();
+
+		let mut transaction = FileTransaction::new();
+		for addon in ["first.jar", "second.jar", "third.jar"] {
+			let staged = transaction.stage(&mods.join(addon)).unwrap();
+			std::fs::write(staged, "new").unwrap();
+		}
+		// Make the move of the second file fail after the first one has already been replaced
+		std::fs::remove_file(get_staging_path(&mods.join("second.jar"))).unwrap();
+
+		assert!(transaction.commit().is_err());
+		assert_eq!(
+			std::fs::read_to_string(mods.join("first.jar")).unwrap(),
+			"old first"
+		);
+		assert_eq!(
+			std::fs::read_to_string(mods.join("second.jar")).unwrap(),
+			"old second"
+		);
+		assert!(!mods.join("third.jar").exists());
+		assert_eq!(mods.read_dir().unwrap().count(), 2);
+	}
+}
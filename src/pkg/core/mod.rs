@@ -1,6 +1,11 @@
 use std::collections::HashSet;
 
+use anyhow::{bail, Context};
 use mcvm_pkg::{repo::RepoPkgEntry, PackageContentType};
+use sha2::{Digest, Sha256};
+
+// Generated by the build script
+include!(concat!(env!("OUT_DIR"), "/core_package_hashes.rs"));
 
 macro_rules! define_core_packages {
 	($($pkg:literal,$ext:literal,$id:ident,$content:ident);*$(;)?) => {
@@ -77,6 +82,7 @@ pub fn get_all_core_packages() -> Vec<(String, RepoPkgEntry)> {
 				path: None,
 				content_type: Some(content_type),
 				flags: HashSet::new(),
+				hash: None,
 			},
 		));
 	}
@@ -88,6 +94,33 @@ pub fn get_core_package_count() -> usize {
 	ALL_CORE_PACKAGE_IDS.len()
 }
 
+/// Checks that the contents of every core package embedded in the binary match the
+/// hashes of the package files that were generated at compile time
+pub fn verify_core_packages() -> anyhow::Result<()> {
+	let mut mismatched = Vec::new();
+	for pkg in ALL_CORE_PACKAGE_IDS {
+		let expected = CORE_PACKAGE_HASHES
+			.iter()
+			.find(|(id, _)| id == pkg)
+			.map(|(_, hash)| *hash)
+			.with_context(|| format!("Core package '{pkg}' has no hash"))?;
+		let contents = get_core_package(pkg).expect("Core package should exist");
+		let actual = hex::encode(Sha256::digest(contents));
+		if actual != expected {
+			mismatched.push(*pkg);
+		}
+	}
+
+	if !mismatched.is_empty() {
+		bail!(
+			"Core packages do not match their expected hashes: {}",
+			mismatched.join(", ")
+		);
+	}
+
+	Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -100,7 +133,7 @@ mod tests {
 			println!("Package: {package}");
 			let contents = get_core_package(package).unwrap();
 			let content_type = get_core_package_content_type(package).unwrap();
-			parse_and_validate(contents, content_type).unwrap();
+			parse_and_validate(contents, content_type.clone()).unwrap();
 
 			if let PackageContentType::Script = content_type {
 				let parsed = lex_and_parse(contents).unwrap();
@@ -110,4 +143,10 @@ mod tests {
 			}
 		}
 	}
+
+	#[test]
+	fn test_verify_core_packages() {
+		verify_core_packages().unwrap();
+		assert_eq!(CORE_PACKAGE_HASHES.len(), ALL_CORE_PACKAGE_IDS.len());
+	}
 }
@@ -1,3 +1,5 @@
+use anyhow::bail;
+use mcvm_shared::util::{get_system_memory, ARCH_STRING, OS_STRING};
 use mcvm_shared::versions::VersionPattern;
 use mcvm_shared::Side;
 
@@ -15,6 +17,7 @@ pub fn eval_condition(condition: &ConditionKind, eval: &EvalData) -> anyhow::Res
 		ConditionKind::Or(left, right) => {
 			Ok(eval_condition(left, eval)? || eval_condition(right.get(), eval)?)
 		}
+		ConditionKind::Group { condition, .. } => eval_condition(condition.get(), eval),
 		ConditionKind::Version(version) => {
 			let version = version.get(&eval.vars)?;
 			let version = VersionPattern::from(&version);
@@ -31,6 +34,13 @@ pub fn eval_condition(condition: &ConditionKind, eval: &EvalData) -> anyhow::Res
 				.modifications
 				.get_modloader(eval.input.params.side),
 		)),
+		ConditionKind::ModloaderVersion(version) => {
+			let version = VersionPattern::from(&version.get(&eval.vars)?);
+			let loader_version = &eval.input.constants.modloader_version;
+			Ok(loader_version
+				.as_ref()
+				.is_some_and(|x| version.matches_by_components(x)))
+		}
 		ConditionKind::PluginLoader(loader) => Ok(loader
 			.get()
 			.matches(&eval.input.constants.modifications.server_type)
@@ -62,6 +72,23 @@ pub fn eval_condition(condition: &ConditionKind, eval: &EvalData) -> anyhow::Res
 			Ok(matches)
 		}
 		ConditionKind::Value(left, right) => Ok(left.get(&eval.vars)? == right.get(&eval.vars)?),
+		ConditionKind::ValueNe(left, right) => Ok(left.get(&eval.vars)? != right.get(&eval.vars)?),
+		ConditionKind::Contains(left, right) => {
+			Ok(left.get(&eval.vars)?.contains(&right.get(&eval.vars)?))
+		}
+		ConditionKind::StartsWith(left, right) => {
+			Ok(left.get(&eval.vars)?.starts_with(&right.get(&eval.vars)?))
+		}
+		ConditionKind::EndsWith(left, right) => {
+			Ok(left.get(&eval.vars)?.ends_with(&right.get(&eval.vars)?))
+		}
+		ConditionKind::Memory(threshold) => {
+			let threshold = threshold.get(&eval.vars)?;
+			let Ok(threshold) = threshold.parse() else {
+				bail!("Memory condition threshold '{threshold}' is not a number of megabytes");
+			};
+			Ok(check_memory_condition(threshold, get_system_memory()))
+		}
 		ConditionKind::Defined(var) => Ok(eval.vars.var_exists(var.get())),
 		ConditionKind::Const(val) => Ok(val.get_clone()),
 	}
@@ -69,27 +96,196 @@ pub fn eval_condition(condition: &ConditionKind, eval: &EvalData) -> anyhow::Res
 
 /// Checks an OS condition to see if it matches the current operating system
 pub fn check_os_condition(condition: &OSCondition) -> bool {
+	os_condition_matches(condition, OS_STRING)
+}
+
+/// Checks a memory condition threshold in megabytes against the system memory in bytes.
+/// If the system memory could not be detected, the condition is not met
+fn check_memory_condition(threshold: u64, system_memory: Option<u64>) -> bool {
+	system_memory.is_some_and(|x| x / 1024 / 1024 >= threshold)
+}
+
+/// Values of `OS_STRING` for Unix-like operating systems
+const UNIX_OS_STRINGS: [&str; 9] = [
+	"linux",
+	"macos",
+	"ios",
+	"android",
+	"freebsd",
+	"dragonfly",
+	"bitrig",
+	"netbsd",
+	"openbsd",
+];
+
+/// Checks an OS condition against an operating system, given as one of the possible values of `OS_STRING`
+fn os_condition_matches(condition: &OSCondition, os: &str) -> bool {
 	match condition {
-		OSCondition::Windows => cfg!(target_os = "windows"),
-		OSCondition::Linux => cfg!(target_os = "linux"),
-		OSCondition::MacOS => cfg!(target_os = "macos"),
-		OSCondition::Unix => cfg!(target_family = "unix"),
-		OSCondition::Other => {
-			!(cfg!(target_os = "windows") || cfg!(target_os = "linux") || cfg!(target_os = "macos"))
-		}
+		OSCondition::Windows => os == "windows",
+		OSCondition::Linux => os == "linux",
+		OSCondition::MacOS => os == "macos",
+		OSCondition::Unix => UNIX_OS_STRINGS.contains(&os),
+		OSCondition::Other => !matches!(os, "windows" | "linux" | "macos"),
 	}
 }
 
 /// Checks an arch condition to see if it matches the current system architecture
 pub fn check_arch_condition(condition: &ArchCondition) -> bool {
-	if cfg!(target_arch = "x86") {
-		return condition == &ArchCondition::X86;
+	condition == &get_arch_condition(ARCH_STRING)
+}
+
+/// Gets the arch condition that an architecture satisfies, given as one of the possible values of `ARCH_STRING`
+fn get_arch_condition(arch: &str) -> ArchCondition {
+	match arch {
+		"x86" => ArchCondition::X86,
+		"x86_64" => ArchCondition::X86_64,
+		"arm" | "armv7" | "aarch64" => ArchCondition::Arm,
+		_ => ArchCondition::Other,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use mcvm_parse::vars::Value;
+	use mcvm_pkg::properties::PackageProperties;
+	use mcvm_shared::lang::Language;
+	use mcvm_shared::modifications::{ClientType, Modloader, ServerType};
+	use mcvm_shared::pkg::PackageStability;
+
+	use crate::config::profile::GameModifications;
+	use crate::pkg::eval::{EvalConstants, EvalInput, EvalParameters, Routine};
+	use crate::plugin::PluginManager;
+
+	use super::*;
+
+	fn get_eval_constants() -> EvalConstants {
+		EvalConstants {
+			version: "1.19.2".into(),
+			version_list: vec!["1.19.2".to_string(), "1.19.3".to_string()],
+			modifications: GameModifications::new(
+				Modloader::Fabric,
+				ClientType::Fabric,
+				ServerType::Fabric,
+			),
+			modloader_version: Some("0.15.7".into()),
+			language: Language::AmericanEnglish,
+			profile_stability: PackageStability::Latest,
+		}
+	}
+
+	fn create_eval_data<'a>(constants: &'a EvalConstants, plugins: &PluginManager) -> EvalData<'a> {
+		let input = EvalInput {
+			constants,
+			params: EvalParameters::new(Side::Client),
+		};
+		let mut eval = EvalData::new(
+			input,
+			"foo".into(),
+			PackageProperties::default(),
+			&Routine::Install,
+			plugins,
+		);
+		eval.vars.set_var("x".into(), "foo".into());
+		eval
+	}
+
+	#[test]
+	fn test_value_ne_condition() {
+		let constants = get_eval_constants();
+		let plugins = PluginManager::new();
+		let eval = create_eval_data(&constants, &plugins);
+
+		let condition =
+			ConditionKind::ValueNe(Value::Var("x".into()), Value::Literal("foo".into()));
+		assert!(!eval_condition(&condition, &eval).unwrap());
+		let condition =
+			ConditionKind::ValueNe(Value::Var("x".into()), Value::Literal("bar".into()));
+		assert!(eval_condition(&condition, &eval).unwrap());
 	}
-	if cfg!(target_arch = "x86_64") {
-		return condition == &ArchCondition::X86_64;
+
+	#[test]
+	fn test_modloader_version_condition() {
+		let constants = get_eval_constants();
+		let plugins = PluginManager::new();
+		let eval = create_eval_data(&constants, &plugins);
+
+		let condition = ConditionKind::ModloaderVersion(Value::Literal("0.15+".into()));
+		assert!(eval_condition(&condition, &eval).unwrap());
+		let condition = ConditionKind::ModloaderVersion(Value::Literal("0.14.0..0.14.25".into()));
+		assert!(!eval_condition(&condition, &eval).unwrap());
+
+		let constants = EvalConstants {
+			modloader_version: None,
+			..get_eval_constants()
+		};
+		let eval = create_eval_data(&constants, &plugins);
+		let condition = ConditionKind::ModloaderVersion(Value::Literal("*".into()));
+		assert!(!eval_condition(&condition, &eval).unwrap());
 	}
-	if cfg!(target_arch = "arm") {
-		return condition == &ArchCondition::Arm;
+
+	#[test]
+	fn test_string_conditions() {
+		let constants = get_eval_constants();
+		let plugins = PluginManager::new();
+		let mut eval = create_eval_data(&constants, &plugins);
+		eval.vars.set_var("version".into(), "1.20-pre1".into());
+
+		let check = |condition: fn(Value, Value) -> ConditionKind, right: &str| {
+			let condition = condition(Value::Var("version".into()), Value::Literal(right.into()));
+			eval_condition(&condition, &eval).unwrap()
+		};
+
+		assert!(check(ConditionKind::Contains, "pre"));
+		assert!(!check(ConditionKind::Contains, "rc"));
+		assert!(check(ConditionKind::StartsWith, "1.20"));
+		assert!(!check(ConditionKind::StartsWith, "pre"));
+		assert!(check(ConditionKind::EndsWith, "pre1"));
+		assert!(!check(ConditionKind::EndsWith, "1.20"));
+	}
+
+	#[test]
+	fn test_os_condition() {
+		let conditions = [
+			OSCondition::Windows,
+			OSCondition::Linux,
+			OSCondition::MacOS,
+			OSCondition::Unix,
+			OSCondition::Other,
+		];
+		let expected = [
+			("linux", [false, true, false, true, false]),
+			("macos", [false, false, true, true, false]),
+			("windows", [true, false, false, false, false]),
+			("freebsd", [false, false, false, true, true]),
+		];
+		for (os, expected) in expected {
+			for (condition, expected) in conditions.iter().zip(expected) {
+				assert_eq!(
+					os_condition_matches(condition, os),
+					expected,
+					"{condition:?} on {os}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_arch_condition() {
+		assert_eq!(get_arch_condition("x86"), ArchCondition::X86);
+		assert_eq!(get_arch_condition("x86_64"), ArchCondition::X86_64);
+		assert_eq!(get_arch_condition("arm"), ArchCondition::Arm);
+		assert_eq!(get_arch_condition("armv7"), ArchCondition::Arm);
+		assert_eq!(get_arch_condition("aarch64"), ArchCondition::Arm);
+		assert_eq!(get_arch_condition("riscv64"), ArchCondition::Other);
+		assert_eq!(get_arch_condition("powerpc64"), ArchCondition::Other);
+	}
+
+	#[test]
+	fn test_memory_condition() {
+		let gigabyte = 1024 * 1024 * 1024;
+		assert!(check_memory_condition(8192, Some(16 * gigabyte)));
+		assert!(check_memory_condition(8192, Some(8 * gigabyte)));
+		assert!(!check_memory_condition(8192, Some(4 * gigabyte)));
+		assert!(!check_memory_condition(0, None));
 	}
-	condition == &ArchCondition::Other
 }
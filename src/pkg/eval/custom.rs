@@ -0,0 +1,311 @@
+use anyhow::{bail, Context};
+use mcvm_pkg::properties::PackageProperties;
+use mcvm_pkg::update::get_installed_content_version;
+use mcvm_pkg::{CustomContentTypeHandler, CustomPackageContents};
+use mcvm_plugin::hooks::{
+	CustomPackageArg, EvalCustomPackage, EvalCustomPackageArg, ParseCustomPackage,
+	ValidateCustomPackage,
+};
+use mcvm_shared::output::NoOp;
+use mcvm_shared::pkg::PackageID;
+
+use crate::io::paths::Paths;
+use crate::plugin::PluginManager;
+
+use super::{
+	create_valid_addon_request, EvalData, EvalInput, Routine, MAX_NOTICE_CHARACTERS,
+	MAX_NOTICE_INSTRUCTIONS,
+};
+
+/// Handler for custom package content types that asks plugins to handle them
+pub struct PluginContentTypes<'a> {
+	/// The plugins to call
+	pub plugins: &'a PluginManager,
+	/// The paths to call the plugins with
+	pub paths: &'a Paths,
+}
+
+impl<'a> CustomContentTypeHandler for PluginContentTypes<'a> {
+	fn parse_custom_package(
+		&self,
+		content_type: &str,
+		contents: &str,
+	) -> anyhow::Result<Option<CustomPackageContents>> {
+		check_content_type_registered(content_type, self.plugins)?;
+		let arg = CustomPackageArg {
+			content_type: content_type.into(),
+			contents: contents.into(),
+		};
+		let results = self
+			.plugins
+			.call_hook(ParseCustomPackage, &arg, self.paths, &mut NoOp)?;
+		for result in results {
+			let result = result.result(&mut NoOp)?;
+			if result.handled {
+				return Ok(Some(result.contents));
+			}
+		}
+
+		Ok(None)
+	}
+
+	fn validate_custom_package(&self, content_type: &str, contents: &str) -> anyhow::Result<()> {
+		check_content_type_registered(content_type, self.plugins)?;
+		let arg = CustomPackageArg {
+			content_type: content_type.into(),
+			contents: contents.into(),
+		};
+		let results = self
+			.plugins
+			.call_hook(ValidateCustomPackage, &arg, self.paths, &mut NoOp)?;
+		for result in results {
+			let result = result.result(&mut NoOp)?;
+			if !result.handled {
+				continue;
+			}
+			if !result.errors.is_empty() {
+				bail!("{}", result.errors.join("\n"));
+			}
+			return Ok(());
+		}
+
+		bail!("No plugin supports the package content type '{content_type}'")
+	}
+}
+
+/// Checks that a custom content type is registered by a plugin, so that typos in
+/// content types aren't passed to plugins as custom content types
+fn check_content_type_registered(
+	content_type: &str,
+	plugins: &PluginManager,
+) -> anyhow::Result<()> {
+	if !plugins.has_content_type(content_type)? {
+		bail!(
+			"Unknown package content type '{content_type}', which is not registered by any plugin"
+		);
+	}
+
+	Ok(())
+}
+
+/// Evaluate a package with a custom content type using the plugin that supports it
+#[allow(clippy::too_many_arguments)]
+pub fn eval_custom_package<'a>(
+	id: PackageID,
+	content_type: &str,
+	contents: &str,
+	input: EvalInput<'a>,
+	properties: PackageProperties,
+	routine: Routine,
+	plugins: &'a PluginManager,
+	paths: &Paths,
+) -> anyhow::Result<EvalData<'a>> {
+	check_content_type_registered(content_type, plugins)?;
+	let content_version =
+		get_installed_content_version(&properties, input.params.content_version.as_ref())
+			.map(String::from);
+	let arg = EvalCustomPackageArg {
+		pkg_id: id.to_string(),
+		content_type: content_type.into(),
+		contents: contents.into(),
+		reason: routine.get_reason(),
+		minecraft_version: input.constants.version.clone(),
+		side: Some(input.params.side),
+		features: input.params.features.clone(),
+		content_version,
+	};
+	let mut eval_data = EvalData::new(input, id, properties, &routine, plugins);
+
+	let results = plugins.call_hook(EvalCustomPackage, &arg, paths, &mut NoOp)?;
+	let mut handled = None;
+	for result in results {
+		let result = result.result(&mut NoOp)?;
+		if result.handled {
+			handled = Some(result);
+			break;
+		}
+	}
+	let Some(result) = handled else {
+		bail!("No plugin supports the package content type '{content_type}'");
+	};
+
+	for addon in result.addon_reqs {
+		if eval_data.addon_reqs.iter().any(|x| x.addon.id == addon.id) {
+			bail!("Duplicate addon id '{}'", addon.id);
+		}
+		let addon_req = create_valid_addon_request(addon, eval_data.id.clone(), &eval_data.input)
+			.context("Plugin created an invalid addon")?;
+		eval_data.addon_reqs.push(addon_req);
+	}
+	eval_data.deps.extend(result.deps);
+	eval_data.conflicts.extend(result.conflicts);
+	eval_data.recommendations.extend(result.recommendations);
+	eval_data.bundled.extend(result.bundled);
+	eval_data.compats.extend(result.compats);
+	eval_data.extensions.extend(result.extensions);
+	eval_data.notices.extend(result.notices);
+
+	// Check notices
+	if eval_data.notices.len() > MAX_NOTICE_INSTRUCTIONS {
+		bail!("Max number of notices was exceded (>{MAX_NOTICE_INSTRUCTIONS})");
+	}
+	for notice in &eval_data.notices {
+		if notice.len() > MAX_NOTICE_CHARACTERS {
+			bail!("Notice message is too long (>{MAX_NOTICE_CHARACTERS})");
+		}
+	}
+
+	Ok(eval_data)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::{HashMap, HashSet};
+	use std::sync::Arc;
+
+	use mcvm_pkg::RequiredPackage;
+	use mcvm_plugin::hooks::{
+		CustomPackageInstructionResult, ParseCustomPackageResult, ValidateCustomPackageResult,
+	};
+	use mcvm_plugin::plugin::{HookHandler, PluginManifest};
+	use mcvm_shared::lang::Language;
+	use mcvm_shared::modifications::{ClientType, Modloader, ServerType};
+	use mcvm_shared::pkg::PackageStability;
+	use mcvm_shared::Side;
+
+	use crate::config::plugin::PluginConfig;
+	use crate::config::profile::GameModifications;
+	use crate::pkg::eval::{EvalConstants, EvalParameters};
+
+	use super::*;
+
+	/// Creates a plugin that supports a content type where each line of the package is a dependency
+	fn create_plugin(paths: &Paths) -> PluginManager {
+		let parse = |arg: String| -> anyhow::Result<String> {
+			let arg: CustomPackageArg = serde_json::from_str(&arg)?;
+			let mut result = ParseCustomPackageResult::default();
+			if arg.content_type == "deps" {
+				result.handled = true;
+				result.contents.meta.name = Some("Dependencies".into());
+			}
+			Ok(serde_json::to_string(&result)?)
+		};
+		let validate = |arg: String| -> anyhow::Result<String> {
+			let arg: CustomPackageArg = serde_json::from_str(&arg)?;
+			let errors = if arg.contents.is_empty() {
+				vec!["Package has no dependencies".into()]
+			} else {
+				Vec::new()
+			};
+			let result = ValidateCustomPackageResult {
+				handled: arg.content_type == "deps",
+				errors,
+			};
+			Ok(serde_json::to_string(&result)?)
+		};
+		let eval = |arg: String| -> anyhow::Result<String> {
+			let arg: EvalCustomPackageArg = serde_json::from_str(&arg)?;
+			let result = CustomPackageInstructionResult {
+				handled: arg.content_type == "deps",
+				deps: arg
+					.contents
+					.lines()
+					.map(|x| {
+						vec![RequiredPackage {
+							value: x.into(),
+							explicit: false,
+						}]
+					})
+					.collect(),
+				..Default::default()
+			};
+			Ok(serde_json::to_string(&result)?)
+		};
+
+		let mut manifest = PluginManifest::new();
+		manifest.content_types = HashSet::from(["deps".to_string()]);
+		manifest.hooks = HashMap::from([
+			(
+				"parse_custom_package".to_string(),
+				HookHandler::Native {
+					function: Arc::new(parse),
+				},
+			),
+			(
+				"validate_custom_package".to_string(),
+				HookHandler::Native {
+					function: Arc::new(validate),
+				},
+			),
+			(
+				"eval_custom_package".to_string(),
+				HookHandler::Native {
+					function: Arc::new(eval),
+				},
+			),
+		]);
+		let config = PluginConfig {
+			id: "deps".into(),
+			custom_config: None,
+		};
+		let mut plugins = PluginManager::new();
+		plugins
+			.add_plugin(config, manifest, paths, None, &mut NoOp)
+			.unwrap();
+
+		plugins
+	}
+
+	#[test]
+	fn test_custom_content_type() {
+		let paths = Paths::new_no_create().unwrap();
+		let plugins = create_plugin(&paths);
+		let handler = PluginContentTypes {
+			plugins: &plugins,
+			paths: &paths,
+		};
+
+		let contents = handler
+			.parse_custom_package("deps", "foo\nbar")
+			.unwrap()
+			.unwrap();
+		assert_eq!(contents.meta.name.as_deref(), Some("Dependencies"));
+		let err = handler.parse_custom_package("other", "foo").unwrap_err();
+		assert!(err
+			.to_string()
+			.contains("Unknown package content type 'other'"));
+		handler.validate_custom_package("deps", "foo").unwrap();
+		assert!(handler.validate_custom_package("deps", "").is_err());
+		assert!(handler.validate_custom_package("other", "foo").is_err());
+
+		let constants = EvalConstants {
+			version: "1.19.2".into(),
+			version_list: vec!["1.19.2".to_string()],
+			modifications: GameModifications::new(
+				Modloader::Vanilla,
+				ClientType::Vanilla,
+				ServerType::Vanilla,
+			),
+			modloader_version: None,
+			language: Language::AmericanEnglish,
+			profile_stability: PackageStability::Latest,
+		};
+		let input = EvalInput {
+			constants: &constants,
+			params: EvalParameters::new(Side::Client),
+		};
+		let eval = eval_custom_package(
+			PackageID::from("test"),
+			"deps",
+			"foo\nbar",
+			input,
+			PackageProperties::default(),
+			Routine::Install,
+			&plugins,
+			&paths,
+		)
+		.unwrap();
+		assert_eq!(eval.deps.len(), 2);
+		assert_eq!(eval.deps[0][0].value, PackageID::from("foo"));
+	}
+}
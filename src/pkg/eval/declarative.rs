@@ -1,12 +1,13 @@
+use std::cmp::Ordering;
+
 use anyhow::bail;
-use itertools::Itertools;
 use mcvm_pkg::declarative::{
 	DeclarativeAddon, DeclarativeAddonVersion, DeclarativeConditionSet, DeclarativePackage,
 };
 use mcvm_pkg::properties::PackageProperties;
 use mcvm_pkg::script_eval::AddonInstructionData;
 use mcvm_pkg::RequiredPackage;
-use mcvm_shared::modifications::{ModloaderMatch, PluginLoaderMatch};
+use mcvm_shared::modifications::{ModloaderMatch, PluginLoaderMatch, ProxyMatch};
 use mcvm_shared::pkg::PackageID;
 
 use crate::plugin::PluginManager;
@@ -45,6 +46,11 @@ fn eval_declarative_package_impl<'a>(
 
 	let mut eval_data = EvalData::new(input, pkg_id.clone(), properties, &routine, plugins);
 
+	// Declarative packages have nothing to run when uninstalling, since their addons are tracked in the lockfile
+	if let Routine::Uninstall = routine {
+		return Ok(eval_data);
+	}
+
 	// Vars for the EvalData that are modified by conditions / versions
 	let mut relations = contents.relations.clone();
 	let mut notices = Vec::new();
@@ -62,6 +68,7 @@ fn eval_declarative_package_impl<'a>(
 	}
 
 	// Select addon versions
+	let mut picked_versions = Vec::new();
 	for (addon_id, addon) in &contents.addons {
 		// Check conditions
 		if !check_multiple_condition_sets(
@@ -92,11 +99,18 @@ fn eval_declarative_package_impl<'a>(
 
 			relations.merge(version.relations.clone());
 			notices.extend(version.notices.iter().cloned());
+			picked_versions.push(version);
 		} else {
 			handle_no_matched_versions(addon)?;
 		}
 	}
 
+	if let Some(content_version) =
+		get_picked_content_version(&picked_versions, &eval_data.input, &eval_data.properties)
+	{
+		eval_data.content_version = Some(content_version);
+	}
+
 	eval_data
 		.deps
 		.extend(relations.dependencies.iter().map(|x| {
@@ -148,53 +162,126 @@ fn eval_declarative_package_impl<'a>(
 	Ok(eval_data)
 }
 
-/// Pick the best addon version from a list of declarative addon versions
+/// Pick the best addon version from a list of declarative addon versions.
+/// The versions are ordered so that the same one is always picked: versions with newer content versions
+/// come first, then versions that are more specific to the loader, and then the ones that were listed first.
 pub fn pick_best_addon_version<'a>(
 	versions: &'a [DeclarativeAddonVersion],
 	input: &'a EvalInput<'a>,
 	properties: &PackageProperties,
 ) -> Option<&'a DeclarativeAddonVersion> {
 	// Filter versions that are not allowed
-	let versions = versions
+	let mut versions: Vec<_> = versions
 		.iter()
-		.filter(|x| check_condition_set(&x.conditional_properties, input, properties));
-
-	// Sort so that versions with less loader matches come first
-	fn get_matches(version: &DeclarativeAddonVersion) -> u16 {
-		let mut out = 0;
-		if let Some(modloaders) = &version.conditional_properties.modloaders {
-			out += modloaders
-				.iter()
-				.fold(0, |acc, x| acc + get_modloader_matches(x));
-		}
+		.filter(|x| check_condition_set(&x.conditional_properties, input, properties))
+		.collect();
+
+	// This sort is stable, so the order that the versions were listed in is kept for ties
+	versions.sort_by(|a, b| {
+		get_content_version_position(a, properties)
+			.cmp(&get_content_version_position(b, properties))
+			.then_with(|| compare_content_versions(b, a))
+			.then_with(|| get_loader_matches(a).cmp(&get_loader_matches(b)))
+	});
+
+	versions.into_iter().next()
+}
 
-		if let Some(plugin_loaders) = &version.conditional_properties.plugin_loaders {
-			out += plugin_loaders
-				.iter()
-				.fold(0, |acc, x| acc + get_plugin_loader_matches(x));
-		}
+/// Get the content version that was installed from the picked addon versions, which is the newest one
+/// that they have and that the requested content version allows. The addon versions were picked with the
+/// stability of the package, so unlike the package properties, this only includes versions that can be installed
+fn get_picked_content_version(
+	versions: &[&DeclarativeAddonVersion],
+	input: &EvalInput,
+	properties: &PackageProperties,
+) -> Option<String> {
+	let available = properties.content_versions.as_ref()?;
+	available
+		.iter()
+		.find(|candidate| {
+			let is_picked = versions.iter().any(|version| {
+				version
+					.conditional_properties
+					.content_versions
+					.as_ref()
+					.is_some_and(|x| x.iter().any(|x| x == *candidate))
+			});
+			let is_allowed = input
+				.params
+				.content_version
+				.as_ref()
+				.map_or(true, |pin| pin.matches_single(candidate, available));
+			is_picked && is_allowed
+		})
+		.cloned()
+}
+
+/// Get the position of the newest content version of an addon version in the
+/// package's list of content versions, which goes from newest to oldest
+fn get_content_version_position(
+	version: &DeclarativeAddonVersion,
+	properties: &PackageProperties,
+) -> Option<usize> {
+	let Some(content_versions) = &properties.content_versions else {
+		return Some(0);
+	};
 
-		out
+	if let Some(versions) = &version.conditional_properties.content_versions {
+		versions
+			.iter()
+			.map(|x| content_versions.iter().position(|candidate| candidate == x))
+			.min()
+			.unwrap_or(Some(content_versions.len()))
+	} else {
+		Some(content_versions.len())
 	}
+}
 
-	let mut versions = versions.sorted_by_cached_key(|x| get_matches(x));
-
-	// Sort so that versions with newer content versions come first
-	if let Some(content_versions) = &properties.content_versions {
-		versions = versions.sorted_by_cached_key(|x| {
-			if let Some(versions) = &x.conditional_properties.content_versions {
-				versions
-					.iter()
-					.map(|x| content_versions.iter().position(|candidate| candidate == x))
-					.min()
-					.unwrap_or(Some(content_versions.len()))
-			} else {
-				Some(content_versions.len())
-			}
-		});
+/// Compare the newest content versions of two addon versions. Versions without
+/// a content version are older than the ones with one.
+fn compare_content_versions(a: &DeclarativeAddonVersion, b: &DeclarativeAddonVersion) -> Ordering {
+	match (get_newest_content_version(a), get_newest_content_version(b)) {
+		(Some(a), Some(b)) => compare_version_strings(a, b),
+		(Some(..), None) => Ordering::Greater,
+		(None, Some(..)) => Ordering::Less,
+		(None, None) => Ordering::Equal,
 	}
+}
 
-	versions.next()
+/// Get the newest content version that an addon version has
+fn get_newest_content_version(version: &DeclarativeAddonVersion) -> Option<&str> {
+	let versions = version.conditional_properties.content_versions.as_ref()?;
+	versions
+		.iter()
+		.map(String::as_str)
+		.max_by(|a, b| compare_version_strings(a, b))
+}
+
+/// Compare two version strings by their semantic versions. Versions that can't be compared
+/// are compared as strings instead so that the result doesn't depend on their order
+fn compare_version_strings(a: &str, b: &str) -> Ordering {
+	version_compare::compare(a, b)
+		.ok()
+		.and_then(|x| x.ord())
+		.unwrap_or_else(|| a.cmp(b))
+}
+
+/// Get the number of loaders that an addon version can match. Versions with less matches are more specific.
+fn get_loader_matches(version: &DeclarativeAddonVersion) -> u16 {
+	let mut out = 0;
+	if let Some(modloaders) = &version.conditional_properties.modloaders {
+		out += modloaders
+			.iter()
+			.fold(0, |acc, x| acc + get_modloader_matches(x));
+	}
+
+	if let Some(plugin_loaders) = &version.conditional_properties.plugin_loaders {
+		out += plugin_loaders
+			.iter()
+			.fold(0, |acc, x| acc + get_plugin_loader_matches(x));
+	}
+
+	out
 }
 
 /// Check multiple sets of addon version conditions
@@ -265,6 +352,12 @@ fn check_condition_set<'a>(
 		}
 	}
 
+	if let Some(proxies) = &conditions.proxies {
+		if !proxies.iter().any(check_proxy_condition) {
+			return false;
+		}
+	}
+
 	if let Some(operating_systems) = &conditions.operating_systems {
 		if !operating_systems.iter().any(check_os_condition) {
 			return false;
@@ -303,6 +396,19 @@ fn check_condition_set<'a>(
 	true
 }
 
+/// Checks a proxy condition against the instance. Instances can't be proxies yet,
+/// so only the condition for not using a proxy matches
+fn check_proxy_condition(proxy: &ProxyMatch) -> bool {
+	match proxy {
+		ProxyMatch::None => true,
+		ProxyMatch::BungeeCord
+		| ProxyMatch::Waterfall
+		| ProxyMatch::Velocity
+		| ProxyMatch::BungeeCordLike
+		| ProxyMatch::Unknown(..) => false,
+	}
+}
+
 /// Handle the case where no versions were matched for an addon
 fn handle_no_matched_versions(addon: &DeclarativeAddon) -> anyhow::Result<()> {
 	// If the addon is optional then this is ok
@@ -336,6 +442,7 @@ mod tests {
 	use mcvm_shared::modifications::{ClientType, Modloader, ServerType};
 	use mcvm_shared::pkg::PackageStability;
 	use mcvm_shared::util::DeserListOrSingle;
+	use mcvm_shared::versions::VersionPattern;
 	use mcvm_shared::Side;
 
 	use crate::config::profile::GameModifications;
@@ -362,6 +469,7 @@ mod tests {
 								"minecraft_versions": [ "1.19.2" ],
 								"modloaders": [ "fabriclike" ],
 								"version": "2",
+								"filename": "test-2.jar",
 								"relations": {
 									"dependencies": [ "foo" ]
 								}
@@ -422,6 +530,7 @@ mod tests {
 
 		let addon = eval.addon_reqs.first().unwrap();
 		assert_eq!(addon.addon.version, Some("2".into()));
+		assert_eq!(addon.addon.file_name, "test-2.jar");
 
 		assert!(eval.deps.contains(&vec![RequiredPackage {
 			value: "foo".into(),
@@ -486,6 +595,99 @@ mod tests {
 			.expect("Version should have been found");
 
 		assert_eq!(version.version, Some("2".into()));
+
+		// Pinning the content version should pick the older version
+		let mut input = input;
+		input.params.content_version = Some(VersionPattern::Single("1".into()));
+		let version = pick_best_addon_version(&versions, &input, &properties)
+			.expect("Version should have been found");
+
+		assert_eq!(version.version, Some("1".into()));
+	}
+
+	#[test]
+	fn test_addon_version_tie_breaking() {
+		let create_version = |content_version: &str, version: &str| DeclarativeAddonVersion {
+			conditional_properties: DeclarativeConditionSet {
+				content_versions: Some(DeserListOrSingle::Single(content_version.into())),
+				..Default::default()
+			},
+			version: Some(version.into()),
+			..Default::default()
+		};
+
+		let constants = get_eval_constants();
+		let input = EvalInput {
+			constants: &constants,
+			params: EvalParameters::new(Side::Client),
+		};
+		let properties = PackageProperties::default();
+
+		// The newest content version should always be picked, no matter the order
+		let versions = [
+			create_version("1.9.0", "a"),
+			create_version("1.10.0", "b"),
+			create_version("1.2.0", "c"),
+		];
+		for order in [[0, 1, 2], [2, 1, 0], [1, 0, 2], [2, 0, 1]] {
+			let versions: Vec<_> = order.iter().map(|i| versions[*i].clone()).collect();
+			let version = pick_best_addon_version(&versions, &input, &properties)
+				.expect("Version should have been found");
+			assert_eq!(version.version.as_deref(), Some("b"));
+		}
+
+		// Versions that are otherwise the same are picked in the order they were listed
+		let versions = [
+			create_version("1.0.0", "first"),
+			create_version("1.0.0", "second"),
+			create_version("0.9.0", "third"),
+		];
+		let version = pick_best_addon_version(&versions, &input, &properties)
+			.expect("Version should have been found");
+		assert_eq!(version.version.as_deref(), Some("first"));
+
+		// Content versions that can't be compared as versions still give the same result in any order
+		let versions = [create_version("alpha", "a"), create_version("beta", "b")];
+		for order in [[0, 1], [1, 0]] {
+			let versions: Vec<_> = order.iter().map(|i| versions[*i].clone()).collect();
+			let version = pick_best_addon_version(&versions, &input, &properties)
+				.expect("Version should have been found");
+			assert_eq!(version.version.as_deref(), Some("b"));
+		}
+	}
+
+	#[test]
+	fn test_proxy_conditions() {
+		let create_version = |proxies: Vec<ProxyMatch>, version: &str| DeclarativeAddonVersion {
+			conditional_properties: DeclarativeConditionSet {
+				proxies: Some(DeserListOrSingle::List(proxies)),
+				..Default::default()
+			},
+			version: Some(version.into()),
+			..Default::default()
+		};
+
+		let constants = get_eval_constants();
+		let input = EvalInput {
+			constants: &constants,
+			params: EvalParameters::new(Side::Server),
+		};
+		let properties = PackageProperties::default();
+
+		let versions = [
+			create_version(vec![ProxyMatch::Velocity], "velocity"),
+			create_version(vec![ProxyMatch::BungeeCordLike], "bungeecord"),
+			create_version(vec![ProxyMatch::Unknown("other".into())], "other"),
+		];
+		assert!(pick_best_addon_version(&versions, &input, &properties).is_none());
+
+		let versions = [create_version(
+			vec![ProxyMatch::Velocity, ProxyMatch::None],
+			"any",
+		)];
+		let version = pick_best_addon_version(&versions, &input, &properties)
+			.expect("Version should have been found");
+		assert_eq!(version.version.as_deref(), Some("any"));
 	}
 
 	fn get_eval_constants() -> EvalConstants {
@@ -497,6 +699,7 @@ mod tests {
 				ClientType::Fabric,
 				ServerType::Fabric,
 			),
+			modloader_version: Some("0.15.7".into()),
 			language: Language::AmericanEnglish,
 			profile_stability: PackageStability::Latest,
 		}
@@ -1,5 +1,7 @@
 /// Evaluating script package conditions
 pub mod conditions;
+/// Handling packages with custom content types from plugins
+pub mod custom;
 /// Evaluating declarative packages
 pub mod declarative;
 /// Evaluating script packages
@@ -8,12 +10,14 @@ pub mod script;
 use anyhow::bail;
 use anyhow::Context;
 use async_trait::async_trait;
-use mcvm_parse::routine::INSTALL_ROUTINE;
+use mcvm_parse::routine::{INSTALL_ROUTINE, UNINSTALL_ROUTINE};
 use mcvm_parse::vars::HashMapVariableStore;
+use mcvm_pkg::metadata::PackageMetadata;
 use mcvm_pkg::properties::PackageProperties;
-use mcvm_pkg::resolve::ResolutionResult;
+use mcvm_pkg::resolve::{ResolutionLimits, ResolutionResult};
 use mcvm_pkg::script_eval::AddonInstructionData;
 use mcvm_pkg::script_eval::EvalReason;
+use mcvm_pkg::update::get_installed_content_version;
 use mcvm_pkg::ConfiguredPackage;
 use mcvm_pkg::PackageContentType;
 use mcvm_pkg::RecommendedPackage;
@@ -39,6 +43,7 @@ use serde::{Deserialize, Serialize};
 
 use self::conditions::check_arch_condition;
 use self::conditions::check_os_condition;
+use self::custom::eval_custom_package;
 use self::declarative::eval_declarative_package;
 use self::script::eval_script_package;
 
@@ -83,6 +88,8 @@ pub enum Routine {
 	Install,
 	/// Install routine, except for resolution
 	InstallResolve,
+	/// Uninstall the package
+	Uninstall,
 }
 
 impl Routine {
@@ -91,6 +98,7 @@ impl Routine {
 		match self {
 			Self::Install => INSTALL_ROUTINE,
 			Self::InstallResolve => INSTALL_ROUTINE,
+			Self::Uninstall => UNINSTALL_ROUTINE,
 		}
 		.into()
 	}
@@ -100,6 +108,7 @@ impl Routine {
 		match self {
 			Self::Install => EvalReason::Install,
 			Self::InstallResolve => EvalReason::Resolve,
+			Self::Uninstall => EvalReason::Uninstall,
 		}
 	}
 }
@@ -120,6 +129,8 @@ pub struct EvalConstants {
 	pub version: String,
 	/// The modifications to the game
 	pub modifications: GameModifications,
+	/// The version of the installed modloader, if it is known
+	pub modloader_version: Option<String>,
 	/// The list of available Minecraft versions
 	pub version_list: Vec<String>,
 	/// The user's configured language
@@ -176,7 +187,9 @@ impl EvalParameters {
 		self.features = features;
 		self.perms = config.permissions;
 		self.stability = config.stability;
-		self.content_version = config.content_version.as_deref().map(VersionPattern::from);
+		self.content_version = config
+			.calculate_content_version(properties)
+			.context("Failed to calculate content version")?;
 
 		Ok(())
 	}
@@ -217,6 +230,8 @@ pub struct EvalData<'a> {
 	pub commands: Vec<Vec<String>>,
 	/// Whether the package uses custom instructions
 	pub uses_custom_instructions: bool,
+	/// The content version of the package that is installed, if it has content versions
+	pub content_version: Option<String>,
 }
 
 impl<'a> EvalData<'a> {
@@ -228,6 +243,9 @@ impl<'a> EvalData<'a> {
 		routine: &Routine,
 		plugins: &PluginManager,
 	) -> Self {
+		let content_version =
+			get_installed_content_version(&properties, input.params.content_version.as_ref())
+				.map(String::from);
 		Self {
 			input,
 			id,
@@ -245,6 +263,7 @@ impl<'a> EvalData<'a> {
 			notices: Vec::new(),
 			commands: Vec::new(),
 			uses_custom_instructions: false,
+			content_version,
 		}
 	}
 }
@@ -259,10 +278,10 @@ impl Package {
 		client: &Client,
 		plugins: &'a PluginManager,
 	) -> anyhow::Result<EvalData<'a>> {
-		self.parse(paths, client).await?;
+		self.parse(paths, client, plugins).await?;
 
 		// Check properties
-		let properties = self.get_properties(paths, client).await?.clone();
+		let properties = self.get_properties(paths, client, plugins).await?.clone();
 		if eval_check_properties(&input, &properties)? {
 			return Ok(EvalData::new(
 				input,
@@ -273,7 +292,7 @@ impl Package {
 			));
 		}
 
-		match self.content_type {
+		match &self.content_type {
 			PackageContentType::Script => {
 				let parsed = self.data.get_mut().contents.get_mut().get_script_contents();
 				let eval = eval_script_package(
@@ -299,6 +318,19 @@ impl Package {
 				)?;
 				Ok(eval)
 			}
+			PackageContentType::Custom(content_type) => {
+				let eval = eval_custom_package(
+					self.id.clone(),
+					content_type,
+					&self.data.get().get_text(),
+					input,
+					properties,
+					routine,
+					plugins,
+					paths,
+				)?;
+				Ok(eval)
+			}
 		}
 	}
 }
@@ -580,6 +612,53 @@ impl<'a> PackageEvaluatorTrait<'a> for PackageEvaluator<'a> {
 			.await?;
 		Ok(properties)
 	}
+
+	async fn get_package_metadata<'b>(
+		&'b mut self,
+		pkg: &ArcPkgReq,
+		common_input: &Self::CommonInput,
+	) -> anyhow::Result<&'b PackageMetadata> {
+		let metadata = self
+			.reg
+			.get_metadata(
+				pkg,
+				common_input.paths,
+				common_input.client,
+				&mut output::NoOp,
+			)
+			.await?;
+		Ok(metadata)
+	}
+
+	async fn get_package_repository(
+		&mut self,
+		pkg: &ArcPkgReq,
+		common_input: &Self::CommonInput,
+	) -> anyhow::Result<Option<String>> {
+		self.reg
+			.get_repository(
+				pkg,
+				common_input.paths,
+				common_input.client,
+				&mut output::NoOp,
+			)
+			.await
+	}
+
+	async fn is_package_available(
+		&mut self,
+		pkg: &ArcPkgReq,
+		common_input: &Self::CommonInput,
+	) -> anyhow::Result<bool> {
+		self.reg
+			.exists(
+				pkg,
+				common_input.paths,
+				common_input.client,
+				&mut output::NoOp,
+			)
+			.await
+	}
 }
 
 /// Resolve package dependencies
@@ -612,7 +691,9 @@ pub async fn resolve(
 		.map(|x| EvalPackageConfig((*x).clone(), x.get_request()))
 		.collect::<Vec<_>>();
 
-	let result = mcvm_pkg::resolve::resolve(&packages, evaluator, input, &common_input).await?;
+	let limits = ResolutionLimits::default();
+	let result =
+		mcvm_pkg::resolve::resolve(&packages, evaluator, input, &common_input, &limits, o).await?;
 
 	for package in &result.unfulfilled_recommendations {
 		print_recommendation_warning(package, o);
@@ -651,3 +732,52 @@ fn print_recommendation_warning(
 
 	o.display(message, MessageLevel::Important);
 }
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use mcvm_shared::pkg::{PkgRequest, PkgRequestSource};
+
+	use super::*;
+	use crate::pkg::reg::CachingStrategy;
+	use crate::pkg::repo::{PkgRepo, PkgRepoLocation};
+
+	#[tokio::test]
+	async fn test_evaluator_metadata_caching() {
+		let dir = tempfile::tempdir().unwrap();
+		let paths = Paths::with_data_dir_no_create(dir.path().to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+
+		let pkg_path = dir.path().join("foo.txt");
+		std::fs::write(&pkg_path, r#"@meta { name "Foo"; }"#).unwrap();
+		let index = serde_json::json!({ "packages": { "foo": { "path": pkg_path } } });
+		let index_path = dir.path().join("index.json");
+		std::fs::write(&index_path, index.to_string()).unwrap();
+
+		let repos = vec![PkgRepo::new("local", PkgRepoLocation::Local(index_path))];
+		let mut reg = PkgRegistry::new(repos, CachingStrategy::default(), PluginManager::new());
+		let client = Client::new();
+		let common_input = EvaluatorCommonInput {
+			paths: &paths,
+			client: &client,
+			plugins: PluginManager::new(),
+		};
+		let mut evaluator = PackageEvaluator { reg: &mut reg };
+		let pkg = Arc::new(PkgRequest::parse("foo", PkgRequestSource::UserRequire));
+
+		let metadata = evaluator
+			.get_package_metadata(&pkg, &common_input)
+			.await
+			.unwrap();
+		assert_eq!(metadata.name.as_deref(), Some("Foo"));
+
+		// The package is not read or evaluated again once its metadata is cached
+		std::fs::write(&pkg_path, r#"@meta { name "Bar"; }"#).unwrap();
+		let metadata = evaluator
+			.get_package_metadata(&pkg, &common_input)
+			.await
+			.unwrap();
+		assert_eq!(metadata.name.as_deref(), Some("Foo"));
+	}
+}
@@ -8,24 +8,28 @@ pub mod reg;
 pub mod repo;
 
 use crate::io::paths::Paths;
+use mcvm_core::io::{json_from_file, json_to_file};
 use mcvm_core::net::download;
 use mcvm_pkg::declarative::{deserialize_declarative_package, DeclarativePackage};
 use mcvm_pkg::repo::PackageFlag;
-use mcvm_pkg::PackageContentType;
+use mcvm_pkg::{CustomContentTypeHandler, CustomPackageContents, PackageContentType};
 use mcvm_shared::later::Later;
 
 use std::collections::HashSet;
 use std::fs;
 use std::future::Future;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use self::core::get_core_package;
+use self::eval::custom::PluginContentTypes;
+use crate::plugin::PluginManager;
 use anyhow::{anyhow, bail, Context};
 use mcvm_parse::parse::{lex_and_parse, Parsed};
 use mcvm_pkg::metadata::{eval_metadata, PackageMetadata};
 use mcvm_pkg::properties::{eval_properties, PackageProperties};
 use mcvm_shared::pkg::PackageID;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 
 /// An installable package that loads content into your game
 #[derive(Debug)]
@@ -38,6 +42,10 @@ pub struct Package {
 	pub content_type: PackageContentType,
 	/// Flags for the package from the repository
 	pub flags: HashSet<PackageFlag>,
+	/// The ID of the repository that the package came from, if any
+	pub repo_id: Option<String>,
+	/// The hash of the package file from the repository index, if any
+	pub hash: Option<String>,
 	/// The data of the package
 	pub data: Later<PkgData>,
 }
@@ -58,6 +66,29 @@ pub enum PkgLocation {
 	Core,
 }
 
+impl PkgLocation {
+	/// Get the contents of the package file at this location. Remote packages are always downloaded.
+	pub async fn fetch(&self, id: &str, client: &Client) -> anyhow::Result<String> {
+		match self {
+			Self::Local(path) => {
+				if !path.exists() {
+					bail!("Local package path does not exist");
+				}
+				Ok(tokio::fs::read_to_string(path).await?)
+			}
+			Self::Remote { url, .. } => {
+				let url = url.as_ref().expect("URL for remote package missing");
+				download::text(url, client).await
+			}
+			Self::Core => {
+				let contents =
+					get_core_package(id).ok_or(anyhow!("Package is not a core package"))?;
+				Ok(contents.to_string())
+			}
+		}
+	}
+}
+
 /// Data pertaining to the contents of a package
 #[derive(Debug)]
 pub struct PkgData {
@@ -91,6 +122,8 @@ pub enum PkgContents {
 	Script(Parsed),
 	/// A declarative package
 	Declarative(Box<DeclarativePackage>),
+	/// A package with a custom content type that was parsed by a plugin
+	Custom(Box<CustomPackageContents>),
 }
 
 impl PkgContents {
@@ -111,6 +144,15 @@ impl PkgContents {
 			panic!("Attempted to get declarative package contents from a non-declarative package");
 		}
 	}
+
+	/// Get the contents with an assertion that it is a custom package
+	pub fn get_custom_contents(&self) -> &CustomPackageContents {
+		if let Self::Custom(contents) = &self {
+			contents
+		} else {
+			panic!("Attempted to get custom package contents from a non-custom package");
+		}
+	}
 }
 
 impl Package {
@@ -127,6 +169,8 @@ impl Package {
 			data: Later::new(),
 			content_type,
 			flags,
+			repo_id: None,
+			hash: None,
 		}
 	}
 
@@ -135,6 +179,7 @@ impl Package {
 		let extension = match self.content_type {
 			PackageContentType::Declarative => ".json",
 			PackageContentType::Script => ".pkg.txt",
+			PackageContentType::Custom(..) => ".txt",
 		};
 		format!("{}{extension}", self.id)
 	}
@@ -145,11 +190,23 @@ impl Package {
 		cache_dir.join(self.filename())
 	}
 
+	/// Whether the contents of the package are available without downloading them
+	pub fn is_cached(&self, paths: &Paths) -> bool {
+		match &self.location {
+			PkgLocation::Remote { .. } => self.cached_path(paths).exists(),
+			PkgLocation::Local(..) | PkgLocation::Core => true,
+		}
+	}
+
 	/// Remove the cached package file
 	pub fn remove_cached(&self, paths: &Paths) -> anyhow::Result<()> {
 		let path = self.cached_path(paths);
 		if path.exists() {
-			fs::remove_file(path)?;
+			fs::remove_file(&path)?;
+		}
+		let info_path = cache_info_path(&path);
+		if info_path.exists() {
+			fs::remove_file(info_path)?;
 		}
 		Ok(())
 	}
@@ -163,29 +220,23 @@ impl Package {
 	) -> anyhow::Result<()> {
 		if self.data.is_empty() {
 			match &self.location {
-				PkgLocation::Local(path) => {
-					if !path.exists() {
-						bail!("Local package path does not exist");
-					}
-					self.data
-						.fill(PkgData::new(&tokio::fs::read_to_string(path).await?));
-				}
-				PkgLocation::Remote { url, .. } => {
+				PkgLocation::Remote { url, repo_id } => {
+					let url = url.as_ref().expect("URL for remote package missing");
 					let path = self.cached_path(paths);
-					if !force && path.exists() {
-						self.data
-							.fill(PkgData::new(&tokio::fs::read_to_string(path).await?));
-					} else {
-						let url = url.as_ref().expect("URL for remote package missing");
-						let text = download::text(url, client).await?;
-						tokio::fs::write(&path, &text).await?;
-						self.data.fill(PkgData::new(&text));
-					}
+					let text = fetch_remote_package(
+						url,
+						&path,
+						repo_id,
+						self.hash.as_deref(),
+						force,
+						client,
+					)
+					.await?;
+					self.data.fill(PkgData::new(&text));
 				}
-				PkgLocation::Core => {
-					let contents = get_core_package(&self.id)
-						.ok_or(anyhow!("Package is not a core package"))?;
-					self.data.fill(PkgData::new(contents));
+				PkgLocation::Local(..) | PkgLocation::Core => {
+					let text = self.location.fetch(&self.id, client).await?;
+					self.data.fill(PkgData::new(&text));
 				}
 			};
 		}
@@ -200,30 +251,39 @@ impl Package {
 		force: bool,
 		client: &Client,
 	) -> Option<impl Future<Output = anyhow::Result<()>> + 'static> {
-		if let PkgLocation::Remote { url, .. } = &self.location {
+		if let PkgLocation::Remote { url, repo_id } = &self.location {
 			let path = self.cached_path(paths);
-			if force || !path.exists() {
-				let url = url
-					.as_ref()
-					.expect("URL for remote package missing")
-					.clone();
-				let client = client.clone();
-				return Some(async move { download::file(url, path, &client).await });
-			}
+			let url = url
+				.as_ref()
+				.expect("URL for remote package missing")
+				.clone();
+			let repo_id = repo_id.clone();
+			let hash = self.hash.clone();
+			let client = client.clone();
+			return Some(async move {
+				fetch_remote_package(&url, &path, &repo_id, hash.as_deref(), force, &client)
+					.await?;
+				Ok(())
+			});
 		}
 
 		None
 	}
 
-	/// Parse the contents of the package
-	pub async fn parse(&mut self, paths: &Paths, client: &Client) -> anyhow::Result<()> {
+	/// Parse the contents of the package. Plugins are used to parse custom content types.
+	pub async fn parse(
+		&mut self,
+		paths: &Paths,
+		client: &Client,
+		plugins: &PluginManager,
+	) -> anyhow::Result<()> {
 		self.ensure_loaded(paths, false, client).await?;
 		let data = self.data.get_mut();
 		if data.contents.is_full() {
 			return Ok(());
 		}
 
-		match self.content_type {
+		match &self.content_type {
 			PackageContentType::Script => {
 				let parsed = lex_and_parse(&data.get_text())?;
 				data.contents.fill(PkgContents::Script(parsed));
@@ -234,6 +294,16 @@ impl Package {
 				data.contents
 					.fill(PkgContents::Declarative(Box::new(contents)));
 			}
+			PackageContentType::Custom(content_type) => {
+				let handler = PluginContentTypes { plugins, paths };
+				let contents = handler
+					.parse_custom_package(content_type, &data.get_text())
+					.context("Failed to parse custom package")?
+					.with_context(|| {
+						format!("No plugin supports the package content type '{content_type}'")
+					})?;
+				data.contents.fill(PkgContents::Custom(Box::new(contents)));
+			}
 		}
 
 		Ok(())
@@ -244,8 +314,11 @@ impl Package {
 		&'a mut self,
 		paths: &Paths,
 		client: &Client,
+		plugins: &PluginManager,
 	) -> anyhow::Result<&'a PackageMetadata> {
-		self.parse(paths, client).await.context("Failed to parse")?;
+		self.parse(paths, client, plugins)
+			.await
+			.context("Failed to parse")?;
 		let data = self.data.get_mut();
 		match self.content_type {
 			PackageContentType::Script => {
@@ -260,6 +333,10 @@ impl Package {
 				let contents = data.contents.get().get_declarative_contents();
 				Ok(&contents.meta)
 			}
+			PackageContentType::Custom(..) => {
+				let contents = data.contents.get().get_custom_contents();
+				Ok(&contents.meta)
+			}
 		}
 	}
 
@@ -268,8 +345,11 @@ impl Package {
 		&'a mut self,
 		paths: &Paths,
 		client: &Client,
+		plugins: &PluginManager,
 	) -> anyhow::Result<&'a PackageProperties> {
-		self.parse(paths, client).await.context("Failed to parse")?;
+		self.parse(paths, client, plugins)
+			.await
+			.context("Failed to parse")?;
 		let data = self.data.get_mut();
 		match self.content_type {
 			PackageContentType::Script => {
@@ -285,12 +365,79 @@ impl Package {
 				let contents = data.contents.get().get_declarative_contents();
 				Ok(&contents.properties)
 			}
+			PackageContentType::Custom(..) => {
+				let contents = data.contents.get().get_custom_contents();
+				Ok(&contents.properties)
+			}
 		}
 	}
 }
 
+/// Information stored alongside a cached remote package file
+#[derive(Serialize, Deserialize)]
+struct CachedPackageInfo {
+	/// The repository the package was downloaded from
+	repo_id: String,
+	/// The ETag the server sent with the package file
+	etag: Option<String>,
+	/// The hash of the package file from the repository index when it was downloaded
+	hash: Option<String>,
+}
+
+/// Get the path to the info file for a cached package file
+fn cache_info_path(path: &Path) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(".cache.json");
+	PathBuf::from(path)
+}
+
+/// Get the contents of a remote package, using the cached file when it is still valid.
+/// The cache is invalidated when the package comes from a different repository or the
+/// hash in the repository index changes. When forced, the server is asked whether the
+/// file has changed since it was cached using its ETag.
+async fn fetch_remote_package(
+	url: &str,
+	path: &Path,
+	repo_id: &str,
+	hash: Option<&str>,
+	force: bool,
+	client: &Client,
+) -> anyhow::Result<String> {
+	let info_path = cache_info_path(path);
+	let info = if path.exists() && info_path.exists() {
+		json_from_file::<CachedPackageInfo>(&info_path).ok()
+	} else {
+		None
+	};
+	let info = info.filter(|x| x.repo_id == repo_id && x.hash.as_deref() == hash);
+
+	if !force && info.is_some() {
+		return Ok(tokio::fs::read_to_string(path).await?);
+	}
+
+	let etag = info.as_ref().and_then(|x| x.etag.as_deref());
+	let Some((text, etag)) = download::text_if_changed(url, etag, client).await? else {
+		return Ok(tokio::fs::read_to_string(path).await?);
+	};
+	tokio::fs::write(path, &text).await?;
+	let info = CachedPackageInfo {
+		repo_id: repo_id.into(),
+		etag,
+		hash: hash.map(String::from),
+	};
+	json_to_file(&info_path, &info).context("Failed to write package cache info")?;
+
+	Ok(text)
+}
+
 #[cfg(test)]
 mod tests {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::sync::Arc;
+
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::TcpListener;
+
 	use super::*;
 
 	#[test]
@@ -317,4 +464,76 @@ mod tests {
 		);
 		assert_eq!(package.filename(), "fabriclike-api.json".to_string());
 	}
+
+	/// Serves a package file with an ETag, returning the URL and a counter
+	/// of how many times the full file was sent
+	async fn serve_with_etag(etag: &'static str) -> (String, Arc<AtomicUsize>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+		let count = Arc::new(AtomicUsize::new(0));
+		let count2 = count.clone();
+		tokio::spawn(async move {
+			loop {
+				let Ok((mut stream, _)) = listener.accept().await else {
+					return;
+				};
+				let mut request = Vec::new();
+				let mut buf = [0; 1024];
+				while !request.ends_with(b"\r\n\r\n") {
+					match stream.read(&mut buf).await {
+						Ok(0) | Err(..) => break,
+						Ok(len) => request.extend_from_slice(&buf[..len]),
+					}
+				}
+
+				let request = String::from_utf8_lossy(&request).to_lowercase();
+				let response = if request.contains(&format!("if-none-match: {etag}")) {
+					"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string()
+				} else {
+					count2.fetch_add(1, Ordering::SeqCst);
+					let body = "@install {}";
+					format!(
+						"HTTP/1.1 200 OK\r\nETag: {etag}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+						body.len()
+					)
+				};
+				let _ = stream.write_all(response.as_bytes()).await;
+				let _ = stream.shutdown().await;
+			}
+		});
+
+		(url, count)
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_etag_cache() {
+		let dir = tempfile::tempdir().unwrap();
+		let dir = dir.path();
+		let path = dir.join("test.pkg.txt");
+		let client = Client::new();
+		let (url, count) = serve_with_etag("\"v1\"").await;
+
+		let text = fetch_remote_package(&url, &path, "repo", Some("a"), false, &client)
+			.await
+			.unwrap();
+		assert_eq!(text, "@install {}");
+		assert_eq!(count.load(Ordering::SeqCst), 1);
+
+		// Forcing a fetch with an unchanged ETag is served from the cache
+		let text = fetch_remote_package(&url, &path, "repo", Some("a"), true, &client)
+			.await
+			.unwrap();
+		assert_eq!(text, "@install {}");
+		assert_eq!(count.load(Ordering::SeqCst), 1);
+
+		// A different repository or index hash invalidates the cache
+		fetch_remote_package(&url, &path, "other", Some("a"), false, &client)
+			.await
+			.unwrap();
+		assert_eq!(count.load(Ordering::SeqCst), 2);
+		fetch_remote_package(&url, &path, "other", Some("b"), false, &client)
+			.await
+			.unwrap();
+		assert_eq!(count.load(Ordering::SeqCst), 3);
+	}
 }
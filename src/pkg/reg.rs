@@ -1,13 +1,13 @@
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use mcvm_core::net::download;
 use mcvm_pkg::metadata::PackageMetadata;
-use mcvm_pkg::parse_and_validate;
+use mcvm_pkg::parse_and_validate_custom;
 use mcvm_pkg::properties::PackageProperties;
 use mcvm_pkg::repo::PackageFlag;
 use mcvm_pkg::PackageContentType;
 use mcvm_pkg::PkgRequest;
 use mcvm_pkg::PkgRequestSource;
-use mcvm_shared::output::MCVMOutput;
+use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
 use mcvm_shared::pkg::ArcPkgReq;
 use reqwest::Client;
 #[cfg(feature = "schema")]
@@ -16,8 +16,9 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 
+use super::eval::custom::PluginContentTypes;
 use super::eval::{EvalData, EvalInput, Routine};
-use super::repo::{query_all, PkgRepo};
+use super::repo::{query_all_concurrent, PkgRepo};
 use super::{Package, PkgContents};
 use crate::plugin::PluginManager;
 use crate::io::paths::Paths;
@@ -35,18 +36,42 @@ pub struct PkgRegistry {
 	pub repos: Vec<PkgRepo>,
 	packages: HashMap<ArcPkgReq, Package>,
 	caching_strategy: CachingStrategy,
+	/// Plugins used to handle packages with custom content types
+	plugins: PluginManager,
+	/// Whether remote packages and repositories can only be used from the cache
+	offline: bool,
 }
 
 impl PkgRegistry {
-	/// Create a new PkgRegistry with repositories and a caching strategy
-	pub fn new(repos: Vec<PkgRepo>, caching_strategy: CachingStrategy) -> Self {
+	/// Create a new PkgRegistry with repositories, a caching strategy, and the plugins
+	/// that handle custom package content types
+	pub fn new(
+		repos: Vec<PkgRepo>,
+		caching_strategy: CachingStrategy,
+		plugins: PluginManager,
+	) -> Self {
 		Self {
 			repos,
 			packages: HashMap::new(),
 			caching_strategy,
+			plugins,
+			offline: false,
 		}
 	}
 
+	/// Set whether to only use cached packages and repository indexes instead of downloading them
+	pub fn set_offline(&mut self, offline: bool) {
+		self.offline = offline;
+		for repo in &mut self.repos {
+			repo.set_offline(offline);
+		}
+	}
+
+	/// Gets whether the registry only uses cached packages and repository indexes
+	pub fn is_offline(&self) -> bool {
+		self.offline
+	}
+
 	/// Clear the registry
 	pub fn clear(&mut self) {
 		self.packages.clear();
@@ -66,6 +91,28 @@ impl PkgRegistry {
 		self.packages.contains_key(req)
 	}
 
+	/// Gets the hash of a package from its repository index, if it is in the registry already
+	pub fn get_hash(&self, req: &PkgRequest) -> Option<&str> {
+		self.packages.get(req).and_then(|x| x.hash.as_deref())
+	}
+
+	/// Checks if a package is in the registry or any of the repositories
+	pub async fn exists(
+		&mut self,
+		req: &ArcPkgReq,
+		paths: &Paths,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<bool> {
+		if self.has_now(req) {
+			return Ok(true);
+		}
+		let query = query_all_concurrent(&mut self.repos, &req.id, paths, client, o)
+			.await
+			.context("Failed to query remote repositories")?;
+		Ok(query.is_some())
+	}
+
 	/// Query repositories to insert a package
 	async fn query_insert(
 		&mut self,
@@ -75,19 +122,19 @@ impl PkgRegistry {
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<&mut Package> {
 		// First check the remote repositories
-		let query = query_all(&mut self.repos, &req.id, paths, client, o)
+		let query = query_all_concurrent(&mut self.repos, &req.id, paths, client, o)
 			.await
 			.context("Failed to query remote repositories")?;
 		if let Some(result) = query {
-			return Ok(self.insert(
-				req.clone(),
-				Package::new(
-					req.id.clone(),
-					result.location,
-					result.content_type,
-					result.flags,
-				),
-			));
+			let mut package = Package::new(
+				req.id.clone(),
+				result.location,
+				result.content_type,
+				result.flags,
+			);
+			package.repo_id = Some(result.repo_id);
+			package.hash = result.hash;
+			return Ok(self.insert(req.clone(), package));
 		} else {
 			Err(anyhow!("Package '{req}' does not exist"))
 		}
@@ -116,11 +163,15 @@ impl PkgRegistry {
 		client: &Client,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<&mut Package> {
-		let force = matches!(self.caching_strategy, CachingStrategy::None);
+		let offline = self.offline;
+		let force = !offline && matches!(self.caching_strategy, CachingStrategy::None);
 		let pkg = self
 			.get(req, paths, client, o)
 			.await
 			.with_context(|| format!("Failed to get package {req}"))?;
+		if offline && !pkg.is_cached(paths) {
+			bail!("Offline mode: package {req} is not cached");
+		}
 		pkg.ensure_loaded(paths, force, client)
 			.await
 			.with_context(|| format!("Failed to load package {req}"))?;
@@ -150,12 +201,25 @@ impl PkgRegistry {
 		client: &Client,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<&'a PackageMetadata> {
+		let plugins = self.plugins.clone();
 		let pkg = self.ensure_package_contents(req, paths, client, o).await?;
-		pkg.get_metadata(paths, client)
+		pkg.get_metadata(paths, client, &plugins)
 			.await
 			.context("Failed to get metadata from package")
 	}
 
+	/// Get the ID of the repository that a package came from
+	pub async fn get_repository(
+		&mut self,
+		req: &ArcPkgReq,
+		paths: &Paths,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<Option<String>> {
+		let pkg = self.get(req, paths, client, o).await?;
+		Ok(pkg.repo_id.clone())
+	}
+
 	/// Get the properties of a package
 	pub async fn get_properties<'a>(
 		&'a mut self,
@@ -164,8 +228,9 @@ impl PkgRegistry {
 		client: &Client,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<&'a PackageProperties> {
+		let plugins = self.plugins.clone();
 		let pkg = self.ensure_package_contents(req, paths, client, o).await?;
-		pkg.get_properties(paths, client)
+		pkg.get_properties(paths, client, &plugins)
 			.await
 			.context("Failed to get properties from package")
 	}
@@ -179,7 +244,7 @@ impl PkgRegistry {
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<PackageContentType> {
 		let pkg = self.ensure_package_contents(req, paths, client, o).await?;
-		Ok(pkg.content_type)
+		Ok(pkg.content_type.clone())
 	}
 
 	/// Load the contents of a package
@@ -203,10 +268,15 @@ impl PkgRegistry {
 		client: &Client,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<()> {
+		let plugins = self.plugins.clone();
 		let pkg = self.ensure_package_contents(req, paths, client, o).await?;
 		let contents = &pkg.data.get().get_text();
 
-		parse_and_validate(contents, pkg.content_type)?;
+		let custom = PluginContentTypes {
+			plugins: &plugins,
+			paths,
+		};
+		parse_and_validate_custom(contents, pkg.content_type.clone(), &custom)?;
 
 		Ok(())
 	}
@@ -219,8 +289,9 @@ impl PkgRegistry {
 		client: &Client,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<&'a PkgContents> {
+		let plugins = self.plugins.clone();
 		let pkg = self.ensure_package_contents(req, paths, client, o).await?;
-		pkg.parse(paths, client)
+		pkg.parse(paths, client, &plugins)
 			.await
 			.context("Failed to parse package")?;
 		Ok(pkg.data.get().contents.get())
@@ -252,7 +323,7 @@ impl PkgRegistry {
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<PackageContentType> {
 		let pkg = self.ensure_package_contents(req, paths, client, o).await?;
-		Ok(pkg.content_type)
+		Ok(pkg.content_type.clone())
 	}
 
 	/// Get the flags of a package
@@ -293,23 +364,92 @@ impl PkgRegistry {
 		self.iter_requests().cloned().collect()
 	}
 
-	/// Get all of the available package requests from the repos
+	/// Get all of the available package requests from the repos. Packages that are
+	/// deprecated or archived are only included if `include_deprecated` is set
 	pub async fn get_all_available_packages(
 		&mut self,
+		include_deprecated: bool,
 		paths: &Paths,
 		client: &Client,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<Vec<ArcPkgReq>> {
-		let out = super::repo::get_all_packages(&mut self.repos, paths, client, o)
+		let out = super::repo::get_all_packages_sorted(&mut self.repos, paths, client, o)
 			.await
 			.context("Failed to retrieve all packages from repos")?
 			.iter()
+			.filter(|(_, entry)| include_deprecated || !entry.is_deprecated())
 			.map(|(id, ..)| Arc::new(PkgRequest::any(id.as_ref(), PkgRequestSource::Repository)))
 			.collect();
 
 		Ok(out)
 	}
 
+	/// Get a map from Modrinth project IDs to the IDs of the packages in the repos that declare them.
+	/// Used to substitute Modrinth dependencies with existing packages
+	pub async fn get_modrinth_id_map(
+		&mut self,
+		paths: &Paths,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<HashMap<String, String>> {
+		let properties = self.get_all_properties(paths, client, o).await?;
+
+		Ok(mcvm_pkg::properties::build_modrinth_id_map(
+			properties
+				.iter()
+				.map(|(package, props)| (package.id.as_ref(), props)),
+		))
+	}
+
+	/// Get a map from CurseForge mod IDs to the IDs of the packages in the repos that declare them.
+	/// Used to find the packages for mods in CurseForge modpacks
+	pub async fn get_curseforge_id_map(
+		&mut self,
+		paths: &Paths,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<HashMap<String, String>> {
+		let properties = self.get_all_properties(paths, client, o).await?;
+
+		Ok(mcvm_pkg::properties::build_curseforge_id_map(
+			properties
+				.iter()
+				.map(|(package, props)| (package.id.as_ref(), props)),
+		))
+	}
+
+	/// Get the properties of all of the packages in the repos. Packages whose properties
+	/// can't be evaluated are skipped with a warning so that one broken package doesn't hide the rest
+	async fn get_all_properties(
+		&mut self,
+		paths: &Paths,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<Vec<(ArcPkgReq, PackageProperties)>> {
+		let packages = self
+			.get_all_available_packages(true, paths, client, o)
+			.await
+			.context("Failed to get list of available packages")?;
+
+		let mut properties = Vec::with_capacity(packages.len());
+		for package in packages {
+			match self.get_properties(&package, paths, client, o).await {
+				Ok(props) => {
+					let props = props.clone();
+					properties.push((package, props));
+				}
+				Err(e) => o.display(
+					MessageContents::Warning(format!(
+						"Skipping package {package} because its properties could not be evaluated: {e:?}"
+					)),
+					MessageLevel::Important,
+				),
+			}
+		}
+
+		Ok(properties)
+	}
+
 	/// Remove cached packages
 	async fn remove_cached_packages(
 		&mut self,
@@ -334,8 +474,12 @@ impl PkgRegistry {
 		client: &Client,
 		o: &mut impl MCVMOutput,
 	) -> anyhow::Result<()> {
+		if self.offline {
+			bail!("Cached packages can't be updated in offline mode");
+		}
+
 		let packages = self
-			.get_all_available_packages(paths, client, o)
+			.get_all_available_packages(true, paths, client, o)
 			.await
 			.context("Failed to get list of available packages")?;
 
@@ -3,33 +3,55 @@ use mcvm_core::net::download;
 use mcvm_pkg::repo::{
 	get_api_url, get_index_url, PackageFlag, RepoIndex, RepoMetadata, RepoPkgEntry,
 };
-use mcvm_pkg::PackageContentType;
+use mcvm_pkg::{parse_and_validate_custom, PackageContentType};
 use mcvm_shared::later::Later;
 
 use anyhow::{bail, Context};
-use mcvm_shared::output::{MCVMOutput, MessageContents, MessageLevel};
+use mcvm_shared::lang::translate::replace_placeholders;
+use mcvm_shared::output::{MCVMOutput, Message, MessageContents, MessageLevel};
 use mcvm_shared::translate;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::fs::File;
+use std::future::Future;
 use std::io::{BufReader, Cursor};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
 use super::core::{
 	get_all_core_packages, get_core_package_content_type, get_core_package_count, is_core_package,
+	verify_core_packages,
 };
+use super::eval::custom::PluginContentTypes;
 use super::PkgLocation;
+use crate::plugin::PluginManager;
+
+/// The number of times to try syncing a remote index before giving up
+const SYNC_ATTEMPTS: u32 = 3;
+/// The delay before retrying a failed sync, which doubles with each retry
+const SYNC_RETRY_DELAY: Duration = Duration::from_millis(250);
 
 /// A remote source for mcvm packages
 #[derive(Debug)]
 pub struct PkgRepo {
 	/// The identifier for the repository
 	pub id: String,
+	/// The priority of the repository. Packages in repositories with a higher
+	/// priority override the ones in repositories with a lower priority
+	pub priority: i32,
+	/// The maximum size in bytes of a remote index that will be downloaded
+	pub max_index_size: Option<u64>,
 	location: PkgRepoLocation,
 	index: Later<RepoIndex>,
+	/// Whether remote indexes can only be used from the cache
+	offline: bool,
 }
 
 /// Location for a PkgRepo
@@ -54,18 +76,48 @@ impl Display for PkgRepoLocation {
 }
 
 impl PkgRepo {
-	/// Create a new PkgRepo
+	/// The default priority for preferred repositories
+	pub const PREFERRED_PRIORITY: i32 = 100;
+	/// The priority of the core repository
+	pub const CORE_PRIORITY: i32 = 50;
+	/// The priority of the std repository
+	pub const STD_PRIORITY: i32 = 0;
+	/// The default priority for backup repositories
+	pub const BACKUP_PRIORITY: i32 = -100;
+
+	/// Create a new PkgRepo with the priority of the std repository
 	pub fn new(id: &str, location: PkgRepoLocation) -> Self {
 		Self {
 			id: id.to_owned(),
+			priority: Self::STD_PRIORITY,
+			max_index_size: None,
 			location,
 			index: Later::new(),
+			offline: false,
 		}
 	}
 
+	/// Set the priority of the repository
+	pub fn with_priority(mut self, priority: i32) -> Self {
+		self.priority = priority;
+		self
+	}
+
+	/// Set the maximum size of the index that will be downloaded
+	pub fn with_max_index_size(mut self, max_index_size: Option<u64>) -> Self {
+		self.max_index_size = max_index_size;
+		self
+	}
+
+	/// Set whether to only use the cached index of a remote repository instead of syncing it
+	pub fn set_offline(&mut self, offline: bool) {
+		self.offline = offline;
+	}
+
 	/// Create the core repository
 	pub fn core() -> Self {
-		Self::new("core", PkgRepoLocation::Core)
+		// We don't want std overriding core
+		Self::new("core", PkgRepoLocation::Core).with_priority(Self::CORE_PRIORITY)
 	}
 
 	/// Create the std repository
@@ -79,7 +131,6 @@ impl PkgRepo {
 	/// Get the default set of repositories
 	pub fn default_repos(enable_core: bool, enable_std: bool) -> Vec<Self> {
 		let mut out = Vec::new();
-		// We don't want std overriding core
 		if enable_core {
 			out.push(Self::core());
 		}
@@ -116,22 +167,65 @@ impl PkgRepo {
 				self.set_index(&mut cursor).context("Failed to set index")?;
 			}
 			PkgRepoLocation::Remote(url) => {
-				let bytes = download::bytes(get_index_url(url), client)
-					.await
-					.context("Failed to download index")?;
+				if self.offline {
+					bail!(
+						"Offline mode: index of repository '{}' is not cached",
+						self.id
+					);
+				}
+				let url = get_index_url(url);
+				let bytes = if let Some(max_size) = self.max_index_size {
+					check_remote_index_size(&url, max_size, client).await?;
+					download_index_limited(&url, max_size, client).await?
+				} else {
+					download::bytes(url, client)
+						.await
+						.context("Failed to download index")?
+						.to_vec()
+				};
 				tokio::fs::write(self.get_path(paths), &bytes)
 					.await
 					.context("Failed to write index to cached file")?;
 				let mut cursor = Cursor::new(&bytes);
 				self.set_index(&mut cursor).context("Failed to set index")?;
 			}
-			PkgRepoLocation::Core => {}
+			PkgRepoLocation::Core => {
+				verify_core_packages().context("Failed to verify core packages")?;
+			}
 		}
 
 		Ok(())
 	}
 
-	/// Make sure that the repository index is downloaded
+	/// Update the cached index file, retrying with a backoff if a remote index fails to download
+	/// because of a transient error
+	async fn sync_with_retry(&mut self, paths: &Paths, client: &Client) -> anyhow::Result<()> {
+		// Only network failures are worth retrying
+		let attempts = match &self.location {
+			PkgRepoLocation::Remote(..) if !self.offline => SYNC_ATTEMPTS,
+			_ => 1,
+		};
+
+		let mut delay = SYNC_RETRY_DELAY;
+		let mut attempt = 1;
+		loop {
+			match self.sync(paths, client).await {
+				Ok(..) => return Ok(()),
+				Err(e) if attempt >= attempts => {
+					return Err(e).with_context(|| format!("Failed after {attempt} attempts"))
+				}
+				Err(e) if !is_transient_error(&e) => return Err(e),
+				Err(..) => {}
+			}
+
+			tokio::time::sleep(delay).await;
+			delay *= 2;
+			attempt += 1;
+		}
+	}
+
+	/// Make sure that the repository index is downloaded. A cached index is used if there is one,
+	/// and the index is only synced if the cache is missing or invalid.
 	pub async fn ensure_index(
 		&mut self,
 		paths: &Paths,
@@ -151,13 +245,13 @@ impl PkgRepo {
 				match self.set_index(&mut file) {
 					Ok(..) => {}
 					Err(..) => {
-						self.sync(paths, client)
+						self.sync_with_retry(paths, client)
 							.await
 							.context("Failed to sync index")?;
 					}
 				};
 			} else {
-				self.sync(paths, client)
+				self.sync_with_retry(paths, client)
 					.await
 					.context("Failed to sync index")?;
 			}
@@ -205,6 +299,8 @@ impl PkgRepo {
 					content_type: get_core_package_content_type(id)
 						.expect("Core package exists and should have a content type"),
 					flags: HashSet::new(),
+					repo_id: self.id.clone(),
+					hash: None,
 				}))
 			} else {
 				Ok(None)
@@ -219,12 +315,34 @@ impl PkgRepo {
 					location,
 					content_type: get_content_type(entry).await,
 					flags: entry.flags.clone(),
+					repo_id: self.id.clone(),
+					hash: entry.hash.clone(),
 				}));
 			}
 			Ok(None)
 		}
 	}
 
+	/// Fetch the contents of a package file from this repository, along with its content type
+	pub async fn fetch_package(
+		&mut self,
+		id: &str,
+		paths: &Paths,
+		client: &Client,
+		o: &mut impl MCVMOutput,
+	) -> anyhow::Result<(String, PackageContentType)> {
+		let Some(result) = self.query(id, paths, client, o).await? else {
+			bail!("Package '{id}' does not exist in repository '{}'", self.id);
+		};
+		let contents = result
+			.location
+			.fetch(id, client)
+			.await
+			.with_context(|| format!("Failed to fetch package '{id}'"))?;
+
+		Ok((contents, result.content_type))
+	}
+
 	/// Get all packages from this repo
 	pub async fn get_all_packages(
 		&mut self,
@@ -285,7 +403,13 @@ impl PkgRepo {
 	}
 }
 
-/// Query a list of repos
+/// Sorts a list of repos from highest to lowest priority. Repos with the same
+/// priority stay in the same order that they were in the list
+pub fn sort_repos(repos: &mut [PkgRepo]) {
+	repos.sort_by_key(|x| std::cmp::Reverse(x.priority));
+}
+
+/// Query a list of repos. They should already be sorted by priority
 pub async fn query_all(
 	repos: &mut [PkgRepo],
 	id: &str,
@@ -298,7 +422,12 @@ pub async fn query_all(
 			Ok(val) => val,
 			Err(e) => {
 				o.display(
-					MessageContents::Error(e.to_string()),
+					MessageContents::Error(translate!(
+						o,
+						RepoQueryFailed,
+						"repo" = &repo.id,
+						"error" = &format!("{e:#}")
+					)),
 					MessageLevel::Important,
 				);
 				continue;
@@ -311,7 +440,150 @@ pub async fn query_all(
 	Ok(None)
 }
 
-/// Get all packages from a list of repositories with the normal priority order
+/// Query a list of repos concurrently. They should already be sorted by priority. All of the repos are queried at once, but the result
+/// is still the match from the first repo in the list that has the package. Queries to the
+/// repos after a match are cancelled as soon as that match is found.
+pub async fn query_all_concurrent(
+	repos: &mut [PkgRepo],
+	id: &str,
+	paths: &Paths,
+	client: &Client,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<Option<RepoQueryResult>> {
+	// Translations can't be borrowed through the shared output, so get this one beforehand
+	let error_message: String = translate!(o, RepoQueryFailed);
+	let o = Mutex::new(o);
+	let queries = repos.iter_mut().map(|repo| {
+		let mut o = SharedOutput(&o);
+		let error_message = &error_message;
+		async move {
+			match repo.query(id, paths, client, &mut o).await {
+				Ok(val) => val,
+				Err(e) => {
+					let message = replace_placeholders(error_message, "repo", &repo.id);
+					let message = replace_placeholders(&message, "error", &format!("{e:#}"));
+					o.display(MessageContents::Error(message), MessageLevel::Important);
+					None
+				}
+			}
+		}
+	});
+
+	Ok(race_in_order(queries).await)
+}
+
+/// Runs futures concurrently and returns the first Some output in the order of the list,
+/// which is only known once all of the futures before it have finished. Futures after
+/// the first Some output that has finished are dropped, since they can no longer win.
+async fn race_in_order<T, F: Future<Output = Option<T>>>(
+	futures: impl IntoIterator<Item = F>,
+) -> Option<T> {
+	let mut futures: Vec<_> = futures.into_iter().map(|x| Some(Box::pin(x))).collect();
+	// None for futures that are still running
+	let mut outputs: Vec<Option<Option<T>>> = futures.iter().map(|_| None).collect();
+
+	std::future::poll_fn(|cx| {
+		for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+			if let Some(fut) = future {
+				if let Poll::Ready(result) = fut.as_mut().poll(cx) {
+					*future = None;
+					*output = Some(result);
+				}
+			}
+		}
+
+		if let Some(best) = outputs.iter().position(|x| matches!(x, Some(Some(..)))) {
+			futures.truncate(best + 1);
+			outputs.truncate(best + 1);
+		}
+
+		for output in &mut outputs {
+			match output {
+				None => return Poll::Pending,
+				Some(None) => {}
+				Some(Some(..)) => return Poll::Ready(output.take().flatten()),
+			}
+		}
+
+		Poll::Ready(None)
+	})
+	.await
+}
+
+/// Checks if an error is likely to go away by itself, such as a timeout, a failed connection,
+/// or a server error, making the request worth retrying
+fn is_transient_error(error: &anyhow::Error) -> bool {
+	error
+		.chain()
+		.filter_map(|x| x.downcast_ref::<reqwest::Error>())
+		.any(|e| match e.status() {
+			Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+			None => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+		})
+}
+
+/// Checks the size of a remote index with a HEAD request before it is downloaded, failing if it is
+/// bigger than the maximum size. The server might not support HEAD requests or report the size, in which case
+/// the check passes.
+async fn check_remote_index_size(url: &str, max_size: u64, client: &Client) -> anyhow::Result<()> {
+	let Ok(response) = client.head(url).send().await else {
+		return Ok(());
+	};
+	// The length of a HEAD response body is always zero, so we have to read the header ourselves
+	let size = response
+		.headers()
+		.get(reqwest::header::CONTENT_LENGTH)
+		.and_then(|x| x.to_str().ok())
+		.and_then(|x| x.parse::<u64>().ok());
+	if let Some(size) = size {
+		if size > max_size {
+			bail!(
+				"Index is {size} bytes, which is larger than the maximum size of {max_size} bytes"
+			);
+		}
+	}
+
+	Ok(())
+}
+
+/// Downloads a remote index, failing as soon as more than the maximum size has been read.
+/// The size reported by the server can be missing or wrong, so the body has to be limited as well
+async fn download_index_limited(
+	url: &str,
+	max_size: u64,
+	client: &Client,
+) -> anyhow::Result<Vec<u8>> {
+	let mut response = download::download(url, client)
+		.await
+		.context("Failed to download index")?;
+	let mut out = Vec::new();
+	while let Some(chunk) = response.chunk().await.context("Failed to read index")? {
+		if (out.len() + chunk.len()) as u64 > max_size {
+			bail!("Index is larger than the maximum size of {max_size} bytes");
+		}
+		out.extend_from_slice(&chunk);
+	}
+
+	Ok(out)
+}
+
+/// Output that can be shared between multiple concurrent tasks
+struct SharedOutput<'a, O: MCVMOutput>(&'a Mutex<&'a mut O>);
+
+impl<'a, O: MCVMOutput> MCVMOutput for SharedOutput<'a, O> {
+	fn display_text(&mut self, text: String, level: MessageLevel) {
+		let mut o = self.0.lock().unwrap_or_else(|e| e.into_inner());
+		o.display_text(text, level);
+	}
+
+	fn display_message(&mut self, message: Message) {
+		let mut o = self.0.lock().unwrap_or_else(|e| e.into_inner());
+		o.display_message(message);
+	}
+}
+
+/// Get all packages from a list of repositories. When multiple repositories have
+/// the same package, the one from the repository with the highest priority is used
 pub async fn get_all_packages(
 	repos: &mut [PkgRepo],
 	paths: &Paths,
@@ -319,18 +591,160 @@ pub async fn get_all_packages(
 	o: &mut impl MCVMOutput,
 ) -> anyhow::Result<HashMap<String, RepoPkgEntry>> {
 	let mut out = HashMap::new();
-	// Iterate in reverse to make sure that repos at the beginning take precendence
-	for repo in repos.iter_mut().rev() {
+	let mut priorities = HashMap::new();
+	for repo in repos.iter_mut() {
+		let packages = repo
+			.get_all_packages(paths, client, o)
+			.await
+			.with_context(|| format!("Failed to get all packages from repository '{}'", repo.id))?;
+		for (id, entry) in packages {
+			// Repos earlier in the list win ties
+			if priorities.get(&id).is_some_and(|x| *x >= repo.priority) {
+				continue;
+			}
+			priorities.insert(id.clone(), repo.priority);
+			out.insert(id, entry);
+		}
+	}
+
+	Ok(out)
+}
+
+/// Get all packages from a list of repositories, sorted by package ID so that the output is
+/// reproducible. Shadowed packages take the same precedence as in `get_all_packages`: the entry
+/// from the repository with the highest priority is used, and ties go to the repository that
+/// is earlier in the list
+pub async fn get_all_packages_sorted(
+	repos: &mut [PkgRepo],
+	paths: &Paths,
+	client: &Client,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<Vec<(String, RepoPkgEntry)>> {
+	let mut out: Vec<_> = get_all_packages(repos, paths, client, o)
+		.await?
+		.into_iter()
+		.collect();
+	out.sort_by(|a, b| a.0.cmp(&b.0));
+
+	Ok(out)
+}
+
+/// A package that is defined by more than one repository, so that
+/// the definitions from all but one of them are never used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowInfo {
+	/// The ID of the package
+	pub package: String,
+	/// The ID of the repository that the package is taken from
+	pub winner: String,
+	/// The IDs of the repositories that are shadowed by the winner, from highest to lowest priority
+	pub shadowed: Vec<String>,
+}
+
+/// Finds all of the packages that are defined by more than one repository,
+/// sorted by package ID
+pub async fn find_shadowed_packages(
+	repos: &mut [PkgRepo],
+	paths: &Paths,
+	client: &Client,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<Vec<ShadowInfo>> {
+	let mut definitions: HashMap<String, Vec<(i32, String)>> = HashMap::new();
+	for repo in repos.iter_mut() {
 		let packages = repo
 			.get_all_packages(paths, client, o)
 			.await
 			.with_context(|| format!("Failed to get all packages from repository '{}'", repo.id))?;
-		out.extend(packages);
+		for (id, _) in packages {
+			definitions
+				.entry(id)
+				.or_default()
+				.push((repo.priority, repo.id.clone()));
+		}
+	}
+
+	let mut out: Vec<_> = definitions
+		.into_iter()
+		.filter(|(_, repos)| repos.len() > 1)
+		.map(|(package, mut repos)| {
+			// This sort is stable, so repos earlier in the list win ties
+			repos.sort_by_key(|(priority, _)| std::cmp::Reverse(*priority));
+			let mut repos = repos.into_iter().map(|(_, id)| id);
+			ShadowInfo {
+				package,
+				winner: repos.next().expect("Package should have multiple repos"),
+				shadowed: repos.collect(),
+			}
+		})
+		.collect();
+	out.sort_by(|a, b| a.package.cmp(&b.package));
+
+	Ok(out)
+}
+
+/// Fetches every package in a repository and checks that it parses and validates, so that
+/// all of the invalid packages in a repository can be reported at once. Packages are
+/// fetched concurrently, up to the transfer limit at a time. Packages with custom content types
+/// are validated by plugins. Returns the result for each package, sorted by package ID
+pub async fn validate_repository(
+	repo: &mut PkgRepo,
+	plugins: &PluginManager,
+	paths: &Paths,
+	client: &Client,
+	o: &mut impl MCVMOutput,
+) -> anyhow::Result<Vec<(String, anyhow::Result<()>)>> {
+	let packages = repo
+		.get_all_packages(paths, client, o)
+		.await
+		.with_context(|| format!("Failed to get all packages from repository '{}'", repo.id))?;
+
+	let mut tasks = JoinSet::new();
+	let semaphore = Arc::new(Semaphore::new(download::get_transfer_limit()));
+	for (id, entry) in packages {
+		let location = if let PkgRepoLocation::Core = &repo.location {
+			Ok(PkgLocation::Core)
+		} else {
+			get_package_location(&entry, &repo.location, &repo.id)
+		};
+		let content_type = get_content_type(&entry).await;
+		let client = client.clone();
+		let semaphore = semaphore.clone();
+		tasks.spawn(async move {
+			let _permit = semaphore.acquire_owned().await;
+			let contents = fetch_package(&id, location, &client).await;
+			(id, contents, content_type)
+		});
 	}
 
+	let custom = PluginContentTypes { plugins, paths };
+	let mut out = Vec::new();
+	while let Some(result) = tasks.join_next().await {
+		let (id, contents, content_type) =
+			result.context("Failed to run package validation task")?;
+		let result = contents.and_then(|contents| {
+			parse_and_validate_custom(&contents, content_type, &custom)
+				.context("Package is invalid")
+		});
+		out.push((id, result));
+	}
+	out.sort_by(|a, b| a.0.cmp(&b.0));
+
 	Ok(out)
 }
 
+/// Fetches the contents of a single package from a repository for validation
+async fn fetch_package(
+	id: &str,
+	location: anyhow::Result<PkgLocation>,
+	client: &Client,
+) -> anyhow::Result<String> {
+	let location = location.context("Failed to get location of package")?;
+	location
+		.fetch(id, client)
+		.await
+		.context("Failed to fetch package")
+}
+
 /// Result from repository querying. This represents an entry
 /// for a package that can be accessed
 pub struct RepoQueryResult {
@@ -340,12 +754,16 @@ pub struct RepoQueryResult {
 	pub content_type: PackageContentType,
 	/// The flags for the package
 	pub flags: HashSet<PackageFlag>,
+	/// The ID of the repository that the package was found in
+	pub repo_id: String,
+	/// The hash of the package file from the repository index
+	pub hash: Option<String>,
 }
 
 /// Get the content type of a package from the repository
 pub async fn get_content_type(entry: &RepoPkgEntry) -> PackageContentType {
 	if let Some(content_type) = &entry.content_type {
-		*content_type
+		content_type.clone()
 	} else {
 		PackageContentType::Script
 	}
@@ -403,3 +821,381 @@ pub fn get_package_location(
 		bail!("Neither url nor path entry present in package")
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+	use std::sync::Arc;
+	use std::time::Instant;
+
+	use mcvm_shared::lang::translate::TranslationKey;
+	use mcvm_shared::output::{NoOp, Recording};
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::TcpListener;
+
+	use super::*;
+	use crate::pkg::core::get_core_package;
+
+	#[tokio::test]
+	async fn test_race_in_order() {
+		let finished = AtomicBool::new(false);
+		// The first repo responds the slowest but should still win
+		let repos = [
+			(60, None),
+			(40, Some("second")),
+			(10, Some("third")),
+			(5000, None),
+		];
+		let queries = repos.iter().map(|(delay, result)| {
+			let finished = &finished;
+			async move {
+				tokio::time::sleep(Duration::from_millis(*delay)).await;
+				if *delay == 5000 {
+					finished.store(true, Ordering::Relaxed);
+				}
+				*result
+			}
+		});
+
+		let start = Instant::now();
+		assert_eq!(race_in_order(queries).await, Some("second"));
+		assert!(start.elapsed() < Duration::from_secs(1));
+		// The last query should have been cancelled
+		assert!(!finished.load(Ordering::Relaxed));
+	}
+
+	#[tokio::test]
+	async fn test_race_in_order_no_match() {
+		let queries = [20, 5].into_iter().map(|delay| async move {
+			tokio::time::sleep(Duration::from_millis(delay)).await;
+			None::<()>
+		});
+
+		assert_eq!(race_in_order(queries).await, None);
+	}
+
+	#[tokio::test]
+	async fn test_shadowed_packages() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+
+		let create_repo = |id: &str, packages: &[&str]| {
+			let packages: serde_json::Map<_, _> = packages
+				.iter()
+				.map(|x| (x.to_string(), serde_json::json!({ "path": x })))
+				.collect();
+			let index = serde_json::json!({ "packages": packages });
+			let path = data_dir.join(format!("{id}.json"));
+			std::fs::write(&path, index.to_string()).unwrap();
+			PkgRepo::new(id, PkgRepoLocation::Local(path))
+		};
+		let mut repos = vec![
+			create_repo("first", &["foo", "bar"]),
+			create_repo("second", &["foo", "baz"]),
+		];
+
+		let client = Client::new();
+		let shadowed = find_shadowed_packages(&mut repos, &paths, &client, &mut NoOp)
+			.await
+			.unwrap();
+		assert_eq!(
+			shadowed,
+			vec![ShadowInfo {
+				package: "foo".into(),
+				winner: "first".into(),
+				shadowed: vec!["second".into()],
+			}]
+		);
+
+		repos[1].priority = 10;
+		let shadowed = find_shadowed_packages(&mut repos, &paths, &client, &mut NoOp)
+			.await
+			.unwrap();
+		assert_eq!(shadowed[0].winner, "second");
+	}
+
+	#[tokio::test]
+	async fn test_get_all_packages_sorted() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+
+		let create_repo = |id: &str, packages: &[&str]| {
+			let packages: serde_json::Map<_, _> = packages
+				.iter()
+				.map(|x| {
+					let entry = serde_json::json!({ "path": format!("{id}/{x}") });
+					(x.to_string(), entry)
+				})
+				.collect();
+			let index = serde_json::json!({ "packages": packages });
+			let path = data_dir.join(format!("{id}.json"));
+			std::fs::write(&path, index.to_string()).unwrap();
+			PkgRepo::new(id, PkgRepoLocation::Local(path))
+		};
+		let mut repos = vec![
+			create_repo("first", &["zoo", "foo", "bar"]),
+			create_repo("second", &["foo", "baz", "abc"]),
+		];
+
+		let client = Client::new();
+		let packages = get_all_packages_sorted(&mut repos, &paths, &client, &mut NoOp)
+			.await
+			.unwrap();
+		let ids: Vec<_> = packages.iter().map(|x| x.0.as_str()).collect();
+		assert_eq!(ids, vec!["abc", "bar", "baz", "foo", "zoo"]);
+		assert_eq!(packages[3].1.path.as_deref(), Some("first/foo"));
+
+		repos[1].priority = 10;
+		let packages = get_all_packages_sorted(&mut repos, &paths, &client, &mut NoOp)
+			.await
+			.unwrap();
+		assert_eq!(packages[3].1.path.as_deref(), Some("second/foo"));
+	}
+
+	/// Output that records its messages and translates repo query failures
+	fn create_translating_output() -> Recording {
+		Recording {
+			translations: HashMap::from([(
+				TranslationKey::RepoQueryFailed,
+				"translated(%repo, %error)".into(),
+			)]),
+			..Default::default()
+		}
+	}
+
+	#[tokio::test]
+	async fn test_repo_query_error_translated() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+
+		// The index for this repo doesn't exist, so querying it will fail
+		let mut repos = vec![PkgRepo::new(
+			"broken",
+			PkgRepoLocation::Local(data_dir.join("missing.json")),
+		)];
+		let client = Client::new();
+
+		let mut o = create_translating_output();
+		let result = query_all(&mut repos, "foo", &paths, &client, &mut o)
+			.await
+			.unwrap();
+		assert!(result.is_none());
+		assert_eq!(o.messages.len(), 1);
+		assert!(o.messages[0].contains("translated(broken, "));
+
+		let mut o = create_translating_output();
+		let result = query_all_concurrent(&mut repos, "foo", &paths, &client, &mut o)
+			.await
+			.unwrap();
+		assert!(result.is_none());
+		assert_eq!(o.messages.len(), 1);
+		assert!(o.messages[0].contains("translated(broken, "));
+	}
+
+	/// Starts a local HTTP server that gives the responses in order, with the last one repeated forever.
+	/// Returns the URL of the server and a counter of the requests that it has received.
+	async fn serve(responses: Vec<(u16, String)>) -> (String, Arc<AtomicUsize>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+		let count = Arc::new(AtomicUsize::new(0));
+		let count2 = count.clone();
+		tokio::spawn(async move {
+			loop {
+				let Ok((mut stream, _)) = listener.accept().await else {
+					return;
+				};
+				let mut request = Vec::new();
+				let mut buf = [0; 1024];
+				while !request.ends_with(b"\r\n\r\n") {
+					match stream.read(&mut buf).await {
+						Ok(0) | Err(..) => break,
+						Ok(len) => request.extend_from_slice(&buf[..len]),
+					}
+				}
+
+				let index = count2.fetch_add(1, Ordering::SeqCst);
+				let (status, body) = &responses[index.min(responses.len() - 1)];
+				let response = format!(
+					"HTTP/1.1 {status} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+					body.len()
+				);
+				let _ = stream.write_all(response.as_bytes()).await;
+				let _ = stream.shutdown().await;
+			}
+		});
+
+		(url, count)
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_sync_retry() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+
+		let index =
+			serde_json::json!({ "packages": { "foo": { "url": "https://example.com/foo.json" } } });
+		let (url, count) = serve(vec![
+			(500, String::new()),
+			(500, String::new()),
+			(200, index.to_string()),
+		])
+		.await;
+
+		let mut repo = PkgRepo::new("flaky", PkgRepoLocation::Remote(url));
+		let client = Client::new();
+		repo.ensure_index(&paths, &client, &mut NoOp).await.unwrap();
+		assert_eq!(count.load(Ordering::SeqCst), 3);
+		assert!(repo.index.get().packages.contains_key("foo"));
+		assert!(repo.get_path(&paths).exists());
+
+		let (url, count) = serve(vec![(500, String::new())]).await;
+		let mut repo = PkgRepo::new("broken", PkgRepoLocation::Remote(url));
+		assert!(repo.ensure_index(&paths, &client, &mut NoOp).await.is_err());
+		assert_eq!(count.load(Ordering::SeqCst), SYNC_ATTEMPTS as usize);
+
+		// Errors that won't go away are not retried
+		let (url, count) = serve(vec![(404, String::new())]).await;
+		let mut repo = PkgRepo::new("missing", PkgRepoLocation::Remote(url));
+		assert!(repo.ensure_index(&paths, &client, &mut NoOp).await.is_err());
+		assert_eq!(count.load(Ordering::SeqCst), 1);
+
+		let (url, count) = serve(vec![(429, String::new()), (200, index.to_string())]).await;
+		let mut repo = PkgRepo::new("limited", PkgRepoLocation::Remote(url));
+		repo.ensure_index(&paths, &client, &mut NoOp).await.unwrap();
+		assert_eq!(count.load(Ordering::SeqCst), 2);
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_fetch_package() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+		let client = Client::new();
+
+		// Remote
+		let index = serde_json::json!({ "packages": { "foo": { "path": "foo.pkg.txt" } } });
+		let (url, _) = serve(vec![
+			(200, index.to_string()),
+			(200, "@meta { name \"Foo\"; }".into()),
+		])
+		.await;
+		let mut repo = PkgRepo::new("remote", PkgRepoLocation::Remote(url));
+		let (contents, content_type) = repo
+			.fetch_package("foo", &paths, &client, &mut NoOp)
+			.await
+			.unwrap();
+		assert_eq!(contents, "@meta { name \"Foo\"; }");
+		assert_eq!(content_type, PackageContentType::Script);
+		assert!(repo
+			.fetch_package("bar", &paths, &client, &mut NoOp)
+			.await
+			.is_err());
+
+		// Local
+		let package_path = data_dir.join("bar.json");
+		std::fs::write(&package_path, "{}").unwrap();
+		let index = serde_json::json!({
+			"packages": {
+				"bar": { "path": package_path, "content_type": "declarative" }
+			}
+		});
+		let index_path = data_dir.join("local.json");
+		std::fs::write(&index_path, index.to_string()).unwrap();
+		let mut repo = PkgRepo::new("local", PkgRepoLocation::Local(index_path));
+		let (contents, content_type) = repo
+			.fetch_package("bar", &paths, &client, &mut NoOp)
+			.await
+			.unwrap();
+		assert_eq!(contents, "{}");
+		assert_eq!(content_type, PackageContentType::Declarative);
+
+		// Core
+		let mut repo = PkgRepo::core();
+		let (contents, content_type) = repo
+			.fetch_package("fabric-rendering-api", &paths, &client, &mut NoOp)
+			.await
+			.unwrap();
+		assert_eq!(contents, get_core_package("fabric-rendering-api").unwrap());
+		assert_eq!(content_type, PackageContentType::Declarative);
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_max_index_size() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+
+		let index =
+			serde_json::json!({ "packages": { "foo": { "url": "https://example.com/foo.json" } } })
+				.to_string();
+		let (url, count) = serve(vec![(200, index.clone())]).await;
+		let client = Client::new();
+
+		let mut repo = PkgRepo::new("large", PkgRepoLocation::Remote(url.clone()))
+			.with_max_index_size(Some(index.len() as u64 - 1));
+		assert!(repo.sync(&paths, &client).await.is_err());
+		// Only the HEAD request should have been made
+		assert_eq!(count.load(Ordering::SeqCst), 1);
+		assert!(!repo.get_path(&paths).exists());
+
+		let mut repo = PkgRepo::new("large", PkgRepoLocation::Remote(url))
+			.with_max_index_size(Some(index.len() as u64));
+		repo.sync(&paths, &client).await.unwrap();
+		assert_eq!(count.load(Ordering::SeqCst), 3);
+		assert!(repo.index.get().packages.contains_key("foo"));
+
+		// Servers that don't report the size with a HEAD request are still limited
+		let (url, count) = serve(vec![(405, String::new()), (200, index.clone())]).await;
+		let mut repo = PkgRepo::new("unreported", PkgRepoLocation::Remote(url))
+			.with_max_index_size(Some(index.len() as u64 - 1));
+		let err = repo.sync(&paths, &client).await.unwrap_err();
+		assert!(format!("{err:?}").contains("larger than the maximum size"));
+		assert_eq!(count.load(Ordering::SeqCst), 2);
+		assert!(!repo.get_path(&paths).exists());
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn test_validate_repository() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let data_dir = data_dir.path();
+		let paths = Paths::with_data_dir_no_create(data_dir.to_owned()).unwrap();
+		std::fs::create_dir_all(&paths.pkg_index_cache).unwrap();
+
+		std::fs::write(data_dir.join("valid.pkg.txt"), "@meta { name \"Valid\"; }").unwrap();
+		std::fs::write(data_dir.join("invalid.json"), "not json").unwrap();
+		let index = serde_json::json!({
+			"packages": {
+				"valid": { "path": data_dir.join("valid.pkg.txt") },
+				"invalid": { "path": data_dir.join("invalid.json"), "content_type": "declarative" }
+			}
+		});
+		let index_path = data_dir.join("local.json");
+		std::fs::write(&index_path, index.to_string()).unwrap();
+
+		let mut repo = PkgRepo::new("local", PkgRepoLocation::Local(index_path));
+		let results = validate_repository(
+			&mut repo,
+			&PluginManager::new(),
+			&paths,
+			&Client::new(),
+			&mut NoOp,
+		)
+		.await
+		.unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].0, "invalid");
+		assert!(results[0].1.is_err());
+		assert_eq!(results[1].0, "valid");
+		assert!(results[1].1.is_ok());
+	}
+}
@@ -239,6 +239,16 @@ impl PluginManager {
 			.call_hook_on_plugin(hook, plugin_id, arg, &paths.core, o)
 	}
 
+	/// Checks if any loaded plugin registers the given custom package content type
+	pub fn has_content_type(&self, content_type: &str) -> anyhow::Result<bool> {
+		let inner = self.inner.lock().map_err(|x| anyhow!("{x}"))?;
+		let out = inner
+			.manager
+			.iter_plugins()
+			.any(|x| x.get_manifest().content_types.contains(content_type));
+		Ok(out)
+	}
+
 	/// Get a lock for the inner mutex
 	pub fn get_lock(&self) -> anyhow::Result<MutexGuard<PluginManagerInner>> {
 		let inner = self.inner.lock().map_err(|x| anyhow!("{x}"))?;
@@ -70,6 +70,15 @@ pub fn hash_file_with_best_hash(path: &Path, result: BestHashResult) -> anyhow::
 	Ok(matches)
 }
 
+/// Get the SHA-256 hash of a file as a hex string
+pub fn hash_file_sha256(path: &Path) -> anyhow::Result<String> {
+	let file = File::open(path).context("Failed to open file for checksum")?;
+	let hash =
+		digest_reader::<Sha256, _>(BufReader::new(file)).context("Failed to compute file hash")?;
+
+	Ok(hex::encode(hash))
+}
+
 /// Digest a reader into a hasher
 pub fn digest_reader<D: Digest, R: Read>(mut reader: R) -> anyhow::Result<Vec<u8>> {
 	let mut digest = D::new();